@@ -1,4 +1,4 @@
-use wiim_api::{Result, WiimClient};
+use wiim_api::{Result, Volume, WiimClient};
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -9,7 +9,7 @@ async fn main() -> Result<()> {
     println!("Now Playing: {now_playing:?}");
 
     println!("Setting volume to 50...");
-    client.set_volume(50).await?;
+    client.set_volume(Volume::try_from(50)?).await?;
 
     println!("Pausing playback...");
     client.pause().await?;
@@ -0,0 +1,468 @@
+//! Alarm clock support
+//!
+//! WiiM devices have three alarm slots (0-2). The device stores alarm times in UTC;
+//! the methods here accept and return times in the device's local timezone (as reported
+//! by the `tz` field of `getStatusEx`) and convert on the way in and out.
+
+use crate::{Result, WiimClient, WiimError};
+use serde::Deserialize;
+use std::fmt;
+
+/// Number of alarm slots available on the device
+pub const ALARM_SLOTS: u8 = 3;
+
+const MINUTES_PER_DAY: i32 = 24 * 60;
+
+/// Days of the week an alarm repeats on, stored as a bitmask (bit 0 = Sunday)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Weekdays(u8);
+
+impl Weekdays {
+    pub const SUNDAY: Weekdays = Weekdays(1 << 0);
+    pub const MONDAY: Weekdays = Weekdays(1 << 1);
+    pub const TUESDAY: Weekdays = Weekdays(1 << 2);
+    pub const WEDNESDAY: Weekdays = Weekdays(1 << 3);
+    pub const THURSDAY: Weekdays = Weekdays(1 << 4);
+    pub const FRIDAY: Weekdays = Weekdays(1 << 5);
+    pub const SATURDAY: Weekdays = Weekdays(1 << 6);
+    pub const WORKDAYS: Weekdays = Weekdays(0b0111110);
+    pub const WEEKEND: Weekdays = Weekdays(0b1000001);
+    pub const ALL: Weekdays = Weekdays(0b1111111);
+
+    const NAMES: [&'static str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+    const FULL_NAMES: [&'static str; 7] = [
+        "sunday",
+        "monday",
+        "tuesday",
+        "wednesday",
+        "thursday",
+        "friday",
+        "saturday",
+    ];
+
+    /// Create from a raw bitmask (bit 0 = Sunday); bits above Saturday are ignored
+    pub fn from_bits(bits: u8) -> Self {
+        Weekdays(bits & Self::ALL.0)
+    }
+
+    pub fn bits(&self) -> u8 {
+        self.0
+    }
+
+    pub fn union(self, other: Weekdays) -> Self {
+        Weekdays(self.0 | other.0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Shift every day forward (positive) or backward (negative), wrapping around the week
+    fn rotate(self, days: i32) -> Self {
+        let shift = days.rem_euclid(7) as u32;
+        let bits = self.0 as u16;
+        let rotated = ((bits << shift) | (bits >> (7 - shift))) & Self::ALL.0 as u16;
+        Weekdays(rotated as u8)
+    }
+
+    /// Parse a day name such as `mon` or `monday`
+    pub fn from_day_name(name: &str) -> Option<Self> {
+        let name = name.to_ascii_lowercase();
+        (0..7)
+            .find(|&i| Self::NAMES[i] == name || Self::FULL_NAMES[i] == name)
+            .map(|i| Weekdays(1 << i))
+    }
+}
+
+impl fmt::Display for Weekdays {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Weekdays::ALL => write!(f, "daily"),
+            Weekdays::WORKDAYS => write!(f, "weekdays"),
+            Weekdays::WEEKEND => write!(f, "weekends"),
+            days => {
+                let names: Vec<&str> = Self::NAMES
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| days.0 & (1 << i) != 0)
+                    .map(|(_, name)| *name)
+                    .collect();
+                write!(f, "{}", names.join(","))
+            }
+        }
+    }
+}
+
+/// Time of day for an alarm
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AlarmTime {
+    pub hour: u8,
+    pub minute: u8,
+}
+
+impl AlarmTime {
+    /// Create a time, validating the hour and minute
+    ///
+    /// # Errors
+    /// Returns `WiimError::InvalidResponse` if hour > 23 or minute > 59
+    pub fn new(hour: u8, minute: u8) -> Result<Self> {
+        if hour > 23 || minute > 59 {
+            return Err(WiimError::InvalidResponse(format!(
+                "Invalid alarm time: {hour:02}:{minute:02}"
+            )));
+        }
+        Ok(Self { hour, minute })
+    }
+
+    fn minutes(&self) -> i32 {
+        self.hour as i32 * 60 + self.minute as i32
+    }
+
+    fn from_minutes(minutes: i32) -> Self {
+        Self {
+            hour: (minutes / 60) as u8,
+            minute: (minutes % 60) as u8,
+        }
+    }
+}
+
+impl std::str::FromStr for AlarmTime {
+    type Err = WiimError;
+
+    /// Parse `HH:MM`
+    fn from_str(value: &str) -> Result<Self> {
+        let invalid = || WiimError::InvalidResponse(format!("Invalid alarm time: {value}"));
+        let (hour, minute) = value.split_once(':').ok_or_else(invalid)?;
+        let hour = hour.parse().map_err(|_| invalid())?;
+        let minute = minute.parse().map_err(|_| invalid())?;
+        AlarmTime::new(hour, minute)
+    }
+}
+
+impl fmt::Display for AlarmTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02}:{:02}", self.hour, self.minute)
+    }
+}
+
+/// What the device does when an alarm fires
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AlarmAction {
+    /// Play a stream URL
+    PlayUrl(String),
+    /// Play a saved preset (1-based)
+    Preset(u8),
+    /// Stop playback (useful as a scheduled "off" alarm)
+    Stop,
+}
+
+impl fmt::Display for AlarmAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AlarmAction::PlayUrl(url) => write!(f, "play {url}"),
+            AlarmAction::Preset(preset) => write!(f, "preset {preset}"),
+            AlarmAction::Stop => write!(f, "stop"),
+        }
+    }
+}
+
+/// A configured alarm
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Alarm {
+    /// Alarm slot (0-2)
+    pub slot: u8,
+    /// Time of day in the device's local timezone
+    pub time: AlarmTime,
+    /// Days the alarm repeats on
+    pub days: Weekdays,
+    pub action: AlarmAction,
+}
+
+/// Raw `getAlarmClock` response
+#[derive(Debug, Deserialize)]
+struct RawAlarm {
+    enable: Option<String>,
+    trigger: Option<String>,
+    operation: Option<String>,
+    week_day: Option<String>,
+    time: Option<String>,
+    path: Option<String>,
+}
+
+// LinkPlay trigger codes
+const TRIGGER_CANCEL: u8 = 0;
+const TRIGGER_DAILY: u8 = 2;
+const TRIGGER_WEEKDAYS: u8 = 4;
+
+// LinkPlay operation codes
+const OPERATION_PLAY: u8 = 1;
+const OPERATION_STOP: u8 = 2;
+
+const PRESET_PATH_PREFIX: &str = "preset:";
+
+/// Parse the device `tz` field (hours, e.g. "-5.0" or "5.5") into minutes east of UTC
+pub(crate) fn parse_tz_offset(tz: Option<&str>) -> i32 {
+    tz.and_then(|tz| tz.trim().parse::<f64>().ok())
+        .map(|hours| (hours * 60.0).round() as i32)
+        .unwrap_or(0)
+}
+
+/// Convert a local time and day set by `offset_minutes`, returning the shifted pair
+fn shift(time: AlarmTime, days: Weekdays, offset_minutes: i32) -> (AlarmTime, Weekdays) {
+    let total = time.minutes() + offset_minutes;
+    let day_shift = total.div_euclid(MINUTES_PER_DAY);
+    let minutes = total.rem_euclid(MINUTES_PER_DAY);
+    (AlarmTime::from_minutes(minutes), days.rotate(day_shift))
+}
+
+fn check_slot(slot: u8) -> Result<()> {
+    if slot >= ALARM_SLOTS {
+        return Err(WiimError::InvalidResponse(format!(
+            "Alarm slot must be 0-{}",
+            ALARM_SLOTS - 1
+        )));
+    }
+    Ok(())
+}
+
+impl Alarm {
+    /// Build the `setAlarmClock` command for this alarm, given the device's UTC offset
+    fn to_command(&self, offset_minutes: i32) -> String {
+        let (utc_time, utc_days) = shift(self.time, self.days, -offset_minutes);
+        let time = format!("{:02}{:02}00", utc_time.hour, utc_time.minute);
+        let (trigger, day) = if self.days == Weekdays::ALL {
+            (TRIGGER_DAILY, String::new())
+        } else {
+            (TRIGGER_WEEKDAYS, format!("{:02x}", utc_days.bits()))
+        };
+        let (operation, path) = match &self.action {
+            AlarmAction::PlayUrl(url) => (OPERATION_PLAY, crate::encode_argument(url)),
+            AlarmAction::Preset(preset) => {
+                (OPERATION_PLAY, format!("{PRESET_PATH_PREFIX}{preset}"))
+            }
+            AlarmAction::Stop => (OPERATION_STOP, String::new()),
+        };
+        format!(
+            "setAlarmClock:{}:{trigger}:{operation}:{time}:{day}:{path}",
+            self.slot
+        )
+    }
+
+    /// Decode a raw alarm, returning `None` for disabled or cancelled slots
+    fn from_raw(slot: u8, raw: RawAlarm, offset_minutes: i32) -> Option<Self> {
+        if raw.enable.as_deref() != Some("1") {
+            return None;
+        }
+        let trigger: u8 = raw.trigger?.parse().ok()?;
+        let time = raw.time?;
+        if time.len() < 4 || !time.is_ascii() {
+            return None;
+        }
+        let utc_time = AlarmTime::new(time[0..2].parse().ok()?, time[2..4].parse().ok()?).ok()?;
+        let utc_days = match trigger {
+            TRIGGER_CANCEL => return None,
+            TRIGGER_DAILY => Weekdays::ALL,
+            _ => Weekdays::from_bits(u8::from_str_radix(raw.week_day.as_deref()?, 16).ok()?),
+        };
+        let (time, days) = shift(utc_time, utc_days, offset_minutes);
+
+        let path = raw.path.unwrap_or_default();
+        let action = match raw.operation.as_deref() {
+            Some(op) if op == OPERATION_STOP.to_string() => AlarmAction::Stop,
+            _ => match path.strip_prefix(PRESET_PATH_PREFIX) {
+                Some(preset) => AlarmAction::Preset(preset.parse().ok()?),
+                None => AlarmAction::PlayUrl(path),
+            },
+        };
+
+        Some(Alarm {
+            slot,
+            time,
+            days,
+            action,
+        })
+    }
+}
+
+impl WiimClient {
+    async fn device_tz_offset(&self) -> Result<i32> {
+        let status = self.get_status_ex().await?;
+        Ok(parse_tz_offset(status.tz.as_deref()))
+    }
+
+    /// Get the alarm in a slot, or `None` if the slot is unused
+    ///
+    /// # Errors
+    /// Returns `WiimError::InvalidResponse` if the slot is out of range
+    pub async fn get_alarm(&self, slot: u8) -> Result<Option<Alarm>> {
+        check_slot(slot)?;
+        let offset = self.device_tz_offset().await?;
+        let response = self.send_command(&format!("getAlarmClock:{slot}")).await?;
+        let raw: RawAlarm = serde_json::from_str(&response)?;
+        Ok(Alarm::from_raw(slot, raw, offset))
+    }
+
+    /// Get all configured alarms
+    pub async fn list_alarms(&self) -> Result<Vec<Alarm>> {
+        let offset = self.device_tz_offset().await?;
+        let mut alarms = Vec::new();
+        for slot in 0..ALARM_SLOTS {
+            let response = self.send_command(&format!("getAlarmClock:{slot}")).await?;
+            let raw: RawAlarm = serde_json::from_str(&response)?;
+            alarms.extend(Alarm::from_raw(slot, raw, offset));
+        }
+        Ok(alarms)
+    }
+
+    /// Create or replace the alarm in `alarm.slot`
+    ///
+    /// # Errors
+    /// Returns `WiimError::InvalidResponse` if the slot is out of range or no days are selected
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use wiim_api::{Alarm, AlarmAction, AlarmTime, Weekdays, WiimClient};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> wiim_api::Result<()> {
+    ///     let client = WiimClient::new("192.168.1.100");
+    ///     client
+    ///         .set_alarm(&Alarm {
+    ///             slot: 0,
+    ///             time: AlarmTime::new(7, 30)?,
+    ///             days: Weekdays::WORKDAYS,
+    ///             action: AlarmAction::Preset(3),
+    ///         })
+    ///         .await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn set_alarm(&self, alarm: &Alarm) -> Result<()> {
+        check_slot(alarm.slot)?;
+        if alarm.days.is_empty() {
+            return Err(WiimError::InvalidResponse(
+                "Alarm must repeat on at least one day".to_string(),
+            ));
+        }
+        let offset = self.device_tz_offset().await?;
+        self.send_command(&alarm.to_command(offset)).await?;
+        Ok(())
+    }
+
+    /// Remove the alarm in a slot
+    pub async fn delete_alarm(&self, slot: u8) -> Result<()> {
+        check_slot(slot)?;
+        self.send_command(&format!("setAlarmClock:{slot}:{TRIGGER_CANCEL}"))
+            .await?;
+        Ok(())
+    }
+
+    /// Silence an alarm that is currently ringing
+    pub async fn stop_alarm(&self) -> Result<()> {
+        self.send_command("alarmStop").await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_weekdays_parsing_and_display() {
+        assert_eq!(Weekdays::from_day_name("Mon"), Some(Weekdays::MONDAY));
+        assert_eq!(
+            Weekdays::from_day_name("saturday"),
+            Some(Weekdays::SATURDAY)
+        );
+        assert_eq!(Weekdays::from_day_name("xyz"), None);
+        assert_eq!(Weekdays::from_day_name("monkey"), None);
+
+        assert_eq!(Weekdays::ALL.to_string(), "daily");
+        assert_eq!(Weekdays::WORKDAYS.to_string(), "weekdays");
+        assert_eq!(
+            Weekdays::MONDAY.union(Weekdays::FRIDAY).to_string(),
+            "mon,fri"
+        );
+    }
+
+    #[test]
+    fn test_weekdays_rotate() {
+        assert_eq!(Weekdays::SATURDAY.rotate(1), Weekdays::SUNDAY);
+        assert_eq!(Weekdays::SUNDAY.rotate(-1), Weekdays::SATURDAY);
+        assert_eq!(Weekdays::WORKDAYS.rotate(0), Weekdays::WORKDAYS);
+        assert_eq!(Weekdays::ALL.rotate(3), Weekdays::ALL);
+    }
+
+    #[test]
+    fn test_alarm_time_parsing() {
+        let time: AlarmTime = "07:30".parse().unwrap();
+        assert_eq!(
+            time,
+            AlarmTime {
+                hour: 7,
+                minute: 30
+            }
+        );
+        assert_eq!(time.to_string(), "07:30");
+        assert!("24:00".parse::<AlarmTime>().is_err());
+        assert!("0730".parse::<AlarmTime>().is_err());
+    }
+
+    #[test]
+    fn test_parse_tz_offset() {
+        assert_eq!(parse_tz_offset(Some("-5.0")), -300);
+        assert_eq!(parse_tz_offset(Some("5.5")), 330);
+        assert_eq!(parse_tz_offset(Some("garbage")), 0);
+        assert_eq!(parse_tz_offset(None), 0);
+    }
+
+    #[test]
+    fn test_alarm_command_converts_to_utc() {
+        let alarm = Alarm {
+            slot: 1,
+            time: AlarmTime::new(21, 0).unwrap(),
+            days: Weekdays::FRIDAY,
+            action: AlarmAction::Preset(3),
+        };
+
+        // 21:00 on Friday at UTC-5 is 02:00 on Saturday UTC
+        assert_eq!(
+            alarm.to_command(-300),
+            "setAlarmClock:1:4:1:020000:40:preset:3"
+        );
+
+        let daily = Alarm {
+            days: Weekdays::ALL,
+            action: AlarmAction::Stop,
+            ..alarm
+        };
+        assert_eq!(daily.to_command(0), "setAlarmClock:1:2:2:210000::");
+
+        let stream = Alarm {
+            action: AlarmAction::PlayUrl("http://radio.example/live?sid=1&fmt=mp3".to_string()),
+            ..daily
+        };
+        assert_eq!(
+            stream.to_command(0),
+            "setAlarmClock:1:2:1:210000::http://radio.example/live?sid=1%26fmt=mp3"
+        );
+    }
+
+    #[test]
+    fn test_alarm_from_raw_round_trip() {
+        let raw: RawAlarm = serde_json::from_str(
+            r#"{"enable": "1", "trigger": "4", "operation": "1",
+                "week_day": "40", "time": "020000", "path": "preset:3"}"#,
+        )
+        .unwrap();
+
+        let alarm = Alarm::from_raw(1, raw, -300).unwrap();
+        assert_eq!(alarm.time.to_string(), "21:00");
+        assert_eq!(alarm.days, Weekdays::FRIDAY);
+        assert_eq!(alarm.action, AlarmAction::Preset(3));
+
+        let disabled: RawAlarm = serde_json::from_str(r#"{"enable": "0"}"#).unwrap();
+        assert!(Alarm::from_raw(0, disabled, 0).is_none());
+    }
+}
@@ -0,0 +1,5 @@
+//! Generates the Kotlin and Swift bindings, see docs/integrations/mobile.md
+
+fn main() {
+    uniffi::uniffi_bindgen_main()
+}
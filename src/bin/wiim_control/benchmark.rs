@@ -0,0 +1,220 @@
+//! `wiim-control benchmark`: how quickly the device answers each endpoint
+//!
+//! Every endpoint is timed over HTTPS and plain HTTP, since LinkPlay firmware often
+//! answers both and one can be noticeably faster. A transport the device doesn't answer
+//! on is reported and skipped. Requests are made without retries, so failures show.
+
+use crate::i18n;
+use crate::output::CommandOutput;
+use serde::Serialize;
+use std::error::Error;
+use std::time::{Duration, Instant};
+use wiim_api::{DeviceFamily, WiimClient};
+
+/// The endpoints status bars and watchers poll
+const ENDPOINTS: [&str; 3] = ["getPlayerStatus", "getMetaInfo", "getStatusEx"];
+
+/// Latency of one endpoint over one transport
+#[derive(Debug, Clone, Serialize)]
+struct EndpointResult {
+    endpoint: &'static str,
+    transport: &'static str,
+    p50_ms: Option<u64>,
+    p95_ms: Option<u64>,
+    max_ms: Option<u64>,
+    failures: usize,
+}
+
+/// Nearest-rank percentile of sorted samples
+fn percentile(sorted: &[Duration], percent: usize) -> Option<Duration> {
+    let rank = (sorted.len() * percent).div_ceil(100).max(1);
+    sorted.get(rank - 1).copied()
+}
+
+/// Comfortable poll interval for a p95 latency: ten times it, in half seconds, at least 1s
+fn suggested_interval(p95: Duration) -> Duration {
+    let half_seconds = (p95.as_millis() * 10).div_ceil(500).max(2);
+    Duration::from_millis(half_seconds as u64 * 500)
+}
+
+async fn measure(
+    client: &WiimClient,
+    endpoint: &'static str,
+    transport: &'static str,
+    requests: usize,
+) -> (EndpointResult, Vec<Duration>) {
+    let mut samples = Vec::with_capacity(requests);
+    let mut failures = 0;
+    for _ in 0..requests {
+        let started = Instant::now();
+        match client.send_raw_command(endpoint).await {
+            Ok(_) => samples.push(started.elapsed()),
+            Err(_) => failures += 1,
+        }
+    }
+    samples.sort();
+    let ms = |d: Option<Duration>| d.map(|d| d.as_millis() as u64);
+    let result = EndpointResult {
+        endpoint,
+        transport,
+        p50_ms: ms(percentile(&samples, 50)),
+        p95_ms: ms(percentile(&samples, 95)),
+        max_ms: ms(samples.last().copied()),
+        failures,
+    };
+    (result, samples)
+}
+
+/// The transports to compare, as (label, client)
+fn transports(client: &WiimClient) -> Result<Vec<(&'static str, WiimClient)>, Box<dyn Error>> {
+    let address = client.get_ip_address();
+    // An address given as a URL pins the transport
+    if address.starts_with("http://") {
+        return Ok(vec![(
+            "http",
+            WiimClient::builder(address).retries(0).build()?,
+        )]);
+    }
+    let host = address.trim_start_matches("https://");
+    Ok(vec![
+        (
+            "https",
+            WiimClient::builder(host)
+                .family(DeviceFamily::WiiM)
+                .retries(0)
+                .build()?,
+        ),
+        (
+            "http",
+            WiimClient::builder(host)
+                .family(DeviceFamily::LinkPlay)
+                .retries(0)
+                .build()?,
+        ),
+    ])
+}
+
+fn format_ms(ms: Option<u64>) -> String {
+    ms.map_or_else(|| "-".to_string(), |ms| format!("{ms} ms"))
+}
+
+pub async fn run(
+    client: &WiimClient,
+    requests: usize,
+    json: bool,
+) -> Result<CommandOutput, Box<dyn Error>> {
+    let requests = requests.max(1);
+    if !json {
+        eprintln!(
+            "{}",
+            i18n::tr("benchmark.measuring", &[("requests", &requests)])
+        );
+    }
+
+    let mut results = Vec::new();
+    let mut unavailable = Vec::new();
+    let mut best: Option<(&'static str, Duration)> = None;
+    for (transport, client) in transports(client)? {
+        if client.test_connection().await.is_err() {
+            unavailable.push(transport);
+            continue;
+        }
+        let mut all_samples = Vec::new();
+        for endpoint in ENDPOINTS {
+            let (result, samples) = measure(&client, endpoint, transport, requests).await;
+            results.push(result);
+            all_samples.extend(samples);
+        }
+        all_samples.sort();
+        if let Some(p95) = percentile(&all_samples, 95) {
+            if best.is_none_or(|(_, best)| p95 < best) {
+                best = Some((transport, p95));
+            }
+        }
+    }
+
+    let mut lines = vec![format!(
+        "{:<16} {:<9} {:>8} {:>8} {:>8} {:>7}",
+        i18n::tr("benchmark.endpoint", &[]),
+        i18n::tr("benchmark.transport", &[]),
+        "p50",
+        "p95",
+        "max",
+        i18n::tr("benchmark.failed", &[])
+    )];
+    for result in &results {
+        lines.push(format!(
+            "{:<16} {:<9} {:>8} {:>8} {:>8} {:>7}",
+            result.endpoint,
+            result.transport,
+            format_ms(result.p50_ms),
+            format_ms(result.p95_ms),
+            format_ms(result.max_ms),
+            result.failures
+        ));
+    }
+    for transport in &unavailable {
+        lines.push(i18n::tr(
+            "benchmark.unavailable",
+            &[("transport", transport)],
+        ));
+    }
+
+    let Some((transport, p95)) = best else {
+        return Err(wiim_api::WiimError::InvalidResponse(
+            "Every benchmark request failed".to_string(),
+        )
+        .into());
+    };
+    let interval = suggested_interval(p95);
+    lines.push(String::new());
+    lines.push(i18n::tr(
+        "benchmark.summary",
+        &[
+            ("transport", &transport),
+            ("p95", &p95.as_millis()),
+            ("interval", &format!("{:.1}s", interval.as_secs_f64())),
+        ],
+    ));
+
+    Ok(CommandOutput::lines(lines).with_state(serde_json::json!({
+        "requests": requests,
+        "results": results,
+        "unavailable": unavailable,
+        "fastest": transport,
+        "suggested_interval_ms": interval.as_millis() as u64,
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentile() {
+        let samples: Vec<Duration> = (1..=20).map(Duration::from_millis).collect();
+        assert_eq!(percentile(&samples, 50), Some(Duration::from_millis(10)));
+        assert_eq!(percentile(&samples, 95), Some(Duration::from_millis(19)));
+        assert_eq!(
+            percentile(&samples[..1], 95),
+            Some(Duration::from_millis(1))
+        );
+        assert_eq!(percentile(&[], 50), None);
+    }
+
+    #[test]
+    fn test_suggested_interval() {
+        assert_eq!(
+            suggested_interval(Duration::from_millis(40)),
+            Duration::from_secs(1)
+        );
+        assert_eq!(
+            suggested_interval(Duration::from_millis(180)),
+            Duration::from_secs(2)
+        );
+        assert_eq!(
+            suggested_interval(Duration::from_millis(420)),
+            Duration::from_millis(4500)
+        );
+    }
+}
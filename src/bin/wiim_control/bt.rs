@@ -0,0 +1,106 @@
+//! `wiim-control bt`: send audio to Bluetooth headphones and speakers
+//!
+//! Devices are addressed by MAC, which `bt scan` and `bt list` print first on each line,
+//! so `bt connect` is easy to bind to a key once a device has been paired.
+
+use crate::i18n;
+use crate::output::CommandOutput;
+use clap::Subcommand;
+use std::error::Error;
+use std::time::Duration;
+use wiim_api::{BluetoothDevice, WiimClient};
+
+#[derive(Subcommand, Clone)]
+pub enum BtCommand {
+    /// Look for nearby devices in pairing mode
+    Scan {
+        /// How long to look (e.g. 5s, 10s)
+        #[arg(long, default_value = "5s", value_parser = crate::parse_duration_arg)]
+        duration: Duration,
+    },
+    /// Pair with a device found by scan and connect to it
+    Pair { mac: String },
+    /// Connect to a paired device
+    Connect { mac: String },
+    /// Disconnect from a device, keeping it paired
+    Disconnect { mac: String },
+    /// List paired devices
+    List,
+}
+
+fn device_line(device: &BluetoothDevice) -> String {
+    let connected = if device.connected {
+        format!(" {}", i18n::tr("bt.connected_marker", &[]))
+    } else {
+        String::new()
+    };
+    format!("{}  {}{connected}", device.address, device.name)
+}
+
+fn device_lines(devices: &[BluetoothDevice], empty_key: &str) -> CommandOutput {
+    let mut lines: Vec<String> = devices.iter().map(device_line).collect();
+    if lines.is_empty() {
+        lines.push(i18n::tr(empty_key, &[]));
+    }
+    CommandOutput::lines(lines).with_state(devices)
+}
+
+pub async fn run(
+    client: &WiimClient,
+    action: BtCommand,
+    json: bool,
+) -> Result<CommandOutput, Box<dyn Error>> {
+    let output = match action {
+        BtCommand::Scan { duration } => {
+            if !json {
+                eprintln!(
+                    "{}",
+                    i18n::tr("bt.scanning", &[("seconds", &duration.as_secs().max(1))])
+                );
+            }
+            let devices = client.scan_bluetooth(duration).await?;
+            device_lines(&devices, "bt.none_found")
+        }
+        BtCommand::List => {
+            let devices = client.list_bluetooth_paired().await?;
+            device_lines(&devices, "bt.none_paired")
+        }
+        BtCommand::Pair { mac } => {
+            client.pair_bluetooth(&mac).await?;
+            CommandOutput::message(i18n::tr("bt.paired", &[("mac", &mac)]))
+                .with_state(serde_json::json!({ "address": mac, "connected": true }))
+        }
+        BtCommand::Connect { mac } => {
+            client.connect_bluetooth(&mac).await?;
+            CommandOutput::message(i18n::tr("bt.connected", &[("mac", &mac)]))
+                .with_state(serde_json::json!({ "address": mac, "connected": true }))
+        }
+        BtCommand::Disconnect { mac } => {
+            client.disconnect_bluetooth(&mac).await?;
+            CommandOutput::message(i18n::tr("bt.disconnected", &[("mac", &mac)]))
+                .with_state(serde_json::json!({ "address": mac, "connected": false }))
+        }
+    };
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_device_line() {
+        let mut device = BluetoothDevice {
+            name: "WH-1000XM4".to_string(),
+            address: "38:18:4c:aa:bb:cc".to_string(),
+            connected: false,
+            role: None,
+        };
+        assert_eq!(device_line(&device), "38:18:4c:aa:bb:cc  WH-1000XM4");
+        device.connected = true;
+        assert_eq!(
+            device_line(&device),
+            "38:18:4c:aa:bb:cc  WH-1000XM4 (connected)"
+        );
+    }
+}
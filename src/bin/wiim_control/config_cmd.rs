@@ -0,0 +1,557 @@
+//! `wiim-control config`: create, inspect, validate and edit the config file
+
+use crate::output::CommandOutput;
+use crate::{default_config_path, validate_template, Config};
+use clap::Subcommand;
+use serde_json::json;
+use std::path::PathBuf;
+use tokio::fs;
+
+const TEXT_TEMPLATE_KEYS: [&str; 5] = ["playing", "paused", "stopped", "loading", "offline"];
+const JSON_TEMPLATE_KEYS: [&str; 5] = ["text", "alt", "tooltip", "class", "percentage"];
+const PROFILE_KEYS: [&str; 4] = ["format", "text_template", "json_template", "device"];
+const MQTT_KEYS: [&str; 8] = [
+    "host",
+    "port",
+    "username",
+    "password",
+    "client_id",
+    "topic_prefix",
+    "discovery_prefix",
+    "interval",
+];
+
+const SCROBBLE_KEYS: [&str; 4] = [
+    "listenbrainz_token",
+    "lastfm_api_key",
+    "lastfm_api_secret",
+    "lastfm_session_key",
+];
+
+const HISTORY_KEYS: [&str; 2] = ["format", "path"];
+const STATSD_KEYS: [&str; 2] = ["address", "prefix"];
+const SCHEDULE_KEYS: [&str; 4] = ["at", "days", "device", "actions"];
+const VOLUME_LINK_KEYS: [&str; 1] = ["devices"];
+const WEBHOOK_KEYS: [&str; 3] = ["url", "events", "template"];
+
+/// Commented-out examples appended to the `device_ip` line of a new config file
+const CONFIG_EXAMPLES: &str = r#"
+# Named devices, usable as `wiim-control --device kitchen ...`
+# [devices]
+# kitchen = "192.168.1.101"
+
+# Default templates (see docs/templates/variables.md)
+# [output.text]
+# playing = "{{artist}} - {{title}} {{quality_info}}"
+# paused = "{{artist}} - {{title}}"
+# stopped = "No music"
+# loading = "Loading..."
+
+# Output profiles, selected with --profile
+# [profiles.waybar]
+# format = "json"
+"#;
+
+#[derive(Subcommand, Clone)]
+pub enum ConfigCommand {
+    /// Create a commented config file (uses --device as the device IP if given)
+    Init {
+        /// Overwrite an existing config file
+        #[arg(long)]
+        force: bool,
+    },
+    /// Print the config file path and contents
+    Show,
+    /// Check the config file for syntax errors, unknown keys and invalid templates
+    Validate,
+    /// Set a value, e.g. `device_ip 192.168.1.50` or `profiles.waybar.format json`
+    Set { key: String, value: String },
+}
+
+/// Contents of a freshly initialized config file
+pub fn default_config_contents(device_ip: &str) -> String {
+    format!("device_ip = \"{device_ip}\"\n{CONFIG_EXAMPLES}")
+}
+
+pub async fn run(
+    command: ConfigCommand,
+    config_path: &Option<PathBuf>,
+    device: Option<&str>,
+) -> Result<CommandOutput, Box<dyn std::error::Error>> {
+    let path = match config_path {
+        Some(path) => path.clone(),
+        None => default_config_path().ok_or("Could not find config directory")?,
+    };
+
+    let output = match command {
+        ConfigCommand::Init { force } => {
+            if path.exists() && !force {
+                return Err(format!(
+                    "{} already exists (use --force to overwrite)",
+                    path.display()
+                )
+                .into());
+            }
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).await?;
+            }
+            let device_ip = device.map_or_else(|| Config::default().device_ip, str::to_string);
+            fs::write(&path, default_config_contents(&device_ip)).await?;
+            CommandOutput::message(format!("📝 Created config at: {}", path.display()))
+                .with_state(json!({ "path": path, "device_ip": device_ip }))
+        }
+        ConfigCommand::Show => {
+            let exists = path.exists();
+            let (message, contents) = if exists {
+                (
+                    format!("# {}", path.display()),
+                    fs::read_to_string(&path).await?,
+                )
+            } else {
+                (
+                    format!(
+                        "# {}\n# File does not exist, using defaults",
+                        path.display()
+                    ),
+                    default_config_contents(&Config::default().device_ip),
+                )
+            };
+            let mut output = CommandOutput::lines(contents.lines().map(str::to_string).collect())
+                .with_state(json!({ "path": path, "exists": exists, "contents": contents }));
+            output.message = Some(message);
+            output
+        }
+        ConfigCommand::Validate => {
+            let content = fs::read_to_string(&path)
+                .await
+                .map_err(|e| format!("Could not read {}: {e}", path.display()))?;
+            let problems = validate_config(&content);
+            let mut report: Vec<String> = problems
+                .warnings
+                .iter()
+                .map(|warning| format!("⚠️ {warning}"))
+                .chain(problems.errors.iter().map(|error| format!("❌ {error}")))
+                .collect();
+            let problem =
+                (!problems.errors.is_empty()).then(|| format!("{} is invalid", path.display()));
+            if problem.is_none() {
+                report.push(format!("✅ {} is valid", path.display()));
+            }
+            let mut output = CommandOutput::message(report.join("\n")).with_state(json!({
+                "path": path,
+                "errors": problems.errors,
+                "warnings": problems.warnings,
+            }));
+            output.problem = problem;
+            output
+        }
+        ConfigCommand::Set { key, value } => {
+            let content = if path.exists() {
+                fs::read_to_string(&path).await?
+            } else {
+                default_config_contents(&Config::default().device_ip)
+            };
+            let updated = set_value(&content, &key, &value)?;
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).await?;
+            }
+            fs::write(&path, updated).await?;
+            CommandOutput::message(format!("📝 Set {key} = \"{value}\" in {}", path.display()))
+                .with_state(json!({ "path": path, "key": key, "value": value }))
+        }
+    };
+
+    Ok(output)
+}
+
+/// Check whether a dotted key path is part of the config schema
+fn check_key(path: &[&str]) -> Result<(), String> {
+    let known = match path {
+        ["device_ip" | "timeout" | "retries" | "locale"] => true,
+        ["devices" | "partials" | "helpers", name] => !name.is_empty(),
+        ["output", "text", key] => TEXT_TEMPLATE_KEYS.contains(key),
+        ["output", "json", key] => JSON_TEMPLATE_KEYS.contains(key),
+        ["profiles", name, key] => !name.is_empty() && PROFILE_KEYS.contains(key),
+        ["mqtt", key] => MQTT_KEYS.contains(key),
+        ["theme", key] => crate::theme::THEME_KEYS.contains(key),
+        ["progress_bar", key] => crate::progress::PROGRESS_BAR_KEYS.contains(key),
+        ["scrobble", key] => SCROBBLE_KEYS.contains(key),
+        ["history", key] => HISTORY_KEYS.contains(key),
+        ["statsd", key] => STATSD_KEYS.contains(key),
+        ["webhooks", name, key] => !name.is_empty() && WEBHOOK_KEYS.contains(key),
+        ["schedule", name, key] => !name.is_empty() && SCHEDULE_KEYS.contains(key),
+        ["volume_link", name, key] => !name.is_empty() && VOLUME_LINK_KEYS.contains(key),
+        _ => false,
+    };
+    if known {
+        Ok(())
+    } else {
+        Err(format!(
+            "Unknown config key '{}'. Valid keys: device_ip, timeout, retries, locale, devices.<name>, \
+             partials.<name>, helpers.<name>, output.text.<{}>, output.json.<{}>, profiles.<name>.<{}>, \
+             mqtt.<{}>, theme.<{}>, progress_bar.<{}>, scrobble.<{}>, history.<{}>, statsd.<{}>, webhooks.<name>.<{}>, \
+             schedule.<name>.<{}>, volume_link.<name>.<{}>",
+            path.join("."),
+            TEXT_TEMPLATE_KEYS.join("|"),
+            JSON_TEMPLATE_KEYS.join("|"),
+            PROFILE_KEYS.join("|"),
+            MQTT_KEYS.join("|"),
+            crate::theme::THEME_KEYS.join("|"),
+            crate::progress::PROGRESS_BAR_KEYS.join("|"),
+            SCROBBLE_KEYS.join("|"),
+            HISTORY_KEYS.join("|"),
+            STATSD_KEYS.join("|"),
+            WEBHOOK_KEYS.join("|"),
+            SCHEDULE_KEYS.join("|"),
+            VOLUME_LINK_KEYS.join("|")
+        ))
+    }
+}
+
+/// Check a value against the rules for its key
+fn check_value(path: &[&str], value: &str) -> Result<(), String> {
+    match path {
+        ["profiles", _, "format"] if value != "text" && value != "json" => {
+            Err(format!("format must be 'text' or 'json', got '{value}'"))
+        }
+        ["timeout"] | ["mqtt", "interval"] => crate::parse_duration_arg(value).map(|_| ()),
+        ["mqtt", "port"] => value
+            .parse::<u16>()
+            .map(|_| ())
+            .map_err(|_| format!("port must be a number from 0 to 65535, got '{value}'")),
+        ["progress_bar", "width"] => value
+            .parse::<usize>()
+            .map(|_| ())
+            .map_err(|_| format!("width must be a whole number, got '{value}'")),
+        ["retries"] => value
+            .parse::<u32>()
+            .map(|_| ())
+            .map_err(|_| format!("retries must be a whole number, got '{value}'")),
+        ["output", ..]
+        | ["profiles", _, "text_template"]
+        | ["partials" | "helpers", _]
+        | ["webhooks", _, "template"] => validate_template(value),
+        ["schedule", _, "at"] => crate::parse_alarm_time(value).map(|_| ()),
+        ["history", "format"] => crate::history::parse_format(value),
+        ["statsd", "address"] => crate::statsd::parse_address(value),
+        ["theme", _] => crate::theme::parse_style(value).map(|_| ()),
+        ["webhooks", _, "url"]
+            if !value.starts_with("http://") && !value.starts_with("https://") =>
+        {
+            Err(format!(
+                "url must start with http:// or https://, got '{value}'"
+            ))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Separator `config set` splits the value of a list-valued key on; `None` for other keys
+fn list_separator(path: &[&str]) -> Option<char> {
+    match path {
+        ["webhooks", _, "events"] | ["schedule", _, "days"] | ["volume_link", _, "devices"] => {
+            Some(',')
+        }
+        ["schedule", _, "actions"] => Some(';'),
+        _ => None,
+    }
+}
+
+/// Check the items of a list-valued key against the rules for its key
+fn check_list(path: &[&str], items: &[String]) -> Result<(), String> {
+    match path {
+        ["webhooks", _, "events"] => crate::webhook::parse_events(items).map(|_| ()),
+        ["schedule", _, "days"] => crate::parse_day_list(items).map(|_| ()),
+        ["volume_link", _, "devices"] => crate::volume_link::parse_devices(items).map(|_| ()),
+        #[cfg(feature = "schedule")]
+        ["schedule", _, "actions"] => crate::schedule::parse_actions(items).map(|_| ()),
+        _ => Ok(()),
+    }
+}
+
+/// Set `key` to `value` in a TOML document, preserving comments and formatting
+fn set_value(content: &str, key: &str, value: &str) -> Result<String, String> {
+    let path: Vec<&str> = key.split('.').collect();
+    check_key(&path)?;
+    let items: Option<Vec<String>> = list_separator(&path).map(|separator| {
+        value
+            .split(separator)
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(str::to_string)
+            .collect()
+    });
+    match &items {
+        Some(items) => check_list(&path, items),
+        None => check_value(&path, value),
+    }
+    .map_err(|e| format!("Invalid value for {key}: {e}"))?;
+
+    let mut document: toml_edit::DocumentMut = content
+        .parse()
+        .map_err(|e| format!("Config file could not be parsed: {e}"))?;
+
+    let (last, parents) = path.split_last().expect("key has at least one segment");
+    let mut table = document.as_table_mut();
+    for part in parents {
+        let entry = table
+            .entry(part)
+            .or_insert_with(|| toml_edit::Item::Table(toml_edit::Table::new()));
+        // Parent tables like [profiles] are only there to hold the leaf tables
+        if let Some(inner) = entry.as_table_mut() {
+            inner.set_implicit(true);
+        }
+        table = entry
+            .as_table_mut()
+            .ok_or_else(|| format!("'{part}' in {key} is not a table"))?;
+    }
+    table[last] = match path.as_slice() {
+        _ if items.is_some() => toml_edit::value(
+            items
+                .iter()
+                .flatten()
+                .map(String::as_str)
+                .collect::<toml_edit::Array>(),
+        ),
+        // Checked above, so the parse cannot fail
+        ["retries"] | ["mqtt", "port"] | ["progress_bar", "width"] => {
+            toml_edit::value(value.parse::<i64>().unwrap_or_default())
+        }
+        _ => toml_edit::value(value),
+    };
+
+    let updated = document.to_string();
+    toml::from_str::<Config>(&updated).map_err(|e| format!("Resulting config is invalid: {e}"))?;
+    Ok(updated)
+}
+
+#[derive(Debug, Default)]
+pub struct ConfigProblems {
+    pub errors: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+/// Validate config file contents: syntax, schema and template syntax
+pub fn validate_config(content: &str) -> ConfigProblems {
+    let mut problems = ConfigProblems::default();
+
+    let table: toml::Table = match toml::from_str(content) {
+        Ok(table) => table,
+        Err(e) => {
+            problems.errors.push(format!("Syntax error: {e}"));
+            return problems;
+        }
+    };
+
+    let mut leaves = Vec::new();
+    collect_leaves(&table, &mut Vec::new(), &mut leaves);
+    for (path, value) in leaves {
+        let segments: Vec<&str> = path.iter().map(String::as_str).collect();
+        if check_key(&segments).is_err() {
+            problems
+                .warnings
+                .push(format!("Unknown key '{}' is ignored", path.join(".")));
+            continue;
+        }
+        match (segments.as_slice(), value) {
+            (_, value) if list_separator(&segments).is_some() => {
+                let items = value.as_array().and_then(|items| {
+                    items
+                        .iter()
+                        .map(|item| item.as_str().map(str::to_string))
+                        .collect::<Option<Vec<_>>>()
+                });
+                match items {
+                    Some(items) => {
+                        if let Err(e) = check_list(&segments, &items) {
+                            problems.errors.push(format!("{}: {e}", path.join(".")));
+                        }
+                    }
+                    None => problems
+                        .errors
+                        .push(format!("{}: expected a list of strings", path.join("."))),
+                }
+            }
+            (["retries"], toml::Value::Integer(retries)) if *retries >= 0 => {}
+            (["retries"], _) => problems
+                .errors
+                .push("retries: expected a whole number".to_string()),
+            (["mqtt", "port"], toml::Value::Integer(port)) if u16::try_from(*port).is_ok() => {}
+            (["mqtt", "port"], _) => problems
+                .errors
+                .push("mqtt.port: expected a port number".to_string()),
+            (["progress_bar", "width"], toml::Value::Integer(width)) if *width >= 0 => {}
+            (["progress_bar", "width"], _) => problems
+                .errors
+                .push("progress_bar.width: expected a whole number".to_string()),
+            (_, toml::Value::String(value)) => {
+                if let Err(e) = check_value(&segments, value) {
+                    problems.errors.push(format!("{}: {e}", path.join(".")));
+                }
+            }
+            _ => problems
+                .errors
+                .push(format!("{}: expected a string", path.join("."))),
+        }
+    }
+
+    if problems.errors.is_empty() {
+        if let Err(e) = toml::from_str::<Config>(content) {
+            problems.errors.push(e.to_string());
+        }
+    }
+
+    problems
+}
+
+fn collect_leaves<'a>(
+    table: &'a toml::Table,
+    prefix: &mut Vec<String>,
+    leaves: &mut Vec<(Vec<String>, &'a toml::Value)>,
+) {
+    for (key, value) in table {
+        prefix.push(key.clone());
+        match value {
+            toml::Value::Table(inner) => collect_leaves(inner, prefix, leaves),
+            _ => leaves.push((prefix.clone(), value)),
+        }
+        prefix.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_config_contents_is_valid() {
+        let problems = validate_config(&default_config_contents("10.0.0.5"));
+        assert!(problems.errors.is_empty());
+        assert!(problems.warnings.is_empty());
+
+        let config: Config = toml::from_str(&default_config_contents("10.0.0.5")).unwrap();
+        assert_eq!(config.device_ip, "10.0.0.5");
+    }
+
+    #[test]
+    fn test_set_value_preserves_comments() {
+        let content = "# My WiiM\ndevice_ip = \"192.168.1.100\"\n";
+        let updated = set_value(content, "device_ip", "192.168.1.50").unwrap();
+        assert!(updated.starts_with("# My WiiM\n"));
+        assert!(updated.contains("device_ip = \"192.168.1.50\""));
+
+        let updated = set_value(&updated, "profiles.bar.text_template", "{{title}}").unwrap();
+        assert!(updated.contains("[profiles.bar]"));
+        assert!(!updated.contains("[profiles]\n"));
+        let config: Config = toml::from_str(&updated).unwrap();
+        assert_eq!(
+            config.profiles.unwrap()["bar"].text_template.as_deref(),
+            Some("{{title}}")
+        );
+    }
+
+    #[test]
+    fn test_set_value_rejects_bad_input() {
+        let content = "device_ip = \"192.168.1.100\"\n";
+        assert!(set_value(content, "volume", "50").is_err());
+        assert!(set_value(content, "profiles.bar.format", "xml").is_err());
+        assert!(set_value(content, "output.text.playing", "{artist}").is_err());
+        assert!(set_value(content, "timeout", "soon").is_err());
+        assert!(set_value(content, "retries", "-1").is_err());
+    }
+
+    #[test]
+    fn test_set_value_writes_typed_values() {
+        let content = "device_ip = \"192.168.1.100\"\n";
+        let updated = set_value(content, "retries", "2").unwrap();
+        let updated = set_value(&updated, "timeout", "800ms").unwrap();
+        assert!(updated.contains("retries = 2\n"));
+        assert!(updated.contains("timeout = \"800ms\""));
+        assert!(validate_config(&updated).errors.is_empty());
+    }
+
+    #[test]
+    fn test_set_value_writes_progress_bar_width() {
+        let content = "device_ip = \"192.168.1.100\"\n";
+        let updated = set_value(content, "progress_bar.width", "20").unwrap();
+        assert!(updated.contains("width = 20\n"));
+        assert!(validate_config(&updated).errors.is_empty());
+        assert!(set_value(content, "progress_bar.width", "wide").is_err());
+    }
+
+    #[test]
+    fn test_validate_config_accepts_progress_bar_width() {
+        let content = "device_ip = \"192.168.1.100\"\n[progress_bar]\nwidth = 20\n";
+        assert!(validate_config(content).errors.is_empty());
+        let content = "device_ip = \"192.168.1.100\"\n[progress_bar]\nwidth = \"20\"\n";
+        assert_eq!(
+            validate_config(content).errors,
+            ["progress_bar.width: expected a whole number"]
+        );
+    }
+
+    #[test]
+    fn test_set_value_writes_lists() {
+        let content = "device_ip = \"192.168.1.100\"\n";
+        let updated = set_value(content, "webhooks.n8n.url", "http://localhost/hook").unwrap();
+        let updated = set_value(&updated, "webhooks.n8n.events", "track, offline").unwrap();
+        assert!(updated.contains("events = [\"track\", \"offline\"]\n"));
+        assert!(validate_config(&updated).errors.is_empty());
+        assert!(set_value(content, "webhooks.n8n.events", "track,volume").is_err());
+
+        let updated = set_value(content, "volume_link.downstairs.devices", "amp, kitchen").unwrap();
+        assert!(updated.contains("devices = [\"amp\", \"kitchen\"]\n"));
+        assert!(validate_config(&updated).errors.is_empty());
+        assert!(set_value(content, "volume_link.downstairs.devices", "amp").is_err());
+    }
+
+    #[cfg(feature = "schedule")]
+    #[test]
+    fn test_set_value_writes_schedule_actions() {
+        let content =
+            "device_ip = \"192.168.1.100\"\n[schedule.morning]\nat = \"07:00\"\nactions = [\"play\"]\n";
+        let updated =
+            set_value(content, "schedule.morning.actions", "preset 2; volume 30").unwrap();
+        let updated = set_value(&updated, "schedule.morning.days", "sat,sun").unwrap();
+        assert!(updated.contains("actions = [\"preset 2\", \"volume 30\"]\n"));
+        assert!(updated.contains("days = [\"sat\", \"sun\"]\n"));
+        assert!(validate_config(&updated).errors.is_empty());
+        assert!(set_value(content, "schedule.morning.actions", "dance").is_err());
+    }
+
+    #[test]
+    fn test_validate_config_checks_lists() {
+        let content =
+            "device_ip = \"192.168.1.100\"\n[webhooks.n8n]\nurl = \"http://localhost/hook\"\n";
+        let valid = format!("{content}events = [\"track\", \"state\"]\n");
+        assert!(validate_config(&valid).errors.is_empty());
+        let unknown = format!("{content}events = [\"track\", \"volume\"]\n");
+        assert_eq!(validate_config(&unknown).errors.len(), 1);
+        assert!(validate_config(&unknown).errors[0]
+            .starts_with("webhooks.n8n.events: unknown webhook event"));
+        let string = format!("{content}events = \"track,state\"\n");
+        assert_eq!(
+            validate_config(&string).errors,
+            ["webhooks.n8n.events: expected a list of strings"]
+        );
+    }
+
+    #[test]
+    fn test_validate_config_reports_problems() {
+        let problems = validate_config(
+            r#"
+            device_ip = "192.168.1.100"
+            colour = "red"
+
+            [output.text]
+            playing = "{title}"
+            "#,
+        );
+        assert_eq!(problems.warnings, ["Unknown key 'colour' is ignored"]);
+        assert_eq!(problems.errors.len(), 1);
+        assert!(problems.errors[0].starts_with("output.text.playing:"));
+
+        let problems = validate_config("device_ip = ");
+        assert!(problems.errors[0].starts_with("Syntax error"));
+    }
+}
@@ -0,0 +1,634 @@
+//! `wiim-control daemon` keeps one warm client per configured device, polls them in the
+//! background and answers status requests over a unix socket, so `--via-daemon status`
+//! returns without any network round trip.
+//!
+//! The protocol is one JSON object per line: the client sends `{"device": "<ip>"}` and gets
+//! back a [`Reply`], always the latest state at once. A query finding that state older
+//! than `--fresh-for` also starts a refresh in the background for the next one; queries
+//! arriving together and the poll share that one device request.
+//!
+//! Edits to the config file are picked up while running: devices are added or removed,
+//! hooks are rebuilt when their sections change, and the schedule and volume links are
+//! restarted. With a `[statsd]` table every poll also sends [metrics](crate::statsd).
+
+use crate::error::CliError;
+use crate::history::Recorder;
+use crate::output::CommandOutput;
+use crate::reload::{ConfigWatcher, Reload};
+#[cfg(feature = "schedule")]
+use crate::schedule::Scheduler;
+use crate::scrobble::Scrobbler;
+use crate::single_flight::{Flight, SingleFlight};
+use crate::statsd::Emitter;
+use crate::volume_link::VolumeLink;
+use crate::webhook::Notifier;
+use crate::{
+    build_client, configured_devices, render_status, resolve_device, status_field_output, Cli,
+    Commands, Config, DeviceNames, ResolvedProfile,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use wiim_api::{NowPlaying, Volume, WiimClient};
+
+/// How long the thin client waits for the daemon before querying the device itself
+const QUERY_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Sections whose change means every device's client and hooks are rebuilt
+const HOOK_SECTIONS: [&str; 8] = [
+    "webhooks", "scrobble", "history", "statsd", "partials", "helpers", "timeout", "retries",
+];
+/// Sections whose change restarts the schedule
+#[cfg(feature = "schedule")]
+const SCHEDULE_SECTIONS: [&str; 5] = ["schedule", "devices", "device_ip", "timeout", "retries"];
+/// Sections whose change restarts the volume links
+const VOLUME_LINK_SECTIONS: [&str; 3] = ["volume_link", "devices", "device_ip"];
+
+/// Socket used when `--daemon-socket` isn't given, in the per-user runtime directory
+///
+/// `None` without one: in a shared directory like /tmp other users could read device
+/// status from the socket, or create it first and answer clients themselves.
+pub fn default_socket() -> Option<PathBuf> {
+    dirs::runtime_dir().map(|dir| dir.join("wiim-control.sock"))
+}
+
+/// Listen on `socket`, readable and writable by this user only
+fn bind(socket: &Path) -> std::io::Result<UnixListener> {
+    use std::os::unix::fs::PermissionsExt;
+    let listener = UnixListener::bind(socket)?;
+    std::fs::set_permissions(socket, std::fs::Permissions::from_mode(0o600))?;
+    Ok(listener)
+}
+
+#[derive(Serialize, Deserialize)]
+struct Request {
+    device: String,
+}
+
+/// The daemon's answer for one device
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum Reply {
+    Ok {
+        now_playing: Box<NowPlaying>,
+        device: DeviceNames,
+    },
+    Error {
+        error: String,
+    },
+}
+
+struct DaemonDevice {
+    client: WiimClient,
+    latest: Mutex<Reply>,
+    /// When `latest` was last fetched from the device
+    refreshed: Mutex<Option<Instant>>,
+    scrobbler: Option<tokio::sync::Mutex<Scrobbler>>,
+    notifier: Option<Mutex<Notifier>>,
+    history: Option<Mutex<Recorder>>,
+    statsd: Option<Mutex<Emitter>>,
+}
+
+/// Everything the daemon keeps for one device before it starts
+struct DeviceSetup {
+    ip: String,
+    client: WiimClient,
+    scrobbler: Option<Scrobbler>,
+    notifier: Option<Notifier>,
+    history: Option<Recorder>,
+    statsd: Option<Emitter>,
+}
+
+type Devices = HashMap<String, Arc<DaemonDevice>>;
+
+struct Daemon {
+    /// Devices by IP, the form the thin client resolves `--device` to; swapped as a whole
+    /// when the config is reloaded
+    devices: RwLock<Arc<Devices>>,
+    links: Mutex<Vec<VolumeLink>>,
+    /// Device requests in progress, by IP and command
+    flights: SingleFlight<(String, &'static str), ()>,
+    /// How old a reply may be before a query starts a refresh; at least the poll interval
+    fresh_for: Duration,
+}
+
+impl DaemonDevice {
+    fn new(setup: DeviceSetup, latest: Reply) -> Self {
+        DaemonDevice {
+            client: setup.client,
+            latest: Mutex::new(latest),
+            refreshed: Mutex::new(None),
+            scrobbler: setup.scrobbler.map(tokio::sync::Mutex::new),
+            notifier: setup.notifier.map(Mutex::new),
+            history: setup.history.map(Mutex::new),
+            statsd: setup.statsd.map(Mutex::new),
+        }
+    }
+
+    /// Query the device, store the result, then feed it to the hooks; names are only
+    /// fetched when the device (re)appears
+    async fn refresh(self: Arc<Self>) {
+        let known = match &*self.latest.lock().unwrap() {
+            Reply::Ok { device, .. } => Some(device.clone()),
+            Reply::Error { .. } => None,
+        };
+        let started = Instant::now();
+        let result = self.client.get_now_playing().await;
+        let latency = started.elapsed();
+        let reply = match result {
+            Ok(now_playing) => Reply::Ok {
+                now_playing: Box::new(now_playing),
+                device: match known {
+                    Some(names) => names,
+                    None => DeviceNames::fetch(&self.client).await,
+                },
+            },
+            Err(e) => Reply::Error {
+                error: e.to_string(),
+            },
+        };
+        *self.latest.lock().unwrap() = reply.clone();
+        *self.refreshed.lock().unwrap() = Some(Instant::now());
+
+        // Queries already see the new state while hooks take their time
+        let (now_playing, error) = match &reply {
+            Reply::Ok { now_playing, .. } => (Some(now_playing.as_ref()), None),
+            Reply::Error { error } => (None, Some(error.as_str())),
+        };
+        if let Some(scrobbler) = &self.scrobbler {
+            scrobbler.lock().await.observe(now_playing).await;
+        }
+        if let Some(history) = &self.history {
+            history.lock().unwrap().observe(now_playing);
+        }
+        if let Some(notifier) = &self.notifier {
+            notifier.lock().unwrap().observe(now_playing, error);
+        }
+        if let Some(statsd) = &self.statsd {
+            statsd.lock().unwrap().observe(now_playing, latency);
+        }
+    }
+
+    fn is_stale(&self, fresh_for: Duration) -> bool {
+        self.refreshed
+            .lock()
+            .unwrap()
+            .is_none_or(|refreshed| refreshed.elapsed() >= fresh_for)
+    }
+}
+
+fn not_polled() -> Reply {
+    Reply::Error {
+        error: "not polled yet".to_string(),
+    }
+}
+
+impl Daemon {
+    fn new(setups: Vec<DeviceSetup>, links: Vec<VolumeLink>, fresh_for: Duration) -> Self {
+        let devices = setups
+            .into_iter()
+            .map(|setup| {
+                (
+                    setup.ip.clone(),
+                    Arc::new(DaemonDevice::new(setup, not_polled())),
+                )
+            })
+            .collect();
+        Self {
+            devices: RwLock::new(Arc::new(devices)),
+            links: Mutex::new(links),
+            flights: SingleFlight::default(),
+            fresh_for,
+        }
+    }
+
+    fn devices(&self) -> Arc<Devices> {
+        self.devices.read().unwrap().clone()
+    }
+
+    /// Switch to a new set of devices, returning the IPs added and removed
+    ///
+    /// With `rebuild` false, devices that are still configured keep their client and
+    /// hooks; otherwise only their latest reply carries over.
+    fn replace(&self, setups: Vec<DeviceSetup>, rebuild: bool) -> (Vec<String>, Vec<String>) {
+        let old = self.devices();
+        let devices: Devices = setups
+            .into_iter()
+            .map(|setup| {
+                let ip = setup.ip.clone();
+                let device = match old.get(&ip) {
+                    Some(existing) if !rebuild => existing.clone(),
+                    Some(existing) => {
+                        let latest = existing.latest.lock().unwrap().clone();
+                        Arc::new(DaemonDevice::new(setup, latest))
+                    }
+                    None => Arc::new(DaemonDevice::new(setup, not_polled())),
+                };
+                (ip, device)
+            })
+            .collect();
+        let mut added: Vec<String> = devices
+            .keys()
+            .filter(|ip| !old.contains_key(*ip))
+            .cloned()
+            .collect();
+        let mut removed: Vec<String> = old
+            .keys()
+            .filter(|ip| !devices.contains_key(*ip))
+            .cloned()
+            .collect();
+        added.sort();
+        removed.sort();
+        *self.devices.write().unwrap() = Arc::new(devices);
+        (added, removed)
+    }
+
+    /// Refresh a device, joining a refresh already under way
+    fn refresh(&self, ip: &str, device: &Arc<DaemonDevice>) -> Flight<()> {
+        let device = device.clone();
+        self.flights
+            .join((ip.to_string(), "status"), move || device.refresh())
+    }
+
+    /// Refresh every device
+    async fn poll(&self) {
+        let devices = self.devices();
+        futures_util::future::join_all(devices.iter().map(|(ip, device)| self.refresh(ip, device)))
+            .await;
+        self.follow_links(&devices).await;
+    }
+
+    /// Carry volume changes from the latest poll over to the other devices of each link
+    async fn follow_links(&self, devices: &Devices) {
+        let changes: Vec<(String, String, u8)> = {
+            let mut links = self.links.lock().unwrap();
+            links
+                .iter_mut()
+                .flat_map(|link| {
+                    let volumes: Vec<Option<u8>> = link
+                        .ips
+                        .iter()
+                        .map(
+                            |ip| match devices.get(ip).map(|d| d.latest.lock().unwrap().clone()) {
+                                Some(Reply::Ok { now_playing, .. }) => {
+                                    Some(now_playing.volume.get())
+                                }
+                                _ => None,
+                            },
+                        )
+                        .collect();
+                    link.observe(&volumes)
+                        .into_iter()
+                        .map(|(index, volume)| (link.name.clone(), link.ips[index].clone(), volume))
+                        .collect::<Vec<_>>()
+                })
+                .collect()
+        };
+        for (name, ip, volume) in changes {
+            let Some(device) = devices.get(&ip) else {
+                continue;
+            };
+            if let Err(e) = device.client.set_volume(Volume::saturating(volume)).await {
+                eprintln!("⚠️ Volume link {name}: couldn't set {ip} to {volume}: {e}");
+            }
+        }
+    }
+
+    fn replace_links(&self, links: Vec<VolumeLink>) {
+        *self.links.lock().unwrap() = links;
+    }
+
+    /// The latest state, without waiting; a stale one is refreshed for the next query
+    fn reply(&self, ip: &str) -> Reply {
+        match self.devices().get(ip) {
+            Some(device) => {
+                if device.is_stale(self.fresh_for) {
+                    // Dropping the flight leaves it running
+                    drop(self.refresh(ip, device));
+                }
+                device.latest.lock().unwrap().clone()
+            }
+            None => Reply::Error {
+                error: format!("{ip} is not served by this daemon"),
+            },
+        }
+    }
+
+    async fn serve(&self, stream: UnixStream) -> std::io::Result<()> {
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = BufReader::new(reader).lines();
+        while let Some(line) = lines.next_line().await? {
+            let reply = match serde_json::from_str::<Request>(&line) {
+                Ok(request) => self.reply(&request.device),
+                Err(e) => Reply::Error {
+                    error: format!("invalid request: {e}"),
+                },
+            };
+            let mut json = serde_json::to_string(&reply).map_err(std::io::Error::other)?;
+            json.push('\n');
+            writer.write_all(json.as_bytes()).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Clients and hooks for every configured device, plus `--device` if it isn't configured
+fn setups(cli: &Cli, config: &Config) -> Result<Vec<DeviceSetup>, Box<dyn Error>> {
+    let mut devices = configured_devices(config);
+    if let Some(device) = &cli.device {
+        let ip = resolve_device(Some(device), config);
+        if !devices.iter().any(|(_, known)| *known == ip) {
+            devices.push((device.clone(), ip));
+        }
+    }
+    devices
+        .into_iter()
+        .map(|(name, ip)| {
+            Ok(DeviceSetup {
+                client: build_client(&ip, cli, config)?,
+                scrobbler: Scrobbler::from_config(config)?,
+                notifier: Notifier::from_config(config.webhooks.as_ref(), &name)
+                    .map_err(CliError::Config)?,
+                history: Recorder::from_config(config, &name).map_err(CliError::Config)?,
+                statsd: Emitter::from_config(config, &name).map_err(CliError::Config)?,
+                ip,
+            })
+        })
+        .collect()
+}
+
+fn log_links(links: &[VolumeLink]) {
+    if !links.is_empty() {
+        eprintln!("🔗 Linking volumes in {} group(s)", links.len());
+    }
+}
+
+#[cfg(feature = "schedule")]
+fn start_schedule(scheduler: Option<Scheduler>) -> Option<tokio::task::JoinHandle<()>> {
+    let scheduler = scheduler?;
+    eprintln!("⏰ Scheduling {} rule(s)", scheduler.len());
+    Some(tokio::spawn(scheduler.run()))
+}
+
+/// Apply a config edit to the running daemon, keeping what's running on any error
+#[cfg_attr(not(feature = "schedule"), allow(unused_variables))]
+fn apply_reload(
+    daemon: &Daemon,
+    schedule: &mut Option<tokio::task::JoinHandle<()>>,
+    reload: &Reload,
+    cli: &Cli,
+) {
+    let config = &reload.config;
+    let rebuild = HOOK_SECTIONS.iter().any(|section| reload.touches(section));
+    if rebuild || reload.touches("devices") || reload.touches("device_ip") {
+        match setups(cli, config) {
+            Ok(setups) => {
+                let (added, removed) = daemon.replace(setups, rebuild);
+                for ip in &added {
+                    eprintln!("➕ Now serving {ip}");
+                }
+                for ip in &removed {
+                    eprintln!("➖ No longer serving {ip}");
+                }
+                if rebuild {
+                    eprintln!("🔁 Rebuilt clients and hooks for every device");
+                }
+            }
+            Err(e) => eprintln!("⚠️ Devices not reloaded: {e}"),
+        }
+    }
+
+    #[cfg(feature = "schedule")]
+    if SCHEDULE_SECTIONS
+        .iter()
+        .any(|section| reload.touches(section))
+    {
+        match Scheduler::from_config(cli, config) {
+            Ok(scheduler) => {
+                if let Some(running) = schedule.take() {
+                    running.abort();
+                }
+                *schedule = start_schedule(scheduler);
+            }
+            Err(e) => eprintln!("⚠️ Schedule not reloaded: {e}"),
+        }
+    }
+
+    if VOLUME_LINK_SECTIONS
+        .iter()
+        .any(|section| reload.touches(section))
+    {
+        match crate::volume_link::from_config(config) {
+            Ok(links) => {
+                log_links(&links);
+                daemon.replace_links(links);
+            }
+            Err(e) => eprintln!("⚠️ Volume links not reloaded: {e}"),
+        }
+    }
+}
+
+pub async fn run(
+    socket: &Path,
+    interval: Duration,
+    fresh_for: Option<Duration>,
+    cli: &Cli,
+    config: &Config,
+) -> Result<CommandOutput, Box<dyn Error>> {
+    let links = crate::volume_link::from_config(config)?;
+    let fresh_for = fresh_for.unwrap_or(interval).max(interval);
+    let daemon = Arc::new(Daemon::new(setups(cli, config)?, links, fresh_for));
+    #[cfg(feature = "schedule")]
+    let scheduler = Scheduler::from_config(cli, config)?;
+
+    if UnixStream::connect(socket).await.is_ok() {
+        return Err(format!("a daemon is already listening on {}", socket.display()).into());
+    }
+    // A socket file left behind by a daemon that didn't shut down cleanly
+    let _ = std::fs::remove_file(socket);
+    let listener = bind(socket)?;
+    eprintln!(
+        "🔌 Serving {} device(s) on {}",
+        daemon.devices().len(),
+        socket.display()
+    );
+    #[cfg(feature = "schedule")]
+    let mut schedule = start_schedule(scheduler);
+    #[cfg(not(feature = "schedule"))]
+    let mut schedule = None;
+    log_links(&daemon.links.lock().unwrap());
+    let mut watcher = ConfigWatcher::new(cli);
+
+    let poller = daemon.clone();
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            poller.poll().await;
+        }
+    });
+
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => {
+                let (stream, _) = accepted?;
+                let daemon = daemon.clone();
+                tokio::spawn(async move {
+                    let _ = daemon.serve(stream).await;
+                });
+            }
+            reload = watcher.changed() => apply_reload(&daemon, &mut schedule, &reload, cli),
+            _ = tokio::signal::ctrl_c() => break,
+        }
+    }
+    let _ = std::fs::remove_file(socket);
+    Ok(CommandOutput::default())
+}
+
+/// Ask the daemon for a device's latest state
+pub async fn query(socket: &Path, device_ip: &str) -> std::io::Result<Reply> {
+    let exchange = async {
+        let stream = UnixStream::connect(socket).await?;
+        let (reader, mut writer) = stream.into_split();
+        let mut request = serde_json::to_string(&Request {
+            device: device_ip.to_string(),
+        })
+        .map_err(std::io::Error::other)?;
+        request.push('\n');
+        writer.write_all(request.as_bytes()).await?;
+
+        let mut line = String::new();
+        BufReader::new(reader).read_line(&mut line).await?;
+        serde_json::from_str(&line).map_err(std::io::Error::other)
+    };
+    tokio::time::timeout(QUERY_TIMEOUT, exchange)
+        .await
+        .map_err(|_| std::io::Error::new(std::io::ErrorKind::TimedOut, "daemon did not answer"))?
+}
+
+/// Answer `status` from the daemon; `None` means the caller should query the device itself
+/// (other commands, daemon not running, or the daemon has no good state for the device)
+pub async fn status(
+    command: &Commands,
+    socket: &Path,
+    device_ip: &str,
+    resolved_profile: &ResolvedProfile,
+    config: &Config,
+) -> Result<Option<CommandOutput>, Box<dyn Error>> {
+    let Commands::Status {
+        follow_waybar: false,
+        get,
+    } = command
+    else {
+        return Ok(None);
+    };
+    let Ok(Reply::Ok {
+        now_playing,
+        device,
+    }) = query(socket, device_ip).await
+    else {
+        return Ok(None);
+    };
+
+    let output = match get {
+        Some(field) => status_field_output(&now_playing, &device, field)?,
+        None => {
+            let line = render_status(&now_playing, &device, resolved_profile, config)?;
+            CommandOutput::line(line).with_state(now_playing)
+        }
+    };
+    Ok(Some(output))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::io::AsyncReadExt;
+    use tokio::net::TcpListener;
+
+    fn setup(ip: &str) -> DeviceSetup {
+        DeviceSetup {
+            ip: ip.to_string(),
+            client: WiimClient::new(ip),
+            scrobbler: None,
+            notifier: None,
+            history: None,
+            statsd: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_query_round_trip() {
+        let socket =
+            std::env::temp_dir().join(format!("wiim-daemon-test-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&socket);
+        let listener = bind(&socket).unwrap();
+        let mode = std::os::unix::fs::PermissionsExt::mode(
+            &std::fs::metadata(&socket).unwrap().permissions(),
+        );
+        assert_eq!(mode & 0o777, 0o600);
+        let daemon = Arc::new(Daemon::new(
+            vec![setup("127.0.0.1:1")],
+            Vec::new(),
+            Duration::from_secs(1),
+        ));
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let daemon = daemon.clone();
+                tokio::spawn(async move { daemon.serve(stream).await });
+            }
+        });
+
+        let reply = query(&socket, "127.0.0.1:1").await.unwrap();
+        assert!(matches!(reply, Reply::Error { error } if error == "not polled yet"));
+
+        let reply = query(&socket, "10.0.0.9").await.unwrap();
+        assert!(matches!(reply, Reply::Error { error } if error.contains("not served")));
+
+        let _ = std::fs::remove_file(&socket);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_queries_share_one_request() {
+        // A device that takes requests and never answers, counting status requests
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let ip = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counted = requests.clone();
+        tokio::spawn(async move {
+            let mut open = Vec::new();
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut request = [0; 1024];
+                let read = socket.read(&mut request).await.unwrap_or(0);
+                if String::from_utf8_lossy(&request[..read]).contains("getPlayerStatus") {
+                    counted.fetch_add(1, Ordering::SeqCst);
+                }
+                open.push(socket);
+            }
+        });
+
+        let daemon = Daemon::new(vec![setup(&ip)], Vec::new(), Duration::from_secs(2));
+        // Every query answers at once from the latest state
+        for _ in 0..5 {
+            assert!(
+                matches!(daemon.reply(&ip), Reply::Error { error } if error == "not polled yet")
+            );
+        }
+        // The poll joins the refresh the first query started
+        let poll = tokio::time::timeout(Duration::from_millis(300), daemon.poll()).await;
+        assert!(poll.is_err());
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_query_without_daemon_fails() {
+        let socket = std::env::temp_dir().join("wiim-daemon-test-missing.sock");
+        assert!(query(&socket, "127.0.0.1").await.is_err());
+    }
+}
@@ -0,0 +1,364 @@
+//! `wiim-control doctor`: step-by-step diagnostics for "is it the network or the tool?"
+
+use crate::config_cmd::validate_config;
+use crate::output::CommandOutput;
+use crate::{cli_device, config_path, Cli, Config};
+use serde::Serialize;
+use std::time::{Duration, Instant};
+use wiim_api::{StatusEx, WiimClient};
+
+/// Number of requests used to measure API latency
+const LATENCY_SAMPLES: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Outcome {
+    Pass,
+    Warn,
+    Fail,
+}
+
+#[derive(Serialize)]
+struct Check {
+    outcome: Outcome,
+    label: &'static str,
+    detail: String,
+    hint: Option<String>,
+}
+
+impl Check {
+    fn pass(label: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            outcome: Outcome::Pass,
+            label,
+            detail: detail.into(),
+            hint: None,
+        }
+    }
+
+    fn warn(label: &'static str, detail: impl Into<String>, hint: impl Into<String>) -> Self {
+        Self {
+            outcome: Outcome::Warn,
+            label,
+            detail: detail.into(),
+            hint: Some(hint.into()),
+        }
+    }
+
+    fn fail(label: &'static str, detail: impl Into<String>, hint: impl Into<String>) -> Self {
+        Self {
+            outcome: Outcome::Fail,
+            label,
+            detail: detail.into(),
+            hint: Some(hint.into()),
+        }
+    }
+
+    fn print(&self) {
+        let icon = match self.outcome {
+            Outcome::Pass => "✅",
+            Outcome::Warn => "⚠️",
+            Outcome::Fail => "❌",
+        };
+        println!("{icon} {:<14}{}", format!("{}:", self.label), self.detail);
+        if let Some(hint) = &self.hint {
+            println!("   → {hint}");
+        }
+    }
+}
+
+/// Checks run so far, printed as they complete unless the result is reported as JSON
+struct Report {
+    checks: Vec<Check>,
+    print: bool,
+}
+
+impl Report {
+    fn add(&mut self, check: Check) {
+        if self.print {
+            check.print();
+        }
+        self.checks.push(check);
+    }
+
+    fn finish(self) -> CommandOutput {
+        let count = |outcome| self.checks.iter().filter(|c| c.outcome == outcome).count();
+        let (failures, warnings) = (count(Outcome::Fail), count(Outcome::Warn));
+        let mut output = CommandOutput::lines(vec![String::new()]);
+        if failures > 0 {
+            output.problem = Some(format!(
+                "doctor found {failures} problem(s) and {warnings} warning(s)"
+            ));
+        } else {
+            output
+                .lines
+                .push(format!("No problems found ({warnings} warning(s))"));
+        }
+        output.with_state(serde_json::json!({ "checks": self.checks }))
+    }
+}
+
+pub async fn run(cli: &Cli) -> Result<CommandOutput, Box<dyn std::error::Error>> {
+    let mut report = Report {
+        checks: Vec::new(),
+        print: !cli.json,
+    };
+
+    let (config_check, config) = check_config(cli).await;
+    report.add(config_check);
+
+    let device_ip = cli_device(cli, &config.unwrap_or_default());
+    let host = device_ip
+        .trim_start_matches("https://")
+        .trim_start_matches("http://")
+        .to_string();
+
+    let mut reachable = None;
+    for scheme in ["https", "http"] {
+        let client = WiimClient::new(&format!("{scheme}://{host}"));
+        let started = Instant::now();
+        let check = match client.test_connection().await {
+            Ok(()) => {
+                let elapsed = started.elapsed();
+                reachable.get_or_insert(client);
+                Check::pass(
+                    if scheme == "https" { "HTTPS" } else { "HTTP" },
+                    format!("{host} answered in {} ms", elapsed.as_millis()),
+                )
+            }
+            Err(e) => Check::warn(
+                if scheme == "https" { "HTTPS" } else { "HTTP" },
+                format!("{host} not reachable: {e}"),
+                if scheme == "https" {
+                    "WiiM devices normally serve the API over HTTPS on port 443"
+                } else {
+                    "Only older LinkPlay firmware serves the API over plain HTTP; this is usually fine"
+                },
+            ),
+        };
+        report.add(check);
+    }
+
+    let Some(client) = reachable else {
+        report.add(Check::fail(
+            "Device",
+            format!("no response from {host}"),
+            "Check the IP in the WiiM Home app (Settings → Device Info), \
+             that the device is powered on, and that this machine is on the same network/VLAN",
+        ));
+        return Ok(report.finish());
+    };
+
+    match client.get_status_ex().await {
+        Ok(status) => {
+            report.add(check_firmware(&status));
+            report.add(check_wifi(&status));
+            report.add(check_internet(&status));
+        }
+        Err(e) => report.add(Check::fail(
+            "Device info",
+            format!("getStatusEx failed: {e}"),
+            "The device answered but returned unexpected data; try `wiim-control raw getStatusEx`",
+        )),
+    }
+
+    report.add(check_latency(&client).await);
+
+    Ok(report.finish())
+}
+
+async fn check_config(cli: &Cli) -> (Check, Option<Config>) {
+    if cli.no_config {
+        return (Check::pass("Config", "not read (--no-config)"), None);
+    }
+    let path = match config_path(cli) {
+        Some(path) => path,
+        None => {
+            return (
+                Check::warn(
+                    "Config",
+                    "could not determine the config directory",
+                    "Pass --config or --device explicitly",
+                ),
+                None,
+            )
+        }
+    };
+
+    if !path.exists() {
+        return (
+            Check::warn(
+                "Config",
+                format!("{} does not exist, using defaults", path.display()),
+                "Create one with `wiim-control --device <your device IP> config init`",
+            ),
+            None,
+        );
+    }
+
+    let content = match tokio::fs::read_to_string(&path).await {
+        Ok(content) => content,
+        Err(e) => {
+            return (
+                Check::fail(
+                    "Config",
+                    format!("{} could not be read", path.display()),
+                    e.to_string(),
+                ),
+                None,
+            )
+        }
+    };
+
+    let problems = validate_config(&content);
+    if let Some(error) = problems.errors.first() {
+        return (
+            Check::fail(
+                "Config",
+                format!("{} is invalid: {error}", path.display()),
+                "Run `wiim-control config validate` for details",
+            ),
+            None,
+        );
+    }
+
+    let config = toml::from_str(&content).ok();
+    let check = match problems.warnings.first() {
+        Some(warning) => Check::warn(
+            "Config",
+            format!("{}: {warning}", path.display()),
+            "Run `wiim-control config validate` for details",
+        ),
+        None => Check::pass("Config", format!("{} is valid", path.display())),
+    };
+    (check, config)
+}
+
+fn check_firmware(status: &StatusEx) -> Check {
+    let firmware = status
+        .firmware
+        .clone()
+        .unwrap_or_else(|| "unknown".to_string());
+    let update_available = status.version_update.as_deref() == Some("1")
+        || status
+            .new_ver
+            .as_deref()
+            .is_some_and(|v| !v.is_empty() && v != "0");
+    if update_available {
+        Check::warn(
+            "Firmware",
+            format!("{firmware} (update available)"),
+            "Install the pending update from the WiiM Home app",
+        )
+    } else {
+        Check::pass("Firmware", firmware)
+    }
+}
+
+fn check_wifi(status: &StatusEx) -> Check {
+    let Some(rssi) = status.rssi_dbm() else {
+        return Check::pass("WiFi", "no WiFi signal reported (wired connection?)");
+    };
+    let quality = status.signal_quality().unwrap_or_default();
+    let band = status.wifi_frequency_ghz().unwrap_or_default();
+    let detail = format!("{quality} ({rssi} dBm) {band}")
+        .trim_end()
+        .to_string();
+    match rssi {
+        rssi if rssi >= -60 => Check::pass("WiFi", detail),
+        rssi if rssi >= -70 => Check::warn(
+            "WiFi",
+            detail,
+            "Signal is usable but marginal; dropouts may occur with hi-res streams",
+        ),
+        _ => Check::fail(
+            "WiFi",
+            detail,
+            "Signal is weak: move the device closer to the access point, \
+             away from metal/electronics, or use ethernet",
+        ),
+    }
+}
+
+fn check_internet(status: &StatusEx) -> Check {
+    if status.has_internet() {
+        Check::pass("Internet", "device reports internet connectivity")
+    } else {
+        Check::warn(
+            "Internet",
+            "device reports no internet connectivity",
+            "Streaming services will not work; check the router's DNS/gateway settings",
+        )
+    }
+}
+
+async fn check_latency(client: &WiimClient) -> Check {
+    let mut samples = Vec::with_capacity(LATENCY_SAMPLES);
+    for _ in 0..LATENCY_SAMPLES {
+        let started = Instant::now();
+        if client.get_player_status().await.is_ok() {
+            samples.push(started.elapsed());
+        }
+    }
+    assess_latency(&mut samples)
+}
+
+fn assess_latency(samples: &mut [Duration]) -> Check {
+    if samples.is_empty() {
+        return Check::fail(
+            "API latency",
+            "all requests failed",
+            "The device is reachable but not answering API requests reliably; try rebooting it",
+        );
+    }
+    samples.sort();
+    let median = samples[samples.len() / 2].as_millis();
+    let max = samples[samples.len() - 1].as_millis();
+    let detail = format!("median {median} ms, max {max} ms");
+    match median {
+        0..=150 => Check::pass("API latency", detail),
+        151..=500 => Check::warn(
+            "API latency",
+            detail,
+            "Responses are slow; status bars should poll no faster than every 2s",
+        ),
+        _ => Check::fail(
+            "API latency",
+            detail,
+            "Responses are very slow; this usually points to WiFi congestion or a busy device",
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_wifi_grades_signal() {
+        let mut status = StatusEx {
+            rssi: Some("-45".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(check_wifi(&status).outcome, Outcome::Pass);
+
+        status.rssi = Some("-65".to_string());
+        assert_eq!(check_wifi(&status).outcome, Outcome::Warn);
+
+        status.rssi = Some("-80".to_string());
+        assert_eq!(check_wifi(&status).outcome, Outcome::Fail);
+    }
+
+    #[test]
+    fn test_assess_latency() {
+        let mut fast = [50, 60, 55].map(Duration::from_millis);
+        assert_eq!(assess_latency(&mut fast).outcome, Outcome::Pass);
+
+        let mut slow = [900, 1200, 800].map(Duration::from_millis);
+        let check = assess_latency(&mut slow);
+        assert_eq!(check.outcome, Outcome::Fail);
+        assert_eq!(check.detail, "median 900 ms, max 1200 ms");
+
+        assert_eq!(assess_latency(&mut []).outcome, Outcome::Fail);
+    }
+}
@@ -0,0 +1,116 @@
+//! Error categories and the process exit codes they map to
+
+use std::error::Error;
+use std::fmt;
+use wiim_api::WiimError;
+
+/// Any failure not covered by a more specific code
+pub const EXIT_FAILURE: i32 = 1;
+/// Invalid arguments (same code clap uses for usage errors)
+pub const EXIT_USAGE: i32 = 2;
+/// Config file missing required values, unparsable, or referencing unknown profiles
+pub const EXIT_CONFIG: i32 = 3;
+/// The device could not be reached (connection refused, timeout, DNS, TLS)
+pub const EXIT_UNREACHABLE: i32 = 4;
+/// The device answered but rejected the command or returned unexpected data
+pub const EXIT_REJECTED: i32 = 5;
+/// `wait-for` gave up before its condition was met
+pub const EXIT_TIMEOUT: i32 = 6;
+
+/// CLI-side errors that need their own exit code
+#[derive(Debug)]
+pub enum CliError {
+    Usage(String),
+    Config(String),
+    Timeout(String),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Usage(message) | CliError::Config(message) | CliError::Timeout(message) => {
+                write!(f, "{message}")
+            }
+        }
+    }
+}
+
+impl Error for CliError {}
+
+/// Map an error to the exit code scripts and service managers can act on
+pub fn exit_code(error: &(dyn Error + 'static)) -> i32 {
+    if let Some(error) = error.downcast_ref::<CliError>() {
+        return match error {
+            CliError::Usage(_) => EXIT_USAGE,
+            CliError::Config(_) => EXIT_CONFIG,
+            CliError::Timeout(_) => EXIT_TIMEOUT,
+        };
+    }
+    if let Some(error) = error.downcast_ref::<WiimError>() {
+        return match error {
+            WiimError::Request(_) | WiimError::CertificateMismatch { .. } => EXIT_UNREACHABLE,
+            WiimError::Timeout(_) => EXIT_TIMEOUT,
+            WiimError::Json(_)
+            | WiimError::InvalidResponse(_)
+            | WiimError::Unsupported { .. }
+            | WiimError::UnsupportedCommand { .. }
+            | WiimError::Device { .. } => EXIT_REJECTED,
+        };
+    }
+    if error.downcast_ref::<toml::de::Error>().is_some() {
+        return EXIT_CONFIG;
+    }
+    EXIT_FAILURE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_exit_codes() {
+        let boxed = |e: Box<dyn Error>| exit_code(e.as_ref());
+
+        assert_eq!(
+            boxed(Box::new(CliError::Usage("bad".to_string()))),
+            EXIT_USAGE
+        );
+        assert_eq!(
+            boxed(Box::new(CliError::Config("bad".to_string()))),
+            EXIT_CONFIG
+        );
+        assert_eq!(
+            boxed(Box::new(CliError::Timeout("slow".to_string()))),
+            EXIT_TIMEOUT
+        );
+        assert_eq!(
+            boxed(Box::new(WiimError::Timeout(Duration::from_secs(3)))),
+            EXIT_TIMEOUT
+        );
+        assert_eq!(
+            boxed(Box::new(WiimError::InvalidResponse(
+                "unknown command".to_string()
+            ))),
+            EXIT_REJECTED
+        );
+        assert_eq!(
+            boxed(Box::new(WiimError::Unsupported {
+                feature: "the hdmi input".to_string(),
+                device: "WiiM Mini".to_string(),
+            })),
+            EXIT_REJECTED
+        );
+        assert_eq!(
+            boxed(Box::new(WiimError::UnsupportedCommand {
+                command: "getSubLPF".to_string(),
+            })),
+            EXIT_REJECTED
+        );
+        let json_error = serde_json::from_str::<u8>("x").unwrap_err();
+        assert_eq!(boxed(Box::new(WiimError::Json(json_error))), EXIT_REJECTED);
+        let toml_error = toml::from_str::<toml::Table>("x =").unwrap_err();
+        assert_eq!(boxed(Box::new(toml_error)), EXIT_CONFIG);
+        assert_eq!(boxed("something else".into()), EXIT_FAILURE);
+    }
+}
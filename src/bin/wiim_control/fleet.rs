@@ -0,0 +1,278 @@
+//! `wiim-control fleet`: an overview of every configured device at once
+
+use crate::influx;
+use crate::output::CommandOutput;
+use crate::theme::{self, Role};
+use crate::{build_client, configured_devices, Cli, Config};
+use clap::Subcommand;
+use futures_util::stream::{self, StreamExt};
+use serde::Serialize;
+use std::error::Error;
+use wiim_api::{
+    DeviceSnapshot, Fleet, FleetEntry, GroupRole, NowPlaying, PlayState, Result as WiimResult,
+    WiimClient,
+};
+
+/// Longest track text shown in the table before it is shortened
+const TRACK_WIDTH: usize = 40;
+/// Devices asked at the same time
+const CONCURRENCY: usize = 8;
+
+#[derive(Subcommand, Clone)]
+pub enum FleetCommand {
+    /// Show state, track, volume and group role of every configured device
+    Status {
+        /// `influx` prints InfluxDB line protocol with playback and Wi-Fi measurements,
+        /// for Telegraf's exec input
+        #[arg(long, value_enum, default_value = "table")]
+        format: FleetFormat,
+    },
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum FleetFormat {
+    Table,
+    Influx,
+}
+
+/// One row of the overview
+#[derive(Debug, Serialize)]
+struct DeviceSummary {
+    device: String,
+    ip: String,
+    ok: bool,
+    state: Option<PlayState>,
+    track: Option<String>,
+    volume: Option<u8>,
+    muted: Option<bool>,
+    group_role: Option<GroupRole>,
+    error: Option<String>,
+}
+
+impl DeviceSummary {
+    fn new(name: &str, ip: &str, result: WiimResult<DeviceSnapshot>) -> Self {
+        let (now_playing, role, error) = match result {
+            Ok(snapshot) => (Some(snapshot.now_playing), Some(snapshot.group_role), None),
+            Err(e) => (None, None, Some(e.to_string())),
+        };
+        Self {
+            device: name.to_string(),
+            ip: ip.to_string(),
+            ok: error.is_none(),
+            state: now_playing.as_ref().map(|n| n.state.clone()),
+            track: now_playing.as_ref().and_then(track_text),
+            volume: now_playing.as_ref().map(|n| n.volume.get()),
+            muted: now_playing.as_ref().map(|n| n.is_muted),
+            group_role: role,
+            error,
+        }
+    }
+
+    fn cells(&self) -> [String; 5] {
+        let volume = match (self.volume, self.muted) {
+            (Some(volume), Some(true)) => format!("{volume}% 🔇"),
+            (Some(volume), _) => format!("{volume}%"),
+            (None, _) => "-".to_string(),
+        };
+        [
+            self.device.clone(),
+            self.state
+                .as_ref()
+                .map_or_else(|| "offline".to_string(), ToString::to_string),
+            self.track
+                .as_deref()
+                .map_or_else(|| "-".to_string(), |track| shorten(track, TRACK_WIDTH)),
+            volume,
+            self.group_role
+                .map_or_else(|| "-".to_string(), |role| role.to_string()),
+        ]
+    }
+
+    /// How each cell of [`cells`](Self::cells) is colored
+    fn roles(&self) -> [Option<Role>; 5] {
+        let state = match self.state {
+            Some(PlayState::Playing) => Role::Good,
+            Some(PlayState::Paused) => Role::Warn,
+            Some(PlayState::Stopped | PlayState::Loading) => Role::Dim,
+            None => Role::Bad,
+        };
+        let missing = |present: bool| if present { None } else { Some(Role::Dim) };
+        [
+            Some(Role::Accent),
+            Some(state),
+            missing(self.track.is_some()),
+            missing(self.volume.is_some()),
+            missing(self.group_role.is_some()),
+        ]
+    }
+}
+
+fn track_text(now_playing: &NowPlaying) -> Option<String> {
+    match (&now_playing.artist, &now_playing.title) {
+        (Some(artist), Some(title)) if !artist.is_empty() => Some(format!("{artist} - {title}")),
+        (_, Some(title)) if !title.is_empty() => Some(title.clone()),
+        _ => None,
+    }
+}
+
+fn shorten(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        text.to_string()
+    } else {
+        let mut shortened: String = text.chars().take(width - 1).collect();
+        shortened.push('…');
+        shortened
+    }
+}
+
+/// Lay out rows under a header with columns padded to their widest cell, coloring
+/// each cell by its role
+fn table(rows: &[[String; 5]], roles: &[[Option<Role>; 5]]) -> Vec<String> {
+    let header = ["NAME", "STATE", "TRACK", "VOLUME", "GROUP"].map(String::from);
+    let header_roles = [Some(Role::Label); 5];
+    let mut widths = [0; 5];
+    for row in std::iter::once(&header).chain(rows) {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    std::iter::once(&header)
+        .chain(rows)
+        .zip(std::iter::once(&header_roles).chain(roles))
+        .map(|(row, row_roles)| {
+            let line: Vec<String> = row
+                .iter()
+                .zip(row_roles)
+                .zip(widths)
+                .map(|((cell, role), width)| {
+                    // Padding stays outside the color so columns line up
+                    let padding = width - cell.chars().count();
+                    let cell = match role {
+                        Some(role) => theme::paint(*role, cell),
+                        None => cell.clone(),
+                    };
+                    format!("{cell}{}", " ".repeat(padding))
+                })
+                .collect();
+            line.join("  ").trim_end().to_string()
+        })
+        .collect()
+}
+
+pub async fn run(
+    command: &FleetCommand,
+    cli: &Cli,
+    config: &Config,
+) -> Result<CommandOutput, Box<dyn Error>> {
+    match command {
+        FleetCommand::Status { format } => {
+            let devices = configured_devices(config);
+            let clients = devices
+                .iter()
+                .map(|(_, ip)| build_client(ip, cli, config))
+                .collect::<Result<Vec<WiimClient>, _>>()?;
+            let fleet: Fleet = devices
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .zip(clients.iter().cloned())
+                .collect();
+            let entries = fleet.poll_all(CONCURRENCY).await;
+            match format {
+                FleetFormat::Table => Ok(table_output(&devices, entries)),
+                FleetFormat::Influx => Ok(influx_output(&devices, &clients, &entries).await),
+            }
+        }
+    }
+}
+
+fn table_output(devices: &[(String, String)], entries: Vec<FleetEntry>) -> CommandOutput {
+    let summaries: Vec<DeviceSummary> = devices
+        .iter()
+        .zip(entries)
+        .map(|((name, ip), entry)| DeviceSummary::new(name, ip, entry.snapshot))
+        .collect();
+    let rows: Vec<[String; 5]> = summaries.iter().map(DeviceSummary::cells).collect();
+    let roles: Vec<_> = summaries.iter().map(DeviceSummary::roles).collect();
+    let offline = summaries.iter().filter(|s| !s.ok).count();
+
+    let mut output = CommandOutput::lines(table(&rows, &roles));
+    if offline > 0 {
+        output.problem = Some(format!(
+            "{offline} of {} devices unreachable",
+            summaries.len()
+        ));
+    }
+    output.with_state(serde_json::json!({ "devices": summaries }))
+}
+
+/// Line protocol for every device, asking the reachable ones for their Wi-Fi details
+///
+/// Unreachable devices are reported as `reachable=false` rather than failing the command,
+/// since Telegraf drops the output of a command that exits non-zero.
+async fn influx_output(
+    devices: &[(String, String)],
+    clients: &[WiimClient],
+    entries: &[FleetEntry],
+) -> CommandOutput {
+    let statuses: Vec<_> = stream::iter(clients.iter().zip(entries))
+        .map(|(client, entry)| async move {
+            match entry.snapshot {
+                Ok(_) => client.get_status_ex().await.ok(),
+                Err(_) => None,
+            }
+        })
+        .buffered(CONCURRENCY)
+        .collect()
+        .await;
+
+    let mut lines = Vec::new();
+    for (((name, ip), entry), status) in devices.iter().zip(entries).zip(&statuses) {
+        lines.push(influx::playback(name, ip, &entry.snapshot));
+        lines.extend(
+            status
+                .as_ref()
+                .and_then(|status| influx::network(name, ip, status)),
+        );
+    }
+    let state = serde_json::json!({ "lines": lines });
+    CommandOutput::lines(lines).with_state(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_table_alignment() {
+        let rows = [
+            [
+                "kitchen".to_string(),
+                "playing".to_string(),
+                "Band - Song".to_string(),
+                "40%".to_string(),
+                "leader".to_string(),
+            ],
+            [
+                "office".to_string(),
+                "offline".to_string(),
+                "-".to_string(),
+                "-".to_string(),
+                "-".to_string(),
+            ],
+        ];
+        assert_eq!(
+            table(&rows, &[[None; 5]; 2]),
+            [
+                "NAME     STATE    TRACK        VOLUME  GROUP",
+                "kitchen  playing  Band - Song  40%     leader",
+                "office   offline  -            -       -",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_shorten() {
+        assert_eq!(shorten("short", 10), "short");
+        assert_eq!(shorten("a much longer track title", 10), "a much lo…");
+    }
+}
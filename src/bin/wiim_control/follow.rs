@@ -0,0 +1,131 @@
+//! `status --follow-waybar`: a long-running waybar module that prints a JSON line on every
+//! change and advances the playback position locally between polls
+
+use crate::{offline, render_status, Config, DeviceNames, OutputFormat, ResolvedProfile};
+use std::time::{Duration, Instant};
+use wiim_api::{NowPlaying, PlayState, Result as WiimResult, WiimClient};
+
+/// How often the device is asked for fresh status
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+/// How often the interpolated position is re-rendered
+const TICK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Advance the position of a playing track by the time since it was fetched
+fn interpolate(now_playing: &NowPlaying, elapsed: Duration) -> NowPlaying {
+    let mut interpolated = now_playing.clone();
+    if now_playing.state == PlayState::Playing {
+        let position = now_playing.position + elapsed;
+        interpolated.position = if !now_playing.duration.is_zero() {
+            position.min(now_playing.duration)
+        } else {
+            position
+        };
+    }
+    interpolated
+}
+
+/// Print waybar JSON whenever the rendered output changes; never returns on its own
+pub async fn run(
+    client: &WiimClient,
+    resolved_profile: &ResolvedProfile,
+    config: &Config,
+) -> WiimResult<()> {
+    // Waybar needs JSON, so a text profile falls back to the configured JSON templates
+    let profile = match resolved_profile.format {
+        OutputFormat::Json => resolved_profile.clone(),
+        OutputFormat::Text => ResolvedProfile {
+            format: OutputFormat::Json,
+            text_template: None,
+            json_templates: None,
+            offline_template: resolved_profile.offline_template.clone(),
+        },
+    };
+
+    let device = DeviceNames::fetch(client).await;
+    let mut ticker = tokio::time::interval(TICK_INTERVAL);
+    let mut last_poll: Option<Instant> = None;
+    let mut current: Option<(NowPlaying, Instant)> = None;
+    let mut last_line = String::new();
+
+    loop {
+        ticker.tick().await;
+
+        // Poll on schedule, and as soon as the interpolated track should have ended
+        let track_ended = current.as_ref().is_some_and(|(now_playing, fetched)| {
+            !now_playing.duration.is_zero()
+                && interpolate(now_playing, fetched.elapsed()).position >= now_playing.duration
+        });
+        if track_ended || last_poll.is_none_or(|polled| polled.elapsed() >= POLL_INTERVAL) {
+            last_poll = Some(Instant::now());
+            match client.get_now_playing().await {
+                Ok(now_playing) => {
+                    if profile.offline_template.is_some() {
+                        offline::save(client, &now_playing).await;
+                    }
+                    current = Some((now_playing, Instant::now()));
+                }
+                Err(e) => {
+                    current = None;
+                    let template = profile.offline_template.as_deref().unwrap_or("");
+                    let cached = offline::load(client).await;
+                    let output = offline::render(template, cached.as_ref(), &e, &profile)?;
+                    emit(output.lines.concat(), &mut last_line);
+                    continue;
+                }
+            }
+        }
+
+        if let Some((now_playing, fetched)) = &current {
+            let interpolated = interpolate(now_playing, fetched.elapsed());
+            emit(
+                render_status(&interpolated, &device, &profile, config)?,
+                &mut last_line,
+            );
+        }
+    }
+}
+
+/// Print a line unless it is identical to the previous one
+fn emit(line: String, last_line: &mut String) {
+    if line != *last_line {
+        println!("{line}");
+        *last_line = line;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiim_api::Volume;
+
+    fn now_playing(state: PlayState) -> NowPlaying {
+        NowPlaying {
+            title: Some("Song".to_string()),
+            state,
+            volume: Volume::saturating(50),
+            position: Duration::from_secs(10),
+            duration: Duration::from_secs(12),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_interpolate_advances_only_while_playing() {
+        let playing = now_playing(PlayState::Playing);
+        assert_eq!(
+            interpolate(&playing, Duration::from_secs(1)).position,
+            Duration::from_secs(11)
+        );
+        // Capped at the track duration
+        assert_eq!(
+            interpolate(&playing, Duration::from_secs(5)).position,
+            Duration::from_secs(12)
+        );
+
+        let paused = now_playing(PlayState::Paused);
+        assert_eq!(
+            interpolate(&paused, Duration::from_secs(5)).position,
+            Duration::from_secs(10)
+        );
+    }
+}
@@ -0,0 +1,521 @@
+//! Listening history: every played track recorded by `watch` and `daemon`, queried with
+//! `wiim-control history`
+//!
+//! History is stored as JSON lines by default, or in SQLite with the `sqlite` feature and
+//! `history.format = "sqlite"`. A track is recorded when it ends: another track starts, the
+//! device stops, or it becomes unreachable.
+
+use crate::output::CommandOutput;
+use crate::Config;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::error::Error;
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use wiim_api::{NowPlaying, PlayState};
+
+/// A jump back this far within the same track is treated as playing it again
+const RESTART_TOLERANCE: Duration = Duration::from_secs(10);
+
+/// Settings from the `[history]` table; its presence turns recording on
+#[derive(Debug, Default, Deserialize)]
+pub struct HistoryConfig {
+    /// "jsonl" (default) or "sqlite"
+    format: Option<String>,
+    /// Defaults to history.jsonl or history.db in the data directory
+    path: Option<String>,
+}
+
+/// One played track
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub device: String,
+    pub artist: Option<String>,
+    pub title: String,
+    pub album: Option<String>,
+    pub source: String,
+    pub sample_rate: Option<String>,
+    pub bit_depth: Option<String>,
+    /// When the track started, in seconds since the Unix epoch
+    pub started_at: u64,
+    pub ended_at: u64,
+    pub duration_ms: u64,
+    /// Time actually spent playing, excluding pauses
+    pub listened_ms: u64,
+}
+
+pub fn parse_format(value: &str) -> Result<(), String> {
+    match value {
+        "jsonl" => Ok(()),
+        "sqlite" if cfg!(feature = "sqlite") => Ok(()),
+        "sqlite" => Err("sqlite history needs wiim-control built with the sqlite feature".into()),
+        _ => Err(format!("format must be 'jsonl' or 'sqlite', got '{value}'")),
+    }
+}
+
+enum Store {
+    Jsonl(PathBuf),
+    #[cfg(feature = "sqlite")]
+    Sqlite(rusqlite::Connection),
+}
+
+impl Store {
+    fn open(settings: &HistoryConfig) -> Result<Self, String> {
+        let format = settings.format.as_deref().unwrap_or("jsonl");
+        parse_format(format).map_err(|e| format!("history.{e}"))?;
+        let path = match &settings.path {
+            Some(path) => PathBuf::from(path),
+            None => {
+                let file = if format == "sqlite" {
+                    "history.db"
+                } else {
+                    "history.jsonl"
+                };
+                dirs::data_dir()
+                    .ok_or("no data directory; set history.path")?
+                    .join("wiim-control")
+                    .join(file)
+            }
+        };
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            std::fs::create_dir_all(parent).map_err(|e| format!("{}: {e}", parent.display()))?;
+        }
+
+        match format {
+            #[cfg(feature = "sqlite")]
+            "sqlite" => sqlite::open(&path).map(Store::Sqlite),
+            _ => Ok(Store::Jsonl(path)),
+        }
+    }
+
+    fn append(&self, entry: &HistoryEntry) -> Result<(), String> {
+        match self {
+            Store::Jsonl(path) => {
+                let line = serde_json::to_string(entry).map_err(|e| e.to_string())?;
+                let mut file = std::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .map_err(|e| format!("{}: {e}", path.display()))?;
+                writeln!(file, "{line}").map_err(|e| format!("{}: {e}", path.display()))
+            }
+            #[cfg(feature = "sqlite")]
+            Store::Sqlite(connection) => sqlite::append(connection, entry),
+        }
+    }
+
+    /// The latest `limit` entries, oldest first
+    fn recent(&self, device: Option<&str>, limit: usize) -> Result<Vec<HistoryEntry>, String> {
+        match self {
+            Store::Jsonl(path) => {
+                let file = match std::fs::File::open(path) {
+                    Ok(file) => file,
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+                    Err(e) => return Err(format!("{}: {e}", path.display())),
+                };
+                let mut entries: Vec<HistoryEntry> = std::io::BufReader::new(file)
+                    .lines()
+                    .map_while(Result::ok)
+                    // A line cut short by a crash shouldn't hide the rest
+                    .filter_map(|line| serde_json::from_str(&line).ok())
+                    .filter(|entry: &HistoryEntry| device.is_none_or(|d| entry.device == d))
+                    .collect();
+                let skip = entries.len().saturating_sub(limit);
+                Ok(entries.split_off(skip))
+            }
+            #[cfg(feature = "sqlite")]
+            Store::Sqlite(connection) => sqlite::recent(connection, device, limit),
+        }
+    }
+}
+
+#[cfg(feature = "sqlite")]
+mod sqlite {
+    use super::HistoryEntry;
+    use rusqlite::{params, Connection};
+    use std::path::Path;
+
+    pub fn open(path: &Path) -> Result<Connection, String> {
+        let connection = Connection::open(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        connection
+            .execute_batch(
+                "CREATE TABLE IF NOT EXISTS history (
+                    id INTEGER PRIMARY KEY,
+                    device TEXT NOT NULL,
+                    artist TEXT,
+                    title TEXT NOT NULL,
+                    album TEXT,
+                    source TEXT NOT NULL,
+                    sample_rate TEXT,
+                    bit_depth TEXT,
+                    started_at INTEGER NOT NULL,
+                    ended_at INTEGER NOT NULL,
+                    duration_ms INTEGER NOT NULL,
+                    listened_ms INTEGER NOT NULL
+                );
+                CREATE INDEX IF NOT EXISTS history_started_at ON history (started_at);",
+            )
+            .map_err(|e| e.to_string())?;
+        Ok(connection)
+    }
+
+    pub fn append(connection: &Connection, entry: &HistoryEntry) -> Result<(), String> {
+        connection
+            .execute(
+                "INSERT INTO history (device, artist, title, album, source, sample_rate, \
+                 bit_depth, started_at, ended_at, duration_ms, listened_ms) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+                params![
+                    entry.device,
+                    entry.artist,
+                    entry.title,
+                    entry.album,
+                    entry.source,
+                    entry.sample_rate,
+                    entry.bit_depth,
+                    entry.started_at as i64,
+                    entry.ended_at as i64,
+                    entry.duration_ms as i64,
+                    entry.listened_ms as i64,
+                ],
+            )
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    pub fn recent(
+        connection: &Connection,
+        device: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<HistoryEntry>, String> {
+        let mut statement = connection
+            .prepare(
+                "SELECT device, artist, title, album, source, sample_rate, bit_depth, \
+                 started_at, ended_at, duration_ms, listened_ms FROM history \
+                 WHERE ?1 IS NULL OR device = ?1 ORDER BY started_at DESC LIMIT ?2",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = statement
+            .query_map(params![device, limit as i64], |row| {
+                Ok(HistoryEntry {
+                    device: row.get(0)?,
+                    artist: row.get(1)?,
+                    title: row.get(2)?,
+                    album: row.get(3)?,
+                    source: row.get(4)?,
+                    sample_rate: row.get(5)?,
+                    bit_depth: row.get(6)?,
+                    started_at: row.get::<_, i64>(7)? as u64,
+                    ended_at: row.get::<_, i64>(8)? as u64,
+                    duration_ms: row.get::<_, i64>(9)? as u64,
+                    listened_ms: row.get::<_, i64>(10)? as u64,
+                })
+            })
+            .map_err(|e| e.to_string())?;
+        let mut entries = rows
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        entries.reverse();
+        Ok(entries)
+    }
+}
+
+struct Session {
+    entry: HistoryEntry,
+    playing: bool,
+    last_seen: SystemTime,
+    last_position: Duration,
+    listened: Duration,
+}
+
+/// Turns a stream of observations into finished history entries
+#[derive(Default)]
+struct Sessions {
+    current: Option<Session>,
+}
+
+impl Sessions {
+    /// Record what the device reports at `now` (`None` when unreachable) and return the
+    /// entry of a track that just ended
+    fn observe(
+        &mut self,
+        device: &str,
+        now_playing: Option<&NowPlaying>,
+        now: SystemTime,
+    ) -> Option<HistoryEntry> {
+        if let Some(session) = &mut self.current {
+            if session.playing {
+                session.listened += now.duration_since(session.last_seen).unwrap_or_default();
+            }
+            session.last_seen = now;
+        }
+
+        let track = now_playing.filter(|now_playing| {
+            now_playing.title.is_some() && now_playing.state != PlayState::Stopped
+        });
+        let continues = match (&self.current, track) {
+            (Some(session), Some(now_playing)) => {
+                session.entry.title.as_str() == now_playing.title.as_deref().unwrap_or_default()
+                    && session.entry.artist == now_playing.artist
+                    && now_playing.position + RESTART_TOLERANCE >= session.last_position
+            }
+            _ => false,
+        };
+        if continues {
+            let (Some(session), Some(now_playing)) = (&mut self.current, track) else {
+                unreachable!("checked above");
+            };
+            session.playing = now_playing.state == PlayState::Playing;
+            session.last_position = now_playing.position;
+            return None;
+        }
+
+        let finished = self.current.take().and_then(|session| {
+            let mut entry = session.entry;
+            entry.listened_ms = session.listened.as_millis() as u64;
+            entry.ended_at = unix_seconds(now);
+            (entry.listened_ms > 0).then_some(entry)
+        });
+        self.current = track.map(|now_playing| Session {
+            entry: HistoryEntry {
+                device: device.to_string(),
+                artist: now_playing.artist.clone(),
+                title: now_playing.title.clone().unwrap_or_default(),
+                album: now_playing.album.clone(),
+                source: now_playing.source.to_string(),
+                sample_rate: now_playing.sample_rate.clone(),
+                bit_depth: now_playing.bit_depth.clone(),
+                started_at: unix_seconds(now).saturating_sub(now_playing.position.as_secs()),
+                ended_at: 0,
+                duration_ms: now_playing.duration.as_millis() as u64,
+                listened_ms: 0,
+            },
+            playing: now_playing.state == PlayState::Playing,
+            last_seen: now,
+            last_position: now_playing.position,
+            listened: Duration::ZERO,
+        });
+        finished
+    }
+}
+
+fn unix_seconds(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Records one device's history
+pub struct Recorder {
+    store: Store,
+    device: String,
+    sessions: Sessions,
+}
+
+impl Recorder {
+    /// `None` when there is no `[history]` table
+    pub fn from_config(config: &Config, device: &str) -> Result<Option<Self>, String> {
+        let Some(settings) = &config.history else {
+            return Ok(None);
+        };
+        Ok(Some(Self {
+            store: Store::open(settings)?,
+            device: device.to_string(),
+            sessions: Sessions::default(),
+        }))
+    }
+
+    /// Name devices by what they call themselves rather than by IP
+    pub fn set_device(&mut self, device: &str) {
+        self.device = device.to_string();
+    }
+
+    /// Feed an update from the device; write failures are reported, not returned
+    pub fn observe(&mut self, now_playing: Option<&NowPlaying>) {
+        let finished = self
+            .sessions
+            .observe(&self.device, now_playing, SystemTime::now());
+        if let Some(entry) = finished {
+            if let Err(e) = self.store.append(&entry) {
+                eprintln!("⚠️ History: {e}");
+            }
+        }
+    }
+}
+
+/// `wiim-control history`: the most recent entries
+pub fn run(
+    config: &Config,
+    device: Option<&str>,
+    limit: usize,
+) -> Result<CommandOutput, Box<dyn Error>> {
+    let Some(settings) = &config.history else {
+        return Err(crate::error::CliError::Config(
+            "history is off; add a [history] table to the config to record it".to_string(),
+        )
+        .into());
+    };
+    let entries = Store::open(settings)
+        .and_then(|store| store.recent(device, limit))
+        .map_err(crate::error::CliError::Config)?;
+
+    let lines = entries
+        .iter()
+        .map(|entry| {
+            let track = match &entry.artist {
+                Some(artist) => format!("{artist} - {}", entry.title),
+                None => entry.title.clone(),
+            };
+            format!(
+                "{}  {}  {track} ({}:{:02} via {})",
+                format_timestamp(entry.started_at),
+                entry.device,
+                entry.listened_ms / 60_000,
+                entry.listened_ms / 1000 % 60,
+                entry.source
+            )
+        })
+        .collect();
+    Ok(CommandOutput::lines(lines).with_state(json!({ "entries": entries })))
+}
+
+/// Seconds since the epoch as `YYYY-MM-DD HH:MM` in UTC
+fn format_timestamp(seconds: u64) -> String {
+    let days = (seconds / 86_400) as i64;
+    let minutes = seconds % 86_400 / 60;
+    // Civil-from-days, proleptic Gregorian calendar
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}",
+        minutes / 60,
+        minutes % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiim_api::{PlaybackSource, Volume};
+
+    fn track(title: &str, state: PlayState, position_ms: u64) -> NowPlaying {
+        NowPlaying {
+            title: Some(title.to_string()),
+            artist: Some("Artist".to_string()),
+            state,
+            volume: Volume::saturating(30),
+            position: Duration::from_millis(position_ms),
+            duration: Duration::from_secs(200),
+            sample_rate: Some("44100".to_string()),
+            bit_depth: Some("16".to_string()),
+            source: PlaybackSource::Spotify,
+            ..Default::default()
+        }
+    }
+
+    fn at(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(1_700_000_000 + secs)
+    }
+
+    #[test]
+    fn test_sessions_record_listened_time() {
+        let mut sessions = Sessions::default();
+        let one = track("One", PlayState::Playing, 0);
+        assert_eq!(sessions.observe("kitchen", Some(&one), at(0)), None);
+        let paused = track("One", PlayState::Paused, 60_000);
+        assert_eq!(sessions.observe("kitchen", Some(&paused), at(60)), None);
+        let resumed = track("One", PlayState::Playing, 60_000);
+        assert_eq!(sessions.observe("kitchen", Some(&resumed), at(300)), None);
+
+        let entry = sessions
+            .observe(
+                "kitchen",
+                Some(&track("Two", PlayState::Playing, 0)),
+                at(330),
+            )
+            .unwrap();
+        assert_eq!(entry.title, "One");
+        assert_eq!(entry.source, "spotify");
+        assert_eq!(entry.listened_ms, 90_000);
+        assert_eq!(entry.started_at, 1_700_000_000);
+        assert_eq!(entry.ended_at, 1_700_000_330);
+
+        // Stopping and going offline end the current track too
+        let entry = sessions
+            .observe(
+                "kitchen",
+                Some(&track("Two", PlayState::Stopped, 0)),
+                at(340),
+            )
+            .unwrap();
+        assert_eq!(entry.title, "Two");
+        assert_eq!(sessions.observe("kitchen", None, at(400)), None);
+    }
+
+    #[test]
+    fn test_jsonl_store_round_trip() {
+        let path = std::env::temp_dir().join(format!("wiim-history-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let store = Store::Jsonl(path.clone());
+
+        let mut sessions = Sessions::default();
+        sessions.observe("kitchen", Some(&track("One", PlayState::Playing, 0)), at(0));
+        let entry = sessions.observe("kitchen", None, at(100)).unwrap();
+        store.append(&entry).unwrap();
+        store
+            .append(&HistoryEntry {
+                device: "office".to_string(),
+                ..entry.clone()
+            })
+            .unwrap();
+
+        assert_eq!(store.recent(None, 10).unwrap().len(), 2);
+        assert_eq!(store.recent(Some("kitchen"), 10).unwrap(), vec![entry]);
+        assert_eq!(store.recent(None, 1).unwrap()[0].device, "office");
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn test_sqlite_store_round_trip() {
+        let path = std::env::temp_dir().join(format!("wiim-history-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let store = Store::Sqlite(sqlite::open(&path).unwrap());
+
+        let mut sessions = Sessions::default();
+        sessions.observe("kitchen", Some(&track("One", PlayState::Playing, 0)), at(0));
+        let first = sessions
+            .observe(
+                "kitchen",
+                Some(&track("Two", PlayState::Playing, 0)),
+                at(100),
+            )
+            .unwrap();
+        let second = sessions.observe("kitchen", None, at(300)).unwrap();
+        store.append(&first).unwrap();
+        store.append(&second).unwrap();
+
+        assert_eq!(store.recent(None, 10).unwrap(), vec![first, second.clone()]);
+        assert_eq!(store.recent(Some("kitchen"), 1).unwrap(), vec![second]);
+        assert!(store.recent(Some("office"), 10).unwrap().is_empty());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00");
+        assert_eq!(format_timestamp(1_700_000_000), "2023-11-14 22:13");
+        assert_eq!(format_timestamp(951_782_400), "2000-02-29 00:00");
+    }
+}
@@ -0,0 +1,181 @@
+//! Human-facing strings in the user's language
+//!
+//! Strings live in TOML locale files, one table per area (`state`, `status`, `confirm`,
+//! ...), with `{name}` placeholders. English, German and Spanish are built in. A file at
+//! `~/.config/wiim-control/locales/<lang>.toml` overrides individual strings or adds a
+//! language. The language comes from `locale` in the config, or else `LC_ALL`,
+//! `LC_MESSAGES` or `LANG`; anything missing falls back to English.
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::path::Path;
+use std::sync::OnceLock;
+
+const BUILT_IN: [(&str, &str); 3] = [
+    ("en", include_str!("locales/en.toml")),
+    ("de", include_str!("locales/de.toml")),
+    ("es", include_str!("locales/es.toml")),
+];
+
+static STRINGS: OnceLock<Strings> = OnceLock::new();
+
+struct Strings {
+    /// The chosen language, with user overrides applied
+    selected: HashMap<String, String>,
+    english: HashMap<String, String>,
+}
+
+/// Flatten nested tables into dotted keys
+fn flatten(prefix: &str, table: &toml::Table, out: &mut HashMap<String, String>) {
+    for (key, value) in table {
+        let key = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{prefix}.{key}")
+        };
+        match value {
+            toml::Value::String(text) => {
+                out.insert(key, text.clone());
+            }
+            toml::Value::Table(table) => flatten(&key, table, out),
+            _ => {}
+        }
+    }
+}
+
+fn parse(source: &str) -> Result<HashMap<String, String>, String> {
+    let table: toml::Table = source.parse().map_err(|e| format!("{e}"))?;
+    let mut strings = HashMap::new();
+    flatten("", &table, &mut strings);
+    Ok(strings)
+}
+
+/// The language part of a locale such as "de_DE.UTF-8"
+fn language(locale: &str) -> Option<String> {
+    let language = locale
+        .split(['_', '.', '@', '-'])
+        .next()?
+        .to_ascii_lowercase();
+    match language.as_str() {
+        "" | "c" | "posix" => None,
+        _ => Some(language),
+    }
+}
+
+fn detect() -> String {
+    // Tests compare against the English strings regardless of the developer's locale
+    if cfg!(test) {
+        return "en".to_string();
+    }
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .into_iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find_map(|locale| language(&locale))
+        .unwrap_or_else(|| "en".to_string())
+}
+
+fn load(language: &str, user_dir: Option<&Path>) -> Result<Strings, String> {
+    let built_in = |name: &str| {
+        BUILT_IN
+            .iter()
+            .find(|(code, _)| *code == name)
+            .map(|(_, source)| parse(source))
+            .transpose()
+    };
+    let english = built_in("en")?.unwrap_or_default();
+    let mut selected = built_in(language)?.unwrap_or_default();
+
+    if let Some(dir) = user_dir {
+        let path = dir.join(format!("{language}.toml"));
+        if let Ok(source) = std::fs::read_to_string(&path) {
+            let overrides = parse(&source).map_err(|e| format!("{}: {e}", path.display()))?;
+            selected.extend(overrides);
+        }
+    }
+    Ok(Strings { selected, english })
+}
+
+/// Where users put their own locale files
+fn user_dir() -> Option<std::path::PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("wiim-control").join("locales"))
+}
+
+/// Pick the language; call once after loading the config
+pub fn init(locale: Option<&str>) -> Result<(), String> {
+    let language = locale.and_then(language).unwrap_or_else(detect);
+    let strings = load(&language, user_dir().as_deref())?;
+    // Only the first initialization counts
+    let _ = STRINGS.set(strings);
+    Ok(())
+}
+
+fn strings() -> &'static Strings {
+    STRINGS.get_or_init(|| {
+        load(&detect(), user_dir().as_deref()).unwrap_or_else(|_| Strings {
+            selected: HashMap::new(),
+            english: parse(BUILT_IN[0].1).unwrap_or_default(),
+        })
+    })
+}
+
+fn lookup(strings: &Strings, key: &str, args: &[(&str, &dyn Display)]) -> String {
+    let template = strings
+        .selected
+        .get(key)
+        .or_else(|| strings.english.get(key))
+        .map(String::as_str)
+        .unwrap_or(key);
+    let mut text = template.to_string();
+    for (name, value) in args {
+        text = text.replace(&format!("{{{name}}}"), &value.to_string());
+    }
+    text
+}
+
+/// The string for `key` with `{name}` placeholders filled in
+pub fn tr(key: &str, args: &[(&str, &dyn Display)]) -> String {
+    lookup(strings(), key, args)
+}
+
+/// "on" or "off"
+pub fn on_off(on: bool) -> String {
+    tr(if on { "common.on" } else { "common.off" }, &[])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_built_in_locales_cover_english() {
+        let english = parse(BUILT_IN[0].1).unwrap();
+        for (code, source) in &BUILT_IN[1..] {
+            let strings = parse(source).unwrap();
+            for key in english.keys() {
+                assert!(strings.contains_key(key), "{code} is missing {key}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_lookup_and_fallback() {
+        let strings = Strings {
+            selected: parse("[confirm]\nvolume_set = \"Lautstärke {level}%\"").unwrap(),
+            english: parse(BUILT_IN[0].1).unwrap(),
+        };
+        assert_eq!(
+            lookup(&strings, "confirm.volume_set", &[("level", &40)]),
+            "Lautstärke 40%"
+        );
+        assert_eq!(lookup(&strings, "confirm.paused", &[]), "⏸️ Paused");
+        assert_eq!(lookup(&strings, "no.such.key", &[]), "no.such.key");
+    }
+
+    #[test]
+    fn test_language_from_locale() {
+        assert_eq!(language("de_DE.UTF-8").as_deref(), Some("de"));
+        assert_eq!(language("es").as_deref(), Some("es"));
+        assert_eq!(language("C.UTF-8"), None);
+        assert_eq!(language(""), None);
+    }
+}
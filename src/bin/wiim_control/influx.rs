@@ -0,0 +1,193 @@
+//! InfluxDB line protocol for `fleet status --format influx`
+//!
+//! Every device gives a `wiim_playback` line, and a `wiim_network` line when it reports
+//! its Wi-Fi details. Lines carry no timestamp, so Telegraf's `exec` input stamps them
+//! when it collects them.
+
+use wiim_api::{DeviceSnapshot, Result as WiimResult, StatusEx};
+
+/// One point: a measurement with its tags and fields
+struct Line {
+    measurement: &'static str,
+    tags: Vec<(&'static str, String)>,
+    fields: Vec<(&'static str, String)>,
+}
+
+impl Line {
+    fn new(measurement: &'static str, device: &str, ip: &str) -> Self {
+        Line {
+            measurement,
+            tags: Vec::new(),
+            fields: Vec::new(),
+        }
+        .tag("device", device)
+        .tag("ip", ip)
+    }
+
+    /// Tags can't be empty, so empty values are left out
+    fn tag(mut self, key: &'static str, value: &str) -> Self {
+        if !value.is_empty() {
+            self.tags.push((key, escape_tag(value)));
+        }
+        self
+    }
+
+    fn int(mut self, key: &'static str, value: Option<i64>) -> Self {
+        if let Some(value) = value {
+            self.fields.push((key, format!("{value}i")));
+        }
+        self
+    }
+
+    fn bool(mut self, key: &'static str, value: bool) -> Self {
+        self.fields.push((key, value.to_string()));
+        self
+    }
+
+    fn string(mut self, key: &'static str, value: Option<&str>) -> Self {
+        if let Some(value) = value.filter(|value| !value.is_empty()) {
+            self.fields.push((key, quote(value)));
+        }
+        self
+    }
+
+    /// The line, or `None` without fields, which line protocol doesn't allow
+    fn render(&self) -> Option<String> {
+        if self.fields.is_empty() {
+            return None;
+        }
+        let tags: String = self
+            .tags
+            .iter()
+            .map(|(key, value)| format!(",{key}={value}"))
+            .collect();
+        let fields: Vec<String> = self
+            .fields
+            .iter()
+            .map(|(key, value)| format!("{key}={value}"))
+            .collect();
+        Some(format!("{}{tags} {}", self.measurement, fields.join(",")))
+    }
+}
+
+/// Commas, equals signs and spaces are escaped in tag values; newlines aren't allowed
+fn escape_tag(value: &str) -> String {
+    value
+        .replace('\n', " ")
+        .replace(',', "\\,")
+        .replace('=', "\\=")
+        .replace(' ', "\\ ")
+}
+
+/// A string field value: quoted, with quotes and backslashes escaped
+fn quote(value: &str) -> String {
+    let escaped = value
+        .replace('\n', " ")
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"");
+    format!("\"{escaped}\"")
+}
+
+fn number(value: Option<&String>) -> Option<i64> {
+    value?.trim().parse().ok()
+}
+
+/// `wiim_playback`: state, source and group role as tags; volume, position and the track
+/// as fields. An unreachable device only gets `reachable=false`.
+pub fn playback(device: &str, ip: &str, snapshot: &WiimResult<DeviceSnapshot>) -> String {
+    let line = Line::new("wiim_playback", device, ip);
+    let line = match snapshot {
+        Ok(snapshot) => {
+            let now_playing = &snapshot.now_playing;
+            line.tag("state", &now_playing.state.to_string())
+                .tag("source", now_playing.source.as_str())
+                .tag("group_role", &snapshot.group_role.to_string())
+                .bool("reachable", true)
+                .int("volume", Some(now_playing.volume.get().into()))
+                .bool("muted", now_playing.is_muted)
+                .int(
+                    "position_ms",
+                    i64::try_from(now_playing.position.as_millis()).ok(),
+                )
+                .int(
+                    "duration_ms",
+                    i64::try_from(now_playing.duration.as_millis()).ok(),
+                )
+                .string("title", now_playing.title.as_deref())
+                .string("artist", now_playing.artist.as_deref())
+                .string("album", now_playing.album.as_deref())
+        }
+        Err(_) => line.bool("reachable", false),
+    };
+    line.render()
+        .expect("playback lines always have a reachable field")
+}
+
+/// `wiim_network`: Wi-Fi signal, noise, frequency and data rate, if the device reports any
+pub fn network(device: &str, ip: &str, status: &StatusEx) -> Option<String> {
+    Line::new("wiim_network", device, ip)
+        .int("rssi_dbm", number(status.rssi.as_ref()))
+        .int("snr_db", number(status.wlan_snr.as_ref()))
+        .int("noise_dbm", number(status.wlan_noise.as_ref()))
+        .int("frequency_mhz", number(status.wlan_freq.as_ref()))
+        .int("data_rate_mbps", number(status.wlan_data_rate.as_ref()))
+        .int("channel", number(status.wifi_channel.as_ref()))
+        .bool("internet", status.internet.as_deref() == Some("1"))
+        .render()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use wiim_api::{GroupRole, NowPlaying, PlayState, PlaybackSource, Volume, WiimError};
+
+    #[test]
+    fn test_playback_line() {
+        let snapshot = DeviceSnapshot {
+            now_playing: NowPlaying {
+                title: Some("Say \"hi\"".to_string()),
+                artist: Some("Nils Frahm".to_string()),
+                state: PlayState::Playing,
+                volume: Volume::saturating(35),
+                position: Duration::from_millis(61_500),
+                duration: Duration::from_secs(240),
+                source: PlaybackSource::Spotify,
+                ..Default::default()
+            },
+            group_role: GroupRole::Leader,
+        };
+        assert_eq!(
+            playback("living room", "192.168.1.100", &Ok(snapshot)),
+            "wiim_playback,device=living\\ room,ip=192.168.1.100,state=playing,source=spotify,\
+             group_role=leader reachable=true,volume=35i,muted=false,position_ms=61500i,\
+             duration_ms=240000i,title=\"Say \\\"hi\\\"\",artist=\"Nils Frahm\""
+        );
+        assert_eq!(
+            playback(
+                "office",
+                "192.168.1.101",
+                &Err(WiimError::Timeout(Duration::from_secs(3)))
+            ),
+            "wiim_playback,device=office,ip=192.168.1.101 reachable=false"
+        );
+    }
+
+    #[test]
+    fn test_network_line() {
+        let status: StatusEx =
+            serde_json::from_str(include_str!("../../fixtures/status_ex_wiim_mini.json")).unwrap();
+        let line = network("kitchen", "192.168.1.101", &status).unwrap();
+        assert!(line.starts_with("wiim_network,device=kitchen,ip=192.168.1.101 rssi_dbm="));
+        assert!(!line.contains('"'));
+    }
+
+    #[test]
+    fn test_escaping() {
+        assert_eq!(escape_tag("a,b=c d"), "a\\,b\\=c\\ d");
+        assert_eq!(
+            quote("back\\slash \"quoted\""),
+            "\"back\\\\slash \\\"quoted\\\"\""
+        );
+    }
+}
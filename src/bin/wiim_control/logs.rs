@@ -0,0 +1,111 @@
+//! `wiim-control logs`: save the device's system log for bug reports
+//!
+//! The log is streamed to the file as it arrives, with a running byte count on stderr
+//! when that is a terminal.
+
+use crate::i18n;
+use crate::output::CommandOutput;
+use serde_json::json;
+use std::error::Error;
+use std::io::{IsTerminal, Write};
+use std::path::PathBuf;
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+use wiim_api::WiimClient;
+
+/// `wiim-<device name>-<time>.log` in the current directory
+fn default_path(device_name: &str) -> PathBuf {
+    let name: String = device_name
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '-' })
+        .collect();
+    let name = if name.is_empty() { "device" } else { &name };
+    let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+    PathBuf::from(format!("wiim-{name}-{stamp}.log"))
+}
+
+fn format_size(bytes: u64) -> String {
+    match bytes {
+        0..1_024 => format!("{bytes} B"),
+        1_024..1_048_576 => format!("{:.0} KB", bytes as f64 / 1_024.0),
+        _ => format!("{:.1} MB", bytes as f64 / 1_048_576.0),
+    }
+}
+
+fn progress_text(received: u64, total: Option<u64>) -> String {
+    match total.filter(|total| *total > 0) {
+        Some(total) => format!(
+            "{} / {} ({}%)",
+            format_size(received),
+            format_size(total),
+            (received.min(total) * 100 / total)
+        ),
+        None => format_size(received),
+    }
+}
+
+pub async fn run(
+    client: &WiimClient,
+    output: Option<PathBuf>,
+    json: bool,
+) -> Result<CommandOutput, Box<dyn Error>> {
+    let path = match output {
+        Some(path) => path,
+        None => default_path(&client.get_device_name().await?.unwrap_or_default()),
+    };
+    let show_progress = !json && std::io::stderr().is_terminal();
+
+    let mut download = client.download_syslog().await?;
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).await?;
+    }
+    let mut file = fs::File::create(&path).await?;
+    while let Some(chunk) = download.next_chunk().await? {
+        file.write_all(&chunk).await?;
+        if show_progress {
+            let progress = progress_text(download.received_bytes(), download.total_bytes());
+            eprint!(
+                "\r\x1b[2K{}",
+                i18n::tr("logs.downloading", &[("progress", &progress)])
+            );
+            let _ = std::io::stderr().flush();
+        }
+    }
+    file.flush().await?;
+    if show_progress {
+        eprintln!();
+    }
+
+    let bytes = download.received_bytes();
+    Ok(CommandOutput::message(i18n::tr(
+        "logs.saved",
+        &[("size", &format_size(bytes)), ("path", &path.display())],
+    ))
+    .with_state(json!({ "path": path, "bytes": bytes })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress_text() {
+        assert_eq!(progress_text(512, None), "512 B");
+        assert_eq!(
+            progress_text(300 * 1_024, Some(3 * 1_048_576)),
+            "300 KB / 3.0 MB (9%)"
+        );
+        assert_eq!(progress_text(10, Some(0)), "10 B");
+    }
+
+    #[test]
+    fn test_default_path() {
+        let path = default_path("Living Room").display().to_string();
+        assert!(path.starts_with("wiim-Living-Room-"));
+        assert!(path.ends_with(".log"));
+        assert!(default_path("")
+            .display()
+            .to_string()
+            .starts_with("wiim-device-"));
+    }
+}
@@ -0,0 +1,2881 @@
+mod benchmark;
+mod bt;
+mod config_cmd;
+mod daemon;
+mod doctor;
+mod error;
+mod fleet;
+mod follow;
+mod history;
+mod i18n;
+mod influx;
+#[cfg(feature = "logs")]
+mod logs;
+mod man;
+#[cfg(feature = "mqtt")]
+mod mqtt;
+mod network;
+mod offline;
+mod output;
+mod progress;
+mod reload;
+mod scene;
+#[cfg(feature = "schedule")]
+mod schedule;
+mod scrobble;
+#[cfg(feature = "serve")]
+mod serve;
+mod signal;
+mod single_flight;
+mod statsd;
+mod templates;
+mod terminal_image;
+mod theme;
+#[cfg(feature = "tui")]
+mod ui;
+mod upgrade;
+mod volume_link;
+mod wait;
+mod webhook;
+
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
+use error::CliError;
+use futures_util::StreamExt;
+use handlebars::Handlebars;
+use output::{CommandOutput, JsonResult};
+use serde::Serialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tokio::fs;
+use wiim_api::{
+    Alarm, AlarmAction, AlarmTime, PlayState, RepeatMode, Result as WiimResult, Volume, Weekdays,
+    WiimClient, ALARM_SLOTS,
+};
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+#[command(name = "wiim-control")]
+#[command(about = "Control and monitor WiiM audio streaming devices")]
+struct Cli {
+    /// WiiM device IP address or name from [devices] (overrides config file)
+    #[arg(short, long)]
+    device: Option<String>,
+
+    /// Run the command on every configured device (device_ip and [devices])
+    #[arg(long, conflicts_with = "device")]
+    all: bool,
+
+    /// Request timeout, e.g. 800ms or 60s (default: 10s, or `timeout` in config)
+    #[arg(long, value_parser = parse_duration_arg)]
+    timeout: Option<Duration>,
+
+    /// Retries after a connection failure or timeout (default: 0, or `retries` in config)
+    #[arg(long)]
+    retries: Option<u32>,
+
+    /// Read volume, mute, loop mode and input changes back and resend dropped commands
+    #[arg(long)]
+    verify: bool,
+
+    /// Write every device response to a cassette file for later --replay
+    #[cfg(feature = "cassette")]
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["all", "replay"])]
+    record: Option<PathBuf>,
+
+    /// Answer from a cassette file recorded with --record instead of the device
+    #[cfg(feature = "cassette")]
+    #[arg(long, value_name = "FILE", conflicts_with = "all")]
+    replay: Option<PathBuf>,
+
+    /// Template printed by status/watch when the device is unreachable, instead of failing
+    #[arg(long)]
+    offline_text: Option<String>,
+
+    /// Output format (legacy, use --profile instead)
+    #[arg(short, long)]
+    format: Option<OutputFormat>,
+
+    /// Output profile (waybar, polybar, custom)
+    #[arg(short, long)]
+    profile: Option<String>,
+
+    /// Template string override (requires --profile)
+    #[arg(short, long)]
+    template: Option<String>,
+
+    /// Config file path (default: ~/.config/wiim-control/config.toml)
+    #[arg(short, long)]
+    config: Option<PathBuf>,
+
+    /// Ignore the config file and use built-in defaults, for the fastest start in scripts
+    /// and status bars
+    #[arg(long, conflicts_with = "config")]
+    no_config: bool,
+
+    /// Print a JSON result ({action, ok, state, error}) on stdout for any command
+    #[arg(long, global = true)]
+    json: bool,
+
+    /// Answer status from a running `wiim-control daemon`, querying the device if it can't
+    #[arg(long, conflicts_with = "all")]
+    via_daemon: bool,
+
+    /// Unix socket of `wiim-control daemon` (default: $XDG_RUNTIME_DIR/wiim-control.sock)
+    #[arg(long, global = true)]
+    daemon_socket: Option<PathBuf>,
+
+    /// When to color terminal output (auto honours NO_COLOR and pipes)
+    #[arg(long, global = true, value_enum, default_value = "auto")]
+    color: theme::ColorChoice,
+
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Debug, Clone, clap::ValueEnum)]
+enum OutputFormat {
+    Text,
+    Json,
+}
+
+#[derive(Subcommand, Clone)]
+enum Commands {
+    /// Show current playback status and track info
+    Status {
+        /// Keep running and print a waybar JSON line on every change, advancing the
+        /// position each second (no waybar `interval` needed)
+        #[arg(long, conflicts_with = "get")]
+        follow_waybar: bool,
+        /// Print a single field (e.g. volume, artist, state, position_ms) with no template;
+        /// exits non-zero if the device doesn't report it
+        #[arg(long, value_name = "FIELD")]
+        get: Option<String>,
+    },
+    /// Play/resume playback
+    Play,
+    /// Pause playback
+    Pause,
+    /// Toggle play/pause
+    Toggle,
+    /// Stop playback
+    Stop,
+    /// Next track, or skip several (e.g. `next 3`)
+    Next {
+        #[arg(default_value = "1", value_parser = clap::value_parser!(u32).range(1..=50))]
+        count: u32,
+    },
+    /// Previous track, or go back several (e.g. `prev 2`)
+    Prev {
+        #[arg(default_value = "1", value_parser = clap::value_parser!(u32).range(1..=50))]
+        count: u32,
+    },
+    /// Set volume (0-100)
+    Volume { level: Volume },
+    /// Increase volume by step (default 5)
+    VolumeUp {
+        #[arg(default_value = "5")]
+        step: u8,
+    },
+    /// Decrease volume by step (default 5)
+    VolumeDown {
+        #[arg(default_value = "5")]
+        step: u8,
+    },
+    /// Mute audio
+    Mute,
+    /// Unmute audio
+    Unmute,
+    /// Show device name, model, firmware, network and WiFi details
+    Info,
+    /// Send a raw LinkPlay API command and print the response
+    Raw {
+        /// Command as passed to httpapi.asp (e.g. 'getShutdown')
+        command: String,
+    },
+    /// Play an audio stream or file URL
+    PlayUrl { url: String },
+    /// Play a local audio file, serving it to the device until playback ends
+    PlayFile { path: PathBuf },
+    /// Play a local directory's audio files as the queue, serving them until playback ends
+    Cast { dir: PathBuf },
+    /// Play an m3u playlist URL
+    PlayPlaylist {
+        url: String,
+        /// Track index to start from
+        #[arg(long, default_value = "0")]
+        index: u32,
+    },
+    /// Show the play queue position and local track list
+    Queue {
+        #[command(subcommand)]
+        action: Option<QueueAction>,
+    },
+    /// Show or set repeat mode
+    Repeat {
+        #[arg(value_enum)]
+        mode: Option<RepeatArg>,
+    },
+    /// Show or set shuffle
+    Shuffle {
+        #[arg(value_enum)]
+        state: Option<Toggle>,
+    },
+    /// Rename the device
+    Rename { name: String },
+    /// Show whether a firmware update is available and install it
+    Upgrade {
+        /// Only report the installed and available versions
+        #[arg(long)]
+        check: bool,
+    },
+    /// Download the device's system log for a bug report
+    #[cfg(feature = "logs")]
+    Logs {
+        /// Where to save the log (default: wiim-<device name>-<time>.log)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Reboot the device
+    Reboot {
+        /// Wait until the device answers again and report the downtime
+        #[arg(long)]
+        wait: bool,
+    },
+    /// Stop playback after a delay: <minutes> (up to 1440), off, or status
+    SleepTimer {
+        #[arg(default_value = "status", value_parser = parse_sleep_timer_arg)]
+        action: SleepTimerArg,
+    },
+    /// Manage alarms
+    Alarm {
+        #[command(subcommand)]
+        action: AlarmCommand,
+    },
+    /// Save or restore named volume, mute, input, EQ and loop mode setups
+    Scene {
+        #[command(subcommand)]
+        action: scene::SceneCommand,
+    },
+    /// Download the current cover art and print its path
+    Art {
+        /// Where to save the image (default: ~/.cache/wiim-control/cover.<ext>)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Display the cover inline (kitty, iTerm2 or sixel terminals)
+        #[arg(long)]
+        show: bool,
+    },
+    /// Keep running and print a status line whenever track, state or volume changes
+    Watch {
+        /// Poll interval (e.g. 2s, 500ms, 1m)
+        #[arg(long, default_value = "2s", value_parser = parse_duration_arg)]
+        interval: Duration,
+        /// Signal a status bar on every change, e.g. waybar:RTMIN+8 or /path/to/bar.pid:RTMIN+2
+        #[arg(long, value_parser = signal::parse_signal_spec)]
+        signal: Option<signal::SignalSpec>,
+    },
+    /// Block until the device reaches a state or the track changes, e.g.
+    /// `wait-for --state playing --timeout 30s`
+    #[command(group(clap::ArgGroup::new("condition").required(true).multiple(true)))]
+    WaitFor {
+        /// Wait until playback is in this state
+        #[arg(long, value_enum, group = "condition")]
+        state: Option<StateArg>,
+        /// Wait until a different track is playing
+        #[arg(long, group = "condition")]
+        track_change: bool,
+        /// Give up after this long (exit code 6), e.g. 30s or 5m (default: wait forever)
+        #[arg(long, value_parser = parse_duration_arg)]
+        timeout: Option<Duration>,
+        /// Poll interval (e.g. 1s, 500ms)
+        #[arg(long, default_value = "1s", value_parser = parse_duration_arg)]
+        interval: Duration,
+    },
+    /// Scan for, pair with and switch Bluetooth headphones and speakers
+    Bt {
+        #[command(subcommand)]
+        action: bt::BtCommand,
+    },
+    /// Show WiFi signal, SNR, noise and data rate
+    Network {
+        /// Keep sampling with a signal sparkline and range until Ctrl-C, then summarize
+        #[arg(long)]
+        watch: bool,
+        /// Sample interval with --watch (e.g. 1s, 500ms)
+        #[arg(long, default_value = "1s", value_parser = parse_duration_arg)]
+        interval: Duration,
+    },
+    /// Time each endpoint (p50/p95) over HTTPS and HTTP and suggest a poll interval
+    Benchmark {
+        /// Requests per endpoint and transport
+        #[arg(short = 'n', long, default_value = "20")]
+        requests: usize,
+    },
+    /// Full-screen dashboard with now playing, volume, group and WiFi, driven by the keyboard
+    #[cfg(feature = "tui")]
+    Ui,
+    /// Check config, connectivity, firmware, latency and WiFi, with hints for fixing problems
+    Doctor,
+    /// Create, show, validate or edit the config file
+    Config {
+        #[command(subcommand)]
+        action: config_cmd::ConfigCommand,
+    },
+    /// Bridge every configured device to MQTT, with Home Assistant discovery
+    #[cfg(feature = "mqtt")]
+    Mqtt,
+    /// Serve a REST API over every configured device
+    #[cfg(feature = "serve")]
+    Serve {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        listen: std::net::SocketAddr,
+
+        /// How often devices are polled for /ws updates (e.g. 2s, 500ms)
+        #[arg(long, default_value = "2s", value_parser = parse_duration_arg)]
+        interval: Duration,
+    },
+    /// Poll every configured device in the background and answer --via-daemon over a unix socket
+    Daemon {
+        /// How often devices are polled (e.g. 2s, 500ms)
+        #[arg(long, default_value = "2s", value_parser = parse_duration_arg)]
+        interval: Duration,
+        /// How old a status may be before a query starts a refresh in the background
+        /// (default and minimum: --interval); queries never wait for it
+        #[arg(long, value_parser = parse_duration_arg)]
+        fresh_for: Option<Duration>,
+    },
+    /// Show recently played tracks recorded by watch and daemon
+    History {
+        /// How many entries to show
+        #[arg(short = 'n', long, default_value = "20")]
+        limit: usize,
+        /// Only show tracks played on this device (as recorded)
+        #[arg(long)]
+        from: Option<String>,
+    },
+    /// Overview of every configured device at once
+    Fleet {
+        #[command(subcommand)]
+        action: fleet::FleetCommand,
+    },
+    /// Generate man pages for wiim-control and all subcommands
+    #[command(hide = true)]
+    Mangen {
+        /// Directory to write the pages to
+        #[arg(default_value = "man")]
+        out_dir: PathBuf,
+    },
+}
+
+/// Parse a human-friendly duration such as `500ms`, `2s`, `5m` or a bare number of seconds
+fn parse_duration_arg(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("invalid duration '{value}' (expected e.g. 500ms, 2s, 5m)"))?;
+    let seconds = match unit {
+        "ms" => number / 1000.0,
+        "" | "s" => number,
+        "m" => number * 60.0,
+        "h" => number * 3600.0,
+        _ => {
+            return Err(format!(
+                "invalid duration unit '{unit}' (use ms, s, m or h)"
+            ))
+        }
+    };
+    // Zero would make poll loops spin without pausing
+    match Duration::try_from_secs_f64(seconds) {
+        Ok(duration) if duration.is_zero() => {
+            Err(format!("duration '{value}' must be greater than zero"))
+        }
+        Ok(duration) => Ok(duration),
+        Err(_) => Err(format!("duration '{value}' is out of range")),
+    }
+}
+
+#[derive(Subcommand, Clone)]
+enum AlarmCommand {
+    /// List configured alarms
+    List,
+    /// Create or replace an alarm, e.g. `alarm set 07:30 weekdays --preset 3`
+    Set {
+        /// Time of day (HH:MM, device local time)
+        #[arg(value_parser = parse_alarm_time)]
+        time: AlarmTime,
+        /// daily, weekdays, weekends, or a list like mon,wed,fri
+        #[arg(default_value = "daily", value_parser = parse_alarm_days)]
+        days: Weekdays,
+        /// Alarm slot (0-2, default: first free slot)
+        #[arg(long)]
+        slot: Option<u8>,
+        /// Play a preset when the alarm fires
+        #[arg(long, conflicts_with_all = ["url", "stop"])]
+        preset: Option<u8>,
+        /// Play a stream URL when the alarm fires
+        #[arg(long, conflicts_with = "stop")]
+        url: Option<String>,
+        /// Stop playback instead of playing something
+        #[arg(long)]
+        stop: bool,
+    },
+    /// Delete the alarm in a slot
+    Delete { slot: u8 },
+    /// Silence a ringing alarm
+    Stop,
+}
+
+fn parse_alarm_time(value: &str) -> Result<AlarmTime, String> {
+    value
+        .parse()
+        .map_err(|_| format!("expected HH:MM, got '{value}'"))
+}
+
+fn parse_alarm_days(value: &str) -> Result<Weekdays, String> {
+    match value.to_ascii_lowercase().as_str() {
+        "daily" | "everyday" => Ok(Weekdays::ALL),
+        "weekdays" => Ok(Weekdays::WORKDAYS),
+        "weekends" => Ok(Weekdays::WEEKEND),
+        list => list.split(',').try_fold(Weekdays::default(), |days, name| {
+            Weekdays::from_day_name(name.trim())
+                .map(|day| days.union(day))
+                .ok_or_else(|| format!("unknown day '{name}'"))
+        }),
+    }
+}
+
+/// Parse a config day list such as `["mon", "wed"]`, each item as for `alarm set`
+fn parse_day_list(items: &[String]) -> Result<Weekdays, String> {
+    if items.is_empty() {
+        return Err("no days given".to_string());
+    }
+    items.iter().try_fold(Weekdays::default(), |days, item| {
+        parse_alarm_days(item).map(|item| days.union(item))
+    })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum SleepTimerArg {
+    Minutes(u64),
+    Off,
+    Status,
+}
+
+/// Longest sleep timer accepted, a day
+const MAX_SLEEP_MINUTES: u64 = 24 * 60;
+
+fn parse_sleep_timer_arg(value: &str) -> Result<SleepTimerArg, String> {
+    match value {
+        "off" | "cancel" => Ok(SleepTimerArg::Off),
+        "status" => Ok(SleepTimerArg::Status),
+        minutes => match minutes.parse::<u64>() {
+            Ok(0) => Ok(SleepTimerArg::Off),
+            Ok(minutes) if minutes <= MAX_SLEEP_MINUTES => Ok(SleepTimerArg::Minutes(minutes)),
+            Ok(_) => Err(format!(
+                "sleep timer can be at most {MAX_SLEEP_MINUTES} minutes, got '{value}'"
+            )),
+            Err(_) => Err(format!(
+                "expected a number of minutes, 'off' or 'status', got '{value}'"
+            )),
+        },
+    }
+}
+
+#[derive(Subcommand, Clone)]
+enum QueueAction {
+    /// Jump to a track in the queue (1-based)
+    Jump { index: u32 },
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum RepeatArg {
+    Off,
+    One,
+    All,
+}
+
+impl From<RepeatArg> for RepeatMode {
+    fn from(arg: RepeatArg) -> Self {
+        match arg {
+            RepeatArg::Off => RepeatMode::Off,
+            RepeatArg::One => RepeatMode::One,
+            RepeatArg::All => RepeatMode::All,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum StateArg {
+    Playing,
+    Paused,
+    Stopped,
+    Loading,
+}
+
+impl From<StateArg> for PlayState {
+    fn from(arg: StateArg) -> Self {
+        match arg {
+            StateArg::Playing => PlayState::Playing,
+            StateArg::Paused => PlayState::Paused,
+            StateArg::Stopped => PlayState::Stopped,
+            StateArg::Loading => PlayState::Loading,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum Toggle {
+    On,
+    Off,
+}
+
+#[derive(Serialize)]
+struct StatusOutput {
+    text: String,
+    alt: String,
+    tooltip: String,
+    class: String,
+    percentage: Option<u8>,
+}
+
+#[derive(Serialize)]
+struct InfoOutput {
+    name: Option<String>,
+    model: Option<String>,
+    firmware: Option<String>,
+    release: Option<String>,
+    ip_address: Option<String>,
+    mac: Option<String>,
+    uuid: Option<String>,
+    device_time: Option<String>,
+    timezone: Option<String>,
+    internet: bool,
+    wifi_network: Option<String>,
+    rssi_dbm: Option<i32>,
+    signal_quality: Option<String>,
+    snr: Option<String>,
+    noise: Option<String>,
+    frequency: Option<String>,
+    data_rate_mbps: Option<u32>,
+}
+
+#[derive(Serialize)]
+struct TemplateContext {
+    // Track Information
+    artist: Option<String>,
+    title: Option<String>,
+    album: Option<String>,
+    album_art_uri: Option<String>,
+
+    // Playback State
+    state: String,
+    /// `state` in the user's language
+    state_label: String,
+    volume: u8,
+    muted: bool,
+    position: String,
+    duration: String,
+    position_ms: u64,
+    duration_ms: u64,
+    /// The position drawn as a bar, empty for streams
+    progress_bar: String,
+    /// Radio or an input with no track length
+    live: bool,
+    repeat: String,
+    shuffle: bool,
+    source: String,
+
+    // Device
+    device_name: Option<String>,
+    group_name: Option<String>,
+
+    // Audio Quality
+    sample_rate: Option<String>,
+    bit_depth: Option<String>,
+    sample_rate_khz: Option<String>,
+    bit_depth_bit: Option<String>,
+    quality_info: Option<String>,
+
+    // Formatted Combinations
+    track_info: String,
+    full_info: String,
+}
+
+#[derive(serde::Deserialize)]
+struct Config {
+    device_ip: String,
+    /// Named devices, usable in place of an IP with --device
+    devices: Option<HashMap<String, String>>,
+    /// Request timeout such as "2s", overridden by --timeout
+    timeout: Option<String>,
+    /// Retries after connection failures, overridden by --retries
+    retries: Option<u32>,
+    output: Option<OutputConfig>,
+    #[allow(dead_code)]
+    profiles: Option<HashMap<String, ProfileConfig>>,
+    /// Reusable template fragments, included with `{{> name}}`
+    partials: Option<HashMap<String, String>>,
+    /// Template-defined helpers, called as `{{name value}}`
+    helpers: Option<HashMap<String, String>>,
+    /// Broker settings for `wiim-control mqtt`
+    #[cfg(feature = "mqtt")]
+    mqtt: Option<mqtt::MqttConfig>,
+    /// ListenBrainz / Last.fm credentials for scrobbling from watch and daemon
+    scrobble: Option<scrobble::ScrobbleConfig>,
+    /// Webhooks fired from watch and daemon, by name
+    webhooks: Option<HashMap<String, webhook::WebhookConfig>>,
+    /// Listening history recorded by watch and daemon
+    history: Option<history::HistoryConfig>,
+    /// Timed rules run by the daemon, by name
+    #[cfg(feature = "schedule")]
+    schedule: Option<HashMap<String, schedule::ScheduleConfig>>,
+    /// Devices whose volumes the daemon keeps together, by name
+    volume_link: Option<HashMap<String, volume_link::VolumeLinkConfig>>,
+    /// StatsD server the daemon sends metrics to
+    statsd: Option<statsd::StatsdConfig>,
+    /// Language for messages such as "de" or "es_ES"; defaults to LANG
+    locale: Option<String>,
+    /// Terminal colors by role, used unless --color=never
+    theme: Option<theme::ThemeConfig>,
+    /// Width and characters of `{{progress_bar}}`
+    progress_bar: Option<progress::ProgressBarConfig>,
+}
+
+#[derive(serde::Deserialize)]
+struct OutputConfig {
+    text: Option<TextTemplates>,
+    json: Option<JsonTemplates>,
+}
+
+#[derive(serde::Deserialize)]
+struct TextTemplates {
+    playing: Option<String>,
+    paused: Option<String>,
+    stopped: Option<String>,
+    loading: Option<String>,
+    offline: Option<String>,
+}
+
+#[derive(serde::Deserialize)]
+struct JsonTemplates {
+    text: Option<String>,
+    alt: Option<String>,
+    tooltip: Option<String>,
+    class: Option<String>,
+    #[allow(dead_code)]
+    percentage: Option<String>,
+}
+
+#[derive(serde::Deserialize, Clone)]
+#[allow(dead_code)]
+struct ProfileConfig {
+    format: Option<String>,
+    text_template: Option<String>,
+    json_template: Option<String>,
+    /// Device IP or name from [devices] to use when --device isn't given
+    device: Option<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            device_ip: "192.168.1.100".to_string(),
+            devices: None,
+            timeout: None,
+            retries: None,
+            output: None,
+            profiles: None,
+            partials: None,
+            helpers: None,
+            #[cfg(feature = "mqtt")]
+            mqtt: None,
+            scrobble: None,
+            webhooks: None,
+            history: None,
+            #[cfg(feature = "schedule")]
+            schedule: None,
+            volume_link: None,
+            statsd: None,
+            locale: None,
+            theme: None,
+            progress_bar: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ResolvedProfile {
+    format: OutputFormat,
+    text_template: Option<String>,
+    json_templates: Option<JsonTemplatesResolved>,
+    /// Rendered instead of an error when the device is unreachable
+    offline_template: Option<String>,
+}
+
+impl From<&wiim_api::NowPlaying> for TemplateContext {
+    fn from(now_playing: &wiim_api::NowPlaying) -> Self {
+        // Helper function to format a time as minutes and seconds
+        fn format_time(time: Duration) -> String {
+            let seconds = time.as_secs();
+            format!("{}:{:02}", seconds / 60, seconds % 60)
+        }
+
+        // Helper function to format sample rate
+        fn format_sample_rate_khz(sample_rate: &Option<String>) -> Option<String> {
+            sample_rate.as_ref().and_then(|sr| {
+                sr.parse::<f32>()
+                    .ok()
+                    .map(|rate| format!("{:.0}kHz", rate / 1000.0))
+            })
+        }
+
+        // Helper function to format bit depth
+        fn format_bit_depth_bit(bit_depth: &Option<String>) -> Option<String> {
+            bit_depth.as_ref().map(|bd| format!("{bd}bit"))
+        }
+
+        // Helper function to format quality info
+        fn format_quality_info(
+            sample_rate: &Option<String>,
+            bit_depth: &Option<String>,
+        ) -> Option<String> {
+            match (sample_rate, bit_depth) {
+                (Some(sr), Some(bd)) => {
+                    if let Ok(rate) = sr.parse::<f32>() {
+                        Some(format!("{:.0}kHz/{}bit", rate / 1000.0, bd))
+                    } else {
+                        None
+                    }
+                }
+                _ => None,
+            }
+        }
+
+        // Helper function to format track info (same logic as original)
+        fn format_track_info(now_playing: &wiim_api::NowPlaying) -> String {
+            match (&now_playing.artist, &now_playing.title) {
+                (Some(artist), Some(title)) => format!("{artist} - {title}"),
+                (Some(artist), None) => artist.clone(),
+                (None, Some(title)) => title.clone(),
+                (None, None) => {
+                    if let Some(album) = &now_playing.album {
+                        album.clone()
+                    } else {
+                        i18n::tr("status.no_track_info", &[])
+                    }
+                }
+            }
+        }
+
+        // Helper function to format full info (same logic as original tooltip)
+        fn format_full_info(now_playing: &wiim_api::NowPlaying) -> String {
+            let mut parts = Vec::new();
+
+            if let Some(title) = &now_playing.title {
+                parts.push(i18n::tr("status.title", &[("title", title)]));
+            }
+            if let Some(artist) = &now_playing.artist {
+                parts.push(i18n::tr("status.artist", &[("artist", artist)]));
+            }
+            if let Some(album) = &now_playing.album {
+                parts.push(i18n::tr("status.album", &[("album", album)]));
+            }
+
+            parts.push(i18n::tr(
+                "status.volume",
+                &[("volume", &now_playing.volume.get())],
+            ));
+
+            if now_playing.is_muted {
+                parts.push(i18n::tr("status.muted", &[]));
+            }
+
+            if let (Some(sample_rate), Some(bit_depth)) =
+                (&now_playing.sample_rate, &now_playing.bit_depth)
+            {
+                if let Ok(rate) = sample_rate.parse::<f32>() {
+                    let quality = format!("{:.0}kHz/{}bit", rate / 1000.0, bit_depth);
+                    parts.push(i18n::tr("status.quality", &[("quality", &quality)]));
+                }
+            }
+
+            // Format position/duration
+            if now_playing.is_live() {
+                parts.push(i18n::tr("status.live", &[]));
+            } else if !now_playing.duration.is_zero() {
+                let position = format_time(now_playing.position);
+                let duration = format_time(now_playing.duration);
+                parts.push(i18n::tr(
+                    "status.time",
+                    &[("position", &position), ("duration", &duration)],
+                ));
+            }
+
+            let loop_mode = &now_playing.loop_mode;
+            parts.push(i18n::tr(
+                "status.loop",
+                &[
+                    ("repeat", &loop_mode.repeat),
+                    ("shuffle", &i18n::on_off(loop_mode.shuffle)),
+                ],
+            ));
+
+            parts.join("\n")
+        }
+
+        let position = format_time(now_playing.position);
+        let duration = if now_playing.is_live() {
+            i18n::tr("common.live", &[])
+        } else {
+            format_time(now_playing.duration)
+        };
+        let sample_rate_khz = format_sample_rate_khz(&now_playing.sample_rate);
+        let bit_depth_bit = format_bit_depth_bit(&now_playing.bit_depth);
+        let quality_info = format_quality_info(&now_playing.sample_rate, &now_playing.bit_depth);
+        let track_info = format_track_info(now_playing);
+        let full_info = format_full_info(now_playing);
+
+        TemplateContext {
+            // Track Information
+            artist: now_playing.artist.clone(),
+            title: now_playing.title.clone(),
+            album: now_playing.album.clone(),
+            album_art_uri: now_playing.album_art_uri.clone(),
+
+            // Playback State
+            state: now_playing.state.to_string(),
+            state_label: i18n::tr(&format!("state.{}", now_playing.state), &[]),
+            volume: now_playing.volume.get(),
+            muted: now_playing.is_muted,
+            position,
+            duration,
+            position_ms: now_playing.position.as_millis() as u64,
+            duration_ms: now_playing.duration.as_millis() as u64,
+            progress_bar: progress::render(now_playing.position, now_playing.duration),
+            live: now_playing.is_live(),
+            repeat: now_playing.loop_mode.repeat.to_string(),
+            shuffle: now_playing.loop_mode.shuffle,
+            source: now_playing.source.to_string(),
+
+            // Device (filled in by with_device when known)
+            device_name: None,
+            group_name: None,
+
+            // Audio Quality
+            sample_rate: now_playing.sample_rate.clone(),
+            bit_depth: now_playing.bit_depth.clone(),
+            sample_rate_khz,
+            bit_depth_bit,
+            quality_info,
+
+            // Formatted Combinations
+            track_info,
+            full_info,
+        }
+    }
+}
+
+impl TemplateContext {
+    fn with_device(mut self, device: &DeviceNames) -> Self {
+        self.device_name = device.device_name.clone();
+        self.group_name = device.group_name.clone();
+        self
+    }
+}
+
+/// Device and multiroom group names for templates
+#[derive(Debug, Default, Clone, Serialize, serde::Deserialize)]
+struct DeviceNames {
+    device_name: Option<String>,
+    group_name: Option<String>,
+}
+
+impl DeviceNames {
+    /// Look up the names; they are cosmetic, so failures leave them empty
+    async fn fetch(client: &WiimClient) -> Self {
+        match client.get_status_ex().await {
+            Ok(status) => Self {
+                device_name: status.device_name,
+                group_name: status.group_name,
+            },
+            Err(_) => Self::default(),
+        }
+    }
+}
+
+fn validate_template(template: &str) -> Result<(), String> {
+    let mut handlebars = Handlebars::new();
+
+    // Check for common syntax mistakes first
+    if template.contains('{') {
+        // Check if there are any single braces (not part of double braces)
+        let mut chars = template.chars().peekable();
+        let mut has_single_braces = false;
+
+        while let Some(ch) = chars.next() {
+            if ch == '{' {
+                // Check if this is a single brace or part of double braces
+                match chars.peek() {
+                    Some('{') => {
+                        // This is a double brace, consume the next '{'
+                        chars.next();
+                    }
+                    _ => {
+                        // This is a single brace
+                        has_single_braces = true;
+                        break;
+                    }
+                }
+            }
+        }
+
+        if has_single_braces {
+            return Err("Invalid template syntax: found single braces. \
+                 Template variables must use double braces like {{variable}}. \
+                 Example: '{{artist}} - {{title}}'"
+                .to_string());
+        }
+    }
+
+    handlebars
+        .register_template_string("validation", template)
+        .map_err(|e| {
+            let error_msg = e.to_string();
+            if error_msg.contains("unclosed") || error_msg.contains("unexpected") {
+                format!(
+                    "Invalid template syntax: {error_msg}. \
+                     Make sure to use double braces like {{{{variable}}}}. \
+                     Example: '{{{{artist}}}} - {{{{title}}}}'"
+                )
+            } else {
+                format!("Invalid template syntax: {error_msg}")
+            }
+        })?;
+    Ok(())
+}
+
+/// Profiles that work without any configuration
+const BUILTIN_PROFILES: [&str; 3] = ["i3blocks", "polybar", "waybar"];
+
+/// Built-in profile definitions, matching the examples in the docs
+fn builtin_profile(name: &str) -> Option<ProfileConfig> {
+    let (format, text_template) = match name {
+        "waybar" => ("json", None),
+        "polybar" => ("text", Some("{{artist}} - {{title}} [{{quality_info}}]")),
+        "i3blocks" => ("text", Some("{{track_info}} | {{volume}}%")),
+        _ => return None,
+    };
+    Some(ProfileConfig {
+        format: Some(format.to_string()),
+        text_template: text_template.map(str::to_string),
+        json_template: None,
+        device: None,
+    })
+}
+
+/// Look up a profile, preferring the config file over the built-in definitions
+fn find_profile(config: &Config, name: &str) -> Option<ProfileConfig> {
+    config
+        .profiles
+        .as_ref()
+        .and_then(|profiles| profiles.get(name).cloned())
+        .or_else(|| builtin_profile(name))
+}
+
+/// The device to talk to: --device, then the profile's own device, then device_ip
+fn cli_device(cli: &Cli, config: &Config) -> String {
+    let profile_device = cli
+        .profile
+        .as_deref()
+        .and_then(|name| find_profile(config, name))
+        .and_then(|profile| profile.device);
+    resolve_device(cli.device.as_deref().or(profile_device.as_deref()), config)
+}
+
+fn resolve_profile(cli: &Cli, config: &Config) -> Result<ResolvedProfile, String> {
+    // 1. CLI --template argument (highest priority)
+    if let Some(template) = &cli.template {
+        // We already validated that --template requires --profile
+        let profile_name = cli.profile.as_ref().unwrap();
+
+        // Validate template syntax
+        if let Err(e) = validate_template(template) {
+            return Err(format!("Invalid template syntax: {e}"));
+        }
+
+        // For template override, we need to determine the output format
+        // Check if the profile exists (configured or built in), otherwise default to text
+        let format = match find_profile(config, profile_name)
+            .and_then(|profile_config| profile_config.format)
+            .as_deref()
+        {
+            Some("json") => OutputFormat::Json,
+            _ => OutputFormat::Text,
+        };
+
+        return Ok(ResolvedProfile {
+            format,
+            text_template: Some(template.clone()),
+            json_templates: None,
+            offline_template: None,
+        });
+    }
+
+    // 2. CLI --profile argument
+    if let Some(profile_name) = &cli.profile {
+        if let Some(profile_config) = find_profile(config, profile_name) {
+            let format = match profile_config.format.as_deref() {
+                Some("json") => OutputFormat::Json,
+                _ => OutputFormat::Text,
+            };
+
+            return Ok(ResolvedProfile {
+                format,
+                text_template: profile_config.text_template.clone(),
+                json_templates: profile_config.json_template.as_ref().map(|_| {
+                    // For now, we'll use the default JSON templates
+                    // This could be enhanced later to support JSON template overrides
+                    get_json_templates(config)
+                }),
+                offline_template: None,
+            });
+        } else {
+            let mut available_profiles: Vec<&str> = BUILTIN_PROFILES.to_vec();
+            if let Some(profiles) = &config.profiles {
+                available_profiles.extend(profiles.keys().map(|k| k.as_str()));
+            }
+            available_profiles.sort_unstable();
+            available_profiles.dedup();
+            let available_list = available_profiles.join(", ");
+            return Err(format!(
+                "Profile '{profile_name}' not found in configuration. Available profiles: {available_list}"
+            ));
+        }
+    }
+
+    // 3. CLI --format argument (legacy, maps to default profiles)
+    if let Some(format) = &cli.format {
+        return Ok(ResolvedProfile {
+            format: format.clone(),
+            text_template: None,
+            json_templates: None,
+            offline_template: None,
+        });
+    }
+
+    // 4. Config file default profile
+    // For now, we'll skip this as the config structure doesn't have a default profile field
+
+    // 5. Built-in default (backward compatibility)
+    Ok(ResolvedProfile {
+        format: OutputFormat::Text,
+        text_template: None,
+        json_templates: None,
+        offline_template: None,
+    })
+}
+
+#[tokio::main]
+async fn main() {
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let action = action_name(&matches);
+    let json = cli.json;
+
+    let result = run(cli).await;
+
+    let code = match result {
+        Ok(output) if json => {
+            let result = JsonResult::from_output(&action, output);
+            result.print();
+            if result.ok {
+                0
+            } else {
+                error::EXIT_FAILURE
+            }
+        }
+        Ok(output) => {
+            if let Some(message) = output.message {
+                eprintln!("{message}");
+            }
+            for line in output.lines {
+                println!("{line}");
+            }
+            match output.problem {
+                Some(problem) => {
+                    eprintln!("{} {problem}", i18n::tr("error.prefix", &[]));
+                    error::EXIT_FAILURE
+                }
+                None => 0,
+            }
+        }
+        Err(e) => {
+            if json {
+                JsonResult::error(&action, &e).print();
+            } else {
+                eprintln!("{} {e}", i18n::tr("error.prefix", &[]));
+            }
+            error::exit_code(e.as_ref())
+        }
+    };
+
+    std::process::exit(code);
+}
+
+/// Subcommand path as typed, e.g. `volume` or `alarm set`
+fn action_name(matches: &clap::ArgMatches) -> String {
+    let mut names = Vec::new();
+    let mut current = matches.subcommand();
+    while let Some((name, sub_matches)) = current {
+        names.push(name);
+        current = sub_matches.subcommand();
+    }
+    names.join(" ")
+}
+
+async fn run(cli: Cli) -> Result<CommandOutput, Box<dyn std::error::Error>> {
+    // Validate that --template requires --profile
+    if cli.template.is_some() && cli.profile.is_none() {
+        return Err(
+            CliError::Usage("--template requires --profile to be specified".to_string()).into(),
+        );
+    }
+
+    // Doctor and config commands report config problems themselves instead of failing to load
+    match cli.command {
+        Commands::Doctor => return doctor::run(&cli).await,
+        Commands::Config { action } => {
+            return config_cmd::run(action, &cli.config, cli.device.as_deref()).await
+        }
+        Commands::Mangen { out_dir } => {
+            let pages = man::generate(Cli::command(), &out_dir)?;
+            let lines = pages.iter().map(|p| p.display().to_string()).collect();
+            return Ok(CommandOutput::lines(lines).with_state(json!({ "pages": pages })));
+        }
+        _ => {}
+    }
+
+    // Load configuration, unless the command can't use it
+    let config = if skips_config(&cli) {
+        Config::default()
+    } else {
+        load_config(&cli.config).await?
+    };
+    templates::init(config.partials.as_ref(), config.helpers.as_ref()).map_err(CliError::Config)?;
+    i18n::init(config.locale.as_deref()).map_err(CliError::Config)?;
+    theme::init(cli.color, config.theme.as_ref()).map_err(CliError::Config)?;
+    progress::init(config.progress_bar.as_ref());
+
+    // Fleet commands always cover every configured device
+    if let Commands::Fleet { action } = &cli.command {
+        return fleet::run(action, &cli, &config).await;
+    }
+    if let Commands::History { limit, from } = &cli.command {
+        return history::run(&config, from.as_deref(), *limit);
+    }
+    if let Commands::Daemon {
+        interval,
+        fresh_for,
+    } = &cli.command
+    {
+        let socket = cli
+            .daemon_socket
+            .clone()
+            .or_else(daemon::default_socket)
+            .ok_or_else(|| {
+                CliError::Usage(
+                    "no per-user runtime directory (XDG_RUNTIME_DIR) for the daemon socket; \
+                     pass --daemon-socket with a path only you can reach"
+                        .to_string(),
+                )
+            })?;
+        return daemon::run(&socket, *interval, *fresh_for, &cli, &config).await;
+    }
+    #[cfg(feature = "mqtt")]
+    if let Commands::Mqtt = &cli.command {
+        return mqtt::run(&cli, &config).await;
+    }
+    #[cfg(feature = "serve")]
+    if let Commands::Serve { listen, interval } = &cli.command {
+        return serve::run(*listen, *interval, &cli, &config).await;
+    }
+
+    // Resolve profile configuration
+    let mut resolved_profile = resolve_profile(&cli, &config)
+        .map_err(|e| CliError::Config(format!("Profile resolution error: {e}")))?;
+    resolved_profile.offline_template = cli.offline_text.clone().or_else(|| {
+        config
+            .output
+            .as_ref()
+            .and_then(|output| output.text.as_ref())
+            .and_then(|text| text.offline.clone())
+    });
+    if let Some(template) = &resolved_profile.offline_template {
+        validate_template(template)
+            .map_err(|e| CliError::Usage(format!("Invalid offline template: {e}")))?;
+    }
+
+    if cli.all {
+        return run_all(&cli, &resolved_profile, &config).await;
+    }
+
+    // Get device IP from CLI arg, profile or config
+    let device_ip = cli_device(&cli, &config);
+
+    // Without a socket there is no daemon to ask, so the device is queried directly
+    let socket = cli
+        .via_daemon
+        .then(|| cli.daemon_socket.clone().or_else(daemon::default_socket))
+        .flatten();
+    if let Some(socket) = socket {
+        let answered = daemon::status(
+            &cli.command,
+            &socket,
+            &device_ip,
+            &resolved_profile,
+            &config,
+        )
+        .await?;
+        if let Some(output) = answered {
+            return Ok(output);
+        }
+    }
+
+    // Create client
+    let client = build_client(&device_ip, &cli, &config)?;
+
+    execute(&client, cli.command, &resolved_profile, &config, cli.json).await
+}
+
+/// Run a command on every configured device concurrently and report each outcome
+async fn run_all(
+    cli: &Cli,
+    resolved_profile: &ResolvedProfile,
+    config: &Config,
+) -> Result<CommandOutput, Box<dyn std::error::Error>> {
+    if matches!(
+        cli.command,
+        Commands::Watch { .. }
+            | Commands::WaitFor { .. }
+            | Commands::Art { .. }
+            | Commands::Network { watch: true, .. }
+            | Commands::Scene {
+                action: scene::SceneCommand::Save { .. }
+            }
+            | Commands::Status {
+                follow_waybar: true,
+                ..
+            }
+    ) {
+        return Err(CliError::Usage(
+            "watch, wait-for, art, network --watch, scene save and status --follow-waybar cannot be used with --all"
+                .to_string(),
+        )
+        .into());
+    }
+    #[cfg(feature = "logs")]
+    if let Commands::Logs { output: Some(_) } = cli.command {
+        return Err(CliError::Usage("logs --output cannot be used with --all".to_string()).into());
+    }
+    #[cfg(feature = "tui")]
+    if let Commands::Ui = cli.command {
+        return Err(CliError::Usage("ui cannot be used with --all".to_string()).into());
+    }
+
+    let devices = configured_devices(config);
+    let clients = devices
+        .iter()
+        .map(|(_, ip)| build_client(ip, cli, config))
+        .collect::<Result<Vec<_>, _>>()?;
+    let results = futures_util::future::join_all(clients.iter().map(|client| {
+        execute(
+            client,
+            cli.command.clone(),
+            resolved_profile,
+            config,
+            cli.json,
+        )
+    }))
+    .await;
+
+    let mut output = CommandOutput::default();
+    let mut messages = Vec::new();
+    let mut states = Vec::new();
+    let mut failed = 0;
+    for ((name, ip), result) in devices.iter().zip(results) {
+        match result {
+            Ok(device_output) => {
+                messages.push(format!(
+                    "{name}: {}",
+                    device_output.message.as_deref().unwrap_or("✅ Done")
+                ));
+                output.lines.extend(
+                    device_output
+                        .lines
+                        .iter()
+                        .map(|line| format!("{name}: {line}")),
+                );
+                states.push(json!({
+                    "device": name,
+                    "ip": ip,
+                    "ok": true,
+                    "state": device_output.state,
+                    "error": null,
+                }));
+            }
+            Err(e) => {
+                failed += 1;
+                messages.push(format!("{name}: ❌ {e}"));
+                states.push(json!({
+                    "device": name,
+                    "ip": ip,
+                    "ok": false,
+                    "state": null,
+                    "error": e.to_string(),
+                }));
+            }
+        }
+    }
+
+    output.message = Some(messages.join("\n"));
+    if failed > 0 {
+        output.problem = Some(format!("{failed} of {} devices failed", devices.len()));
+    }
+    Ok(output.with_state(json!({ "devices": states })))
+}
+
+/// Run a device command against a single client
+async fn execute(
+    client: &WiimClient,
+    command: Commands,
+    resolved_profile: &ResolvedProfile,
+    config: &Config,
+    json: bool,
+) -> Result<CommandOutput, Box<dyn std::error::Error>> {
+    let output = match command {
+        Commands::Status {
+            follow_waybar: true,
+            ..
+        } => {
+            follow::run(client, resolved_profile, config).await?;
+            CommandOutput::default()
+        }
+        Commands::Status {
+            get: Some(field), ..
+        } => {
+            let (now_playing, device) =
+                tokio::join!(client.get_now_playing(), DeviceNames::fetch(client));
+            status_field_output(&now_playing?, &device, &field)?
+        }
+        Commands::Status { .. } => {
+            match tokio::join!(client.get_now_playing(), DeviceNames::fetch(client)) {
+                (Ok(now_playing), device) => {
+                    if resolved_profile.offline_template.is_some() {
+                        offline::save(client, &now_playing).await;
+                    }
+                    let line =
+                        render_terminal_status(&now_playing, &device, resolved_profile, config)?;
+                    CommandOutput::line(line).with_state(now_playing)
+                }
+                (Err(e), _) => match &resolved_profile.offline_template {
+                    Some(template) if offline::is_unreachable(&e) => {
+                        let cached = offline::load(client).await;
+                        offline::render(template, cached.as_ref(), &e, resolved_profile)?
+                    }
+                    _ => return Err(e.into()),
+                },
+            }
+        }
+        Commands::Play => {
+            client.resume().await?;
+            CommandOutput::message(i18n::tr("confirm.playing", &[]))
+                .with_state(json!({ "state": PlayState::Playing }))
+        }
+        Commands::Pause => {
+            client.pause().await?;
+            CommandOutput::message(i18n::tr("confirm.paused", &[]))
+                .with_state(json!({ "state": PlayState::Paused }))
+        }
+        Commands::Toggle => {
+            client.toggle_play_pause().await?;
+            CommandOutput::message(i18n::tr("confirm.toggled", &[]))
+        }
+        Commands::Stop => {
+            client.stop().await?;
+            CommandOutput::message(i18n::tr("confirm.stopped", &[]))
+                .with_state(json!({ "state": PlayState::Stopped }))
+        }
+        Commands::Next { count } => {
+            skip_tracks(client, count, true).await?;
+            let message = match count {
+                1 => i18n::tr("confirm.next_track", &[]),
+                n => i18n::tr("confirm.skipped", &[("count", &n)]),
+            };
+            CommandOutput::message(message).with_state(json!({ "skipped": count }))
+        }
+        Commands::Prev { count } => {
+            skip_tracks(client, count, false).await?;
+            let message = match count {
+                1 => i18n::tr("confirm.previous_track", &[]),
+                n => i18n::tr("confirm.went_back", &[("count", &n)]),
+            };
+            CommandOutput::message(message).with_state(json!({ "skipped": count }))
+        }
+        Commands::Volume { level } => {
+            client.set_volume(level).await?;
+            CommandOutput::message(i18n::tr("confirm.volume_set", &[("level", &level.get())]))
+                .with_state(json!({ "volume": level }))
+        }
+        Commands::VolumeUp { step } => {
+            let new_volume = client.volume_up(Some(step)).await?;
+            CommandOutput::message(i18n::tr(
+                "confirm.volume_up",
+                &[("level", &new_volume.get())],
+            ))
+            .with_state(json!({ "volume": new_volume }))
+        }
+        Commands::VolumeDown { step } => {
+            let new_volume = client.volume_down(Some(step)).await?;
+            CommandOutput::message(i18n::tr(
+                "confirm.volume_down",
+                &[("level", &new_volume.get())],
+            ))
+            .with_state(json!({ "volume": new_volume }))
+        }
+        Commands::Mute => {
+            client.mute().await?;
+            CommandOutput::message(i18n::tr("confirm.muted", &[]))
+                .with_state(json!({ "muted": true }))
+        }
+        Commands::Unmute => {
+            client.unmute().await?;
+            CommandOutput::message(i18n::tr("confirm.unmuted", &[]))
+                .with_state(json!({ "muted": false }))
+        }
+        Commands::Info => {
+            let json = matches!(resolved_profile.format, OutputFormat::Json);
+            handle_info(client, json).await?
+        }
+        Commands::Raw { command } => {
+            let response = client.send_raw_command(&command).await?;
+            let response = response.trim_end();
+            CommandOutput::line(response).with_state(json!({ "response": response }))
+        }
+        Commands::PlayUrl { url } => {
+            client.play_url(&url).await?;
+            CommandOutput::message(i18n::tr("confirm.playing_url", &[("url", &url)]))
+                .with_state(json!({ "url": url }))
+        }
+        Commands::PlayFile { path } => {
+            if !json {
+                eprintln!(
+                    "{}",
+                    i18n::tr("confirm.serving_file", &[("path", &path.display())])
+                );
+            }
+            client.play_file(&path).await?;
+            CommandOutput::message(i18n::tr(
+                "confirm.file_finished",
+                &[("path", &path.display())],
+            ))
+            .with_state(json!({ "path": path }))
+        }
+        Commands::Cast { dir } => {
+            if !json {
+                eprintln!(
+                    "{}",
+                    i18n::tr("confirm.casting", &[("dir", &dir.display())])
+                );
+            }
+            client.play_directory(&dir).await?;
+            CommandOutput::message(i18n::tr(
+                "confirm.cast_finished",
+                &[("dir", &dir.display())],
+            ))
+            .with_state(json!({ "dir": dir }))
+        }
+        Commands::PlayPlaylist { url, index } => {
+            client.play_playlist(&url, index).await?;
+            CommandOutput::message(i18n::tr("confirm.playing_playlist", &[("url", &url)]))
+                .with_state(json!({ "url": url, "index": index }))
+        }
+        Commands::Queue { action: None } => handle_queue(client).await?,
+        Commands::Queue {
+            action: Some(QueueAction::Jump { index }),
+        } => {
+            client.play_index(index).await?;
+            CommandOutput::message(i18n::tr("confirm.jumped", &[("index", &index)]))
+                .with_state(json!({ "current": index }))
+        }
+        Commands::Repeat { mode: None } => {
+            let repeat = client.get_loop_mode().await?.repeat;
+            CommandOutput::line(repeat.to_string()).with_state(json!({ "repeat": repeat }))
+        }
+        Commands::Repeat { mode: Some(mode) } => {
+            let repeat = RepeatMode::from(mode);
+            client.set_repeat(repeat).await?;
+            CommandOutput::message(i18n::tr("confirm.repeat", &[("repeat", &repeat)]))
+                .with_state(json!({ "repeat": repeat }))
+        }
+        Commands::Shuffle { state: None } => {
+            let shuffle = client.get_loop_mode().await?.shuffle;
+            CommandOutput::line(if shuffle { "on" } else { "off" })
+                .with_state(json!({ "shuffle": shuffle }))
+        }
+        Commands::Shuffle { state: Some(state) } => {
+            let shuffle = matches!(state, Toggle::On);
+            client.set_shuffle(shuffle).await?;
+            CommandOutput::message(i18n::tr(
+                "confirm.shuffle",
+                &[("shuffle", &i18n::on_off(shuffle))],
+            ))
+            .with_state(json!({ "shuffle": shuffle }))
+        }
+        Commands::Rename { name } => {
+            client.set_device_name(&name).await?;
+            let applied = client.get_device_name().await?.unwrap_or_default();
+            let message = if applied == name.trim() {
+                i18n::tr("confirm.renamed", &[("name", &applied)])
+            } else {
+                i18n::tr(
+                    "confirm.rename_mismatch",
+                    &[("requested", &name.trim()), ("name", &applied)],
+                )
+            };
+            CommandOutput::message(message).with_state(json!({ "name": applied }))
+        }
+        Commands::Upgrade { check } => upgrade::run(client, check, json).await?,
+        #[cfg(feature = "logs")]
+        Commands::Logs { output } => logs::run(client, output, json).await?,
+        Commands::Reboot { wait } => {
+            client.reboot().await?;
+            if wait {
+                if !json {
+                    eprintln!("{}", i18n::tr("confirm.rebooting_wait", &[]));
+                }
+                let message = handle_reboot_wait(client).await?;
+                CommandOutput::message(message).with_state(json!({ "online": true }))
+            } else {
+                CommandOutput::message(i18n::tr("confirm.rebooting", &[]))
+            }
+        }
+        Commands::SleepTimer { action } => match action {
+            SleepTimerArg::Minutes(minutes) => {
+                client
+                    .set_sleep_timer(Duration::from_secs(minutes * 60))
+                    .await?;
+                CommandOutput::message(i18n::tr("confirm.sleep_set", &[("minutes", &minutes)]))
+                    .with_state(json!({ "remaining_secs": minutes * 60 }))
+            }
+            SleepTimerArg::Off => {
+                client.cancel_sleep_timer().await?;
+                CommandOutput::message(i18n::tr("confirm.sleep_cancelled", &[]))
+                    .with_state(json!({ "remaining_secs": null }))
+            }
+            SleepTimerArg::Status => {
+                let remaining = client.get_sleep_timer().await?;
+                let line = remaining.map_or_else(|| "off".to_string(), format_remaining);
+                CommandOutput::line(line)
+                    .with_state(json!({ "remaining_secs": remaining.map(|r| r.as_secs()) }))
+            }
+        },
+        Commands::Alarm { action } => handle_alarm(client, action).await?,
+        Commands::Scene { action } => scene::run(client, action).await?,
+        Commands::Art { output, show } => {
+            let path = handle_art(client, output).await?;
+            let preview = match terminal_image::detect() {
+                Some(protocol) if show && !json => {
+                    let bytes = fs::read(&path).await?;
+                    terminal_image::render(&bytes, protocol).ok()
+                }
+                _ => None,
+            };
+            let line = preview.unwrap_or_else(|| path.display().to_string());
+            CommandOutput::line(line).with_state(json!({ "path": path }))
+        }
+        Commands::Watch { interval, signal } => {
+            let hooks = WatchHooks {
+                scrobbler: scrobble::Scrobbler::from_config(config)?,
+                notifier: webhook::Notifier::from_config(
+                    config.webhooks.as_ref(),
+                    client.get_ip_address(),
+                )
+                .map_err(CliError::Config)?,
+                history: history::Recorder::from_config(config, client.get_ip_address())
+                    .map_err(CliError::Config)?,
+            };
+            handle_watch(
+                client,
+                interval,
+                signal.as_ref(),
+                hooks,
+                resolved_profile,
+                config,
+                json,
+            )
+            .await?;
+            CommandOutput::default()
+        }
+        Commands::WaitFor {
+            state,
+            track_change,
+            timeout,
+            interval,
+        } => {
+            let condition = wait::Condition {
+                state: state.map(PlayState::from),
+                track_change,
+            };
+            wait::run(client, &condition, timeout, interval).await?
+        }
+        Commands::Benchmark { requests } => benchmark::run(client, requests, json).await?,
+        Commands::Bt { action } => bt::run(client, action, json).await?,
+        Commands::Network { watch, interval } => {
+            network::run(client, watch, interval, json).await?
+        }
+        Commands::Doctor
+        | Commands::Config { .. }
+        | Commands::Mangen { .. }
+        | Commands::Daemon { .. }
+        | Commands::History { .. }
+        | Commands::Fleet { .. } => {
+            unreachable!("handled before connecting to a device")
+        }
+        #[cfg(feature = "mqtt")]
+        Commands::Mqtt => unreachable!("handled before connecting to a device"),
+        #[cfg(feature = "serve")]
+        Commands::Serve { .. } => unreachable!("handled before connecting to a device"),
+        #[cfg(feature = "tui")]
+        Commands::Ui => ui::run(client).await?,
+    };
+
+    Ok(output)
+}
+
+/// Optional consumers of watch updates besides the printed output
+struct WatchHooks {
+    scrobbler: Option<scrobble::Scrobbler>,
+    notifier: Option<webhook::Notifier>,
+    history: Option<history::Recorder>,
+}
+
+async fn handle_watch(
+    client: &WiimClient,
+    interval: Duration,
+    signal: Option<&signal::SignalSpec>,
+    mut hooks: WatchHooks,
+    resolved_profile: &ResolvedProfile,
+    config: &Config,
+    json: bool,
+) -> WiimResult<()> {
+    let mut updates = std::pin::pin!(client.watch(interval));
+    let device = DeviceNames::fetch(client).await;
+    if let Some(name) = &device.device_name {
+        if let Some(notifier) = &mut hooks.notifier {
+            notifier.set_device(name);
+        }
+        if let Some(history) = &mut hooks.history {
+            history.set_device(name);
+        }
+    }
+    let mut online = None;
+    while let Some(update) = updates.next().await {
+        // Errors repeat on every poll, so only the transition to offline counts as a change
+        let now_online = update.is_ok();
+        if let Some(signal) = signal.filter(|_| now_online || online != Some(false)) {
+            if let Err(e) = signal.send() {
+                eprintln!("⚠️ {e}");
+            }
+        }
+        online = Some(now_online);
+        if let Some(scrobbler) = &mut hooks.scrobbler {
+            scrobbler.observe(update.as_ref().ok()).await;
+        }
+        if let Some(history) = &mut hooks.history {
+            history.observe(update.as_ref().ok());
+        }
+        if let Some(notifier) = &mut hooks.notifier {
+            let error = update.as_ref().err().map(|e| e.to_string());
+            notifier.observe(update.as_ref().ok(), error.as_deref());
+        }
+
+        match update {
+            Ok(now_playing) => {
+                if resolved_profile.offline_template.is_some() {
+                    offline::save(client, &now_playing).await;
+                }
+                if json {
+                    let state = serde_json::to_value(&now_playing)?;
+                    JsonResult::ok("watch", state).print();
+                } else {
+                    let line =
+                        render_terminal_status(&now_playing, &device, resolved_profile, config)?;
+                    println!("{line}");
+                }
+            }
+            Err(e) if json => JsonResult::error("watch", e).print(),
+            Err(e) => match &resolved_profile.offline_template {
+                Some(template) if offline::is_unreachable(&e) => {
+                    let cached = offline::load(client).await;
+                    let output = offline::render(template, cached.as_ref(), &e, resolved_profile)?;
+                    for line in output.lines {
+                        println!("{line}");
+                    }
+                }
+                _ => eprintln!("⚠️ {e}"),
+            },
+        }
+    }
+    Ok(())
+}
+
+/// Pause between skips; the device drops or reorders track changes sent back to back
+const SKIP_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Skip `count` tracks forwards or backwards, pacing the commands
+async fn skip_tracks(client: &WiimClient, count: u32, forward: bool) -> WiimResult<()> {
+    for i in 0..count {
+        if i > 0 {
+            tokio::time::sleep(SKIP_INTERVAL).await;
+        }
+        if forward {
+            client.next_track().await?;
+        } else {
+            client.previous_track().await?;
+        }
+    }
+    Ok(())
+}
+
+/// Look up a template variable as plain text for `status --get`; `None` if it has no value
+fn status_field(context: &TemplateContext, field: &str) -> Result<Option<String>, String> {
+    let serde_json::Value::Object(fields) = serde_json::to_value(context).unwrap_or_default()
+    else {
+        unreachable!("TemplateContext serializes to an object")
+    };
+    match fields.get(field) {
+        Some(serde_json::Value::Null) => Ok(None),
+        Some(serde_json::Value::String(value)) => Ok(Some(value.clone())),
+        Some(value) => Ok(Some(value.to_string())),
+        None => {
+            let mut names: Vec<&str> = fields.keys().map(String::as_str).collect();
+            names.sort_unstable();
+            Err(format!(
+                "Unknown field '{field}'. Available fields: {}",
+                names.join(", ")
+            ))
+        }
+    }
+}
+
+/// Output of `status --get FIELD`
+fn status_field_output(
+    now_playing: &wiim_api::NowPlaying,
+    device: &DeviceNames,
+    field: &str,
+) -> Result<CommandOutput, CliError> {
+    let context = TemplateContext::from(now_playing).with_device(device);
+    Ok(
+        match status_field(&context, field).map_err(CliError::Usage)? {
+            Some(value) => CommandOutput::line(&value).with_state(json!({ field: value })),
+            None => CommandOutput {
+                problem: Some(format!("{field} is not available")),
+                ..Default::default()
+            }
+            .with_state(json!({ field: null })),
+        },
+    )
+}
+
+/// Render now playing information using the resolved profile's text or JSON templates
+fn render_status(
+    now_playing: &wiim_api::NowPlaying,
+    device: &DeviceNames,
+    resolved_profile: &ResolvedProfile,
+    config: &Config,
+) -> WiimResult<String> {
+    let context = TemplateContext::from(now_playing).with_device(device);
+
+    match resolved_profile.format {
+        OutputFormat::Text => {
+            let template = if let Some(text_template) = &resolved_profile.text_template {
+                // Use the resolved template from profile or CLI override
+                text_template.clone()
+            } else {
+                // Fall back to the existing template resolution logic
+                get_text_template(config, &now_playing.state)
+            };
+            render_template(&template, &context)
+        }
+        OutputFormat::Json => {
+            let templates = if let Some(json_templates) = &resolved_profile.json_templates {
+                // Use the resolved JSON templates from profile
+                json_templates.clone()
+            } else {
+                // Fall back to the existing template resolution logic
+                get_json_templates(config)
+            };
+            let output = StatusOutput {
+                text: render_template(&templates.text, &context)?,
+                alt: render_template(&templates.alt, &context)?,
+                tooltip: render_template(&templates.tooltip, &context)?,
+                class: render_template(&templates.class, &context)?,
+                percentage: Some(now_playing.volume.get()),
+            };
+            Ok(serde_json::to_string(&output)?)
+        }
+    }
+}
+
+/// [`render_status`] for printing to the terminal, colored by state when the
+/// built-in template is in use (custom templates are left as written)
+fn render_terminal_status(
+    now_playing: &wiim_api::NowPlaying,
+    device: &DeviceNames,
+    resolved_profile: &ResolvedProfile,
+    config: &Config,
+) -> WiimResult<String> {
+    let line = render_status(now_playing, device, resolved_profile, config)?;
+    let built_in = matches!(resolved_profile.format, OutputFormat::Text)
+        && resolved_profile.text_template.is_none()
+        && configured_text_template(config, &now_playing.state).is_none();
+    if !built_in {
+        return Ok(line);
+    }
+    let role = match now_playing.state {
+        PlayState::Playing => theme::Role::Accent,
+        PlayState::Paused => theme::Role::Warn,
+        PlayState::Stopped | PlayState::Loading => theme::Role::Dim,
+    };
+    Ok(theme::paint(role, &line))
+}
+
+impl From<&wiim_api::StatusEx> for InfoOutput {
+    fn from(status: &wiim_api::StatusEx) -> Self {
+        let device_time = match (&status.date, &status.time) {
+            (Some(date), Some(time)) => Some(format!("{} {time}", date.replace(':', "-"))),
+            _ => None,
+        };
+
+        InfoOutput {
+            name: status.device_name.clone(),
+            model: status.project.clone(),
+            firmware: status.firmware.clone(),
+            release: status.release.clone(),
+            ip_address: status.ip_address().map(str::to_string),
+            mac: status.mac.clone(),
+            uuid: status.uuid.clone(),
+            device_time,
+            timezone: status.app_timezone_id.clone(),
+            internet: status.has_internet(),
+            wifi_network: status.wifi_network(),
+            rssi_dbm: status.rssi_dbm(),
+            signal_quality: status.signal_quality(),
+            snr: status.wlan_snr.clone(),
+            noise: status.wlan_noise.clone(),
+            frequency: status.wifi_frequency_ghz(),
+            data_rate_mbps: status.data_rate_mbps(),
+        }
+    }
+}
+
+async fn handle_info(client: &WiimClient, json: bool) -> WiimResult<CommandOutput> {
+    let status = client.get_status_ex().await?;
+    let info = InfoOutput::from(&status);
+
+    let lines = if json {
+        vec![serde_json::to_string(&info)?]
+    } else {
+        let lines = info_lines(&info);
+        // Translated labels vary in length, so align on the longest
+        let width = lines
+            .iter()
+            .map(|(label, _)| label.chars().count() + 1)
+            .max()
+            .unwrap_or(0);
+        lines
+            .into_iter()
+            .map(|(label, value)| {
+                // Pad before painting so escape codes don't count toward the width
+                let label = theme::paint(theme::Role::Label, &format!("{label:<width$}"));
+                format!("{label}{value}")
+            })
+            .collect()
+    };
+
+    Ok(CommandOutput::lines(lines).with_state(info))
+}
+
+fn info_lines(info: &InfoOutput) -> Vec<(String, String)> {
+    let unknown = || i18n::tr("common.unknown", &[]);
+    let or_unknown = |value: &Option<String>| value.clone().unwrap_or_else(unknown);
+
+    let signal = match (&info.signal_quality, info.rssi_dbm) {
+        (Some(quality), Some(rssi)) => format!("{quality} ({rssi} dBm)"),
+        _ => unknown(),
+    };
+    let internet = if info.internet {
+        "info.connected"
+    } else {
+        "info.offline"
+    };
+
+    [
+        ("info.name", or_unknown(&info.name)),
+        ("info.model", or_unknown(&info.model)),
+        ("info.firmware", or_unknown(&info.firmware)),
+        ("info.release", or_unknown(&info.release)),
+        ("info.ip_address", or_unknown(&info.ip_address)),
+        ("info.mac", or_unknown(&info.mac)),
+        ("info.uuid", or_unknown(&info.uuid)),
+        ("info.device_time", or_unknown(&info.device_time)),
+        ("info.timezone", or_unknown(&info.timezone)),
+        ("info.internet", i18n::tr(internet, &[])),
+        ("info.wifi_network", or_unknown(&info.wifi_network)),
+        ("info.signal", signal),
+        (
+            "info.snr_noise",
+            format!(
+                "{} dB / {} dBm",
+                info.snr.as_deref().unwrap_or("?"),
+                info.noise.as_deref().unwrap_or("?")
+            ),
+        ),
+        ("info.frequency", or_unknown(&info.frequency)),
+        (
+            "info.data_rate",
+            info.data_rate_mbps
+                .map(|rate| format!("{rate} Mbps"))
+                .unwrap_or_else(unknown),
+        ),
+    ]
+    .into_iter()
+    .map(|(key, value)| (i18n::tr(key, &[]), value))
+    .collect()
+}
+
+async fn handle_queue(client: &WiimClient) -> WiimResult<CommandOutput> {
+    let position = client.get_queue_position().await?;
+    let mut lines = vec![if position.count == 0 {
+        i18n::tr("queue.empty", &[])
+    } else {
+        i18n::tr(
+            "queue.position",
+            &[("current", &position.current), ("count", &position.count)],
+        )
+    }];
+
+    // The local track list is only available for USB/local sources
+    let mut tracks = Vec::new();
+    if let Ok(track_list) = client.get_track_list().await {
+        lines.extend(queue_lines(&track_list, position.current));
+        tracks = track_list
+            .list
+            .iter()
+            .map(wiim_api::TrackEntry::display_name)
+            .collect();
+    }
+
+    Ok(CommandOutput::lines(lines).with_state(json!({
+        "current": position.current,
+        "count": position.count,
+        "tracks": tracks,
+    })))
+}
+
+fn queue_lines(track_list: &wiim_api::TrackList, current: u32) -> Vec<String> {
+    track_list
+        .list
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| {
+            let number = i as u32 + 1;
+            let marker = if number == current { "▶" } else { " " };
+            let name = entry
+                .display_name()
+                .unwrap_or_else(|| i18n::tr("queue.unknown_track", &[]));
+            format!("{marker} {number:>3}. {name}")
+        })
+        .collect()
+}
+
+const REBOOT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+const REBOOT_GO_DOWN_TIMEOUT: Duration = Duration::from_secs(30);
+const REBOOT_COME_BACK_TIMEOUT: Duration = Duration::from_secs(180);
+
+/// Wait for the device to go offline and come back, returning a summary message
+async fn handle_reboot_wait(client: &WiimClient) -> Result<String, Box<dyn std::error::Error>> {
+    // The device keeps answering for a moment after accepting the command
+    let started = Instant::now();
+    while client.test_connection().await.is_ok() {
+        if started.elapsed() > REBOOT_GO_DOWN_TIMEOUT {
+            return Ok(i18n::tr("confirm.reboot_ignored", &[]));
+        }
+        tokio::time::sleep(REBOOT_POLL_INTERVAL).await;
+    }
+
+    let went_down = Instant::now();
+    loop {
+        tokio::time::sleep(REBOOT_POLL_INTERVAL).await;
+        if client.test_connection().await.is_ok() {
+            let downtime = went_down.elapsed().as_secs();
+            return Ok(i18n::tr("confirm.back_online", &[("seconds", &downtime)]));
+        }
+        if went_down.elapsed() > REBOOT_COME_BACK_TIMEOUT {
+            let seconds = REBOOT_COME_BACK_TIMEOUT.as_secs();
+            return Err(CliError::Timeout(i18n::tr(
+                "confirm.reboot_timeout",
+                &[("seconds", &seconds)],
+            ))
+            .into());
+        }
+    }
+}
+
+fn alarm_state(alarm: &Alarm) -> serde_json::Value {
+    json!({
+        "slot": alarm.slot,
+        "time": alarm.time.to_string(),
+        "days": alarm.days.to_string(),
+        "action": alarm.action.to_string(),
+    })
+}
+
+async fn handle_alarm(
+    client: &WiimClient,
+    action: AlarmCommand,
+) -> Result<CommandOutput, Box<dyn std::error::Error>> {
+    let output = match action {
+        AlarmCommand::List => {
+            let alarms = client.list_alarms().await?;
+            let mut lines: Vec<String> = alarms
+                .iter()
+                .map(|alarm| {
+                    format!(
+                        "[{}] {} {} → {}",
+                        alarm.slot, alarm.time, alarm.days, alarm.action
+                    )
+                })
+                .collect();
+            if lines.is_empty() {
+                lines.push(i18n::tr("alarm.none", &[]));
+            }
+            let state: Vec<_> = alarms.iter().map(alarm_state).collect();
+            CommandOutput::lines(lines).with_state(state)
+        }
+        AlarmCommand::Set {
+            time,
+            days,
+            slot,
+            preset,
+            url,
+            stop,
+        } => {
+            let action = match (preset, url) {
+                _ if stop => AlarmAction::Stop,
+                (Some(preset), _) => AlarmAction::Preset(preset),
+                (None, Some(url)) => AlarmAction::PlayUrl(url),
+                (None, None) => {
+                    return Err(CliError::Usage(
+                        "Specify what the alarm does with --preset, --url or --stop".to_string(),
+                    )
+                    .into())
+                }
+            };
+            let slot = match slot {
+                Some(slot) => slot,
+                None => first_free_alarm_slot(&client.list_alarms().await?)?,
+            };
+            let alarm = Alarm {
+                slot,
+                time,
+                days,
+                action,
+            };
+            client.set_alarm(&alarm).await?;
+            CommandOutput::message(i18n::tr(
+                "confirm.alarm_set",
+                &[
+                    ("slot", &slot),
+                    ("time", &alarm.time),
+                    ("days", &alarm.days),
+                    ("action", &alarm.action),
+                ],
+            ))
+            .with_state(alarm_state(&alarm))
+        }
+        AlarmCommand::Delete { slot } => {
+            client.delete_alarm(slot).await?;
+            CommandOutput::message(i18n::tr("confirm.alarm_deleted", &[("slot", &slot)]))
+                .with_state(json!({ "slot": slot }))
+        }
+        AlarmCommand::Stop => {
+            client.stop_alarm().await?;
+            CommandOutput::message(i18n::tr("confirm.alarm_stopped", &[]))
+        }
+    };
+    Ok(output)
+}
+
+/// Download the current cover art, skipping the download when the cached copy is current
+async fn handle_art(
+    client: &WiimClient,
+    output: Option<PathBuf>,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let meta = client.get_meta_info().await?;
+    let url = meta
+        .meta_data
+        .album_art_uri
+        .filter(|url| url.starts_with("http"))
+        .ok_or("No album art available for the current track")?;
+
+    let cache_dir = dirs::cache_dir()
+        .ok_or("Could not find cache directory")?
+        .join("wiim-control");
+    let marker = cache_dir.join("cover.url");
+
+    const COVER_EXTENSIONS: [&str; 4] = ["jpg", "png", "webp", "gif"];
+
+    let art = match output {
+        Some(path) => {
+            let art = client.download_album_art(&url).await?;
+            if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                fs::create_dir_all(parent).await?;
+            }
+            fs::write(&path, &art.bytes).await?;
+            return Ok(path);
+        }
+        None => {
+            if fs::read_to_string(&marker).await.ok().as_deref() == Some(url.as_str()) {
+                for ext in COVER_EXTENSIONS {
+                    let cached = cache_dir.join(format!("cover.{ext}"));
+                    if cached.exists() {
+                        return Ok(cached);
+                    }
+                }
+            }
+            client.download_album_art(&url).await?
+        }
+    };
+
+    // Replace any previous cover so only the current one is ever found
+    fs::create_dir_all(&cache_dir).await?;
+    for ext in COVER_EXTENSIONS {
+        let _ = fs::remove_file(cache_dir.join(format!("cover.{ext}"))).await;
+    }
+    let path = cache_dir.join(format!("cover.{}", art.extension()));
+    fs::write(&path, &art.bytes).await?;
+    fs::write(&marker, &url).await?;
+    Ok(path)
+}
+
+fn first_free_alarm_slot(alarms: &[Alarm]) -> WiimResult<u8> {
+    (0..ALARM_SLOTS)
+        .find(|slot| alarms.iter().all(|alarm| alarm.slot != *slot))
+        .ok_or_else(|| {
+            wiim_api::WiimError::InvalidResponse(
+                "All alarm slots are in use; pass --slot to replace one".to_string(),
+            )
+        })
+}
+
+fn format_remaining(remaining: Duration) -> String {
+    let total = remaining.as_secs();
+    let (hours, minutes, seconds) = (total / 3600, (total % 3600) / 60, total % 60);
+    if hours > 0 {
+        format!("{hours}h {minutes:02}m {seconds:02}s")
+    } else {
+        format!("{minutes}m {seconds:02}s")
+    }
+}
+
+/// The `[output.text]` template for a state, if the config sets one
+fn configured_text_template<'a>(config: &'a Config, state: &PlayState) -> Option<&'a String> {
+    let text = config.output.as_ref()?.text.as_ref()?;
+    match state {
+        PlayState::Playing => text.playing.as_ref(),
+        PlayState::Paused => text.paused.as_ref(),
+        PlayState::Stopped => text.stopped.as_ref(),
+        PlayState::Loading => text.loading.as_ref(),
+    }
+}
+
+fn get_text_template(config: &Config, state: &PlayState) -> String {
+    let default_icon = match state {
+        PlayState::Playing => "▶️",
+        PlayState::Paused => "⏸️",
+        PlayState::Stopped => "⏹️",
+        PlayState::Loading => "⏳",
+    };
+
+    if let Some(template) = configured_text_template(config, state) {
+        return template.clone();
+    }
+
+    // Default template that matches current behavior
+    format!("{default_icon} {{{{track_info}}}}")
+}
+
+#[derive(Debug, Clone)]
+struct JsonTemplatesResolved {
+    text: String,
+    alt: String,
+    tooltip: String,
+    class: String,
+}
+
+fn get_json_templates(config: &Config) -> JsonTemplatesResolved {
+    let defaults = JsonTemplatesResolved {
+        text: "{{track_info}}".to_string(),
+        alt: "{{state}}".to_string(),
+        tooltip: "{{full_info}}".to_string(),
+        class: "{{state}}".to_string(),
+    };
+
+    if let Some(output) = &config.output {
+        if let Some(json) = &output.json {
+            return JsonTemplatesResolved {
+                text: json.text.clone().unwrap_or(defaults.text),
+                alt: json.alt.clone().unwrap_or(defaults.alt),
+                tooltip: json.tooltip.clone().unwrap_or(defaults.tooltip),
+                class: json.class.clone().unwrap_or(defaults.class),
+            };
+        }
+    }
+
+    defaults
+}
+
+fn render_template(template: &str, context: &impl Serialize) -> WiimResult<String> {
+    templates::render(template, context).map_err(wiim_api::WiimError::InvalidResponse)
+}
+
+/// Resolve `--device` (an IP or a name from `[devices]`), falling back to `device_ip`
+fn resolve_device(device: Option<&str>, config: &Config) -> String {
+    match device {
+        Some(device) => config
+            .devices
+            .as_ref()
+            .and_then(|devices| devices.get(device))
+            .map_or_else(|| device.to_string(), Clone::clone),
+        None => config.device_ip.clone(),
+    }
+}
+
+/// Create a client using timeout and retry settings from the CLI, falling back to the config
+fn build_client(
+    device_ip: &str,
+    cli: &Cli,
+    config: &Config,
+) -> Result<WiimClient, Box<dyn std::error::Error>> {
+    let timeout = match (cli.timeout, &config.timeout) {
+        (Some(timeout), _) => Some(timeout),
+        (None, Some(timeout)) => Some(
+            parse_duration_arg(timeout)
+                .map_err(|e| CliError::Config(format!("Invalid timeout in config: {e}")))?,
+        ),
+        (None, None) => None,
+    };
+
+    let mut builder = WiimClient::builder(device_ip);
+    if let Some(timeout) = timeout {
+        builder = builder.timeout(timeout);
+    }
+    if let Some(retries) = cli.retries.or(config.retries) {
+        builder = builder.retries(retries);
+    }
+    #[cfg(feature = "cassette")]
+    if let Some(path) = &cli.record {
+        builder = builder.cassette(wiim_api::Cassette::record(path));
+    } else if let Some(path) = &cli.replay {
+        builder = builder.cassette(wiim_api::Cassette::replay(path)?);
+    }
+    Ok(builder.verify_setters(cli.verify).build()?)
+}
+
+/// Every configured device as (name, IP): `device_ip` plus the `[devices]` table, without duplicates
+fn configured_devices(config: &Config) -> Vec<(String, String)> {
+    let mut devices: Vec<(String, String)> = config
+        .devices
+        .iter()
+        .flatten()
+        .map(|(name, ip)| (name.clone(), ip.clone()))
+        .collect();
+    devices.sort();
+    if !devices.iter().any(|(_, ip)| *ip == config.device_ip) {
+        devices.insert(0, (config.device_ip.clone(), config.device_ip.clone()));
+    }
+    devices
+}
+
+/// Default config location: ~/.config/wiim-control/config.toml
+fn default_config_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("wiim-control").join("config.toml"))
+}
+
+/// The config file in use: `--config`, the default location, or none with `--no-config`
+fn config_path(cli: &Cli) -> Option<PathBuf> {
+    if cli.no_config {
+        return None;
+    }
+    cli.config.clone().or_else(default_config_path)
+}
+
+/// Whether the config file can be left unread: with `--no-config`, or for `status --get`
+/// on a `--device` given as an address rather than a name from [devices]
+///
+/// Status bars run `--device 192.168.1.100 status --get volume` every few seconds; the
+/// timeout and retries then come from the command line alone.
+fn skips_config(cli: &Cli) -> bool {
+    let literal_device = cli
+        .device
+        .as_deref()
+        .is_some_and(|device| device.parse::<IpAddr>().is_ok() || device.contains("://"));
+    cli.no_config
+        || (literal_device
+            && cli.profile.is_none()
+            && matches!(cli.command, Commands::Status { get: Some(_), .. }))
+}
+
+/// Read the config file, or use the defaults if there is none; nothing is created here
+/// (that's `config init`)
+async fn load_config(config_path: &Option<PathBuf>) -> Result<Config, Box<dyn std::error::Error>> {
+    let config_file = match config_path {
+        Some(path) => path.clone(),
+        None => match default_config_path() {
+            Some(path) => path,
+            None => return Ok(Config::default()),
+        },
+    };
+
+    match fs::read_to_string(&config_file).await {
+        Ok(content) => Ok(toml::from_str(&content)
+            .map_err(|e| CliError::Config(format!("{}: {e}", config_file.display())))?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Config::default()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiim_api::{LoopMode, NowPlaying, PlayState, PlaybackSource, RepeatMode};
+
+    fn create_test_now_playing() -> NowPlaying {
+        NowPlaying {
+            title: Some("Test Title".to_string()),
+            artist: Some("Test Artist".to_string()),
+            album: Some("Test Album".to_string()),
+            album_art_uri: Some("https://example.com/art.jpg".to_string()),
+            state: PlayState::Playing,
+            volume: Volume::saturating(75),
+            position: Duration::from_secs(60),
+            duration: Duration::from_secs(180),
+            sample_rate: Some("44100".to_string()),
+            bit_depth: Some("16".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_template_context_creation() {
+        let now_playing = create_test_now_playing();
+        let context = TemplateContext::from(&now_playing);
+
+        assert_eq!(context.artist, Some("Test Artist".to_string()));
+        assert_eq!(context.title, Some("Test Title".to_string()));
+        assert_eq!(context.album, Some("Test Album".to_string()));
+        assert_eq!(context.state, "playing");
+        assert_eq!(context.volume, 75);
+        assert!(!context.muted);
+        assert_eq!(context.position, "1:00");
+        assert_eq!(context.duration, "3:00");
+        assert_eq!(context.progress_bar, "███░░░░░░░");
+        assert_eq!(context.sample_rate_khz, Some("44kHz".to_string()));
+        assert_eq!(context.bit_depth_bit, Some("16bit".to_string()));
+        assert_eq!(context.quality_info, Some("44kHz/16bit".to_string()));
+        assert_eq!(context.track_info, "Test Artist - Test Title");
+    }
+
+    #[test]
+    fn test_template_context_with_missing_fields() {
+        let now_playing = NowPlaying {
+            artist: Some("Test Artist".to_string()),
+            volume: Volume::saturating(50),
+            is_muted: true,
+            ..Default::default()
+        };
+
+        let context = TemplateContext::from(&now_playing);
+
+        assert_eq!(context.artist, Some("Test Artist".to_string()));
+        assert_eq!(context.title, None);
+        assert_eq!(context.album, None);
+        assert_eq!(context.state, "stopped");
+        assert_eq!(context.volume, 50);
+        assert!(context.muted);
+        assert_eq!(context.position, "0:00");
+        assert_eq!(context.duration, "0:00");
+        assert_eq!(context.sample_rate_khz, None);
+        assert_eq!(context.bit_depth_bit, None);
+        assert_eq!(context.quality_info, None);
+        assert_eq!(context.track_info, "Test Artist");
+    }
+
+    #[test]
+    fn test_template_context_no_track_info() {
+        let now_playing = NowPlaying {
+            volume: Volume::saturating(50),
+            ..Default::default()
+        };
+
+        let context = TemplateContext::from(&now_playing);
+        assert_eq!(context.track_info, "No track info");
+    }
+
+    #[test]
+    fn test_template_context_live_stream() {
+        let mut now_playing = create_test_now_playing();
+        now_playing.duration = Duration::ZERO;
+        now_playing.position = Duration::from_secs(95);
+        now_playing.source = PlaybackSource::Network;
+
+        let context = TemplateContext::from(&now_playing);
+        assert!(context.live);
+        assert_eq!(context.position, "1:35");
+        assert_eq!(context.duration, "live");
+        assert_eq!(context.progress_bar, "");
+        assert!(context.full_info.contains("📡 Live stream"));
+    }
+
+    #[test]
+    fn test_render_template_basic() {
+        let now_playing = create_test_now_playing();
+        let context = TemplateContext::from(&now_playing);
+
+        let result = render_template("{{artist}} - {{title}}", &context);
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), "Test Artist - Test Title");
+    }
+
+    #[test]
+    fn test_render_template_with_missing_fields() {
+        let now_playing = NowPlaying {
+            artist: Some("Test Artist".to_string()),
+            state: PlayState::Playing,
+            volume: Volume::saturating(50),
+            ..Default::default()
+        };
+
+        let context = TemplateContext::from(&now_playing);
+
+        let result = render_template("{{artist}} - {{title}}", &context);
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), "Test Artist - ");
+    }
+
+    #[test]
+    fn test_skips_config() {
+        let skips = |args: &[&str]| {
+            skips_config(&Cli::try_parse_from([&["wiim-control"], args].concat()).unwrap())
+        };
+        assert!(skips(&[
+            "--device",
+            "192.168.1.100",
+            "status",
+            "--get",
+            "volume"
+        ]));
+        assert!(skips(&["--no-config", "status"]));
+        // A name has to be looked up in [devices]
+        assert!(!skips(&[
+            "--device", "kitchen", "status", "--get", "volume"
+        ]));
+        assert!(!skips(&["--device", "192.168.1.100", "status"]));
+        assert!(!skips(&["status", "--get", "volume"]));
+    }
+
+    #[test]
+    fn test_render_template_invalid_syntax() {
+        let now_playing = create_test_now_playing();
+        let context = TemplateContext::from(&now_playing);
+
+        let result = render_template("{{artist} - {{title}}", &context);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_get_text_template_default() {
+        let config = Config::default();
+        let template = get_text_template(&config, &PlayState::Playing);
+        assert_eq!(template, "▶️ {{track_info}}");
+
+        let template = get_text_template(&config, &PlayState::Paused);
+        assert_eq!(template, "⏸️ {{track_info}}");
+
+        let template = get_text_template(&config, &PlayState::Stopped);
+        assert_eq!(template, "⏹️ {{track_info}}");
+
+        let template = get_text_template(&config, &PlayState::Loading);
+        assert_eq!(template, "⏳ {{track_info}}");
+    }
+
+    #[test]
+    fn test_get_json_templates_default() {
+        let config = Config::default();
+        let templates = get_json_templates(&config);
+
+        assert_eq!(templates.text, "{{track_info}}");
+        assert_eq!(templates.alt, "{{state}}");
+        assert_eq!(templates.tooltip, "{{full_info}}");
+        assert_eq!(templates.class, "{{state}}");
+    }
+
+    #[test]
+    fn test_info_output_from_status_ex() {
+        let status = wiim_api::StatusEx {
+            device_name: Some("Living Room".to_string()),
+            project: Some("Muzo_Mini".to_string()),
+            apcli0: Some("192.168.86.52".to_string()),
+            date: Some("2025:07:18".to_string()),
+            time: Some("04:56:40".to_string()),
+            rssi: Some("-45".to_string()),
+            internet: Some("1".to_string()),
+            ..Default::default()
+        };
+
+        let info = InfoOutput::from(&status);
+        assert_eq!(info.name, Some("Living Room".to_string()));
+        assert_eq!(info.ip_address, Some("192.168.86.52".to_string()));
+        assert_eq!(info.device_time, Some("2025-07-18 04:56:40".to_string()));
+        assert_eq!(info.signal_quality, Some("Excellent".to_string()));
+        assert!(info.internet);
+
+        let lines = info_lines(&info);
+        let line = |label: &str, value: &str| (label.to_string(), value.to_string());
+        assert!(lines.contains(&line("Signal:", "Excellent (-45 dBm)")));
+        assert!(lines.contains(&line("Firmware:", "unknown")));
+    }
+
+    #[test]
+    fn test_queue_lines_marks_current_track() {
+        let track_list: wiim_api::TrackList = serde_json::from_str(
+            r#"{"num": "2", "list": [{"name": "01.flac"}, {"title": "Second"}]}"#,
+        )
+        .unwrap();
+
+        let lines = queue_lines(&track_list, 2);
+        assert_eq!(lines, vec!["    1. 01.flac", "▶   2. Second"]);
+    }
+
+    #[test]
+    fn test_parse_sleep_timer_arg() {
+        assert_eq!(
+            parse_sleep_timer_arg("45").unwrap(),
+            SleepTimerArg::Minutes(45)
+        );
+        assert_eq!(parse_sleep_timer_arg("off").unwrap(), SleepTimerArg::Off);
+        assert_eq!(parse_sleep_timer_arg("0").unwrap(), SleepTimerArg::Off);
+        assert_eq!(
+            parse_sleep_timer_arg("status").unwrap(),
+            SleepTimerArg::Status
+        );
+        assert!(parse_sleep_timer_arg("soon").is_err());
+        assert!(parse_sleep_timer_arg("1440").is_ok());
+        assert!(parse_sleep_timer_arg("1441").is_err());
+        assert!(parse_sleep_timer_arg("999999999999999999").is_err());
+    }
+
+    #[test]
+    fn test_parse_alarm_days() {
+        assert_eq!(parse_alarm_days("weekdays").unwrap(), Weekdays::WORKDAYS);
+        assert_eq!(parse_alarm_days("Daily").unwrap(), Weekdays::ALL);
+        assert_eq!(
+            parse_alarm_days("mon, wed,fri").unwrap(),
+            Weekdays::MONDAY
+                .union(Weekdays::WEDNESDAY)
+                .union(Weekdays::FRIDAY)
+        );
+        assert!(parse_alarm_days("mon,funday").is_err());
+    }
+
+    #[test]
+    fn test_parse_day_list() {
+        let items = |items: &[&str]| {
+            items
+                .iter()
+                .map(|item| item.to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            parse_day_list(&items(&["sat", "weekdays"])).unwrap(),
+            Weekdays::WORKDAYS.union(Weekdays::SATURDAY)
+        );
+        assert!(parse_day_list(&items(&["mon", "funday"])).is_err());
+        assert!(parse_day_list(&[]).is_err());
+    }
+
+    #[test]
+    fn test_first_free_alarm_slot() {
+        let alarm = |slot| Alarm {
+            slot,
+            time: AlarmTime::new(7, 0).unwrap(),
+            days: Weekdays::ALL,
+            action: AlarmAction::Stop,
+        };
+        assert_eq!(first_free_alarm_slot(&[]).unwrap(), 0);
+        assert_eq!(first_free_alarm_slot(&[alarm(0), alarm(2)]).unwrap(), 1);
+        assert!(first_free_alarm_slot(&[alarm(0), alarm(1), alarm(2)]).is_err());
+    }
+
+    #[test]
+    fn test_parse_duration_arg() {
+        assert_eq!(parse_duration_arg("2s").unwrap(), Duration::from_secs(2));
+        assert_eq!(
+            parse_duration_arg("500ms").unwrap(),
+            Duration::from_millis(500)
+        );
+        assert_eq!(parse_duration_arg("1m").unwrap(), Duration::from_secs(60));
+        assert_eq!(parse_duration_arg("3").unwrap(), Duration::from_secs(3));
+        assert_eq!(
+            parse_duration_arg("1.5s").unwrap(),
+            Duration::from_millis(1500)
+        );
+        assert!(parse_duration_arg("fast").is_err());
+        assert!(parse_duration_arg("2d").is_err());
+        assert!(parse_duration_arg("0s").is_err());
+        assert!(parse_duration_arg("0").is_err());
+        assert!(parse_duration_arg("99999999999999999999999s").is_err());
+    }
+
+    #[test]
+    fn test_format_remaining() {
+        assert_eq!(format_remaining(Duration::from_secs(2670)), "44m 30s");
+        assert_eq!(format_remaining(Duration::from_secs(3725)), "1h 02m 05s");
+    }
+
+    #[test]
+    fn test_validate_template_single_braces() {
+        let result = validate_template("{artist} - {title}");
+        assert!(result.is_err());
+        let error_msg = result.unwrap_err();
+        assert!(error_msg.contains("Invalid template syntax: found single braces"));
+        assert!(error_msg.contains("double braces like {{variable}}"));
+    }
+
+    #[test]
+    fn test_validate_template_double_braces() {
+        let result = validate_template("{{artist}} - {{title}}");
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_validate_template_mixed_braces() {
+        let result = validate_template("{{artist}} - {title}");
+        assert!(result.is_err());
+        let error_msg = result.unwrap_err();
+        assert!(error_msg.contains("Invalid template syntax: found single braces"));
+    }
+
+    #[test]
+    fn test_validate_template_unclosed_braces() {
+        let result = validate_template("{{artist} - {{title}}");
+        assert!(result.is_err());
+        let error_msg = result.unwrap_err();
+        println!("Actual error message: {error_msg}");
+        // The unclosed brace should be caught by our single brace detection or handlebars
+        assert!(error_msg.contains("Invalid template syntax"));
+    }
+
+    #[test]
+    fn test_validate_template_no_braces() {
+        let result = validate_template("Now Playing");
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_template_context_formatting() {
+        let now_playing = NowPlaying {
+            title: Some("Test Title".to_string()),
+            artist: Some("Test Artist".to_string()),
+            album: Some("Test Album".to_string()),
+            state: PlayState::Playing,
+            volume: Volume::saturating(85),
+            is_muted: true,
+            position: Duration::from_secs(125), // 2:05
+            duration: Duration::from_secs(245), // 4:05
+            sample_rate: Some("96000".to_string()),
+            bit_depth: Some("24".to_string()),
+            loop_mode: LoopMode {
+                repeat: RepeatMode::All,
+                shuffle: true,
+            },
+            source: PlaybackSource::LineIn,
+            ..Default::default()
+        };
+
+        let device = DeviceNames {
+            device_name: Some("Kitchen".to_string()),
+            group_name: Some("Downstairs".to_string()),
+        };
+        let context = TemplateContext::from(&now_playing).with_device(&device);
+
+        assert_eq!(context.position, "2:05");
+        assert_eq!(context.duration, "4:05");
+        assert_eq!(context.sample_rate_khz, Some("96kHz".to_string()));
+        assert_eq!(context.bit_depth_bit, Some("24bit".to_string()));
+        assert_eq!(context.quality_info, Some("96kHz/24bit".to_string()));
+        assert_eq!(context.volume, 85);
+        assert!(context.muted);
+        assert!(context.full_info.contains("Volume: 85%"));
+        assert!(context.full_info.contains("🔇 Muted"));
+        assert!(context.full_info.contains("Quality: 96kHz/24bit"));
+        assert!(context.full_info.contains("Time: 2:05 / 4:05"));
+        assert!(context.full_info.contains("Repeat: all · Shuffle: on"));
+        assert_eq!(context.repeat, "all");
+        assert!(context.shuffle);
+        assert_eq!(context.source, "line-in");
+        assert_eq!(context.device_name.as_deref(), Some("Kitchen"));
+        assert_eq!(context.group_name.as_deref(), Some("Downstairs"));
+    }
+
+    #[test]
+    fn test_status_field() {
+        let context = TemplateContext::from(&create_test_now_playing());
+        assert_eq!(
+            status_field(&context, "artist").unwrap().as_deref(),
+            Some("Test Artist")
+        );
+        assert_eq!(
+            status_field(&context, "volume").unwrap().as_deref(),
+            Some("75")
+        );
+        assert_eq!(status_field(&context, "device_name").unwrap(), None);
+        let error = status_field(&context, "artsit").unwrap_err();
+        assert!(error.contains("artist, bit_depth"));
+    }
+
+    #[test]
+    fn test_resolve_device() {
+        let config: Config = toml::from_str(
+            r#"
+            device_ip = "192.168.1.100"
+
+            [devices]
+            kitchen = "192.168.1.101"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(resolve_device(None, &config), "192.168.1.100");
+        assert_eq!(resolve_device(Some("kitchen"), &config), "192.168.1.101");
+        assert_eq!(resolve_device(Some("10.0.0.5"), &config), "10.0.0.5");
+    }
+
+    #[test]
+    fn test_configured_devices() {
+        let config: Config = toml::from_str(
+            r#"
+            device_ip = "192.168.1.100"
+
+            [devices]
+            office = "192.168.1.102"
+            kitchen = "192.168.1.101"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            configured_devices(&config),
+            [
+                ("192.168.1.100".to_string(), "192.168.1.100".to_string()),
+                ("kitchen".to_string(), "192.168.1.101".to_string()),
+                ("office".to_string(), "192.168.1.102".to_string()),
+            ]
+        );
+
+        // device_ip is not repeated when it also has a name
+        let config: Config = toml::from_str(
+            r#"
+            device_ip = "192.168.1.101"
+
+            [devices]
+            kitchen = "192.168.1.101"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(configured_devices(&config).len(), 1);
+    }
+
+    #[test]
+    fn test_builtin_profiles_without_config() {
+        let cli = Cli::parse_from(["wiim-control", "--profile", "waybar", "status"]);
+        let resolved = resolve_profile(&cli, &Config::default()).unwrap();
+        assert!(matches!(resolved.format, OutputFormat::Json));
+
+        let cli = Cli::parse_from(["wiim-control", "--profile", "polybar", "status"]);
+        let resolved = resolve_profile(&cli, &Config::default()).unwrap();
+        assert!(matches!(resolved.format, OutputFormat::Text));
+        assert!(resolved.text_template.is_some());
+
+        let cli = Cli::parse_from(["wiim-control", "--profile", "lemonbar", "status"]);
+        let error = resolve_profile(&cli, &Config::default()).unwrap_err();
+        assert!(error.contains("i3blocks, polybar, waybar"));
+    }
+
+    #[test]
+    fn test_config_profile_overrides_builtin() {
+        let config: Config = toml::from_str(
+            r#"
+            device_ip = "192.168.1.100"
+
+            [profiles.polybar]
+            format = "text"
+            text_template = "{{title}}"
+            "#,
+        )
+        .unwrap();
+        let cli = Cli::parse_from(["wiim-control", "--profile", "polybar", "status"]);
+        let resolved = resolve_profile(&cli, &config).unwrap();
+        assert_eq!(resolved.text_template.as_deref(), Some("{{title}}"));
+    }
+
+    #[test]
+    fn test_profile_device() {
+        let config: Config = toml::from_str(
+            r#"
+            device_ip = "192.168.1.100"
+
+            [devices]
+            bedroom = "192.168.1.102"
+
+            [profiles.bedroom_bar]
+            format = "json"
+            device = "bedroom"
+        "#,
+        )
+        .unwrap();
+        let cli = Cli::parse_from(["wiim-control", "--profile", "bedroom_bar", "status"]);
+        assert_eq!(cli_device(&cli, &config), "192.168.1.102");
+
+        let cli = Cli::parse_from([
+            "wiim-control",
+            "--profile",
+            "bedroom_bar",
+            "--device",
+            "10.0.0.5",
+            "status",
+        ]);
+        assert_eq!(cli_device(&cli, &config), "10.0.0.5");
+
+        let cli = Cli::parse_from(["wiim-control", "--profile", "waybar", "status"]);
+        assert_eq!(cli_device(&cli, &config), "192.168.1.100");
+    }
+}
@@ -0,0 +1,59 @@
+//! `wiim-control mangen`: man pages generated from the clap definitions
+
+use clap::Command;
+use std::path::{Path, PathBuf};
+
+/// Write `wiim-control.1` plus one page per (nested) subcommand into `dir`
+pub fn generate(mut cmd: Command, dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(dir)?;
+    // Building propagates display names, so subcommand pages are titled `wiim-control-<name>`
+    cmd.build();
+    let mut written = Vec::new();
+    write_pages(&cmd, dir, &mut written)?;
+    Ok(written)
+}
+
+fn write_pages(cmd: &Command, dir: &Path, written: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    let name = cmd.get_display_name().unwrap_or_else(|| cmd.get_name());
+    let path = dir.join(format!("{name}.1"));
+    let mut page = Vec::new();
+    clap_mangen::Man::new(cmd.clone()).render(&mut page)?;
+    std::fs::write(&path, page)?;
+    written.push(path);
+
+    // Skip hidden commands and clap's generated `help` subcommands
+    for subcommand in cmd.get_subcommands() {
+        if !subcommand.is_hide_set() && subcommand.get_name() != "help" {
+            write_pages(subcommand, dir, written)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Cli;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_generate_pages_for_subcommands() {
+        let dir = std::env::temp_dir().join(format!("wiim-control-man-{}", std::process::id()));
+        let written = generate(Cli::command(), &dir).unwrap();
+        let names: Vec<String> = written
+            .iter()
+            .map(|p| p.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+
+        assert!(names.contains(&"wiim-control.1".to_string()));
+        assert!(names.contains(&"wiim-control-volume.1".to_string()));
+        assert!(names.contains(&"wiim-control-alarm-set.1".to_string()));
+        assert!(!names.contains(&"wiim-control-mangen.1".to_string()));
+        assert!(!names.iter().any(|name| name.contains("-help")));
+
+        let page = std::fs::read_to_string(dir.join("wiim-control-volume.1")).unwrap();
+        assert!(page.contains("wiim\\-control\\-volume"));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,552 @@
+//! `wiim-control mqtt`: publish every configured device's state to an MQTT broker, accept
+//! commands, and announce the devices to Home Assistant through MQTT discovery
+//!
+//! Topics, with `<prefix>` from `mqtt.topic_prefix` (default `wiim`) and `<id>` derived from
+//! the device name:
+//!
+//! - `<prefix>/bridge/availability`: `online` / `offline` (last will)
+//! - `<prefix>/<id>/availability`: `online` / `offline` depending on whether the device answers
+//! - `<prefix>/<id>/state`: retained JSON with state, track, volume, mute, source and loop mode
+//! - `<prefix>/<id>/command`: `play`, `pause`, `toggle`, `stop`, `next` or `prev`
+//! - `<prefix>/<id>/volume/set`: 0-100
+//! - `<prefix>/<id>/mute/set`: `ON` / `OFF`
+//! - `<prefix>/<id>/play_url/set`: a stream URL
+//!
+//! Devices added to or removed from the config file are announced or removed while running;
+//! changes to `[mqtt]` itself need a restart.
+
+use crate::output::CommandOutput;
+use crate::reload::ConfigWatcher;
+use crate::{build_client, configured_devices, parse_duration_arg, Cli, Config};
+use rumqttc::{AsyncClient, Event, EventLoop, LastWill, MqttOptions, Packet, QoS};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::error::Error;
+use std::time::Duration;
+use wiim_api::{NowPlaying, Result as WiimResult, Volume, WiimClient};
+
+/// Settings from the `[mqtt]` table
+#[derive(Debug, Default, Deserialize)]
+pub struct MqttConfig {
+    host: Option<String>,
+    port: Option<u16>,
+    username: Option<String>,
+    password: Option<String>,
+    client_id: Option<String>,
+    topic_prefix: Option<String>,
+    discovery_prefix: Option<String>,
+    /// Poll interval such as "2s"
+    interval: Option<String>,
+}
+
+const DEFAULT_PORT: u16 = 1883;
+const DEFAULT_TOPIC_PREFIX: &str = "wiim";
+const DEFAULT_DISCOVERY_PREFIX: &str = "homeassistant";
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Turn a device name or IP into an MQTT topic segment and Home Assistant object id
+fn device_id(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// A command received on a device's topics
+#[derive(Debug, PartialEq)]
+enum DeviceCommand {
+    Play,
+    Pause,
+    Toggle,
+    Stop,
+    Next,
+    Prev,
+    Volume(Volume),
+    Mute(bool),
+    PlayUrl(String),
+}
+
+/// Parse a publish on `<prefix>/<id>/...` into the device id and command
+fn parse_command(prefix: &str, topic: &str, payload: &str) -> Option<(String, DeviceCommand)> {
+    let rest = topic.strip_prefix(prefix)?.strip_prefix('/')?;
+    let (id, action) = rest.split_once('/')?;
+    let payload = payload.trim();
+    let command = match action {
+        "command" => match payload.to_ascii_lowercase().as_str() {
+            "play" => DeviceCommand::Play,
+            "pause" => DeviceCommand::Pause,
+            "toggle" => DeviceCommand::Toggle,
+            "stop" => DeviceCommand::Stop,
+            "next" => DeviceCommand::Next,
+            "prev" | "previous" => DeviceCommand::Prev,
+            _ => return None,
+        },
+        // Home Assistant number entities send floats such as "35.0"
+        "volume/set" => DeviceCommand::Volume(Volume::saturating(
+            payload.parse::<f64>().ok()?.clamp(0.0, 100.0) as u8,
+        )),
+        "mute/set" => DeviceCommand::Mute(payload.eq_ignore_ascii_case("on")),
+        "play_url/set" if !payload.is_empty() => DeviceCommand::PlayUrl(payload.to_string()),
+        _ => return None,
+    };
+    Some((id.to_string(), command))
+}
+
+async fn apply(client: &WiimClient, command: DeviceCommand) -> WiimResult<()> {
+    match command {
+        DeviceCommand::Play => client.resume().await,
+        DeviceCommand::Pause => client.pause().await,
+        DeviceCommand::Toggle => client.toggle_play_pause().await,
+        DeviceCommand::Stop => client.stop().await,
+        DeviceCommand::Next => client.next_track().await,
+        DeviceCommand::Prev => client.previous_track().await,
+        DeviceCommand::Volume(volume) => client.set_volume(volume).await,
+        DeviceCommand::Mute(true) => client.mute().await,
+        DeviceCommand::Mute(false) => client.unmute().await,
+        DeviceCommand::PlayUrl(url) => client.play_url(&url).await,
+    }
+}
+
+/// The retained state payload
+fn state_payload(now_playing: &NowPlaying) -> Value {
+    let track = match (&now_playing.artist, &now_playing.title) {
+        (Some(artist), Some(title)) if !artist.is_empty() => format!("{artist} - {title}"),
+        (_, Some(title)) => title.clone(),
+        _ => String::new(),
+    };
+    json!({
+        "state": now_playing.state,
+        "track": track,
+        "title": now_playing.title,
+        "artist": now_playing.artist,
+        "album": now_playing.album,
+        "album_art_uri": now_playing.album_art_uri,
+        "volume": now_playing.volume,
+        "muted": now_playing.is_muted,
+        "position_ms": now_playing.position.as_millis() as u64,
+        "duration_ms": now_playing.duration.as_millis() as u64,
+        "source": now_playing.source,
+        "repeat": now_playing.loop_mode.repeat,
+        "shuffle": now_playing.loop_mode.shuffle,
+    })
+}
+
+/// Home Assistant discovery messages (topic, retained config payload) for one device
+///
+/// Home Assistant's MQTT integration has no media player platform, so the device is
+/// announced as a group of standard entities: sensors for the track and playback state,
+/// a number for the volume, a switch for mute and buttons for the transport controls.
+fn discovery_messages(
+    discovery_prefix: &str,
+    topic_prefix: &str,
+    name: &str,
+    ip: &str,
+) -> Vec<(String, Value)> {
+    let id = device_id(name);
+    let base = format!("{topic_prefix}/{id}");
+    let common = json!({
+        "availability": [
+            { "topic": format!("{topic_prefix}/bridge/availability") },
+            { "topic": format!("{base}/availability") },
+        ],
+        "availability_mode": "all",
+        "device": {
+            "identifiers": [format!("wiim_{id}")],
+            "name": name,
+            "manufacturer": "WiiM",
+            "configuration_url": format!("https://{ip}"),
+        },
+    });
+
+    let mut entities = vec![
+        (
+            "sensor",
+            "now_playing",
+            json!({
+                "name": "Now playing",
+                "icon": "mdi:music",
+                "state_topic": format!("{base}/state"),
+                "value_template": "{{ value_json.track }}",
+                "json_attributes_topic": format!("{base}/state"),
+            }),
+        ),
+        (
+            "sensor",
+            "playback_state",
+            json!({
+                "name": "Playback state",
+                "icon": "mdi:play-pause",
+                "state_topic": format!("{base}/state"),
+                "value_template": "{{ value_json.state }}",
+            }),
+        ),
+        (
+            "number",
+            "volume",
+            json!({
+                "name": "Volume",
+                "icon": "mdi:volume-high",
+                "state_topic": format!("{base}/state"),
+                "value_template": "{{ value_json.volume }}",
+                "command_topic": format!("{base}/volume/set"),
+                "min": 0,
+                "max": 100,
+                "step": 1,
+                "mode": "slider",
+            }),
+        ),
+        (
+            "switch",
+            "mute",
+            json!({
+                "name": "Mute",
+                "icon": "mdi:volume-off",
+                "state_topic": format!("{base}/state"),
+                "value_template": "{{ 'ON' if value_json.muted else 'OFF' }}",
+                "command_topic": format!("{base}/mute/set"),
+            }),
+        ),
+    ];
+    for (action, label, icon) in [
+        ("play", "Play", "mdi:play"),
+        ("pause", "Pause", "mdi:pause"),
+        ("stop", "Stop", "mdi:stop"),
+        ("next", "Next track", "mdi:skip-next"),
+        ("prev", "Previous track", "mdi:skip-previous"),
+    ] {
+        entities.push((
+            "button",
+            action,
+            json!({
+                "name": label,
+                "icon": icon,
+                "command_topic": format!("{base}/command"),
+                "payload_press": action,
+            }),
+        ));
+    }
+
+    entities
+        .into_iter()
+        .map(|(component, object, mut payload)| {
+            payload["unique_id"] = json!(format!("wiim_{id}_{object}"));
+            payload["object_id"] = json!(format!("wiim_{id}_{object}"));
+            for (key, value) in common.as_object().expect("object literal") {
+                payload[key] = value.clone();
+            }
+            (
+                format!("{discovery_prefix}/{component}/wiim_{id}/{object}/config"),
+                payload,
+            )
+        })
+        .collect()
+}
+
+struct Device {
+    name: String,
+    ip: String,
+    client: WiimClient,
+    last: Option<NowPlaying>,
+    online: Option<bool>,
+}
+
+struct Bridge {
+    mqtt: AsyncClient,
+    topic_prefix: String,
+    discovery_prefix: String,
+    devices: HashMap<String, Device>,
+}
+
+impl Bridge {
+    async fn publish(&self, topic: String, payload: String) {
+        if let Err(e) = self
+            .mqtt
+            .publish(topic, QoS::AtLeastOnce, true, payload)
+            .await
+        {
+            eprintln!("⚠️ MQTT publish failed: {e}");
+        }
+    }
+
+    /// Announce the devices and subscribe to their command topics, after every (re)connect
+    async fn announce(&mut self) {
+        let prefix = &self.topic_prefix;
+        for topic in [
+            format!("{prefix}/+/command"),
+            format!("{prefix}/+/volume/set"),
+            format!("{prefix}/+/mute/set"),
+            format!("{prefix}/+/play_url/set"),
+            format!("{}/status", self.discovery_prefix),
+        ] {
+            if let Err(e) = self.mqtt.subscribe(topic, QoS::AtLeastOnce).await {
+                eprintln!("⚠️ MQTT subscribe failed: {e}");
+            }
+        }
+        self.publish(format!("{prefix}/bridge/availability"), "online".into())
+            .await;
+
+        for device in self.devices.values() {
+            for (topic, payload) in
+                discovery_messages(&self.discovery_prefix, prefix, &device.name, &device.ip)
+            {
+                self.publish(topic, payload.to_string()).await;
+            }
+        }
+        // Republish state so new subscribers (or a restarted Home Assistant) catch up
+        for device in self.devices.values_mut() {
+            device.last = None;
+            device.online = None;
+        }
+    }
+
+    /// Switch to the devices of a reloaded config: removed devices' entities are deleted
+    /// from Home Assistant, added ones are announced, unchanged ones keep their state
+    async fn set_devices(&mut self, mut devices: HashMap<String, Device>) {
+        let prefix = self.topic_prefix.clone();
+        let mut kept = Vec::new();
+        for (id, old) in std::mem::take(&mut self.devices) {
+            match devices.get_mut(&id) {
+                Some(new) if new.ip == old.ip => {
+                    *new = old;
+                    kept.push(id);
+                }
+                _ => {
+                    eprintln!("➖ No longer bridging {}", old.name);
+                    // An empty retained config removes the entity from Home Assistant
+                    for (topic, _) in
+                        discovery_messages(&self.discovery_prefix, &prefix, &old.name, &old.ip)
+                    {
+                        self.publish(topic, String::new()).await;
+                    }
+                    self.publish(format!("{prefix}/{id}/availability"), "offline".into())
+                        .await;
+                }
+            }
+        }
+        for (id, device) in &devices {
+            if kept.contains(id) {
+                continue;
+            }
+            eprintln!("➕ Now bridging {}", device.name);
+            for (topic, payload) in
+                discovery_messages(&self.discovery_prefix, &prefix, &device.name, &device.ip)
+            {
+                self.publish(topic, payload.to_string()).await;
+            }
+        }
+        self.devices = devices;
+    }
+
+    async fn poll(&mut self) {
+        let results = futures_util::future::join_all(
+            self.devices
+                .values()
+                .map(|device| device.client.get_now_playing()),
+        )
+        .await;
+
+        let mut messages = Vec::new();
+        for ((id, device), result) in self.devices.iter_mut().zip(results) {
+            let online = result.is_ok();
+            if device.online != Some(online) {
+                device.online = Some(online);
+                let availability = if online { "online" } else { "offline" };
+                messages.push((
+                    format!("{}/{id}/availability", self.topic_prefix),
+                    availability.to_string(),
+                ));
+            }
+            if let Ok(now_playing) = result {
+                let changed = device
+                    .last
+                    .as_ref()
+                    .is_none_or(|last| now_playing.differs_from(last));
+                if changed {
+                    messages.push((
+                        format!("{}/{id}/state", self.topic_prefix),
+                        state_payload(&now_playing).to_string(),
+                    ));
+                    device.last = Some(now_playing);
+                }
+            }
+        }
+        for (topic, payload) in messages {
+            self.publish(topic, payload).await;
+        }
+    }
+
+    async fn handle_publish(&mut self, topic: &str, payload: &str) {
+        // Home Assistant announces restarts here; discovery must be sent again
+        if topic == format!("{}/status", self.discovery_prefix) {
+            if payload == "online" {
+                self.announce().await;
+            }
+            return;
+        }
+        let Some((id, command)) = parse_command(&self.topic_prefix, topic, payload) else {
+            return;
+        };
+        let Some(device) = self.devices.get_mut(&id) else {
+            eprintln!("⚠️ MQTT command for unknown device '{id}'");
+            return;
+        };
+        match apply(&device.client, command).await {
+            // Force a fresh state publish on the next poll
+            Ok(()) => device.last = None,
+            Err(e) => eprintln!("⚠️ {}: {e}", device.name),
+        }
+    }
+}
+
+/// Every configured device by MQTT id
+fn bridged_devices(cli: &Cli, config: &Config) -> Result<HashMap<String, Device>, Box<dyn Error>> {
+    let mut devices = HashMap::new();
+    for (name, ip) in configured_devices(config) {
+        let client = build_client(&ip, cli, config)?;
+        devices.insert(
+            device_id(&name),
+            Device {
+                name,
+                ip,
+                client,
+                last: None,
+                online: None,
+            },
+        );
+    }
+    Ok(devices)
+}
+
+pub async fn run(cli: &Cli, config: &Config) -> Result<CommandOutput, Box<dyn Error>> {
+    let settings = config.mqtt.as_ref();
+    let setting = |get: fn(&MqttConfig) -> Option<&String>| settings.and_then(get).cloned();
+
+    let host = setting(|s| s.host.as_ref()).ok_or_else(|| {
+        crate::error::CliError::Config("mqtt.host must be set to use the MQTT bridge".to_string())
+    })?;
+    let port = settings.and_then(|s| s.port).unwrap_or(DEFAULT_PORT);
+    let interval = match setting(|s| s.interval.as_ref()) {
+        Some(interval) => parse_duration_arg(&interval)
+            .map_err(|e| crate::error::CliError::Config(format!("mqtt.interval: {e}")))?,
+        None => DEFAULT_INTERVAL,
+    };
+    let topic_prefix =
+        setting(|s| s.topic_prefix.as_ref()).unwrap_or_else(|| DEFAULT_TOPIC_PREFIX.to_string());
+    let discovery_prefix = setting(|s| s.discovery_prefix.as_ref())
+        .unwrap_or_else(|| DEFAULT_DISCOVERY_PREFIX.to_string());
+    let client_id = setting(|s| s.client_id.as_ref()).unwrap_or_else(|| "wiim-control".to_string());
+
+    let mut options = MqttOptions::new(client_id, host, port);
+    options.set_keep_alive(Duration::from_secs(30));
+    options.set_last_will(LastWill::new(
+        format!("{topic_prefix}/bridge/availability"),
+        "offline",
+        QoS::AtLeastOnce,
+        true,
+    ));
+    if let Some(username) = setting(|s| s.username.as_ref()) {
+        options.set_credentials(
+            username,
+            setting(|s| s.password.as_ref()).unwrap_or_default(),
+        );
+    }
+    let (mqtt, mut eventloop): (AsyncClient, EventLoop) = AsyncClient::new(options, 64);
+
+    let mut bridge = Bridge {
+        mqtt,
+        topic_prefix,
+        discovery_prefix,
+        devices: bridged_devices(cli, config)?,
+    };
+
+    let mut watcher = ConfigWatcher::new(cli);
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => bridge.poll().await,
+            reload = watcher.changed() => {
+                if reload.touches("mqtt") {
+                    eprintln!("⚠️ [mqtt] changes take effect after a restart");
+                }
+                let relevant = ["devices", "device_ip", "timeout", "retries"];
+                if relevant.iter().any(|section| reload.touches(section)) {
+                    match bridged_devices(cli, &reload.config) {
+                        Ok(devices) => bridge.set_devices(devices).await,
+                        Err(e) => eprintln!("⚠️ Devices not reloaded: {e}"),
+                    }
+                }
+            }
+            event = eventloop.poll() => match event {
+                Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                    eprintln!("📡 Connected to MQTT broker");
+                    bridge.announce().await;
+                }
+                Ok(Event::Incoming(Packet::Publish(publish))) => {
+                    let payload = String::from_utf8_lossy(&publish.payload).into_owned();
+                    bridge.handle_publish(&publish.topic, &payload).await;
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    // The event loop reconnects on the next poll; don't spin while the broker is down
+                    eprintln!("⚠️ MQTT connection error: {e}");
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_command() {
+        assert_eq!(
+            parse_command("wiim", "wiim/kitchen/command", "next"),
+            Some(("kitchen".to_string(), DeviceCommand::Next))
+        );
+        assert_eq!(
+            parse_command("wiim", "wiim/kitchen/volume/set", "35.0"),
+            Some((
+                "kitchen".to_string(),
+                DeviceCommand::Volume(Volume::saturating(35))
+            ))
+        );
+        assert_eq!(
+            parse_command("wiim", "wiim/kitchen/mute/set", "OFF"),
+            Some(("kitchen".to_string(), DeviceCommand::Mute(false)))
+        );
+        assert_eq!(parse_command("wiim", "wiim/kitchen/command", "dance"), None);
+        assert_eq!(parse_command("wiim", "other/kitchen/command", "play"), None);
+        assert_eq!(parse_command("wiim", "wiim/kitchen/state", "{}"), None);
+    }
+
+    #[test]
+    fn test_discovery_messages() {
+        let messages = discovery_messages("homeassistant", "wiim", "Living Room", "192.168.1.5");
+        let (topic, volume) = messages
+            .iter()
+            .find(|(topic, _)| topic.contains("/number/"))
+            .unwrap();
+        assert_eq!(topic, "homeassistant/number/wiim_living_room/volume/config");
+        assert_eq!(volume["command_topic"], "wiim/living_room/volume/set");
+        assert_eq!(volume["unique_id"], "wiim_living_room_volume");
+        assert_eq!(volume["device"]["identifiers"][0], "wiim_living_room");
+
+        // Every entity has a unique id
+        let mut ids: Vec<&str> = messages
+            .iter()
+            .map(|(_, payload)| payload["unique_id"].as_str().unwrap())
+            .collect();
+        ids.sort_unstable();
+        ids.dedup();
+        assert_eq!(ids.len(), messages.len());
+    }
+}
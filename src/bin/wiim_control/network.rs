@@ -0,0 +1,229 @@
+//! `wiim-control network`: the device's Wi-Fi figures, once or continuously
+//!
+//! With `--watch` a line is printed per sample with a sparkline of the recent signal
+//! and its range so far, which makes it easy to carry the device around and compare
+//! spots. Ctrl-C ends the watch with a summary of the whole session.
+
+use crate::i18n;
+use crate::output::{CommandOutput, JsonResult};
+use serde::Serialize;
+use serde_json::json;
+use std::collections::VecDeque;
+use std::error::Error;
+use std::time::Duration;
+use wiim_api::{StatusEx, WiimClient};
+
+/// Samples shown in the sparkline
+const SPARKLINE_WIDTH: usize = 30;
+/// Signal range the sparkline spans; anything outside is drawn at the ends
+const SPARKLINE_FLOOR_DBM: i32 = -90;
+const SPARKLINE_CEILING_DBM: i32 = -30;
+const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// One sample of the Wi-Fi figures in `getStatusEx`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+struct Reading {
+    rssi_dbm: Option<i32>,
+    snr_db: Option<i32>,
+    noise_dbm: Option<i32>,
+    data_rate_mbps: Option<u32>,
+}
+
+impl From<&StatusEx> for Reading {
+    fn from(status: &StatusEx) -> Self {
+        let figure = |value: &Option<String>| value.as_deref()?.trim().parse().ok();
+        Reading {
+            rssi_dbm: status.rssi_dbm(),
+            snr_db: figure(&status.wlan_snr),
+            noise_dbm: figure(&status.wlan_noise),
+            data_rate_mbps: status.data_rate_mbps(),
+        }
+    }
+}
+
+impl Reading {
+    fn line(&self) -> String {
+        i18n::tr(
+            "network.reading",
+            &[
+                ("rssi", &or_unknown(self.rssi_dbm)),
+                ("snr", &or_unknown(self.snr_db)),
+                ("noise", &or_unknown(self.noise_dbm)),
+                ("rate", &or_unknown(self.data_rate_mbps)),
+            ],
+        )
+    }
+}
+
+fn or_unknown<T: ToString>(value: Option<T>) -> String {
+    value.map_or_else(|| "?".to_string(), |value| value.to_string())
+}
+
+/// Lowest, highest and average of one figure over the session
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+struct Range {
+    min: i32,
+    max: i32,
+    mean: f64,
+    #[serde(skip)]
+    count: u32,
+}
+
+impl Range {
+    fn add(range: &mut Option<Range>, value: Option<i32>) {
+        let Some(value) = value else { return };
+        match range {
+            Some(range) => {
+                range.min = range.min.min(value);
+                range.max = range.max.max(value);
+                range.count += 1;
+                range.mean += (f64::from(value) - range.mean) / f64::from(range.count);
+            }
+            None => {
+                *range = Some(Range {
+                    min: value,
+                    max: value,
+                    mean: f64::from(value),
+                    count: 1,
+                })
+            }
+        }
+    }
+}
+
+/// What `--watch` has seen so far
+#[derive(Debug, Default, Serialize)]
+struct History {
+    samples: u32,
+    failures: u32,
+    rssi_dbm: Option<Range>,
+    snr_db: Option<Range>,
+    #[serde(skip)]
+    recent_rssi: VecDeque<i32>,
+}
+
+impl History {
+    fn add(&mut self, reading: &Reading) {
+        self.samples += 1;
+        Range::add(&mut self.rssi_dbm, reading.rssi_dbm);
+        Range::add(&mut self.snr_db, reading.snr_db);
+        if let Some(rssi) = reading.rssi_dbm {
+            if self.recent_rssi.len() == SPARKLINE_WIDTH {
+                self.recent_rssi.pop_front();
+            }
+            self.recent_rssi.push_back(rssi);
+        }
+    }
+
+    fn line(&self, reading: &Reading) -> String {
+        let range = self
+            .rssi_dbm
+            .map(|range| {
+                format!(
+                    "  {}",
+                    i18n::tr("network.range", &[("min", &range.min), ("max", &range.max)])
+                )
+            })
+            .unwrap_or_default();
+        format!("{} {}{range}", sparkline(&self.recent_rssi), reading.line())
+    }
+
+    fn summary(&self) -> String {
+        let Some(rssi) = self.rssi_dbm else {
+            return i18n::tr("network.no_samples", &[]);
+        };
+        i18n::tr(
+            "network.summary",
+            &[
+                ("samples", &self.samples),
+                ("min", &rssi.min),
+                ("max", &rssi.max),
+                ("mean", &format!("{:.0}", rssi.mean)),
+            ],
+        )
+    }
+}
+
+fn sparkline(values: &VecDeque<i32>) -> String {
+    let span = f64::from(SPARKLINE_CEILING_DBM - SPARKLINE_FLOOR_DBM);
+    values
+        .iter()
+        .map(|value| {
+            let level = f64::from(
+                value.clamp(&SPARKLINE_FLOOR_DBM, &SPARKLINE_CEILING_DBM) - SPARKLINE_FLOOR_DBM,
+            ) / span;
+            BARS[(level * (BARS.len() - 1) as f64).round() as usize]
+        })
+        .collect()
+}
+
+pub async fn run(
+    client: &WiimClient,
+    watch: bool,
+    interval: Duration,
+    json: bool,
+) -> Result<CommandOutput, Box<dyn Error>> {
+    if !watch {
+        let reading = Reading::from(&client.get_status_ex().await?);
+        return Ok(CommandOutput::line(reading.line()).with_state(reading));
+    }
+
+    let mut history = History::default();
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => break,
+            _ = ticker.tick() => {}
+        }
+        match client.get_status_ex().await {
+            Ok(status) => {
+                let reading = Reading::from(&status);
+                history.add(&reading);
+                if json {
+                    JsonResult::ok("network", json!(reading)).print();
+                } else {
+                    println!("{}", history.line(&reading));
+                }
+            }
+            Err(e) if json => {
+                history.failures += 1;
+                JsonResult::error("network", e).print();
+            }
+            Err(e) => {
+                history.failures += 1;
+                eprintln!("⚠️ {e}");
+            }
+        }
+    }
+    Ok(CommandOutput::message(history.summary()).with_state(&history))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sparkline() {
+        let values: VecDeque<i32> = [-95, -90, -60, -30, -20].into();
+        assert_eq!(sparkline(&values), "▁▁▅██");
+    }
+
+    #[test]
+    fn test_history() {
+        let mut history = History::default();
+        for rssi in [-60, -70, -50] {
+            history.add(&Reading {
+                rssi_dbm: Some(rssi),
+                ..Default::default()
+            });
+        }
+        history.add(&Reading::default());
+        let rssi = history.rssi_dbm.unwrap();
+        assert_eq!((rssi.min, rssi.max), (-70, -50));
+        assert!((rssi.mean + 60.0).abs() < 1e-9);
+        assert_eq!(history.samples, 4);
+        assert_eq!(history.recent_rssi.len(), 3);
+        assert_eq!(history.snr_db, None);
+    }
+}
@@ -0,0 +1,182 @@
+//! Offline rendering for status bars: when the device is unreachable, show a configurable
+//! template (optionally with the last known track) instead of failing
+
+use crate::output::CommandOutput;
+use crate::{render_template, OutputFormat, ResolvedProfile, StatusOutput, TemplateContext};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::fs;
+use wiim_api::{NowPlaying, Result as WiimResult, WiimClient, WiimError};
+
+/// Last successful status of a device
+#[derive(Serialize, Deserialize)]
+pub struct CachedStatus {
+    /// Seconds since the Unix epoch
+    saved_at: u64,
+    now_playing: NowPlaying,
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+fn cache_path(client: &WiimClient) -> Option<PathBuf> {
+    let device: String = client
+        .get_ip_address()
+        .trim_start_matches("https://")
+        .trim_start_matches("http://")
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    dirs::cache_dir().map(|dir| {
+        dir.join("wiim-control")
+            .join(format!("last-status-{device}.json"))
+    })
+}
+
+/// Remember the latest status; failures are ignored since the cache is best-effort
+pub async fn save(client: &WiimClient, now_playing: &NowPlaying) {
+    let Some(path) = cache_path(client) else {
+        return;
+    };
+    let cached = CachedStatus {
+        saved_at: unix_now(),
+        now_playing: now_playing.clone(),
+    };
+    if let Ok(content) = serde_json::to_string(&cached) {
+        if let Some(parent) = path.parent() {
+            let _ = fs::create_dir_all(parent).await;
+        }
+        let _ = fs::write(&path, content).await;
+    }
+}
+
+pub async fn load(client: &WiimClient) -> Option<CachedStatus> {
+    let content = fs::read_to_string(cache_path(client)?).await.ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// Whether an error means the device could not be reached at all
+pub fn is_unreachable(error: &WiimError) -> bool {
+    matches!(error, WiimError::Request(_))
+}
+
+/// Human-friendly age such as "just now", "5m ago" or "2h ago"
+fn format_age(seconds: u64) -> String {
+    match seconds {
+        0..=59 => "just now".to_string(),
+        60..=3599 => format!("{}m ago", seconds / 60),
+        3600..=86399 => format!("{}h ago", seconds / 3600),
+        _ => format!("{}d ago", seconds / 86400),
+    }
+}
+
+/// Render the offline template with the last known track (if any) and these extra variables:
+/// `offline` (always true), `last_seen` (e.g. "5m ago") and `error`
+pub fn render(
+    template: &str,
+    cached: Option<&CachedStatus>,
+    error: &WiimError,
+    resolved_profile: &ResolvedProfile,
+) -> WiimResult<CommandOutput> {
+    let age = cached.map(|cached| unix_now().saturating_sub(cached.saved_at));
+
+    let mut context = match cached {
+        Some(cached) => serde_json::to_value(TemplateContext::from(&cached.now_playing))?,
+        None => json!({}),
+    };
+    context["offline"] = json!(true);
+    context["last_seen"] = json!(age.map(format_age));
+    context["error"] = json!(error.to_string());
+
+    let text = render_template(template, &context)?;
+    let line = match resolved_profile.format {
+        OutputFormat::Text => text,
+        OutputFormat::Json => serde_json::to_string(&StatusOutput {
+            text,
+            alt: "offline".to_string(),
+            tooltip: format!("Device unreachable: {error}"),
+            class: "offline".to_string(),
+            percentage: None,
+        })?,
+    };
+
+    Ok(CommandOutput::line(line).with_state(json!({
+        "offline": true,
+        "last_seen_secs": age,
+        "now_playing": cached.map(|cached| &cached.now_playing),
+        "error": error.to_string(),
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiim_api::{PlayState, Volume};
+
+    fn offline_error() -> WiimError {
+        WiimError::InvalidResponse("connection refused".to_string())
+    }
+
+    fn text_profile() -> ResolvedProfile {
+        ResolvedProfile {
+            format: OutputFormat::Text,
+            text_template: None,
+            json_templates: None,
+            offline_template: None,
+        }
+    }
+
+    #[test]
+    fn test_format_age() {
+        assert_eq!(format_age(5), "just now");
+        assert_eq!(format_age(300), "5m ago");
+        assert_eq!(format_age(7200), "2h ago");
+        assert_eq!(format_age(3 * 86400), "3d ago");
+    }
+
+    #[test]
+    fn test_render_with_last_known_track() {
+        let cached = CachedStatus {
+            saved_at: unix_now() - 120,
+            now_playing: NowPlaying {
+                title: Some("Song".to_string()),
+                artist: Some("Band".to_string()),
+                state: PlayState::Playing,
+                volume: Volume::saturating(30),
+                ..Default::default()
+            },
+        };
+        let template = "⚠️ offline{{#if title}} · {{artist}} - {{title}} ({{last_seen}}){{/if}}";
+
+        let output = render(template, Some(&cached), &offline_error(), &text_profile()).unwrap();
+        assert_eq!(output.lines, ["⚠️ offline · Band - Song (2m ago)"]);
+        assert_eq!(output.state["offline"], true);
+
+        let output = render(template, None, &offline_error(), &text_profile()).unwrap();
+        assert_eq!(output.lines, ["⚠️ offline"]);
+    }
+
+    #[test]
+    fn test_render_json_profile() {
+        let profile = ResolvedProfile {
+            format: OutputFormat::Json,
+            ..text_profile()
+        };
+        let output = render("offline", None, &offline_error(), &profile).unwrap();
+        let status: serde_json::Value = serde_json::from_str(&output.lines[0]).unwrap();
+        assert_eq!(status["text"], "offline");
+        assert_eq!(status["class"], "offline");
+    }
+}
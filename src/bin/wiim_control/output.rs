@@ -0,0 +1,124 @@
+//! Command results, printed for humans or as one JSON object per result with `--json`
+
+use serde::Serialize;
+use serde_json::Value;
+
+/// What a command produced
+#[derive(Debug, Default)]
+pub struct CommandOutput {
+    /// Confirmation printed to stderr in text mode
+    pub message: Option<String>,
+    /// Lines printed to stdout in text mode
+    pub lines: Vec<String>,
+    /// Resulting state, reported as `state` in JSON mode
+    pub state: Value,
+    /// Set when the command ran but found problems (e.g. failed doctor checks)
+    pub problem: Option<String>,
+}
+
+impl CommandOutput {
+    pub fn message(message: impl Into<String>) -> Self {
+        Self {
+            message: Some(message.into()),
+            ..Default::default()
+        }
+    }
+
+    pub fn lines(lines: Vec<String>) -> Self {
+        Self {
+            lines,
+            ..Default::default()
+        }
+    }
+
+    pub fn line(line: impl Into<String>) -> Self {
+        Self::lines(vec![line.into()])
+    }
+
+    pub fn with_state(mut self, state: impl Serialize) -> Self {
+        self.state = serde_json::to_value(state).unwrap_or(Value::Null);
+        self
+    }
+}
+
+/// The `--json` envelope: `{"action", "ok", "state", "error"}`
+#[derive(Debug, Serialize)]
+pub struct JsonResult<'a> {
+    pub action: &'a str,
+    pub ok: bool,
+    pub state: Value,
+    pub error: Option<String>,
+}
+
+impl<'a> JsonResult<'a> {
+    pub fn ok(action: &'a str, state: Value) -> Self {
+        Self {
+            action,
+            ok: true,
+            state,
+            error: None,
+        }
+    }
+
+    pub fn error(action: &'a str, error: impl ToString) -> Self {
+        Self {
+            action,
+            ok: false,
+            state: Value::Null,
+            error: Some(error.to_string()),
+        }
+    }
+
+    pub fn from_output(action: &'a str, output: CommandOutput) -> Self {
+        Self {
+            action,
+            ok: output.problem.is_none(),
+            state: output.state,
+            error: output.problem,
+        }
+    }
+
+    pub fn print(&self) {
+        match serde_json::to_string(self) {
+            Ok(json) => println!("{json}"),
+            Err(e) => eprintln!("Failed to serialize result: {e}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_json_result_envelope() {
+        let output =
+            CommandOutput::message("🔊 Volume set to 40%").with_state(json!({"volume": 40}));
+        let result = serde_json::to_value(JsonResult::from_output("volume", output)).unwrap();
+        assert_eq!(
+            result,
+            json!({"action": "volume", "ok": true, "state": {"volume": 40}, "error": null})
+        );
+
+        let result =
+            serde_json::to_value(JsonResult::error("play", "HTTP request failed")).unwrap();
+        assert_eq!(
+            result,
+            json!({"action": "play", "ok": false, "state": null, "error": "HTTP request failed"})
+        );
+    }
+
+    #[test]
+    fn test_problem_marks_result_failed() {
+        let output = CommandOutput {
+            problem: Some("1 check failed".to_string()),
+            ..Default::default()
+        }
+        .with_state(json!({"checks": []}));
+        let result = JsonResult::from_output("doctor", output);
+        assert!(!result.ok);
+        assert_eq!(result.state, json!({"checks": []}));
+        assert_eq!(result.error.as_deref(), Some("1 check failed"));
+    }
+}
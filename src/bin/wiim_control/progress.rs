@@ -0,0 +1,95 @@
+//! The `{{progress_bar}}` template variable
+//!
+//! ```toml
+//! [progress_bar]
+//! width = 20
+//! filled = "━"
+//! empty = "─"
+//! ```
+
+use serde::Deserialize;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+static STYLE: OnceLock<ProgressBarConfig> = OnceLock::new();
+
+const DEFAULT_WIDTH: usize = 10;
+const DEFAULT_FILLED: &str = "█";
+const DEFAULT_EMPTY: &str = "░";
+
+pub const PROGRESS_BAR_KEYS: [&str; 3] = ["width", "filled", "empty"];
+
+/// `[progress_bar]` in the config
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct ProgressBarConfig {
+    /// Number of characters in the bar
+    width: Option<usize>,
+    /// Drawn for the part already played
+    filled: Option<String>,
+    /// Drawn for the part still to come
+    empty: Option<String>,
+}
+
+/// Use the configured bar style; call once after loading the config
+pub fn init(config: Option<&ProgressBarConfig>) {
+    // Only the first initialization counts
+    let _ = STYLE.set(config.cloned().unwrap_or_default());
+}
+
+impl ProgressBarConfig {
+    fn render(&self, position: Duration, duration: Duration) -> String {
+        // Streams have no length, so there is nothing to show
+        if duration.is_zero() {
+            return String::new();
+        }
+        let width = self.width.unwrap_or(DEFAULT_WIDTH);
+        let played = position.min(duration).as_secs_f64() / duration.as_secs_f64();
+        let filled = ((played * width as f64).round() as usize).min(width);
+        format!(
+            "{}{}",
+            self.filled
+                .as_deref()
+                .unwrap_or(DEFAULT_FILLED)
+                .repeat(filled),
+            self.empty
+                .as_deref()
+                .unwrap_or(DEFAULT_EMPTY)
+                .repeat(width - filled)
+        )
+    }
+}
+
+/// The track position as a bar, or an empty string when the duration is unknown
+pub fn render(position: Duration, duration: Duration) -> String {
+    STYLE
+        .get_or_init(ProgressBarConfig::default)
+        .render(position, duration)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_default_style() {
+        let style = ProgressBarConfig::default();
+        let secs = Duration::from_secs;
+        assert_eq!(style.render(secs(0), secs(200)), "░░░░░░░░░░");
+        assert_eq!(style.render(secs(100), secs(200)), "█████░░░░░");
+        assert_eq!(style.render(secs(250), secs(200)), "██████████");
+        assert_eq!(style.render(secs(30), secs(0)), "");
+    }
+
+    #[test]
+    fn test_render_configured_style() {
+        let style = ProgressBarConfig {
+            width: Some(4),
+            filled: Some("=".to_string()),
+            empty: Some("-".to_string()),
+        };
+        assert_eq!(
+            style.render(Duration::from_secs(45), Duration::from_secs(60)),
+            "===-"
+        );
+    }
+}
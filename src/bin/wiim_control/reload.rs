@@ -0,0 +1,146 @@
+//! Picking up config file edits in the long-running modes (`daemon`, `serve`, `mqtt`)
+//!
+//! The file's modification time is polled; when it changes the file is parsed again and
+//! the top-level sections that differ are reported, so each mode can rebuild only what it
+//! needs. A file that no longer parses is reported and the running config is kept.
+
+use crate::{config_path, templates, Cli, Config};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// How often the config file is checked for changes
+const CHECK_INTERVAL: Duration = Duration::from_secs(2);
+
+/// A successfully parsed config edit
+pub struct Reload {
+    pub config: Config,
+    /// Top-level keys that were added, removed or changed, e.g. `devices` or `webhooks`
+    pub sections: Vec<String>,
+}
+
+impl Reload {
+    pub fn touches(&self, section: &str) -> bool {
+        self.sections.iter().any(|changed| changed == section)
+    }
+}
+
+pub struct ConfigWatcher {
+    /// `None` with `--no-config`, when there is nothing to watch
+    path: Option<PathBuf>,
+    modified: Option<SystemTime>,
+    table: toml::Table,
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+fn read(path: &Path) -> Result<(toml::Table, Config), String> {
+    let content = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let table = content.parse::<toml::Table>().map_err(|e| e.to_string())?;
+    let config = toml::from_str(&content).map_err(|e| e.to_string())?;
+    Ok((table, config))
+}
+
+/// Top-level keys whose values differ between two versions of the file
+fn changed_sections(old: &toml::Table, new: &toml::Table) -> Vec<String> {
+    let mut sections: Vec<String> = old
+        .keys()
+        .chain(new.keys())
+        .filter(|key| old.get(*key) != new.get(*key))
+        .cloned()
+        .collect();
+    sections.sort();
+    sections.dedup();
+    sections
+}
+
+impl ConfigWatcher {
+    /// Watch the file given with `--config`, or the default config file
+    pub fn new(cli: &Cli) -> Self {
+        let path = config_path(cli);
+        let table = path
+            .as_deref()
+            .and_then(|path| read(path).ok())
+            .map(|(table, _)| table)
+            .unwrap_or_default();
+        Self {
+            modified: path.as_deref().and_then(modified),
+            path,
+            table,
+        }
+    }
+
+    /// Wait until the file changes and parses, then return the new config
+    ///
+    /// Partials and helpers are re-registered before returning, so templates rendered
+    /// afterwards already use them.
+    pub async fn changed(&mut self) -> Reload {
+        let Some(path) = &self.path else {
+            return std::future::pending().await;
+        };
+        loop {
+            tokio::time::sleep(CHECK_INTERVAL).await;
+            let current = modified(path);
+            if current == self.modified {
+                continue;
+            }
+            self.modified = current;
+
+            let (table, config) = match read(path) {
+                Ok(parsed) => parsed,
+                Err(e) => {
+                    eprintln!(
+                        "⚠️ Not reloading {}: {e}; keeping the running config",
+                        path.display()
+                    );
+                    continue;
+                }
+            };
+            let sections = changed_sections(&self.table, &table);
+            if sections.is_empty() {
+                continue;
+            }
+            if let Err(e) = templates::reload(config.partials.as_ref(), config.helpers.as_ref()) {
+                eprintln!("⚠️ Not reloading {}: {e}", path.display());
+                continue;
+            }
+            self.table = table;
+            eprintln!("🔄 Config reloaded: {} changed", sections.join(", "));
+            return Reload { config, sections };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changed_sections() {
+        let old: toml::Table = r#"
+            device_ip = "192.168.1.100"
+            [devices]
+            kitchen = "192.168.1.101"
+            [webhooks.ha]
+            url = "http://ha/hook"
+        "#
+        .parse()
+        .unwrap();
+        let new: toml::Table = r#"
+            device_ip = "192.168.1.100"
+            [devices]
+            kitchen = "192.168.1.101"
+            office = "192.168.1.102"
+            [history]
+            format = "jsonl"
+        "#
+        .parse()
+        .unwrap();
+        assert_eq!(
+            changed_sections(&old, &new),
+            ["devices", "history", "webhooks"]
+        );
+        assert!(changed_sections(&new, &new).is_empty());
+    }
+}
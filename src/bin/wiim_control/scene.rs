@@ -0,0 +1,147 @@
+//! `wiim-control scene`: named snapshots of volume, mute, input, EQ preset and loop mode
+//!
+//! Each scene is a TOML file in `~/.config/wiim-control/scenes/`, so scenes can also be
+//! written or tweaked by hand.
+
+use crate::error::CliError;
+use crate::i18n;
+use crate::output::CommandOutput;
+use clap::Subcommand;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use wiim_api::{Scene, WiimClient};
+
+#[derive(Subcommand, Clone)]
+pub enum SceneCommand {
+    /// Save the current volume, mute, input, EQ preset and loop mode under a name
+    Save {
+        #[arg(value_parser = parse_name)]
+        name: String,
+    },
+    /// Put the device back into a saved scene
+    Apply {
+        #[arg(value_parser = parse_name)]
+        name: String,
+    },
+}
+
+/// Scene names become file names, so keep them to letters, digits, `-` and `_`
+fn parse_name(value: &str) -> Result<String, String> {
+    let valid = !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(value.to_string())
+    } else {
+        Err(format!(
+            "invalid scene name '{value}' (use letters, digits, - and _)"
+        ))
+    }
+}
+
+fn scenes_dir() -> Result<PathBuf, CliError> {
+    dirs::config_dir()
+        .map(|dir| dir.join("wiim-control").join("scenes"))
+        .ok_or_else(|| CliError::Config("Could not find config directory".to_string()))
+}
+
+fn scene_path(dir: &Path, name: &str) -> PathBuf {
+    dir.join(format!("{name}.toml"))
+}
+
+fn save(dir: &Path, name: &str, scene: &Scene) -> Result<PathBuf, Box<dyn Error>> {
+    std::fs::create_dir_all(dir)?;
+    let path = scene_path(dir, name);
+    std::fs::write(&path, toml::to_string(scene)?)?;
+    Ok(path)
+}
+
+fn saved_names(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            let is_toml = path.extension().is_some_and(|ext| ext == "toml");
+            is_toml
+                .then(|| path.file_stem()?.to_str().map(str::to_string))
+                .flatten()
+        })
+        .collect();
+    names.sort();
+    names
+}
+
+fn load(dir: &Path, name: &str) -> Result<Scene, Box<dyn Error>> {
+    let path = scene_path(dir, name);
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            let saved = saved_names(dir);
+            let saved = if saved.is_empty() {
+                "none saved yet".to_string()
+            } else {
+                format!("saved: {}", saved.join(", "))
+            };
+            return Err(CliError::Usage(format!("No scene named '{name}' ({saved})")).into());
+        }
+        Err(e) => return Err(e.into()),
+    };
+    toml::from_str(&content)
+        .map_err(|e| CliError::Config(format!("Invalid scene {}: {e}", path.display())).into())
+}
+
+pub async fn run(
+    client: &WiimClient,
+    action: SceneCommand,
+) -> Result<CommandOutput, Box<dyn Error>> {
+    let dir = scenes_dir()?;
+    let output = match action {
+        SceneCommand::Save { name } => {
+            let scene = client.capture_scene().await?;
+            save(&dir, &name, &scene)?;
+            CommandOutput::message(i18n::tr("scene.saved", &[("name", &name)])).with_state(scene)
+        }
+        SceneCommand::Apply { name } => {
+            let scene = load(&dir, &name)?;
+            client.apply_scene(&scene).await?;
+            CommandOutput::message(i18n::tr("scene.applied", &[("name", &name)])).with_state(scene)
+        }
+    };
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiim_api::{LoopMode, PlaybackSource, Volume};
+
+    #[test]
+    fn test_parse_name() {
+        assert!(parse_name("evening").is_ok());
+        assert!(parse_name("party_2").is_ok());
+        assert!(parse_name("").is_err());
+        assert!(parse_name("../config").is_err());
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = std::env::temp_dir().join(format!("wiim-scenes-{}", std::process::id()));
+        let scene = Scene {
+            volume: Volume::saturating(25),
+            muted: false,
+            source: PlaybackSource::LineIn,
+            eq: None,
+            loop_mode: LoopMode::default(),
+        };
+        save(&dir, "evening", &scene).unwrap();
+        assert_eq!(load(&dir, "evening").unwrap(), scene);
+        assert_eq!(saved_names(&dir), ["evening"]);
+
+        let error = load(&dir, "party").unwrap_err();
+        assert_eq!(error.to_string(), "No scene named 'party' (saved: evening)");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
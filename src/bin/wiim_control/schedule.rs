@@ -0,0 +1,282 @@
+//! Time-based rules run by `wiim-control daemon`, such as "weekdays 07:00: preset 2 at
+//! volume 30" or "23:00: fade to 0 and stop"
+//!
+//! Each `[schedule.<name>]` table has `at` (HH:MM, local time), an optional `days` list (as
+//! for alarms, default daily), optional `device` (a name from `[devices]`, an IP, or `all`;
+//! default `device_ip`) and an `actions` list run in order.
+
+use crate::{
+    build_client, configured_devices, parse_alarm_time, parse_day_list, parse_duration_arg,
+    resolve_device, Cli, Config,
+};
+use chrono::{Datelike, Local, Timelike};
+use serde::Deserialize;
+use std::error::Error;
+use std::time::Duration;
+use wiim_api::{AlarmTime, Volume, Weekdays, WiimClient};
+
+/// Settings from a `[schedule.<name>]` table
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScheduleConfig {
+    at: String,
+    days: Option<Vec<String>>,
+    device: Option<String>,
+    actions: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    Play,
+    Pause,
+    Stop,
+    Mute,
+    Unmute,
+    Preset(u8),
+    Volume(Volume),
+    Url(String),
+    /// Change the volume one step at a time to reach `to` after `over`
+    Fade {
+        to: Volume,
+        over: Duration,
+    },
+}
+
+fn parse_volume(value: &str) -> Result<Volume, String> {
+    value
+        .parse()
+        .ok()
+        .and_then(Volume::new)
+        .ok_or_else(|| format!("volume must be between 0 and 100, got '{value}'"))
+}
+
+fn parse_action(action: &str) -> Result<Action, String> {
+    let words: Vec<&str> = action.split_whitespace().collect();
+    match words.as_slice() {
+        ["play"] => Ok(Action::Play),
+        ["pause"] => Ok(Action::Pause),
+        ["stop"] => Ok(Action::Stop),
+        ["mute"] => Ok(Action::Mute),
+        ["unmute"] => Ok(Action::Unmute),
+        ["preset", preset] => preset
+            .parse()
+            .ok()
+            .filter(|preset| (1..=12).contains(preset))
+            .map(Action::Preset)
+            .ok_or_else(|| format!("preset must be between 1 and 12, got '{preset}'")),
+        ["volume", volume] => parse_volume(volume).map(Action::Volume),
+        ["url", url] if url.starts_with("http://") || url.starts_with("https://") => {
+            Ok(Action::Url(url.to_string()))
+        }
+        ["fade", to, "over", over] => Ok(Action::Fade {
+            to: parse_volume(to)?,
+            over: parse_duration_arg(over)?,
+        }),
+        _ => Err(format!(
+            "unknown action '{action}', expected play, pause, stop, mute, unmute, preset N, \
+             volume N, url URL or fade N over DURATION"
+        )),
+    }
+}
+
+/// Parse an action list such as `["preset 2", "volume 30"]`
+pub fn parse_actions(items: &[String]) -> Result<Vec<Action>, String> {
+    let actions = items
+        .iter()
+        .map(|action| parse_action(action))
+        .collect::<Result<Vec<_>, _>>()?;
+    if actions.is_empty() {
+        return Err("no actions given".to_string());
+    }
+    Ok(actions)
+}
+
+/// Volumes to set, one per step, to get from `from` to `to`
+fn fade_levels(from: u8, to: u8) -> Vec<u8> {
+    if from <= to {
+        (from + 1..=to).collect()
+    } else {
+        (to..from).rev().collect()
+    }
+}
+
+async fn fade(client: &WiimClient, to: Volume, over: Duration) -> wiim_api::Result<()> {
+    let from = client.get_volume().await?;
+    let levels = fade_levels(from.get(), to.get());
+    let step = over / levels.len().max(1) as u32;
+    for level in levels {
+        tokio::time::sleep(step).await;
+        client.set_volume(Volume::saturating(level)).await?;
+    }
+    Ok(())
+}
+
+async fn run_action(client: &WiimClient, action: &Action) -> wiim_api::Result<()> {
+    match action {
+        Action::Play => client.resume().await,
+        Action::Pause => client.pause().await,
+        Action::Stop => client.stop().await,
+        Action::Mute => client.mute().await,
+        Action::Unmute => client.unmute().await,
+        Action::Preset(preset) => client.play_preset(*preset).await,
+        Action::Volume(volume) => client.set_volume(*volume).await,
+        Action::Url(url) => client.play_url(url).await,
+        Action::Fade { to, over } => fade(client, *to, *over).await,
+    }
+}
+
+struct Rule {
+    name: String,
+    at: AlarmTime,
+    days: Weekdays,
+    actions: Vec<Action>,
+    clients: Vec<WiimClient>,
+}
+
+impl Rule {
+    fn is_due(&self, weekday: Weekdays, hour: u32, minute: u32) -> bool {
+        self.days.union(weekday) == self.days
+            && u32::from(self.at.hour) == hour
+            && u32::from(self.at.minute) == minute
+    }
+}
+
+/// Runs configured rules at their times
+pub struct Scheduler {
+    rules: Vec<Rule>,
+}
+
+impl Scheduler {
+    /// `None` when there is no `[schedule]` table
+    pub fn from_config(cli: &Cli, config: &Config) -> Result<Option<Self>, Box<dyn Error>> {
+        let Some(schedule) = config.schedule.as_ref().filter(|s| !s.is_empty()) else {
+            return Ok(None);
+        };
+        let mut rules = Vec::new();
+        for (name, rule) in schedule {
+            let invalid =
+                |e: String| crate::error::CliError::Config(format!("schedule.{name}: {e}"));
+            let ips = match rule.device.as_deref() {
+                Some("all") => configured_devices(config)
+                    .into_iter()
+                    .map(|(_, ip)| ip)
+                    .collect(),
+                device => vec![resolve_device(device, config)],
+            };
+            rules.push(Rule {
+                name: name.clone(),
+                at: parse_alarm_time(&rule.at).map_err(invalid)?,
+                days: match &rule.days {
+                    Some(days) => parse_day_list(days).map_err(invalid)?,
+                    None => Weekdays::ALL,
+                },
+                actions: parse_actions(&rule.actions).map_err(invalid)?,
+                clients: ips
+                    .iter()
+                    .map(|ip| build_client(ip, cli, config))
+                    .collect::<Result<_, _>>()?,
+            });
+        }
+        Ok(Some(Self { rules }))
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// Check the rules at the start of every local minute, forever
+    pub async fn run(self) {
+        let rules: Vec<std::sync::Arc<Rule>> =
+            self.rules.into_iter().map(std::sync::Arc::new).collect();
+        let mut last_checked = None;
+        loop {
+            let now = Local::now();
+            // Clock adjustments could otherwise run a minute's rules twice
+            let minute = now.format("%Y-%m-%d %H:%M").to_string();
+            let checked = last_checked.replace(minute.clone()) == Some(minute);
+            let weekday = Weekdays::from_bits(1 << now.weekday().num_days_from_sunday());
+            for rule in rules
+                .iter()
+                .filter(|rule| !checked && rule.is_due(weekday, now.hour(), now.minute()))
+            {
+                let rule = rule.clone();
+                tokio::spawn(async move {
+                    eprintln!("⏰ Running schedule {}", rule.name);
+                    futures_util::future::join_all(rule.clients.iter().map(|client| async {
+                        for action in &rule.actions {
+                            if let Err(e) = run_action(client, action).await {
+                                eprintln!(
+                                    "⚠️ Schedule {} on {}: {e}",
+                                    rule.name,
+                                    client.get_ip_address()
+                                );
+                                return;
+                            }
+                        }
+                    }))
+                    .await;
+                });
+            }
+            // Wake just after the next minute starts
+            let into_minute = Duration::from_secs(u64::from(now.second()))
+                + Duration::from_nanos(u64::from(now.nanosecond() % 1_000_000_000));
+            let until_next = Duration::from_secs(60).saturating_sub(into_minute);
+            tokio::time::sleep(until_next + Duration::from_millis(50)).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_actions() {
+        let parse_actions = |items: &[&str]| {
+            parse_actions(
+                &items
+                    .iter()
+                    .map(|item| item.to_string())
+                    .collect::<Vec<_>>(),
+            )
+        };
+        assert_eq!(
+            parse_actions(&["preset 2", "volume 30"]).unwrap(),
+            vec![Action::Preset(2), Action::Volume(Volume::saturating(30))]
+        );
+        assert_eq!(
+            parse_actions(&["fade 0 over 10m", "stop"]).unwrap(),
+            vec![
+                Action::Fade {
+                    to: Volume::MIN,
+                    over: Duration::from_secs(600)
+                },
+                Action::Stop
+            ]
+        );
+        assert!(parse_actions(&["volume 130"]).is_err());
+        assert!(parse_actions(&["preset 0"]).is_err());
+        assert!(parse_actions(&["dance"]).is_err());
+        assert!(parse_actions(&[]).is_err());
+    }
+
+    #[test]
+    fn test_fade_levels() {
+        assert_eq!(fade_levels(3, 0), vec![2, 1, 0]);
+        assert_eq!(fade_levels(5, 8), vec![6, 7, 8]);
+        assert!(fade_levels(4, 4).is_empty());
+    }
+
+    #[test]
+    fn test_rule_is_due() {
+        let rule = Rule {
+            name: "morning".to_string(),
+            at: "07:00".parse().unwrap(),
+            days: Weekdays::WORKDAYS,
+            actions: vec![Action::Play],
+            clients: Vec::new(),
+        };
+        assert!(rule.is_due(Weekdays::MONDAY, 7, 0));
+        assert!(!rule.is_due(Weekdays::MONDAY, 7, 1));
+        assert!(!rule.is_due(Weekdays::SUNDAY, 7, 0));
+    }
+}
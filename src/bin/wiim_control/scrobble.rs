@@ -0,0 +1,351 @@
+//! Submit listens to ListenBrainz and/or Last.fm from `watch` and `daemon`
+//!
+//! A track counts as listened once it has played for half its length or four minutes,
+//! whichever comes first, and only if it is longer than 30 seconds. Tracks without a known
+//! length (radio streams) need four minutes. Play time is measured between observations, so
+//! sparse updates such as `watch`'s change events are enough.
+
+use crate::error::CliError;
+use crate::Config;
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use wiim_api::{NowPlaying, PlayState};
+
+const LISTENBRAINZ_URL: &str = "https://api.listenbrainz.org/1/submit-listens";
+const LASTFM_URL: &str = "https://ws.audioscrobbler.com/2.0/";
+
+/// Tracks this short are never scrobbled
+const MIN_TRACK_LENGTH: Duration = Duration::from_secs(30);
+/// Play time after which any track counts, regardless of length
+const MAX_LISTEN_THRESHOLD: Duration = Duration::from_secs(240);
+/// A jump back this far within the same track is treated as playing it again
+const RESTART_TOLERANCE: Duration = Duration::from_secs(10);
+
+const LASTFM_INCOMPLETE: &str =
+    "scrobble.lastfm_api_key, lastfm_api_secret and lastfm_session_key must be set together";
+
+/// Settings from the `[scrobble]` table
+#[derive(Debug, Default, Deserialize)]
+pub struct ScrobbleConfig {
+    listenbrainz_token: Option<String>,
+    lastfm_api_key: Option<String>,
+    lastfm_api_secret: Option<String>,
+    /// Obtained once through Last.fm's desktop authentication flow
+    lastfm_session_key: Option<String>,
+}
+
+struct Lastfm {
+    api_key: String,
+    api_secret: String,
+    session_key: String,
+}
+
+/// A completed listen, ready to submit
+#[derive(Debug, Clone, PartialEq)]
+pub struct Listen {
+    pub artist: String,
+    pub title: String,
+    pub album: Option<String>,
+    pub duration: Duration,
+    /// When the track started playing, in seconds since the Unix epoch
+    pub listened_at: u64,
+}
+
+struct Play {
+    listen: Listen,
+    playing: bool,
+    played: Duration,
+    last_seen: SystemTime,
+    last_position: Duration,
+    scrobbled: bool,
+}
+
+impl Play {
+    fn threshold(&self) -> Option<Duration> {
+        match self.listen.duration {
+            Duration::ZERO => Some(MAX_LISTEN_THRESHOLD),
+            length if length <= MIN_TRACK_LENGTH => None,
+            length => Some((length / 2).min(MAX_LISTEN_THRESHOLD)),
+        }
+    }
+}
+
+/// Follows playback across observations and decides when a track counts as listened
+#[derive(Default)]
+pub struct PlayTracker {
+    current: Option<Play>,
+}
+
+impl PlayTracker {
+    /// Record what the device reports at `now` (`None` when it's unreachable) and return a
+    /// listen once the current or just-finished track crosses the threshold
+    pub fn observe(&mut self, now_playing: Option<&NowPlaying>, now: SystemTime) -> Option<Listen> {
+        let mut listen = None;
+        if let Some(play) = &mut self.current {
+            if play.playing {
+                play.played += now.duration_since(play.last_seen).unwrap_or_default();
+            }
+            play.last_seen = now;
+            let crossed = play.threshold().is_some_and(|t| play.played >= t);
+            if crossed && !play.scrobbled {
+                play.scrobbled = true;
+                listen = Some(play.listen.clone());
+            }
+        }
+
+        let Some(now_playing) = now_playing else {
+            self.current = None;
+            return listen;
+        };
+        let (Some(artist), Some(title)) = (&now_playing.artist, &now_playing.title) else {
+            self.current = None;
+            return listen;
+        };
+
+        let playing = now_playing.state == PlayState::Playing;
+        let same_play = self.current.as_ref().is_some_and(|play| {
+            play.listen.artist == *artist
+                && play.listen.title == *title
+                && now_playing.position + RESTART_TOLERANCE >= play.last_position
+        });
+        match &mut self.current {
+            Some(play) if same_play => {
+                play.playing = playing;
+                play.last_position = now_playing.position;
+            }
+            _ => {
+                let since_epoch = now.duration_since(UNIX_EPOCH).unwrap_or_default();
+                let started = since_epoch.saturating_sub(now_playing.position);
+                self.current = Some(Play {
+                    listen: Listen {
+                        artist: artist.clone(),
+                        title: title.clone(),
+                        album: now_playing.album.clone(),
+                        duration: now_playing.duration,
+                        listened_at: started.as_secs(),
+                    },
+                    playing,
+                    played: Duration::ZERO,
+                    last_seen: now,
+                    last_position: now_playing.position,
+                    scrobbled: false,
+                });
+            }
+        }
+        listen
+    }
+}
+
+/// Submits listens to the services configured in `[scrobble]`
+pub struct Scrobbler {
+    http: reqwest::Client,
+    listenbrainz_token: Option<String>,
+    lastfm: Option<Lastfm>,
+    tracker: PlayTracker,
+}
+
+impl Scrobbler {
+    /// `None` when no service is configured
+    pub fn from_config(config: &Config) -> Result<Option<Self>, CliError> {
+        let Some(settings) = &config.scrobble else {
+            return Ok(None);
+        };
+        let lastfm = match (
+            &settings.lastfm_api_key,
+            &settings.lastfm_api_secret,
+            &settings.lastfm_session_key,
+        ) {
+            (Some(api_key), Some(api_secret), Some(session_key)) => Some(Lastfm {
+                api_key: api_key.clone(),
+                api_secret: api_secret.clone(),
+                session_key: session_key.clone(),
+            }),
+            (None, None, None) => None,
+            _ => return Err(CliError::Config(LASTFM_INCOMPLETE.to_string())),
+        };
+        if settings.listenbrainz_token.is_none() && lastfm.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            http: reqwest::Client::new(),
+            listenbrainz_token: settings.listenbrainz_token.clone(),
+            lastfm,
+            tracker: PlayTracker::default(),
+        }))
+    }
+
+    /// Feed an update from the device; submission failures are reported, not returned
+    pub async fn observe(&mut self, now_playing: Option<&NowPlaying>) {
+        let Some(listen) = self.tracker.observe(now_playing, SystemTime::now()) else {
+            return;
+        };
+        if let Some(token) = &self.listenbrainz_token {
+            if let Err(e) = self.submit_listenbrainz(token, &listen).await {
+                eprintln!("⚠️ ListenBrainz: {e}");
+            }
+        }
+        if let Some(lastfm) = &self.lastfm {
+            if let Err(e) = self.submit_lastfm(lastfm, &listen).await {
+                eprintln!("⚠️ Last.fm: {e}");
+            }
+        }
+    }
+
+    async fn submit_listenbrainz(&self, token: &str, listen: &Listen) -> Result<(), String> {
+        let mut additional_info = json!({ "media_player": "wiim-control" });
+        if !listen.duration.is_zero() {
+            additional_info["duration_ms"] = json!(listen.duration.as_millis() as u64);
+        }
+        let body = json!({
+            "listen_type": "single",
+            "payload": [{
+                "listened_at": listen.listened_at,
+                "track_metadata": {
+                    "artist_name": listen.artist,
+                    "track_name": listen.title,
+                    "release_name": listen.album,
+                    "additional_info": additional_info,
+                },
+            }],
+        });
+        let response = self
+            .http
+            .post(LISTENBRAINZ_URL)
+            .header("Authorization", format!("Token {token}"))
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(format!("HTTP {}", response.status()))
+        }
+    }
+
+    async fn submit_lastfm(&self, lastfm: &Lastfm, listen: &Listen) -> Result<(), String> {
+        let mut params = lastfm_params(lastfm, listen);
+        params.insert("format", "json".to_string());
+        let response: serde_json::Value = self
+            .http
+            .post(LASTFM_URL)
+            .form(&params)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?
+            .json()
+            .await
+            .map_err(|e| e.to_string())?;
+        match response.get("message").and_then(|m| m.as_str()) {
+            Some(message) if response.get("error").is_some() => Err(message.to_string()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Signed `track.scrobble` parameters; `api_sig` is the MD5 of the sorted name/value pairs
+/// followed by the secret
+fn lastfm_params(lastfm: &Lastfm, listen: &Listen) -> BTreeMap<&'static str, String> {
+    let mut params = BTreeMap::from([
+        ("method", "track.scrobble".to_string()),
+        ("api_key", lastfm.api_key.clone()),
+        ("sk", lastfm.session_key.clone()),
+        ("artist", listen.artist.clone()),
+        ("track", listen.title.clone()),
+        ("timestamp", listen.listened_at.to_string()),
+    ]);
+    if let Some(album) = &listen.album {
+        params.insert("album", album.clone());
+    }
+    if !listen.duration.is_zero() {
+        params.insert("duration", listen.duration.as_secs().to_string());
+    }
+    let mut signed: String = params.iter().map(|(k, v)| format!("{k}{v}")).collect();
+    signed.push_str(&lastfm.api_secret);
+    params.insert("api_sig", format!("{:x}", md5::compute(signed)));
+    params
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiim_api::Volume;
+
+    fn track(title: &str, state: PlayState, position_ms: u64, duration_ms: u64) -> NowPlaying {
+        NowPlaying {
+            title: Some(title.to_string()),
+            artist: Some("Artist".to_string()),
+            state,
+            volume: Volume::saturating(30),
+            position: Duration::from_millis(position_ms),
+            duration: Duration::from_millis(duration_ms),
+            ..Default::default()
+        }
+    }
+
+    fn at(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(1_700_000_000 + secs)
+    }
+
+    #[test]
+    fn test_half_length_rule() {
+        let mut tracker = PlayTracker::default();
+        let song = track("Song", PlayState::Playing, 0, 200_000);
+        assert_eq!(tracker.observe(Some(&song), at(0)), None);
+        assert_eq!(tracker.observe(Some(&song), at(99)), None);
+
+        let listen = tracker.observe(Some(&song), at(100)).unwrap();
+        assert_eq!(listen.title, "Song");
+        assert_eq!(listen.listened_at, 1_700_000_000);
+
+        // Only once per play
+        assert_eq!(tracker.observe(Some(&song), at(150)), None);
+    }
+
+    #[test]
+    fn test_track_change_scrobbles_finished_track() {
+        let mut tracker = PlayTracker::default();
+        let long = track("Long", PlayState::Playing, 0, 3_600_000);
+        tracker.observe(Some(&long), at(0));
+
+        // Four minutes count even though half the track is far longer
+        let next = track("Next", PlayState::Playing, 0, 180_000);
+        let listen = tracker.observe(Some(&next), at(240)).unwrap();
+        assert_eq!(listen.title, "Long");
+    }
+
+    #[test]
+    fn test_paused_time_and_short_tracks_do_not_count() {
+        let mut tracker = PlayTracker::default();
+        let paused = track("Song", PlayState::Paused, 0, 200_000);
+        tracker.observe(Some(&paused), at(0));
+        assert_eq!(tracker.observe(Some(&paused), at(500)), None);
+
+        let jingle = track("Jingle", PlayState::Playing, 0, 20_000);
+        tracker.observe(Some(&jingle), at(500));
+        assert_eq!(tracker.observe(None, at(600)), None);
+    }
+
+    #[test]
+    fn test_lastfm_signature() {
+        let lastfm = Lastfm {
+            api_key: "key".to_string(),
+            api_secret: "secret".to_string(),
+            session_key: "session".to_string(),
+        };
+        let listen = Listen {
+            artist: "Artist".to_string(),
+            title: "Song".to_string(),
+            album: None,
+            duration: Duration::ZERO,
+            listened_at: 1_700_000_000,
+        };
+        let params = lastfm_params(&lastfm, &listen);
+        let expected = md5::compute(
+            "api_keykeyartistArtistmethodtrack.scrobblesksessiontimestamp1700000000trackSongsecret",
+        );
+        assert_eq!(params["api_sig"], format!("{expected:x}"));
+    }
+}
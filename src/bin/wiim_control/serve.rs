@@ -0,0 +1,475 @@
+//! `wiim-control serve`: a small REST gateway over every configured device
+//!
+//! - `GET  /devices`: configured devices as `[{"name", "ip"}]`
+//! - `GET  /devices/{name}/now-playing`: now playing information
+//! - `POST /devices/{name}/{action}`: `play`, `pause`, `toggle`, `stop`, `next` or `prev`
+//! - `POST /devices/{name}/volume`: `{"volume": 0-100}`
+//! - `POST /devices/{name}/mute`: `{"muted": true|false}`
+//! - `GET  /ws`: WebSocket streaming [`Event`]s as JSON, starting with the current state
+//!
+//! Errors are JSON `{"error", "kind"}` with 404 for unknown devices or actions, 400 for bad
+//! input, and 502 when the device is unreachable or rejects the request.
+//!
+//! Devices added to or removed from the config file are picked up while running.
+
+use crate::output::CommandOutput;
+use crate::reload::ConfigWatcher;
+use crate::{build_client, configured_devices, Cli, Config};
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use std::error::Error;
+use std::future::IntoFuture;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use wiim_api::{DeviceErrorKind, NowPlaying, Volume, WiimClient, WiimError};
+
+/// How many events a slow WebSocket client may fall behind before it skips ahead
+const EVENT_BUFFER: usize = 64;
+
+pub struct GatewayDevice {
+    pub name: String,
+    pub ip: String,
+    pub client: WiimClient,
+}
+
+/// A state change pushed to `/ws` clients
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    /// The device answered and something worth showing changed
+    NowPlaying {
+        device: String,
+        now_playing: NowPlaying,
+    },
+    /// The device stopped answering
+    Offline { device: String, error: String },
+}
+
+impl Event {
+    /// Whether this event carries news compared to the device's previous event
+    fn supersedes(&self, previous: &Event) -> bool {
+        match (self, previous) {
+            (
+                Event::NowPlaying { now_playing, .. },
+                Event::NowPlaying {
+                    now_playing: last, ..
+                },
+            ) => now_playing.differs_from(last),
+            (Event::Offline { .. }, Event::Offline { .. }) => false,
+            _ => true,
+        }
+    }
+}
+
+/// Shared state of the gateway's handlers
+#[derive(Clone)]
+pub struct Gateway {
+    /// Swapped as a whole when the config is reloaded
+    devices: Arc<RwLock<Arc<Vec<GatewayDevice>>>>,
+    latest: Arc<Mutex<HashMap<String, Event>>>,
+    events: broadcast::Sender<Event>,
+}
+
+impl Gateway {
+    pub fn new(devices: Vec<GatewayDevice>) -> Self {
+        let (events, _) = broadcast::channel(EVENT_BUFFER);
+        Self {
+            devices: Arc::new(RwLock::new(Arc::new(devices))),
+            latest: Arc::new(Mutex::new(HashMap::new())),
+            events,
+        }
+    }
+
+    /// Poll every device once and broadcast the events that changed
+    pub async fn poll(&self) {
+        let devices = self.devices();
+        let results = futures_util::future::join_all(
+            devices.iter().map(|device| device.client.get_now_playing()),
+        )
+        .await;
+
+        let mut latest = self.latest.lock().unwrap();
+        for (device, result) in devices.iter().zip(results) {
+            let event = match result {
+                Ok(now_playing) => Event::NowPlaying {
+                    device: device.name.clone(),
+                    now_playing,
+                },
+                Err(e) => Event::Offline {
+                    device: device.name.clone(),
+                    error: e.to_string(),
+                },
+            };
+            let changed = latest
+                .get(&device.name)
+                .is_none_or(|previous| event.supersedes(previous));
+            if changed {
+                latest.insert(device.name.clone(), event.clone());
+                // Nobody listening is fine; the snapshot still gets updated
+                let _ = self.events.send(event);
+            }
+        }
+    }
+
+    /// The last known event of every device, in configuration order
+    fn snapshot(&self) -> Vec<Event> {
+        let latest = self.latest.lock().unwrap();
+        self.devices()
+            .iter()
+            .filter_map(|device| latest.get(&device.name).cloned())
+            .collect()
+    }
+
+    pub fn devices(&self) -> Arc<Vec<GatewayDevice>> {
+        self.devices.read().unwrap().clone()
+    }
+
+    /// Switch to a new set of devices, forgetting the state of removed ones
+    pub fn set_devices(&self, devices: Vec<GatewayDevice>) {
+        self.latest
+            .lock()
+            .unwrap()
+            .retain(|name, _| devices.iter().any(|device| device.name == *name));
+        *self.devices.write().unwrap() = Arc::new(devices);
+    }
+
+    fn client(&self, name: &str) -> Result<WiimClient, ApiError> {
+        self.devices()
+            .iter()
+            .find(|device| device.name == name)
+            .map(|device| device.client.clone())
+            .ok_or_else(|| ApiError::NotFound(format!("no device named '{name}'")))
+    }
+}
+
+pub enum ApiError {
+    NotFound(String),
+    BadRequest(String),
+    Device(WiimError),
+}
+
+impl From<WiimError> for ApiError {
+    fn from(error: WiimError) -> Self {
+        ApiError::Device(error)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let (status, kind, message) = match self {
+            ApiError::NotFound(message) => (StatusCode::NOT_FOUND, "not_found", message),
+            ApiError::BadRequest(message) => (StatusCode::BAD_REQUEST, "bad_request", message),
+            ApiError::Device(error) => {
+                let (status, kind) = match error {
+                    WiimError::Request(_) => (StatusCode::BAD_GATEWAY, "unreachable"),
+                    WiimError::Timeout(_) => (StatusCode::GATEWAY_TIMEOUT, "timeout"),
+                    WiimError::CertificateMismatch { .. } => {
+                        (StatusCode::BAD_GATEWAY, "certificate_mismatch")
+                    }
+                    WiimError::Json(_) | WiimError::InvalidResponse(_) => {
+                        (StatusCode::BAD_GATEWAY, "rejected")
+                    }
+                    WiimError::Unsupported { .. } | WiimError::UnsupportedCommand { .. } => {
+                        (StatusCode::NOT_IMPLEMENTED, "unsupported")
+                    }
+                    WiimError::Device {
+                        kind: DeviceErrorKind::Busy,
+                        ..
+                    } => (StatusCode::SERVICE_UNAVAILABLE, "busy"),
+                    WiimError::Device { .. } => (StatusCode::BAD_GATEWAY, "rejected"),
+                };
+                (status, kind, error.to_string())
+            }
+        };
+        (status, Json(json!({ "error": message, "kind": kind }))).into_response()
+    }
+}
+
+#[derive(Serialize)]
+struct DeviceEntry<'a> {
+    name: &'a str,
+    ip: &'a str,
+}
+
+async fn list_devices(State(gateway): State<Gateway>) -> Json<serde_json::Value> {
+    let devices = gateway.devices();
+    let devices: Vec<DeviceEntry> = devices
+        .iter()
+        .map(|device| DeviceEntry {
+            name: &device.name,
+            ip: &device.ip,
+        })
+        .collect();
+    Json(json!(devices))
+}
+
+async fn now_playing(
+    State(gateway): State<Gateway>,
+    Path(name): Path<String>,
+) -> Result<Json<NowPlaying>, ApiError> {
+    let client = gateway.client(&name)?;
+    Ok(Json(client.get_now_playing().await?))
+}
+
+async fn transport(
+    State(gateway): State<Gateway>,
+    Path((name, action)): Path<(String, String)>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let client = gateway.client(&name)?;
+    match action.as_str() {
+        "play" => client.resume().await?,
+        "pause" => client.pause().await?,
+        "toggle" => client.toggle_play_pause().await?,
+        "stop" => client.stop().await?,
+        "next" => client.next_track().await?,
+        "prev" => client.previous_track().await?,
+        _ => return Err(ApiError::NotFound(format!("unknown action '{action}'"))),
+    }
+    Ok(Json(json!({ "action": action, "ok": true })))
+}
+
+#[derive(Deserialize)]
+struct VolumeRequest {
+    volume: u8,
+}
+
+async fn set_volume(
+    State(gateway): State<Gateway>,
+    Path(name): Path<String>,
+    Json(request): Json<VolumeRequest>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let volume = Volume::new(request.volume)
+        .ok_or_else(|| ApiError::BadRequest("volume must be between 0 and 100".to_string()))?;
+    let client = gateway.client(&name)?;
+    client.set_volume(volume).await?;
+    Ok(Json(json!({ "volume": request.volume })))
+}
+
+#[derive(Deserialize)]
+struct MuteRequest {
+    muted: bool,
+}
+
+async fn set_mute(
+    State(gateway): State<Gateway>,
+    Path(name): Path<String>,
+    Json(request): Json<MuteRequest>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let client = gateway.client(&name)?;
+    if request.muted {
+        client.mute().await?;
+    } else {
+        client.unmute().await?;
+    }
+    Ok(Json(json!({ "muted": request.muted })))
+}
+
+async fn websocket(State(gateway): State<Gateway>, upgrade: WebSocketUpgrade) -> Response {
+    upgrade.on_upgrade(move |socket| stream_events(socket, gateway))
+}
+
+async fn send_event(socket: &mut WebSocket, event: &Event) -> bool {
+    let Ok(json) = serde_json::to_string(event) else {
+        return false;
+    };
+    socket.send(Message::Text(json.into())).await.is_ok()
+}
+
+async fn stream_events(mut socket: WebSocket, gateway: Gateway) {
+    // Subscribe before taking the snapshot so no change falls in between
+    let mut events = gateway.events.subscribe();
+    for event in gateway.snapshot() {
+        if !send_event(&mut socket, &event).await {
+            return;
+        }
+    }
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(event) => {
+                    if !send_event(&mut socket, &event).await {
+                        return;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return,
+            },
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
+
+pub fn router(gateway: Gateway) -> Router {
+    Router::new()
+        .route("/ws", get(websocket))
+        .route("/devices", get(list_devices))
+        .route("/devices/{name}/now-playing", get(now_playing))
+        .route("/devices/{name}/volume", post(set_volume))
+        .route("/devices/{name}/mute", post(set_mute))
+        .route("/devices/{name}/{action}", post(transport))
+        .with_state(gateway)
+}
+
+fn gateway_devices(cli: &Cli, config: &Config) -> Result<Vec<GatewayDevice>, Box<dyn Error>> {
+    configured_devices(config)
+        .into_iter()
+        .map(|(name, ip)| {
+            let client = build_client(&ip, cli, config)?;
+            Ok(GatewayDevice { name, ip, client })
+        })
+        .collect()
+}
+
+pub async fn run(
+    listen: SocketAddr,
+    interval: Duration,
+    cli: &Cli,
+    config: &Config,
+) -> Result<CommandOutput, Box<dyn Error>> {
+    let devices = gateway_devices(cli, config)?;
+
+    let listener = tokio::net::TcpListener::bind(listen).await?;
+    eprintln!(
+        "🌐 Serving {} device(s) on http://{}",
+        devices.len(),
+        listener.local_addr()?
+    );
+    let gateway = Gateway::new(devices);
+    let poller = gateway.clone();
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            poller.poll().await;
+        }
+    });
+
+    let server = axum::serve(listener, router(gateway.clone())).into_future();
+    tokio::pin!(server);
+    let mut watcher = ConfigWatcher::new(cli);
+    loop {
+        tokio::select! {
+            result = &mut server => {
+                result?;
+                return Ok(CommandOutput::default());
+            }
+            reload = watcher.changed() => {
+                let relevant = ["devices", "device_ip", "timeout", "retries"];
+                if !relevant.iter().any(|section| reload.touches(section)) {
+                    continue;
+                }
+                match gateway_devices(cli, &reload.config) {
+                    Ok(devices) => {
+                        eprintln!("🌐 Now serving {} device(s)", devices.len());
+                        gateway.set_devices(devices);
+                    }
+                    Err(e) => eprintln!("⚠️ Devices not reloaded: {e}"),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unreachable_gateway() -> Gateway {
+        let client = WiimClient::builder("127.0.0.1:1")
+            .timeout(Duration::from_millis(500))
+            .build()
+            .unwrap();
+        Gateway::new(vec![GatewayDevice {
+            name: "kitchen".to_string(),
+            ip: "127.0.0.1:1".to_string(),
+            client,
+        }])
+    }
+
+    async fn spawn_gateway() -> String {
+        let gateway = unreachable_gateway();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router(gateway)).await });
+        format!("http://{address}")
+    }
+
+    #[tokio::test]
+    async fn test_gateway_routes() {
+        let base = spawn_gateway().await;
+        let http = reqwest::Client::new();
+
+        let devices: serde_json::Value = http
+            .get(format!("{base}/devices"))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(devices, json!([{"name": "kitchen", "ip": "127.0.0.1:1"}]));
+
+        let response = http
+            .get(format!("{base}/devices/office/now-playing"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 404);
+
+        let response = http
+            .post(format!("{base}/devices/kitchen/dance"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 404);
+
+        let response = http
+            .post(format!("{base}/devices/kitchen/volume"))
+            .json(&json!({"volume": 150}))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 400);
+
+        // Nothing listens on the device address
+        let response = http
+            .get(format!("{base}/devices/kitchen/now-playing"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 502);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["kind"], "unreachable");
+    }
+
+    #[tokio::test]
+    async fn test_poll_broadcasts_changes_once() {
+        let gateway = unreachable_gateway();
+        let mut events = gateway.events.subscribe();
+
+        gateway.poll().await;
+        let event = events.try_recv().unwrap();
+        assert!(matches!(&event, Event::Offline { device, .. } if device == "kitchen"));
+        assert_eq!(gateway.snapshot().len(), 1);
+
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["type"], "offline");
+        assert_eq!(json["device"], "kitchen");
+
+        // Still offline: nothing new to push
+        gateway.poll().await;
+        assert!(events.try_recv().is_err());
+    }
+}
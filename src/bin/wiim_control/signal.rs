@@ -0,0 +1,133 @@
+//! `watch --signal`: poke a signal-driven status bar whenever the state changes
+//!
+//! Bars like waybar and i3blocks can refresh a module on `SIGRTMIN+n` instead of polling.
+//! Signals are delivered with the system `kill`/`pkill` tools, so no platform code is needed.
+
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+/// First real-time signal number as seen by programs linked against glibc or musl
+const SIGRTMIN: u32 = 34;
+const SIGRTMAX: u32 = 64;
+
+/// Where to send the signal
+#[derive(Debug, Clone, PartialEq)]
+pub enum SignalTarget {
+    /// A file containing the PID (anything containing a `/`)
+    PidFile(PathBuf),
+    /// Every process with this name
+    ProcessName(String),
+}
+
+/// A parsed `<pid-file|process-name>:<signal>` specification
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignalSpec {
+    pub target: SignalTarget,
+    pub signal: u32,
+}
+
+/// Parse `waybar:RTMIN+8`, `/run/user/1000/bar.pid:RTMIN+2` or `i3blocks:10`
+pub fn parse_signal_spec(value: &str) -> Result<SignalSpec, String> {
+    let (target, signal) = value.rsplit_once(':').ok_or_else(|| {
+        format!("invalid signal spec '{value}' (expected <pid-file|process-name>:RTMIN+<n>)")
+    })?;
+    if target.is_empty() {
+        return Err(format!("missing process name or PID file in '{value}'"));
+    }
+
+    let signal = parse_signal(signal)?;
+    let target = if target.contains('/') {
+        SignalTarget::PidFile(PathBuf::from(target))
+    } else {
+        SignalTarget::ProcessName(target.to_string())
+    };
+    Ok(SignalSpec { target, signal })
+}
+
+fn parse_signal(value: &str) -> Result<u32, String> {
+    let value = value.trim_start_matches("SIG");
+    let signal = if let Some(offset) = value.strip_prefix("RTMIN") {
+        let offset = match offset.strip_prefix('+') {
+            Some(n) => n
+                .parse::<u32>()
+                .map_err(|_| format!("invalid real-time signal offset '{n}'"))?,
+            None if offset.is_empty() => 0,
+            None => return Err(format!("invalid signal 'RTMIN{offset}'")),
+        };
+        SIGRTMIN
+            .checked_add(offset)
+            .ok_or_else(|| format!("signal RTMIN+{offset} is out of range (1-{SIGRTMAX})"))?
+    } else {
+        value
+            .parse()
+            .map_err(|_| format!("invalid signal '{value}' (use RTMIN+<n> or a number)"))?
+    };
+    if signal == 0 || signal > SIGRTMAX {
+        return Err(format!("signal {signal} is out of range (1-{SIGRTMAX})"));
+    }
+    Ok(signal)
+}
+
+impl SignalSpec {
+    /// Send the signal; a target that isn't running is not an error
+    pub fn send(&self) -> Result<(), String> {
+        let mut command = match &self.target {
+            SignalTarget::PidFile(path) => {
+                let pid = std::fs::read_to_string(path)
+                    .map_err(|e| format!("failed to read PID file {}: {e}", path.display()))?;
+                let pid = pid.trim();
+                if pid.parse::<u32>().is_err() {
+                    return Err(format!("{} does not contain a PID", path.display()));
+                }
+                let mut command = Command::new("kill");
+                command.arg(format!("-{}", self.signal)).arg(pid);
+                command
+            }
+            SignalTarget::ProcessName(name) => {
+                let mut command = Command::new("pkill");
+                command.arg(format!("-{}", self.signal)).arg("-x").arg(name);
+                command
+            }
+        };
+        command
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .map(|_| ())
+            .map_err(|e| format!("failed to send signal {}: {e}", self.signal))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_signal_spec() {
+        assert_eq!(
+            parse_signal_spec("waybar:RTMIN+8").unwrap(),
+            SignalSpec {
+                target: SignalTarget::ProcessName("waybar".to_string()),
+                signal: 42,
+            }
+        );
+        assert_eq!(
+            parse_signal_spec("/run/user/1000/bar.pid:SIGRTMIN").unwrap(),
+            SignalSpec {
+                target: SignalTarget::PidFile(PathBuf::from("/run/user/1000/bar.pid")),
+                signal: 34,
+            }
+        );
+        assert_eq!(parse_signal_spec("i3blocks:10").unwrap().signal, 10);
+
+        assert!(parse_signal_spec("waybar").is_err());
+        assert!(parse_signal_spec(":RTMIN+8").is_err());
+        assert!(parse_signal_spec("waybar:RTMIN+40").is_err());
+        assert_eq!(
+            parse_signal_spec("waybar:RTMIN+4294967295").unwrap_err(),
+            "signal RTMIN+4294967295 is out of range (1-64)"
+        );
+        assert!(parse_signal_spec("waybar:RTMIN-1").is_err());
+        assert!(parse_signal_spec("waybar:HUPP").is_err());
+    }
+}
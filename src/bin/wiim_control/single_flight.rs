@@ -0,0 +1,123 @@
+//! Sharing one device request between callers that want the same thing at once
+//!
+//! The daemon's poll and refreshes started by queries for a stale status go through here,
+//! so a device is never asked twice at the same time: a caller finding a request already
+//! running for its key joins it instead of sending its own. Requests run as their own
+//! tasks, so nobody has to wait for one, and a caller giving up doesn't cancel it.
+
+use futures_util::future::{BoxFuture, FutureExt, Shared};
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+
+/// A request in progress; awaiting it gives its result, dropping it leaves it running
+pub type Flight<V> = Shared<BoxFuture<'static, V>>;
+
+struct Calls<K, V> {
+    /// Tells a finished request apart from a newer one for the same key
+    next_id: u64,
+    running: HashMap<K, (u64, Flight<V>)>,
+}
+
+/// Requests in progress, by key (e.g. device and command)
+pub struct SingleFlight<K, V> {
+    calls: Arc<Mutex<Calls<K, V>>>,
+}
+
+impl<K, V> Default for SingleFlight<K, V> {
+    fn default() -> Self {
+        SingleFlight {
+            calls: Arc::new(Mutex::new(Calls {
+                next_id: 0,
+                running: HashMap::new(),
+            })),
+        }
+    }
+}
+
+impl<K, V> SingleFlight<K, V>
+where
+    K: Eq + Hash + Clone + Send + 'static,
+    V: Clone + Send + Sync + 'static,
+{
+    /// The request running for `key`, or a new one from `fetch` if there is none
+    pub fn join<F, Fut>(&self, key: K, fetch: F) -> Flight<V>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = V> + Send + 'static,
+    {
+        let mut calls = self.calls.lock().unwrap();
+        if let Some((_, flight)) = calls.running.get(&key) {
+            return flight.clone();
+        }
+        let id = calls.next_id;
+        calls.next_id += 1;
+
+        let registry = Arc::clone(&self.calls);
+        let finished = key.clone();
+        let fetch = fetch();
+        let task = tokio::spawn(async move {
+            let value = fetch.await;
+            let mut calls = registry.lock().unwrap();
+            if calls
+                .running
+                .get(&finished)
+                .is_some_and(|(running, _)| *running == id)
+            {
+                calls.running.remove(&finished);
+            }
+            value
+        });
+        let flight = async move {
+            match task.await {
+                Ok(value) => value,
+                Err(e) => std::panic::resume_unwind(e.into_panic()),
+            }
+        }
+        .boxed()
+        .shared();
+        calls.running.insert(key, (id, flight.clone()));
+        flight
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    fn counting(fetches: &Arc<AtomicUsize>) -> impl Future<Output = usize> + Send + 'static {
+        let fetches = fetches.clone();
+        async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            fetches.fetch_add(1, Ordering::SeqCst) + 1
+        }
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_callers_share_one_fetch() {
+        let flights = SingleFlight::default();
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let calls: Vec<_> = (0..5)
+            .map(|_| flights.join("status", || counting(&fetches)))
+            .collect();
+        let results = futures_util::future::join_all(calls).await;
+        assert_eq!(results, [1; 5]);
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+
+        // Other keys don't wait for each other, and a finished request isn't reused
+        assert_eq!(flights.join("info", || counting(&fetches)).await, 2);
+        assert_eq!(flights.join("status", || counting(&fetches)).await, 3);
+    }
+
+    #[tokio::test]
+    async fn test_dropped_flight_keeps_running() {
+        let flights = SingleFlight::default();
+        let fetches = Arc::new(AtomicUsize::new(0));
+        drop(flights.join("status", || counting(&fetches)));
+        assert_eq!(flights.join("status", || counting(&fetches)).await, 1);
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,196 @@
+//! StatsD metrics from `wiim-control daemon`, for monitoring without Prometheus
+//!
+//! With a `[statsd]` table, every poll of a device sends over UDP, under
+//! `<prefix>.<device>.` (prefix `wiim` by default):
+//! - `request`: how long the status request took, as a timer
+//! - `errors`: a counter, when the device didn't answer
+//! - `state.<state>`: a counter, when the device starts playing, pauses, stops, loads or
+//!   goes `offline`
+//! - `track_change`: a counter, when the title, artist or album changes
+//!
+//! Transitions are counted from the second poll on, so restarting the daemon counts
+//! nothing. Metrics are sent without waiting for or checking delivery.
+
+use crate::webhook::{detect, WebhookEvent};
+use crate::Config;
+use serde::Deserialize;
+use std::net::{ToSocketAddrs, UdpSocket};
+use std::time::Duration;
+use wiim_api::NowPlaying;
+
+const DEFAULT_ADDRESS: &str = "127.0.0.1:8125";
+const DEFAULT_PREFIX: &str = "wiim";
+
+/// Settings from the `[statsd]` table; its presence turns metrics on
+#[derive(Debug, Default, Deserialize)]
+pub struct StatsdConfig {
+    /// `host:port` of the StatsD server, default 127.0.0.1:8125
+    address: Option<String>,
+    /// First part of every metric name, default "wiim"
+    prefix: Option<String>,
+}
+
+/// Check an address has the `host:port` form
+pub fn parse_address(value: &str) -> Result<(), String> {
+    match value.rsplit_once(':') {
+        Some((host, port)) if !host.is_empty() && port.parse::<u16>().is_ok() => Ok(()),
+        _ => Err(format!("address must be host:port, got '{value}'")),
+    }
+}
+
+/// Letters, digits, `-` and `_` are kept; anything else, dots included, would change the
+/// metric's path and becomes `_`
+fn metric_segment(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Sends one device's metrics
+pub struct Emitter {
+    socket: UdpSocket,
+    /// `<prefix>.<device>.`
+    prefix: String,
+    /// The last observation; `None` until the first one
+    last: Option<Option<NowPlaying>>,
+}
+
+impl Emitter {
+    /// `None` when there is no `[statsd]` table
+    pub fn from_config(config: &Config, device: &str) -> Result<Option<Self>, String> {
+        let Some(settings) = &config.statsd else {
+            return Ok(None);
+        };
+        let address = settings.address.as_deref().unwrap_or(DEFAULT_ADDRESS);
+        parse_address(address).map_err(|e| format!("statsd.{e}"))?;
+        let target = address
+            .to_socket_addrs()
+            .ok()
+            .and_then(|mut addresses| addresses.next())
+            .ok_or_else(|| format!("statsd.address: can't resolve '{address}'"))?;
+        let local = if target.is_ipv4() {
+            "0.0.0.0:0"
+        } else {
+            "[::]:0"
+        };
+        let socket = UdpSocket::bind(local)
+            .and_then(|socket| socket.connect(target).map(|_| socket))
+            .and_then(|socket| socket.set_nonblocking(true).map(|_| socket))
+            .map_err(|e| format!("statsd: {e}"))?;
+        let prefix = settings.prefix.as_deref().unwrap_or(DEFAULT_PREFIX);
+        Ok(Some(Self {
+            socket,
+            prefix: format!("{prefix}.{}.", metric_segment(device)),
+            last: None,
+        }))
+    }
+
+    /// Feed a poll's result and how long it took; sending failures are ignored
+    pub fn observe(&mut self, now_playing: Option<&NowPlaying>, latency: Duration) {
+        let packet = self.metrics(now_playing, latency).join("\n");
+        let _ = self.socket.send(packet.as_bytes());
+    }
+
+    fn metrics(&mut self, now_playing: Option<&NowPlaying>, latency: Duration) -> Vec<String> {
+        let prefix = &self.prefix;
+        let mut metrics = vec![format!("{prefix}request:{}|ms", latency.as_millis())];
+        if now_playing.is_none() {
+            metrics.push(format!("{prefix}errors:1|c"));
+        }
+        let events = match &self.last {
+            Some(previous) => detect(previous.as_ref(), now_playing),
+            None => Vec::new(),
+        };
+        for event in events {
+            let metric = match event {
+                WebhookEvent::Track => "track_change".to_string(),
+                WebhookEvent::State | WebhookEvent::Offline | WebhookEvent::Online => {
+                    let state =
+                        now_playing.map_or_else(|| "offline".to_string(), |n| n.state.to_string());
+                    format!("state.{state}")
+                }
+            };
+            metrics.push(format!("{prefix}{metric}:1|c"));
+        }
+        self.last = Some(now_playing.cloned());
+        metrics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiim_api::{PlayState, PlaybackSource, Volume};
+
+    fn now_playing(title: &str, state: PlayState) -> NowPlaying {
+        NowPlaying {
+            title: Some(title.to_string()),
+            artist: Some("Band".to_string()),
+            state,
+            volume: Volume::saturating(30),
+            duration: Duration::from_secs(200),
+            source: PlaybackSource::Spotify,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_metrics() {
+        let config: Config = toml::from_str(
+            r#"
+            device_ip = "192.168.1.100"
+            [statsd]
+            prefix = "home"
+            "#,
+        )
+        .unwrap();
+        let mut emitter = Emitter::from_config(&config, "living room")
+            .unwrap()
+            .unwrap();
+        let latency = Duration::from_millis(42);
+
+        let first = now_playing("One", PlayState::Playing);
+        assert_eq!(
+            emitter.metrics(Some(&first), latency),
+            ["home.living_room.request:42|ms"]
+        );
+        let paused = now_playing("One", PlayState::Paused);
+        assert_eq!(
+            emitter.metrics(Some(&paused), latency),
+            [
+                "home.living_room.request:42|ms",
+                "home.living_room.state.paused:1|c"
+            ]
+        );
+        let next = now_playing("Two", PlayState::Paused);
+        assert_eq!(
+            emitter.metrics(Some(&next), latency),
+            [
+                "home.living_room.request:42|ms",
+                "home.living_room.track_change:1|c"
+            ]
+        );
+        assert_eq!(
+            emitter.metrics(None, latency),
+            [
+                "home.living_room.request:42|ms",
+                "home.living_room.errors:1|c",
+                "home.living_room.state.offline:1|c"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_address() {
+        assert!(parse_address("127.0.0.1:8125").is_ok());
+        assert!(parse_address("statsd.local:8125").is_ok());
+        assert!(parse_address("statsd.local").is_err());
+        assert!(parse_address(":8125").is_err());
+    }
+}
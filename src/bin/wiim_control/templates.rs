@@ -0,0 +1,209 @@
+//! Template registry with user-defined partials and helpers from `[partials]` and `[helpers]`
+//!
+//! ```toml
+//! [partials]
+//! quality = "{{#if quality_info}}[{{quality_info}}]{{/if}}"
+//!
+//! [helpers]
+//! paren = "{{#if value}}({{value}}){{/if}}"
+//! ```
+//!
+//! `{{> quality}}` renders a partial with the current context. `{{paren album}}` renders a
+//! helper's template with `value` set to the first argument, `args` to all arguments and any
+//! `key=value` pairs as extra variables.
+//!
+//! Status templates are compiled the first time they are rendered and kept in the registry,
+//! so `watch` and repeated renders don't parse them again.
+
+use handlebars::{
+    Context, Handlebars, Helper, HelperDef, HelperResult, Output, RenderContext, RenderError,
+};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+static REGISTRY: RwLock<Option<Arc<Handlebars<'static>>>> = RwLock::new(None);
+
+/// Name a template is kept under in the registry; the NUL keeps it apart from partials
+pub fn cache_name(template: &str) -> String {
+    format!("\0{template}")
+}
+
+/// A helper whose output is itself a template, registered as `template_name`
+struct TemplateHelper {
+    template_name: String,
+}
+
+impl HelperDef for TemplateHelper {
+    fn call<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'reg, 'rc>,
+        r: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+        out: &mut dyn Output,
+    ) -> HelperResult {
+        let args: Vec<Value> = h.params().iter().map(|p| p.value().clone()).collect();
+        let mut context = Map::new();
+        context.insert(
+            "value".to_string(),
+            args.first().cloned().unwrap_or_default(),
+        );
+        for (key, value) in h.hash() {
+            context.insert(key.to_string(), value.value().clone());
+        }
+        context.insert("args".to_string(), Value::Array(args));
+
+        let rendered = r
+            .render(&self.template_name, &context)
+            .map_err(|e| RenderError::new(format!("helper '{}': {e}", h.name())))?;
+        out.write(&rendered)?;
+        Ok(())
+    }
+}
+
+/// Build a registry with the given partials and helpers
+fn build(
+    partials: &HashMap<String, String>,
+    helpers: &HashMap<String, String>,
+) -> Result<Handlebars<'static>, String> {
+    let mut handlebars = Handlebars::new();
+    for (name, template) in partials {
+        handlebars
+            .register_partial(name, template)
+            .map_err(|e| format!("Invalid partial '{name}': {e}"))?;
+    }
+    for (name, template) in helpers {
+        // Compiled up front so syntax errors surface at startup, not mid-render
+        let template_name = cache_name(&format!("helper:{name}"));
+        handlebars
+            .register_template_string(&template_name, template)
+            .map_err(|e| format!("Invalid helper '{name}': {e}"))?;
+        handlebars.register_helper(name, Box::new(TemplateHelper { template_name }));
+    }
+    Ok(handlebars)
+}
+
+/// Register the configured partials and helpers; call once after loading the config
+pub fn init(
+    partials: Option<&HashMap<String, String>>,
+    helpers: Option<&HashMap<String, String>>,
+) -> Result<(), String> {
+    let empty = HashMap::new();
+    let handlebars = build(partials.unwrap_or(&empty), helpers.unwrap_or(&empty))?;
+    // Only the first initialization counts; later calls keep the existing registry
+    REGISTRY
+        .write()
+        .unwrap()
+        .get_or_insert_with(|| Arc::new(handlebars));
+    Ok(())
+}
+
+/// Replace the partials and helpers after the config file changed; on error the
+/// existing registry stays in place
+pub fn reload(
+    partials: Option<&HashMap<String, String>>,
+    helpers: Option<&HashMap<String, String>>,
+) -> Result<(), String> {
+    let empty = HashMap::new();
+    let handlebars = build(partials.unwrap_or(&empty), helpers.unwrap_or(&empty))?;
+    *REGISTRY.write().unwrap() = Some(Arc::new(handlebars));
+    Ok(())
+}
+
+/// The shared registry, without user partials or helpers if `init` was never called
+pub fn registry() -> Arc<Handlebars<'static>> {
+    if let Some(registry) = REGISTRY.read().unwrap().as_ref() {
+        return registry.clone();
+    }
+    REGISTRY
+        .write()
+        .unwrap()
+        .get_or_insert_with(|| Arc::new(Handlebars::new()))
+        .clone()
+}
+
+/// Render a template with the shared registry, compiling it only the first time
+pub fn render(template: &str, context: &impl Serialize) -> Result<String, String> {
+    let name = cache_name(template);
+    let mut handlebars = registry();
+    if !handlebars.has_template(&name) {
+        let mut compiled = handlebars.as_ref().clone();
+        compiled
+            .register_template_string(&name, template)
+            .map_err(|e| format!("Template error: {e}"))?;
+        let compiled = Arc::new(compiled);
+        let mut shared = REGISTRY.write().unwrap();
+        // A reload in the meantime wins; this template is compiled again next time
+        if shared
+            .as_ref()
+            .is_none_or(|current| Arc::ptr_eq(current, &handlebars))
+        {
+            *shared = Some(compiled.clone());
+        }
+        handlebars = compiled;
+    }
+    handlebars
+        .render(&name, context)
+        .map_err(|e| format!("Template render error: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn map(entries: &[(&str, &str)]) -> HashMap<String, String> {
+        entries
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_partials_and_helpers() {
+        let handlebars = build(
+            &map(&[("quality", "{{#if quality_info}}[{{quality_info}}]{{/if}}")]),
+            &map(&[
+                ("paren", "{{#if value}}({{value}}){{/if}}"),
+                ("pair", "{{value}}{{sep}}{{lookup args 1}}"),
+            ]),
+        )
+        .unwrap();
+        let context = json!({"title": "Song", "album": "LP", "quality_info": "24bit"});
+
+        let rendered = handlebars
+            .render_template("{{title}} {{paren album}} {{> quality}}", &context)
+            .unwrap();
+        assert_eq!(rendered, "Song (LP) [24bit]");
+
+        let rendered = handlebars
+            .render_template("{{pair title album sep=\" / \"}}", &context)
+            .unwrap();
+        assert_eq!(rendered, "Song / LP");
+
+        let rendered = handlebars
+            .render_template("{{title}}{{paren artist}}", &context)
+            .unwrap();
+        assert_eq!(rendered, "Song");
+    }
+
+    #[test]
+    fn test_render_caches_templates() {
+        let template = "{{title}} ({{album}})";
+        let context = json!({"title": "Song", "album": "LP"});
+        assert_eq!(render(template, &context).unwrap(), "Song (LP)");
+        assert!(registry().has_template(&cache_name(template)));
+        assert_eq!(render(template, &context).unwrap(), "Song (LP)");
+        assert!(render("{{title}", &context)
+            .unwrap_err()
+            .starts_with("Template error"));
+    }
+
+    #[test]
+    fn test_invalid_definitions_are_rejected() {
+        assert!(build(&map(&[("bad", "{{#if}}")]), &HashMap::new()).is_err());
+        assert!(build(&HashMap::new(), &map(&[("bad", "{{/each}}")])).is_err());
+    }
+}
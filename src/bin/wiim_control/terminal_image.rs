@@ -0,0 +1,218 @@
+//! Inline image rendering for terminals that support a graphics protocol
+//!
+//! Supports the kitty graphics protocol, iTerm2 inline images and DEC sixel.
+//! Detection is based on environment variables, since querying the terminal
+//! would require putting it into raw mode.
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use image::{DynamicImage, ImageFormat};
+use std::io::Cursor;
+
+/// Largest dimension (in pixels) of the rendered preview
+pub const PREVIEW_SIZE: u32 = 300;
+
+/// Kitty limits each escape sequence payload to 4096 bytes
+const KITTY_CHUNK_SIZE: usize = 4096;
+
+/// Number of levels per channel in the sixel palette (6x6x6 colour cube)
+const SIXEL_LEVELS: u32 = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    Kitty,
+    Iterm,
+    Sixel,
+}
+
+/// Guess the graphics protocol supported by the current terminal
+pub fn detect() -> Option<Protocol> {
+    detect_from(|key| std::env::var(key).ok())
+}
+
+fn detect_from(env: impl Fn(&str) -> Option<String>) -> Option<Protocol> {
+    let term = env("TERM").unwrap_or_default();
+    let term_program = env("TERM_PROGRAM").unwrap_or_default();
+
+    if env("KITTY_WINDOW_ID").is_some() || term == "xterm-kitty" || term == "xterm-ghostty" {
+        return Some(Protocol::Kitty);
+    }
+    if term_program == "iTerm.app"
+        || term_program == "WezTerm"
+        || env("LC_TERMINAL").as_deref() == Some("iTerm2")
+    {
+        return Some(Protocol::Iterm);
+    }
+    if term.starts_with("foot") || term.contains("sixel") || term_program == "mlterm" {
+        return Some(Protocol::Sixel);
+    }
+    None
+}
+
+/// Render an encoded image (JPEG, PNG, ...) as an escape sequence for `protocol`
+pub fn render(bytes: &[u8], protocol: Protocol) -> image::ImageResult<String> {
+    match protocol {
+        // iTerm2 decodes the original file itself
+        Protocol::Iterm => Ok(iterm_sequence(bytes)),
+        Protocol::Kitty => {
+            let image = image::load_from_memory(bytes)?.thumbnail(PREVIEW_SIZE, PREVIEW_SIZE);
+            let mut png = Vec::new();
+            image.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+            Ok(kitty_sequence(&png))
+        }
+        Protocol::Sixel => {
+            let image = image::load_from_memory(bytes)?.thumbnail(PREVIEW_SIZE, PREVIEW_SIZE);
+            Ok(sixel_sequence(&image))
+        }
+    }
+}
+
+fn iterm_sequence(bytes: &[u8]) -> String {
+    format!(
+        "\x1b]1337;File=inline=1;size={};width={PREVIEW_SIZE}px;preserveAspectRatio=1:{}\x07",
+        bytes.len(),
+        BASE64.encode(bytes)
+    )
+}
+
+fn kitty_sequence(png: &[u8]) -> String {
+    let payload = BASE64.encode(png);
+    let chunks: Vec<&[u8]> = payload.as_bytes().chunks(KITTY_CHUNK_SIZE).collect();
+    let mut out = String::new();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = u8::from(i + 1 < chunks.len());
+        // Chunks are slices of base64 output, so always valid UTF-8
+        let chunk = std::str::from_utf8(chunk).unwrap_or_default();
+        if i == 0 {
+            out.push_str(&format!("\x1b_Ga=T,f=100,m={more};{chunk}\x1b\\"));
+        } else {
+            out.push_str(&format!("\x1b_Gm={more};{chunk}\x1b\\"));
+        }
+    }
+    out
+}
+
+/// Map an 8-bit channel value to the nearest palette level
+fn sixel_level(value: u8) -> u32 {
+    (value as u32 * (SIXEL_LEVELS - 1) + 127) / 255
+}
+
+fn sixel_sequence(image: &DynamicImage) -> String {
+    let rgb = image.to_rgb8();
+    let (width, height) = rgb.dimensions();
+    let palette_index = |x: u32, y: u32| {
+        let [r, g, b] = rgb.get_pixel(x, y).0;
+        (sixel_level(r) * SIXEL_LEVELS + sixel_level(g)) * SIXEL_LEVELS + sixel_level(b)
+    };
+
+    let mut out = format!("\x1bPq\"1;1;{width};{height}");
+    let max_level = SIXEL_LEVELS - 1;
+    for index in 0..SIXEL_LEVELS.pow(3) {
+        let (r, g, b) = (
+            index / (SIXEL_LEVELS * SIXEL_LEVELS),
+            (index / SIXEL_LEVELS) % SIXEL_LEVELS,
+            index % SIXEL_LEVELS,
+        );
+        out.push_str(&format!(
+            "#{index};2;{};{};{}",
+            r * 100 / max_level,
+            g * 100 / max_level,
+            b * 100 / max_level
+        ));
+    }
+
+    for band_top in (0..height).step_by(6) {
+        let band_rows = (band_top..(band_top + 6).min(height)).collect::<Vec<_>>();
+        let mut colors: Vec<u32> = band_rows
+            .iter()
+            .flat_map(|&y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| palette_index(x, y))
+            .collect();
+        colors.sort_unstable();
+        colors.dedup();
+
+        for color in colors {
+            out.push_str(&format!("#{color}"));
+            let columns: Vec<u8> = (0..width)
+                .map(|x| {
+                    let bits = band_rows
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, &y)| palette_index(x, y) == color)
+                        .fold(0u8, |bits, (row, _)| bits | (1 << row));
+                    63 + bits
+                })
+                .collect();
+            push_sixel_run_length(&mut out, &columns);
+            out.push('$');
+        }
+        out.push('-');
+    }
+
+    out.push_str("\x1b\\");
+    out
+}
+
+/// Append sixel data using `!<count><char>` repeat introducers for runs
+fn push_sixel_run_length(out: &mut String, columns: &[u8]) {
+    let mut i = 0;
+    while i < columns.len() {
+        let value = columns[i];
+        let run = columns[i..].iter().take_while(|&&c| c == value).count();
+        if run > 3 {
+            out.push_str(&format!("!{run}{}", value as char));
+        } else {
+            out.extend(std::iter::repeat_n(value as char, run));
+        }
+        i += run;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env_with(vars: &'static [(&'static str, &'static str)]) -> impl Fn(&str) -> Option<String> {
+        move |key| {
+            vars.iter()
+                .find(|(k, _)| *k == key)
+                .map(|(_, v)| v.to_string())
+        }
+    }
+
+    #[test]
+    fn test_detect_protocol() {
+        assert_eq!(
+            detect_from(env_with(&[("TERM", "xterm-kitty")])),
+            Some(Protocol::Kitty)
+        );
+        assert_eq!(
+            detect_from(env_with(&[("TERM_PROGRAM", "iTerm.app")])),
+            Some(Protocol::Iterm)
+        );
+        assert_eq!(
+            detect_from(env_with(&[("TERM", "foot")])),
+            Some(Protocol::Sixel)
+        );
+        assert_eq!(detect_from(env_with(&[("TERM", "xterm-256color")])), None);
+    }
+
+    #[test]
+    fn test_kitty_sequence_chunks_payload() {
+        let sequence = kitty_sequence(&[0u8; 5000]);
+        assert!(sequence.starts_with("\x1b_Ga=T,f=100,m=1;"));
+        assert!(sequence.contains("\x1b_Gm=0;"));
+        assert!(sequence.ends_with("\x1b\\"));
+    }
+
+    #[test]
+    fn test_sixel_sequence_for_solid_image() {
+        let image =
+            DynamicImage::ImageRgb8(image::RgbImage::from_pixel(10, 6, image::Rgb([255, 0, 0])));
+        let sequence = sixel_sequence(&image);
+        assert!(sequence.starts_with("\x1bPq\"1;1;10;6"));
+        // Pure red is palette entry (5 * 6 + 0) * 6 + 0 = 180, all six rows set for 10 columns
+        assert!(sequence.contains("#180!10~$-"));
+        assert!(sequence.ends_with("\x1b\\"));
+    }
+}
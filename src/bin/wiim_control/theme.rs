@@ -0,0 +1,198 @@
+//! Colors for terminal output
+//!
+//! Text is painted by [`Role`] rather than by color, so `[theme]` in the config can
+//! restyle every command at once. Colors are only written when `--color` allows it:
+//! `auto` (the default) turns them off for pipes, `TERM=dumb` and a non-empty `NO_COLOR`.
+
+use serde::Deserialize;
+use std::io::IsTerminal;
+use std::sync::OnceLock;
+
+static THEME: OnceLock<Theme> = OnceLock::new();
+
+/// When to color output, from `--color`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ColorChoice {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    fn enabled(self) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+                let dumb = std::env::var("TERM").is_ok_and(|term| term == "dumb");
+                !no_color && !dumb && std::io::stdout().is_terminal()
+            }
+        }
+    }
+}
+
+/// What a piece of text is, which decides its color
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    /// Device names and the current track
+    Accent,
+    /// Field labels and table headers
+    Label,
+    /// Playing, connected
+    Good,
+    /// Paused, muted
+    Warn,
+    /// Offline, errors
+    Bad,
+    /// Stopped, placeholders for missing values
+    Dim,
+}
+
+/// `[theme]` in the config: a style per role, such as "bold cyan", "208" or "#ff8800"
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct ThemeConfig {
+    accent: Option<String>,
+    label: Option<String>,
+    good: Option<String>,
+    warn: Option<String>,
+    bad: Option<String>,
+    dim: Option<String>,
+}
+
+pub const THEME_KEYS: [&str; 6] = ["accent", "label", "good", "warn", "bad", "dim"];
+
+/// ANSI escape parameters for each role; empty when colors are off
+#[derive(Debug, Default)]
+struct Theme {
+    accent: String,
+    label: String,
+    good: String,
+    warn: String,
+    bad: String,
+    dim: String,
+}
+
+impl Theme {
+    fn new(config: &ThemeConfig) -> Result<Self, String> {
+        let style = |value: &Option<String>, default: &str| {
+            parse_style(value.as_deref().unwrap_or(default))
+        };
+        Ok(Theme {
+            accent: style(&config.accent, "bold cyan")?,
+            label: style(&config.label, "bold")?,
+            good: style(&config.good, "green")?,
+            warn: style(&config.warn, "yellow")?,
+            bad: style(&config.bad, "red")?,
+            dim: style(&config.dim, "bright_black")?,
+        })
+    }
+
+    fn sgr(&self, role: Role) -> &str {
+        match role {
+            Role::Accent => &self.accent,
+            Role::Label => &self.label,
+            Role::Good => &self.good,
+            Role::Warn => &self.warn,
+            Role::Bad => &self.bad,
+            Role::Dim => &self.dim,
+        }
+    }
+}
+
+const COLOR_NAMES: [&str; 8] = [
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+];
+
+/// Turn a style like "bold bright_blue" into ANSI SGR parameters ("1;94")
+pub fn parse_style(style: &str) -> Result<String, String> {
+    let mut codes = Vec::new();
+    for word in style.split_whitespace() {
+        let word = word.to_ascii_lowercase();
+        let code = match word.as_str() {
+            "bold" => "1".to_string(),
+            "dim" => "2".to_string(),
+            "italic" => "3".to_string(),
+            "underline" => "4".to_string(),
+            "none" | "plain" => continue,
+            _ => color_code(&word).ok_or_else(|| {
+                format!(
+                    "unknown style '{word}' (expected bold, dim, italic, underline, a color \
+                     name like cyan or bright_blue, 0-255, or #rrggbb)"
+                )
+            })?,
+        };
+        codes.push(code);
+    }
+    Ok(codes.join(";"))
+}
+
+fn color_code(word: &str) -> Option<String> {
+    if let Some(hex) = word.strip_prefix('#') {
+        if hex.len() != 6 {
+            return None;
+        }
+        let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+        return Some(format!(
+            "38;2;{};{};{}",
+            channel(0)?,
+            channel(2)?,
+            channel(4)?
+        ));
+    }
+    if let Ok(index) = word.parse::<u8>() {
+        return Some(format!("38;5;{index}"));
+    }
+    let (base, name) = match word.strip_prefix("bright_") {
+        Some(name) => (90, name),
+        None => (30, word),
+    };
+    let offset = COLOR_NAMES.iter().position(|color| *color == name)?;
+    Some((base + offset).to_string())
+}
+
+/// Set up colors once the config is loaded
+pub fn init(choice: ColorChoice, config: Option<&ThemeConfig>) -> Result<(), String> {
+    let theme = if choice.enabled() {
+        Theme::new(config.unwrap_or(&ThemeConfig::default()))
+            .map_err(|e| format!("Invalid [theme]: {e}"))?
+    } else {
+        Theme::default()
+    };
+    // Only the first initialization counts
+    let _ = THEME.set(theme);
+    Ok(())
+}
+
+/// `text` in the color for `role`, or unchanged when colors are off
+pub fn paint(role: Role, text: &str) -> String {
+    let sgr = THEME.get().map(|theme| theme.sgr(role)).unwrap_or_default();
+    if sgr.is_empty() || text.is_empty() {
+        text.to_string()
+    } else {
+        format!("\x1b[{sgr}m{text}\x1b[0m")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_style() {
+        assert_eq!(parse_style("bold cyan").unwrap(), "1;36");
+        assert_eq!(parse_style("bright_black").unwrap(), "90");
+        assert_eq!(parse_style("208").unwrap(), "38;5;208");
+        assert_eq!(parse_style("#FF8800").unwrap(), "38;2;255;136;0");
+        assert_eq!(parse_style("none").unwrap(), "");
+        assert!(parse_style("teal").is_err());
+        assert!(parse_style("#ff88").is_err());
+    }
+
+    #[test]
+    fn test_paint_without_theme_is_plain() {
+        // Tests never call init, so nothing is colored
+        assert_eq!(paint(Role::Accent, "kitchen"), "kitchen");
+    }
+}
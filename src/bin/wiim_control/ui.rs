@@ -0,0 +1,363 @@
+//! `wiim-control ui`: a full-screen dashboard with keyboard transport controls
+//!
+//! Now playing is polled every second and the progress bar advances in between, while
+//! the slower-changing group and Wi-Fi details are refreshed every ten seconds. Keys are
+//! read on their own thread so a slow device never makes the keyboard lag.
+
+use crate::i18n;
+use crate::output::CommandOutput;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Gauge, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use std::error::Error;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use wiim_api::{GroupFollower, GroupRole, NowPlaying, PlayState, WiimClient};
+
+/// How often now playing is fetched
+const PLAYER_INTERVAL: Duration = Duration::from_secs(1);
+/// How often group members and Wi-Fi quality are fetched
+const DETAILS_INTERVAL: Duration = Duration::from_secs(10);
+/// How often the screen is redrawn, so the progress bar moves smoothly
+const FRAME_INTERVAL: Duration = Duration::from_millis(250);
+/// How far the arrow keys seek
+const SEEK_STEP: Duration = Duration::from_secs(10);
+
+#[derive(Default)]
+struct Dashboard {
+    device_name: Option<String>,
+    now_playing: Option<NowPlaying>,
+    /// When `now_playing` was fetched, to advance the position in between
+    fetched_at: Option<Instant>,
+    role: Option<GroupRole>,
+    followers: Vec<GroupFollower>,
+    /// Signal quality and RSSI
+    wifi: Option<(String, i32)>,
+    /// The last failed request or action, shown until the next success
+    error: Option<String>,
+}
+
+enum Action {
+    Quit,
+    TogglePlayPause,
+    Next,
+    Previous,
+    Stop,
+    VolumeUp,
+    VolumeDown,
+    ToggleMute,
+    SeekBack,
+    SeekForward,
+}
+
+fn action_for(code: KeyCode) -> Option<Action> {
+    match code {
+        KeyCode::Char('q') | KeyCode::Esc => Some(Action::Quit),
+        KeyCode::Char(' ') => Some(Action::TogglePlayPause),
+        KeyCode::Char('n') => Some(Action::Next),
+        KeyCode::Char('p') => Some(Action::Previous),
+        KeyCode::Char('s') => Some(Action::Stop),
+        KeyCode::Char('+') | KeyCode::Char('=') | KeyCode::Up => Some(Action::VolumeUp),
+        KeyCode::Char('-') | KeyCode::Down => Some(Action::VolumeDown),
+        KeyCode::Char('m') => Some(Action::ToggleMute),
+        KeyCode::Left => Some(Action::SeekBack),
+        KeyCode::Right => Some(Action::SeekForward),
+        _ => None,
+    }
+}
+
+fn format_time(time: Duration) -> String {
+    let seconds = time.as_secs();
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+impl Dashboard {
+    /// Playback position, advanced by the time since it was fetched while playing
+    fn position(&self) -> Duration {
+        let Some(now_playing) = &self.now_playing else {
+            return Duration::ZERO;
+        };
+        let elapsed = match (&now_playing.state, self.fetched_at) {
+            (PlayState::Playing, Some(fetched_at)) => fetched_at.elapsed(),
+            _ => Duration::ZERO,
+        };
+        let position = now_playing.position + elapsed;
+        if !now_playing.duration.is_zero() {
+            position.min(now_playing.duration)
+        } else {
+            position
+        }
+    }
+
+    async fn refresh_player(&mut self, client: &WiimClient) {
+        match client.get_now_playing().await {
+            Ok(now_playing) => {
+                self.now_playing = Some(now_playing);
+                self.fetched_at = Some(Instant::now());
+                self.error = None;
+            }
+            Err(e) => self.error = Some(e.to_string()),
+        }
+    }
+
+    async fn refresh_details(&mut self, client: &WiimClient) {
+        if let Ok(status) = client.get_status_ex().await {
+            self.device_name = status.device_name.clone();
+            self.wifi = status.signal_quality().zip(status.rssi_dbm());
+        }
+        self.role = client.get_group_role().await.ok();
+        self.followers = match self.role {
+            Some(GroupRole::Leader) => client.get_group_followers().await.unwrap_or_default(),
+            _ => Vec::new(),
+        };
+    }
+
+    async fn perform(&mut self, client: &WiimClient, action: &Action) {
+        let result = match action {
+            Action::Quit => Ok(()),
+            Action::TogglePlayPause => client.toggle_play_pause().await,
+            Action::Next => client.next_track().await,
+            Action::Previous => client.previous_track().await,
+            Action::Stop => client.stop().await,
+            Action::VolumeUp => client.volume_up(None).await.map(|_| ()),
+            Action::VolumeDown => client.volume_down(None).await.map(|_| ()),
+            Action::ToggleMute => match &self.now_playing {
+                Some(now_playing) if now_playing.is_muted => client.unmute().await,
+                _ => client.mute().await,
+            },
+            Action::SeekBack | Action::SeekForward => {
+                let position = self.position();
+                let target = if matches!(action, Action::SeekBack) {
+                    position.saturating_sub(SEEK_STEP)
+                } else {
+                    position + SEEK_STEP
+                };
+                client.seek(target).await
+            }
+        };
+        match result {
+            Ok(()) => self.refresh_player(client).await,
+            Err(e) => self.error = Some(e.to_string()),
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [header, playing, volume, group, footer] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Length(6),
+            Constraint::Length(3),
+            Constraint::Min(3),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let name = self.device_name.as_deref().unwrap_or("WiiM");
+        let mut header_spans = vec![name.bold()];
+        if let Some(now_playing) = &self.now_playing {
+            header_spans.push(format!("  {} · {}", now_playing.source, now_playing.state).into());
+        }
+        if let Some((quality, rssi)) = &self.wifi {
+            header_spans.push(format!("  📶 {quality} ({rssi} dBm)").into());
+        }
+        frame.render_widget(Line::from(header_spans), header);
+
+        self.draw_now_playing(frame, playing);
+
+        let (ratio, label) = match &self.now_playing {
+            Some(now_playing) if now_playing.is_muted => (0.0, i18n::tr("ui.muted", &[])),
+            Some(now_playing) => (
+                now_playing.volume.fraction(),
+                now_playing.volume.to_string(),
+            ),
+            None => (0.0, String::new()),
+        };
+        frame.render_widget(
+            Gauge::default()
+                .block(Block::bordered().title(i18n::tr("ui.volume", &[])))
+                .gauge_style(Style::default().fg(Color::Cyan))
+                .ratio(ratio)
+                .label(label),
+            volume,
+        );
+
+        let mut group_lines = vec![Line::from(
+            self.role.map(|role| role.to_string()).unwrap_or_default(),
+        )];
+        group_lines.extend(self.followers.iter().map(|follower| {
+            Line::from(format!(
+                "  • {} {}",
+                follower.name.as_deref().unwrap_or("?"),
+                follower.ip.as_deref().unwrap_or_default()
+            ))
+        }));
+        frame.render_widget(
+            Paragraph::new(group_lines).block(Block::bordered().title(i18n::tr("ui.group", &[]))),
+            group,
+        );
+
+        let footer_line = match &self.error {
+            Some(error) => Line::from(format!("⚠️ {error}")).fg(Color::Red),
+            None => Line::from(i18n::tr("ui.help", &[])).add_modifier(Modifier::DIM),
+        };
+        frame.render_widget(footer_line, footer);
+    }
+
+    fn draw_now_playing(&self, frame: &mut Frame, area: ratatui::layout::Rect) {
+        let block = Block::bordered().title(i18n::tr("ui.now_playing", &[]));
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+        let [text, progress] =
+            Layout::vertical([Constraint::Length(3), Constraint::Length(1)]).areas(inner);
+
+        let Some(now_playing) = &self.now_playing else {
+            frame.render_widget(Paragraph::new(i18n::tr("ui.nothing_playing", &[])), text);
+            return;
+        };
+        let lines = vec![
+            Line::from(now_playing.title.clone().unwrap_or_default()).bold(),
+            Line::from(now_playing.artist.clone().unwrap_or_default()),
+            Line::from(now_playing.album.clone().unwrap_or_default()).add_modifier(Modifier::DIM),
+        ];
+        frame.render_widget(Paragraph::new(lines), text);
+
+        let position = self.position();
+        let (ratio, label) = if !now_playing.duration.is_zero() {
+            (
+                position.as_secs_f64() / now_playing.duration.as_secs_f64(),
+                format!(
+                    "{} / {}",
+                    format_time(position),
+                    format_time(now_playing.duration)
+                ),
+            )
+        } else {
+            (0.0, format_time(position))
+        };
+        frame.render_widget(
+            Gauge::default()
+                .gauge_style(Style::default().fg(Color::Green))
+                .ratio(ratio.clamp(0.0, 1.0))
+                .label(label),
+            progress,
+        );
+    }
+}
+
+/// Forward key presses from a blocking thread until the dashboard closes
+fn spawn_key_reader() -> mpsc::UnboundedReceiver<KeyCode> {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    std::thread::spawn(move || {
+        while !sender.is_closed() {
+            // Poll with a timeout so the thread notices when the dashboard is gone
+            if !event::poll(FRAME_INTERVAL).unwrap_or(false) {
+                continue;
+            }
+            if let Ok(Event::Key(key)) = event::read() {
+                if key.kind == KeyEventKind::Press && sender.send(key.code).is_err() {
+                    break;
+                }
+            }
+        }
+    });
+    receiver
+}
+
+async fn run_dashboard(client: &WiimClient, terminal: &mut DefaultTerminal) -> std::io::Result<()> {
+    let mut dashboard = Dashboard::default();
+    dashboard.refresh_details(client).await;
+    dashboard.refresh_player(client).await;
+
+    let mut keys = spawn_key_reader();
+    let mut frames = tokio::time::interval(FRAME_INTERVAL);
+    let mut player = tokio::time::interval(PLAYER_INTERVAL);
+    let mut details = tokio::time::interval(DETAILS_INTERVAL);
+    for timer in [&mut frames, &mut player, &mut details] {
+        timer.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    }
+    loop {
+        terminal.draw(|frame| dashboard.draw(frame))?;
+        tokio::select! {
+            key = keys.recv() => match key.and_then(action_for) {
+                Some(Action::Quit) => return Ok(()),
+                Some(action) => dashboard.perform(client, &action).await,
+                None if key.is_none() => return Ok(()),
+                None => {}
+            },
+            _ = player.tick() => dashboard.refresh_player(client).await,
+            _ = details.tick() => dashboard.refresh_details(client).await,
+            _ = frames.tick() => {}
+        }
+    }
+}
+
+pub async fn run(client: &WiimClient) -> Result<CommandOutput, Box<dyn Error>> {
+    let mut terminal = ratatui::init();
+    let result = run_dashboard(client, &mut terminal).await;
+    ratatui::restore();
+    result?;
+    Ok(CommandOutput::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+    use wiim_api::{PlaybackSource, Volume};
+
+    fn now_playing(state: PlayState) -> NowPlaying {
+        NowPlaying {
+            title: Some("Blue in Green".to_string()),
+            artist: Some("Miles Davis".to_string()),
+            album: Some("Kind of Blue".to_string()),
+            state,
+            volume: Volume::saturating(35),
+            position: Duration::from_secs(60),
+            duration: Duration::from_secs(337),
+            source: PlaybackSource::Network,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_position_advances_only_while_playing() {
+        let mut dashboard = Dashboard {
+            now_playing: Some(now_playing(PlayState::Paused)),
+            fetched_at: Some(Instant::now() - Duration::from_secs(5)),
+            ..Default::default()
+        };
+        assert_eq!(dashboard.position(), Duration::from_secs(60));
+        dashboard.now_playing = Some(now_playing(PlayState::Playing));
+        assert!(dashboard.position() >= Duration::from_secs(65));
+        dashboard.fetched_at = Some(Instant::now() - Duration::from_secs(600));
+        assert_eq!(dashboard.position(), Duration::from_secs(337));
+    }
+
+    #[test]
+    fn test_draw() {
+        let dashboard = Dashboard {
+            device_name: Some("Living Room".to_string()),
+            now_playing: Some(now_playing(PlayState::Paused)),
+            fetched_at: Some(Instant::now()),
+            role: Some(GroupRole::Standalone),
+            wifi: Some(("Good".to_string(), -55)),
+            ..Default::default()
+        };
+        let mut terminal = Terminal::new(TestBackend::new(60, 14)).unwrap();
+        terminal.draw(|frame| dashboard.draw(frame)).unwrap();
+        let screen: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect();
+        assert!(screen.contains("Living Room"));
+        assert!(screen.contains("Blue in Green"));
+        assert!(screen.contains("1:00 / 5:37"));
+        assert!(screen.contains("35%"));
+    }
+}
@@ -0,0 +1,134 @@
+//! `wiim-control upgrade`: check for and install firmware updates
+//!
+//! Progress goes to stderr, prefixed with the device name so `--all upgrade` stays
+//! readable, while the final result is the usual command output.
+
+use crate::error::CliError;
+use crate::i18n;
+use crate::output::CommandOutput;
+use serde_json::json;
+use std::error::Error;
+use std::time::{Duration, Instant};
+use wiim_api::{UpdateProgress, WiimClient};
+
+/// How often a running update is polled
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+/// Downloading and flashing take a few minutes on a typical connection
+const UPDATE_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+/// How long the device may stay away while it reboots into the new firmware
+const REBOOT_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+fn progress_line(progress: &UpdateProgress) -> String {
+    match progress {
+        UpdateProgress::Checking => i18n::tr("upgrade.checking", &[]),
+        UpdateProgress::Downloading => i18n::tr("upgrade.downloading", &[]),
+        UpdateProgress::Installing { percent } => {
+            let percent = percent.map(|p| format!(" {p}%")).unwrap_or_default();
+            i18n::tr("upgrade.installing", &[("percent", &percent)])
+        }
+        UpdateProgress::Complete => i18n::tr("upgrade.rebooting", &[]),
+        other => other.to_string(),
+    }
+}
+
+pub async fn run(
+    client: &WiimClient,
+    check: bool,
+    json: bool,
+) -> Result<CommandOutput, Box<dyn Error>> {
+    let status = client.get_status_ex().await?;
+    let name = status.device_name.clone().unwrap_or_default();
+    let firmware = status.firmware_status();
+    let current = firmware.current.clone().unwrap_or_default();
+
+    let Some(available) = firmware.available else {
+        return Ok(CommandOutput::message(i18n::tr(
+            "upgrade.up_to_date",
+            &[("version", &current)],
+        ))
+        .with_state(json!({ "current": current, "available": null, "updated": false })));
+    };
+    if check {
+        return Ok(CommandOutput::message(i18n::tr(
+            "upgrade.available",
+            &[("current", &current), ("available", &available)],
+        ))
+        .with_state(json!({ "current": current, "available": available, "updated": false })));
+    }
+
+    client.start_firmware_update().await?;
+    let started = Instant::now();
+    let mut last_line = String::new();
+    // Polling stops answering once the device reboots into the new firmware
+    while let Ok(progress) = client.get_update_progress().await {
+        if let UpdateProgress::Failed { code } = progress {
+            return Err(wiim_api::WiimError::InvalidResponse(format!(
+                "Firmware update failed on {name} (code {code})"
+            ))
+            .into());
+        }
+        let line = progress_line(&progress);
+        if !json && line != last_line {
+            eprintln!("{name}: {line}");
+            last_line = line;
+        }
+        if started.elapsed() > UPDATE_TIMEOUT {
+            return Err(CliError::Timeout(format!(
+                "Firmware update on {name} still running after {} minutes",
+                UPDATE_TIMEOUT.as_secs() / 60
+            ))
+            .into());
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+
+    if !json {
+        eprintln!("{name}: {}", i18n::tr("upgrade.rebooting", &[]));
+    }
+    client
+        .wait_until_reachable(REBOOT_TIMEOUT)
+        .await
+        .map_err(|_| {
+            CliError::Timeout(format!(
+                "{name} did not come back within {} minutes of updating",
+                REBOOT_TIMEOUT.as_secs() / 60
+            ))
+        })?;
+    let installed = client
+        .get_firmware_status()
+        .await?
+        .current
+        .unwrap_or_default();
+    if installed == current {
+        return Err(wiim_api::WiimError::InvalidResponse(format!(
+            "{name} is still on {current} after the update"
+        ))
+        .into());
+    }
+    Ok(CommandOutput::message(i18n::tr(
+        "upgrade.done",
+        &[("from", &current), ("to", &installed)],
+    ))
+    .with_state(json!({ "current": installed, "previous": current, "updated": true })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress_line() {
+        assert_eq!(
+            progress_line(&UpdateProgress::Installing { percent: Some(40) }),
+            "💾 Installing firmware 40%"
+        );
+        assert_eq!(
+            progress_line(&UpdateProgress::Installing { percent: None }),
+            "💾 Installing firmware"
+        );
+        assert_eq!(
+            progress_line(&UpdateProgress::Other { code: 99 }),
+            "stage 99"
+        );
+    }
+}
@@ -0,0 +1,183 @@
+//! Volume links kept by `wiim-control daemon`: devices that follow each other's volume
+//! without being in a LinkPlay group, e.g. a streamer feeding an amp and a kitchen speaker
+//!
+//! Each `[volume_link.<name>]` table has `devices`, a list of two or more names from
+//! `[devices]` or IPs. The offsets between the devices are taken when the
+//! daemon first reaches all of them; after that, a change on one device moves the others
+//! by the same amount. Volumes are clamped to 0-100, but the offsets are kept, so lowering
+//! everything to silence and back restores the same balance.
+
+use crate::error::CliError;
+use crate::{configured_devices, resolve_device, Config};
+use serde::Deserialize;
+
+/// Settings from a `[volume_link.<name>]` table
+#[derive(Debug, Clone, Deserialize)]
+pub struct VolumeLinkConfig {
+    devices: Vec<String>,
+}
+
+/// Check a device list such as `["amp", "kitchen"]`
+pub fn parse_devices(items: &[String]) -> Result<Vec<String>, String> {
+    let mut devices: Vec<String> = Vec::new();
+    for device in items {
+        if devices.contains(device) {
+            return Err(format!("'{device}' is listed twice"));
+        }
+        devices.push(device.clone());
+    }
+    if devices.len() < 2 {
+        return Err("a volume link needs at least two devices".to_string());
+    }
+    Ok(devices)
+}
+
+/// One set of devices whose volumes move together
+#[derive(Debug)]
+pub struct VolumeLink {
+    pub name: String,
+    /// Member IPs, in the order they were configured
+    pub ips: Vec<String>,
+    /// Each member's offset from the first one, once all have been seen
+    offsets: Option<Vec<i32>>,
+    /// The first member's volume before clamping
+    reference: i32,
+    /// The volume each member should be at, `None` while it was unreachable
+    expected: Vec<Option<u8>>,
+}
+
+impl VolumeLink {
+    pub fn new(name: String, ips: Vec<String>) -> Self {
+        let expected = vec![None; ips.len()];
+        VolumeLink {
+            name,
+            ips,
+            offsets: None,
+            reference: 0,
+            expected,
+        }
+    }
+
+    /// Take in each member's volume from the latest poll (`None` when unreachable) and
+    /// return the volumes to set, as (member index, volume)
+    ///
+    /// The first member whose volume differs from what was expected leads; a member that
+    /// comes back after being unreachable is brought in line instead of leading.
+    pub fn observe(&mut self, volumes: &[Option<u8>]) -> Vec<(usize, u8)> {
+        let Some(offsets) = &self.offsets else {
+            let all: Option<Vec<u8>> = volumes.iter().copied().collect();
+            if let Some(all) = all {
+                let first = i32::from(all[0]);
+                self.offsets = Some(all.iter().map(|v| i32::from(*v) - first).collect());
+                self.reference = first;
+                self.expected = volumes.to_vec();
+            }
+            return Vec::new();
+        };
+
+        let leader =
+            volumes
+                .iter()
+                .zip(&self.expected)
+                .enumerate()
+                .find_map(|(index, (seen, expected))| match (seen, expected) {
+                    (Some(volume), Some(expected)) if volume != expected => Some((index, *volume)),
+                    _ => None,
+                });
+        if let Some((index, volume)) = leader {
+            self.reference = i32::from(volume) - offsets[index];
+        }
+
+        let mut changes = Vec::new();
+        for (index, seen) in volumes.iter().enumerate() {
+            let target = (self.reference + offsets[index]).clamp(0, 100) as u8;
+            self.expected[index] = seen.map(|_| target);
+            if seen.is_some_and(|volume| volume != target) {
+                changes.push((index, target));
+            }
+        }
+        changes
+    }
+}
+
+/// The configured links, sorted by name; members must be devices the daemon serves
+pub fn from_config(config: &Config) -> Result<Vec<VolumeLink>, CliError> {
+    let served: Vec<String> = configured_devices(config)
+        .into_iter()
+        .map(|(_, ip)| ip)
+        .collect();
+    let mut links = Vec::new();
+    for (name, link) in config.volume_link.iter().flatten() {
+        let invalid = |e: String| CliError::Config(format!("volume_link.{name}: {e}"));
+        let mut ips = Vec::new();
+        for device in parse_devices(&link.devices).map_err(invalid)? {
+            let ip = resolve_device(Some(&device), config);
+            if !served.contains(&ip) {
+                return Err(invalid(format!(
+                    "'{device}' is not device_ip or one of [devices]"
+                )));
+            }
+            ips.push(ip);
+        }
+        links.push(VolumeLink::new(name.clone(), ips));
+    }
+    links.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(links)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn link() -> VolumeLink {
+        VolumeLink::new(
+            "living".to_string(),
+            vec!["10.0.0.2".to_string(), "10.0.0.3".to_string()],
+        )
+    }
+
+    #[test]
+    fn test_parse_devices() {
+        let parse_devices = |items: &[&str]| {
+            parse_devices(
+                &items
+                    .iter()
+                    .map(|item| item.to_string())
+                    .collect::<Vec<_>>(),
+            )
+        };
+        assert_eq!(
+            parse_devices(&["amp", "kitchen"]).unwrap(),
+            ["amp", "kitchen"]
+        );
+        assert!(parse_devices(&["amp"]).is_err());
+        assert!(parse_devices(&["amp", "amp"]).is_err());
+    }
+
+    #[test]
+    fn test_observe_keeps_offsets() {
+        let mut link = link();
+        // Nothing happens until both devices have been seen
+        assert!(link.observe(&[Some(40), None]).is_empty());
+        assert!(link.observe(&[Some(40), Some(20)]).is_empty());
+        assert!(link.observe(&[Some(40), Some(20)]).is_empty());
+
+        assert_eq!(link.observe(&[Some(50), Some(20)]), [(1, 30)]);
+        assert!(link.observe(&[Some(50), Some(30)]).is_empty());
+        assert_eq!(link.observe(&[Some(50), Some(25)]), [(0, 45)]);
+
+        // Clamped at 0, but the offset comes back
+        assert_eq!(link.observe(&[Some(10), Some(25)]), [(1, 0)]);
+        assert_eq!(link.observe(&[Some(40), Some(0)]), [(1, 20)]);
+    }
+
+    #[test]
+    fn test_observe_resyncs_returning_device() {
+        let mut link = link();
+        link.observe(&[Some(40), Some(20)]);
+        assert!(link.observe(&[Some(40), None]).is_empty());
+        assert!(link.observe(&[Some(60), None]).is_empty());
+        // The speaker was switched back on at its old volume; it follows instead of leading
+        assert_eq!(link.observe(&[Some(60), Some(20)]), [(1, 40)]);
+    }
+}
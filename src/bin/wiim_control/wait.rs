@@ -0,0 +1,132 @@
+//! `wiim-control wait-for`: block until the device reaches a state or the track changes
+
+use crate::error::CliError;
+use crate::i18n;
+use crate::output::CommandOutput;
+use std::error::Error;
+use std::time::{Duration, Instant};
+use wiim_api::{NowPlaying, PlayState, WiimClient};
+
+/// What `wait-for` waits for; every given condition must hold at once
+#[derive(Debug, Clone)]
+pub struct Condition {
+    pub state: Option<PlayState>,
+    pub track_change: bool,
+}
+
+impl Condition {
+    fn is_met(&self, initial: Option<&NowPlaying>, now_playing: &NowPlaying) -> bool {
+        let state_ok = self
+            .state
+            .as_ref()
+            .is_none_or(|state| now_playing.state == *state);
+        // Without a first reading there is nothing to compare against yet
+        let track_ok = !self.track_change
+            || initial.is_some_and(|initial| now_playing.is_different_track(initial));
+        state_ok && track_ok
+    }
+
+    fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(state) = &self.state {
+            parts.push(format!("state {state}"));
+        }
+        if self.track_change {
+            parts.push("a track change".to_string());
+        }
+        parts.join(" and ")
+    }
+}
+
+pub async fn run(
+    client: &WiimClient,
+    condition: &Condition,
+    timeout: Option<Duration>,
+    interval: Duration,
+) -> Result<CommandOutput, Box<dyn Error>> {
+    let started = Instant::now();
+    let mut initial: Option<NowPlaying> = None;
+
+    loop {
+        let last_error = match client.get_now_playing().await {
+            Ok(now_playing) => {
+                if condition.is_met(initial.as_ref(), &now_playing) {
+                    return Ok(met(condition, now_playing));
+                }
+                initial.get_or_insert(now_playing);
+                None
+            }
+            // The device may still be starting the stream; keep trying until the timeout
+            Err(e) => Some(e),
+        };
+
+        if let Some(timeout) = timeout {
+            if started.elapsed() + interval > timeout {
+                let mut message = format!(
+                    "Timed out after {}s waiting for {}",
+                    timeout.as_secs_f32(),
+                    condition.describe()
+                );
+                if let Some(e) = last_error {
+                    message.push_str(&format!(" (last error: {e})"));
+                }
+                return Err(CliError::Timeout(message).into());
+            }
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+fn met(condition: &Condition, now_playing: NowPlaying) -> CommandOutput {
+    let message = if condition.track_change {
+        let track = match (&now_playing.artist, &now_playing.title) {
+            (Some(artist), Some(title)) => format!("{artist} - {title}"),
+            (_, Some(title)) => title.clone(),
+            _ => i18n::tr("queue.unknown_track", &[]),
+        };
+        i18n::tr("wait.track_changed", &[("track", &track)])
+    } else {
+        let state = i18n::tr(&format!("state.{}", now_playing.state), &[]);
+        i18n::tr("wait.state_reached", &[("state", &state)])
+    };
+    CommandOutput::message(message).with_state(now_playing)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiim_api::Volume;
+
+    fn playing(title: &str, state: PlayState) -> NowPlaying {
+        NowPlaying {
+            title: Some(title.to_string()),
+            state,
+            volume: Volume::saturating(50),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_state_condition() {
+        let condition = Condition {
+            state: Some(PlayState::Playing),
+            track_change: false,
+        };
+        assert!(!condition.is_met(None, &playing("A", PlayState::Loading)));
+        assert!(condition.is_met(None, &playing("A", PlayState::Playing)));
+    }
+
+    #[test]
+    fn test_track_change_condition() {
+        let condition = Condition {
+            state: Some(PlayState::Playing),
+            track_change: true,
+        };
+        let first = playing("A", PlayState::Playing);
+        assert!(!condition.is_met(None, &first));
+        assert!(!condition.is_met(Some(&first), &first));
+        assert!(!condition.is_met(Some(&first), &playing("B", PlayState::Loading)));
+        assert!(condition.is_met(Some(&first), &playing("B", PlayState::Playing)));
+        assert_eq!(condition.describe(), "state playing and a track change");
+    }
+}
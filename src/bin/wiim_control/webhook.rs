@@ -0,0 +1,320 @@
+//! POST JSON to user URLs when the track or playback state changes or a device goes offline,
+//! from `watch` and `daemon`
+//!
+//! Each `[webhooks.<name>]` table has a `url`, optional `events` list (default all)
+//! and an optional Handlebars `template` for the body. Without a template the body is
+//! `{"event", "device", "now_playing", "error"}`.
+
+use crate::{templates, TemplateContext};
+use handlebars::Handlebars;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::time::Duration;
+use wiim_api::NowPlaying;
+
+/// Attempts after the first failed delivery, spaced by [`RETRY_DELAY`] times the attempt
+const RETRIES: u32 = 3;
+const RETRY_DELAY: Duration = Duration::from_secs(2);
+
+/// Settings from a `[webhooks.<name>]` table
+#[derive(Debug, Clone, Deserialize)]
+pub struct WebhookConfig {
+    url: String,
+    /// Event names; all events when missing
+    events: Option<Vec<String>>,
+    template: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebhookEvent {
+    /// Title, artist or album changed
+    Track,
+    /// Playing, paused, stopped or loading changed
+    State,
+    /// The device stopped answering
+    Offline,
+    /// The device answers again
+    Online,
+}
+
+impl WebhookEvent {
+    const ALL: [WebhookEvent; 4] = [
+        WebhookEvent::Track,
+        WebhookEvent::State,
+        WebhookEvent::Offline,
+        WebhookEvent::Online,
+    ];
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEvent::Track => "track",
+            WebhookEvent::State => "state",
+            WebhookEvent::Offline => "offline",
+            WebhookEvent::Online => "online",
+        }
+    }
+}
+
+/// Parse an event list such as `["track", "offline"]`
+pub fn parse_events(names: &[String]) -> Result<Vec<WebhookEvent>, String> {
+    names
+        .iter()
+        .map(|name| {
+            WebhookEvent::ALL
+                .into_iter()
+                .find(|event| event.as_str() == name)
+                .ok_or_else(|| {
+                    format!(
+                        "unknown webhook event '{name}', expected track, state, offline or online"
+                    )
+                })
+        })
+        .collect()
+}
+
+/// Events between two observations; `None` means the device was unreachable
+pub fn detect(previous: Option<&NowPlaying>, current: Option<&NowPlaying>) -> Vec<WebhookEvent> {
+    match (previous, current) {
+        (Some(_), None) => vec![WebhookEvent::Offline],
+        (None, Some(_)) => vec![WebhookEvent::Online],
+        (None, None) => Vec::new(),
+        (Some(previous), Some(current)) => {
+            let mut events = Vec::new();
+            if current.title != previous.title
+                || current.artist != previous.artist
+                || current.album != previous.album
+            {
+                events.push(WebhookEvent::Track);
+            }
+            if current.state != previous.state {
+                events.push(WebhookEvent::State);
+            }
+            events
+        }
+    }
+}
+
+struct Webhook {
+    name: String,
+    url: String,
+    events: Vec<WebhookEvent>,
+    template: Option<String>,
+}
+
+/// Sends the configured webhooks for one device
+pub struct Notifier {
+    http: reqwest::Client,
+    /// The shared registry, escaping for JSON strings instead of HTML
+    handlebars: Handlebars<'static>,
+    hooks: Vec<Webhook>,
+    device: String,
+    /// The last observation; `None` until the first one, so startup fires nothing
+    last: Option<Option<NowPlaying>>,
+}
+
+impl Notifier {
+    /// `None` when no webhooks are configured
+    pub fn from_config(
+        webhooks: Option<&HashMap<String, WebhookConfig>>,
+        device: &str,
+    ) -> Result<Option<Self>, String> {
+        let Some(webhooks) = webhooks.filter(|webhooks| !webhooks.is_empty()) else {
+            return Ok(None);
+        };
+        let mut hooks = Vec::new();
+        for (name, webhook) in webhooks {
+            let events = match &webhook.events {
+                Some(events) => {
+                    parse_events(events).map_err(|e| format!("webhooks.{name}: {e}"))?
+                }
+                None => WebhookEvent::ALL.to_vec(),
+            };
+            hooks.push(Webhook {
+                name: name.clone(),
+                url: webhook.url.clone(),
+                events,
+                template: webhook.template.clone(),
+            });
+        }
+
+        let mut handlebars = templates::registry().as_ref().clone();
+        handlebars.register_escape_fn(escape_json);
+        for hook in &hooks {
+            if let Some(template) = &hook.template {
+                handlebars
+                    .register_template_string(&templates::cache_name(template), template)
+                    .map_err(|e| format!("webhooks.{}: template error: {e}", hook.name))?;
+            }
+        }
+        Ok(Some(Self {
+            http: reqwest::Client::new(),
+            handlebars,
+            hooks,
+            device: device.to_string(),
+            last: None,
+        }))
+    }
+
+    /// Name devices by what they call themselves rather than by IP
+    pub fn set_device(&mut self, device: &str) {
+        self.device = device.to_string();
+    }
+
+    /// Feed an observation (`None` when unreachable) and send webhooks for what changed;
+    /// deliveries run in the background
+    pub fn observe(&mut self, now_playing: Option<&NowPlaying>, error: Option<&str>) {
+        let events = match &self.last {
+            Some(previous) => detect(previous.as_ref(), now_playing),
+            None => Vec::new(),
+        };
+        self.last = Some(now_playing.cloned());
+
+        for event in events {
+            for hook in self
+                .hooks
+                .iter()
+                .filter(|hook| hook.events.contains(&event))
+            {
+                let body = match self.body(hook, event, now_playing, error) {
+                    Ok(body) => body,
+                    Err(e) => {
+                        eprintln!("⚠️ Webhook {}: {e}", hook.name);
+                        continue;
+                    }
+                };
+                tokio::spawn(deliver(
+                    self.http.clone(),
+                    hook.name.clone(),
+                    hook.url.clone(),
+                    body,
+                ));
+            }
+        }
+    }
+
+    fn body(
+        &self,
+        hook: &Webhook,
+        event: WebhookEvent,
+        now_playing: Option<&NowPlaying>,
+        error: Option<&str>,
+    ) -> Result<String, String> {
+        let Some(template) = &hook.template else {
+            let body = json!({
+                "event": event.as_str(),
+                "device": self.device,
+                "now_playing": now_playing,
+                "error": error,
+            });
+            return Ok(body.to_string());
+        };
+
+        let mut context = match now_playing {
+            Some(now_playing) => serde_json::to_value(TemplateContext::from(now_playing))
+                .map_err(|e| e.to_string())?,
+            None => json!({}),
+        };
+        if let Value::Object(fields) = &mut context {
+            fields.insert("event".to_string(), json!(event.as_str()));
+            fields.insert("device".to_string(), json!(self.device));
+            fields.insert("error".to_string(), json!(error));
+        }
+        self.handlebars
+            .render(&templates::cache_name(template), &context)
+            .map_err(|e| format!("template error: {e}"))
+    }
+}
+
+/// Escape a value for use inside a JSON string
+fn escape_json(value: &str) -> String {
+    let quoted = Value::String(value.to_string()).to_string();
+    quoted[1..quoted.len() - 1].to_string()
+}
+
+async fn deliver(http: reqwest::Client, name: String, url: String, body: String) {
+    for attempt in 0..=RETRIES {
+        if attempt > 0 {
+            tokio::time::sleep(RETRY_DELAY * attempt).await;
+        }
+        let result = http
+            .post(&url)
+            .header("Content-Type", "application/json")
+            .body(body.clone())
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        match result {
+            Ok(_) => return,
+            Err(e) if attempt == RETRIES => eprintln!("⚠️ Webhook {name}: {e}"),
+            Err(_) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiim_api::{PlayState, Volume};
+
+    fn now_playing(title: &str, state: PlayState) -> NowPlaying {
+        NowPlaying {
+            title: Some(title.to_string()),
+            artist: Some("Artist \"Quoted\"".to_string()),
+            state,
+            volume: Volume::saturating(30),
+            duration: Duration::from_secs(200),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_detect_events() {
+        let playing = now_playing("One", PlayState::Playing);
+        let paused = now_playing("One", PlayState::Paused);
+        let next = now_playing("Two", PlayState::Paused);
+
+        assert_eq!(detect(Some(&playing), Some(&playing)), vec![]);
+        assert_eq!(
+            detect(Some(&playing), Some(&paused)),
+            vec![WebhookEvent::State]
+        );
+        assert_eq!(detect(Some(&playing), Some(&next)).len(), 2);
+        assert_eq!(detect(Some(&playing), None), vec![WebhookEvent::Offline]);
+        assert_eq!(detect(None, Some(&playing)), vec![WebhookEvent::Online]);
+    }
+
+    #[test]
+    fn test_parse_events() {
+        assert_eq!(
+            parse_events(&["track".to_string(), "offline".to_string()]).unwrap(),
+            vec![WebhookEvent::Track, WebhookEvent::Offline]
+        );
+        assert!(parse_events(&["track".to_string(), "volume".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_template_body_is_json_escaped() {
+        let webhooks = HashMap::from([(
+            "n8n".to_string(),
+            WebhookConfig {
+                url: "http://localhost/hook".to_string(),
+                events: None,
+                template: Some(r#"{"text": "{{event}}: {{artist}} - {{title}}"}"#.to_string()),
+            },
+        )]);
+        let notifier = Notifier::from_config(Some(&webhooks), "kitchen")
+            .unwrap()
+            .unwrap();
+        let body = notifier
+            .body(
+                &notifier.hooks[0],
+                WebhookEvent::Track,
+                Some(&now_playing("One", PlayState::Playing)),
+                None,
+            )
+            .unwrap();
+        let body: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["text"], "track: Artist \"Quoted\" - One");
+    }
+}
@@ -0,0 +1,133 @@
+//! `wiim-mpris`: expose a WiiM device as an MPRIS media player on the D-Bus session bus,
+//! so playerctl, desktop media controls and waybar's mpris module can drive it
+//!
+//! The config file is checked on every poll; when it changes and the device resolves to a
+//! different address, the player switches to it without restarting.
+
+mod player;
+
+use clap::Parser;
+use player::{Player, Root};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use wiim_api::WiimClient;
+
+const OBJECT_PATH: &str = "/org/mpris/MediaPlayer2";
+
+#[derive(Parser)]
+#[command(name = "wiim-mpris", version)]
+#[command(about = "Expose a WiiM device as an MPRIS player on the D-Bus session bus")]
+struct Args {
+    /// Device IP address or name from [devices] (defaults to device_ip in the config)
+    #[arg(short, long)]
+    device: Option<String>,
+
+    /// wiim-control config file to read the device from
+    #[arg(short, long)]
+    config: Option<PathBuf>,
+
+    /// How often to poll the device, in seconds
+    #[arg(long, default_value = "2")]
+    interval: u64,
+
+    /// Bus name suffix, giving org.mpris.MediaPlayer2.<name>
+    #[arg(long, default_value = "wiim")]
+    name: String,
+}
+
+fn config_path(args: &Args) -> Option<PathBuf> {
+    args.config
+        .clone()
+        .or_else(|| dirs::config_dir().map(|dir| dir.join("wiim-control").join("config.toml")))
+}
+
+fn modified(path: Option<&Path>) -> Option<SystemTime> {
+    std::fs::metadata(path?).and_then(|m| m.modified()).ok()
+}
+
+/// Resolve the device like wiim-control does: an IP, a name from [devices], or device_ip
+fn resolve_device(args: &Args) -> Result<String, Box<dyn std::error::Error>> {
+    let config: toml::Table = match config_path(args).filter(|path| path.exists()) {
+        Some(path) => toml::from_str(&std::fs::read_to_string(&path)?)
+            .map_err(|e| format!("{}: {e}", path.display()))?,
+        None => toml::Table::new(),
+    };
+
+    if let Some(device) = &args.device {
+        let named = config
+            .get("devices")
+            .and_then(|devices| devices.get(device))
+            .and_then(|ip| ip.as_str());
+        return Ok(named.unwrap_or(device).to_string());
+    }
+    config
+        .get("device_ip")
+        .and_then(|ip| ip.as_str())
+        .map(str::to_string)
+        .ok_or_else(|| "no device given and no device_ip in the config; use --device".into())
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+    let mut device = resolve_device(&args)?;
+    let mut client = WiimClient::new(&device);
+    let config_file = config_path(&args);
+    let mut config_modified = modified(config_file.as_deref());
+
+    let connection = zbus::connection::Builder::session()?
+        .name(format!("org.mpris.MediaPlayer2.{}", args.name))?
+        .serve_at(OBJECT_PATH, Root)?
+        .serve_at(OBJECT_PATH, Player::new(client.clone()))?
+        .build()
+        .await?;
+    let player = connection
+        .object_server()
+        .interface::<_, Player>(OBJECT_PATH)
+        .await?;
+
+    let mut ticker = tokio::time::interval(Duration::from_secs(args.interval.max(1)));
+    loop {
+        ticker.tick().await;
+
+        let current = modified(config_file.as_deref());
+        if current != config_modified {
+            config_modified = current;
+            match resolve_device(&args) {
+                Ok(resolved) if resolved != device => {
+                    eprintln!("🔄 Config reloaded: now controlling {resolved}");
+                    device = resolved;
+                    client = WiimClient::new(&device);
+                    player.get_mut().await.set_client(client.clone());
+                }
+                Ok(_) => {}
+                Err(e) => eprintln!("⚠️ Not reloading the config: {e}"),
+            }
+        }
+
+        let now_playing = match client.get_now_playing().await {
+            Ok(now_playing) => Some(now_playing),
+            Err(e) => {
+                eprintln!("⚠️ {e}");
+                None
+            }
+        };
+
+        let mut state = player.get_mut().await;
+        let previous = state.update(now_playing.clone());
+        let changed = match (&previous, &now_playing) {
+            (Some(previous), Some(current)) => current.differs_from(previous),
+            (None, None) => false,
+            _ => true,
+        };
+        if changed {
+            let emitter = player.signal_emitter();
+            state.playback_status_changed(emitter).await?;
+            state.metadata_changed(emitter).await?;
+            state.volume_changed(emitter).await?;
+            state.loop_status_changed(emitter).await?;
+            state.shuffle_changed(emitter).await?;
+            state.can_seek_changed(emitter).await?;
+        }
+    }
+}
@@ -0,0 +1,416 @@
+//! The `org.mpris.MediaPlayer2` and `org.mpris.MediaPlayer2.Player` interfaces
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::time::{Duration, Instant};
+use wiim_api::{LoopMode, NowPlaying, PlayState, RepeatMode, Volume, WiimClient, WiimError};
+use zbus::fdo;
+use zbus::object_server::SignalEmitter;
+use zbus::zvariant::{ObjectPath, OwnedObjectPath, OwnedValue, Value};
+
+/// MPRIS positions and lengths are in microseconds
+fn micros(duration: Duration) -> i64 {
+    duration.as_micros() as i64
+}
+
+fn to_fdo(error: WiimError) -> fdo::Error {
+    fdo::Error::Failed(error.to_string())
+}
+
+fn owned(value: impl Into<Value<'static>>) -> OwnedValue {
+    value
+        .into()
+        .try_into()
+        .expect("plain values convert without file descriptors")
+}
+
+/// Stable object path for a track, derived from its metadata
+fn track_id(now_playing: &NowPlaying) -> OwnedObjectPath {
+    let mut hasher = DefaultHasher::new();
+    (&now_playing.title, &now_playing.artist, &now_playing.album).hash(&mut hasher);
+    let path = format!("/org/wiim_api/track/t{:016x}", hasher.finish());
+    ObjectPath::try_from(path)
+        .expect("hex digits form a valid object path")
+        .into()
+}
+
+pub fn playback_status(state: Option<&PlayState>) -> &'static str {
+    match state {
+        Some(PlayState::Playing | PlayState::Loading) => "Playing",
+        Some(PlayState::Paused) => "Paused",
+        Some(PlayState::Stopped) | None => "Stopped",
+    }
+}
+
+pub fn loop_status(mode: LoopMode) -> &'static str {
+    match mode.repeat {
+        RepeatMode::Off => "None",
+        RepeatMode::One => "Track",
+        RepeatMode::All => "Playlist",
+    }
+}
+
+fn repeat_mode(loop_status: &str) -> fdo::Result<RepeatMode> {
+    match loop_status {
+        "None" => Ok(RepeatMode::Off),
+        "Track" => Ok(RepeatMode::One),
+        "Playlist" => Ok(RepeatMode::All),
+        other => Err(fdo::Error::InvalidArgs(format!(
+            "unknown loop status '{other}'"
+        ))),
+    }
+}
+
+pub fn metadata(now_playing: &NowPlaying) -> HashMap<String, OwnedValue> {
+    let mut metadata = HashMap::new();
+    metadata.insert("mpris:trackid".to_string(), owned(track_id(now_playing)));
+    if !now_playing.duration.is_zero() {
+        metadata.insert(
+            "mpris:length".to_string(),
+            owned(micros(now_playing.duration)),
+        );
+    }
+    if let Some(art) = &now_playing.album_art_uri {
+        metadata.insert("mpris:artUrl".to_string(), owned(art.clone()));
+    }
+    if let Some(title) = &now_playing.title {
+        metadata.insert("xesam:title".to_string(), owned(title.clone()));
+    }
+    if let Some(artist) = &now_playing.artist {
+        metadata.insert("xesam:artist".to_string(), owned(vec![artist.clone()]));
+    }
+    if let Some(album) = &now_playing.album {
+        metadata.insert("xesam:album".to_string(), owned(album.clone()));
+    }
+    metadata
+}
+
+/// The root interface: identity only, the device has no window to raise
+pub struct Root;
+
+#[zbus::interface(name = "org.mpris.MediaPlayer2")]
+impl Root {
+    fn raise(&self) {}
+
+    fn quit(&self) {}
+
+    #[zbus(property)]
+    fn can_quit(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn can_raise(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn has_track_list(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn identity(&self) -> String {
+        "WiiM".to_string()
+    }
+
+    #[zbus(property)]
+    fn supported_uri_schemes(&self) -> Vec<String> {
+        vec!["http".to_string(), "https".to_string()]
+    }
+
+    #[zbus(property)]
+    fn supported_mime_types(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+/// The player interface, backed by the last polled status
+pub struct Player {
+    client: WiimClient,
+    now_playing: Option<NowPlaying>,
+    fetched: Instant,
+}
+
+impl Player {
+    pub fn new(client: WiimClient) -> Self {
+        Self {
+            client,
+            now_playing: None,
+            fetched: Instant::now(),
+        }
+    }
+
+    /// Control a different device, such as after the config file changed
+    pub fn set_client(&mut self, client: WiimClient) {
+        self.client = client;
+    }
+
+    /// Replace the polled status, returning the previous one
+    pub fn update(&mut self, now_playing: Option<NowPlaying>) -> Option<NowPlaying> {
+        self.fetched = Instant::now();
+        std::mem::replace(&mut self.now_playing, now_playing)
+    }
+
+    /// Current position, advanced locally since the last poll while playing
+    fn current_position(&self) -> Duration {
+        let Some(now_playing) = &self.now_playing else {
+            return Duration::ZERO;
+        };
+        let mut position = now_playing.position;
+        if now_playing.state == PlayState::Playing {
+            position += self.fetched.elapsed();
+        }
+        if !now_playing.duration.is_zero() {
+            position = position.min(now_playing.duration);
+        }
+        position
+    }
+
+    async fn seek_to(
+        &mut self,
+        position: Duration,
+        emitter: &SignalEmitter<'_>,
+    ) -> fdo::Result<()> {
+        self.client.seek(position).await.map_err(to_fdo)?;
+        if let Some(now_playing) = &mut self.now_playing {
+            now_playing.position = position;
+            self.fetched = Instant::now();
+        }
+        Self::seeked(emitter, micros(position))
+            .await
+            .map_err(fdo::Error::from)
+    }
+}
+
+#[zbus::interface(name = "org.mpris.MediaPlayer2.Player")]
+impl Player {
+    async fn play(&self) -> fdo::Result<()> {
+        self.client.resume().await.map_err(to_fdo)
+    }
+
+    async fn pause(&self) -> fdo::Result<()> {
+        self.client.pause().await.map_err(to_fdo)
+    }
+
+    async fn play_pause(&self) -> fdo::Result<()> {
+        self.client.toggle_play_pause().await.map_err(to_fdo)
+    }
+
+    async fn stop(&self) -> fdo::Result<()> {
+        self.client.stop().await.map_err(to_fdo)
+    }
+
+    async fn next(&self) -> fdo::Result<()> {
+        self.client.next_track().await.map_err(to_fdo)
+    }
+
+    async fn previous(&self) -> fdo::Result<()> {
+        self.client.previous_track().await.map_err(to_fdo)
+    }
+
+    /// Move by `offset` microseconds relative to the current position
+    async fn seek(
+        &mut self,
+        offset: i64,
+        #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+    ) -> fdo::Result<()> {
+        let target =
+            Duration::from_micros((micros(self.current_position()) + offset).max(0) as u64);
+        match self.now_playing.as_ref().map(|n| n.duration) {
+            // Seeking past the end skips to the next track, as the spec asks
+            Some(duration) if !duration.is_zero() && target >= duration => self.next().await,
+            _ => self.seek_to(target, &emitter).await,
+        }
+    }
+
+    async fn set_position(
+        &mut self,
+        track_id: ObjectPath<'_>,
+        position: i64,
+        #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+    ) -> fdo::Result<()> {
+        // Requests for a track that is no longer playing are ignored, per the spec
+        let Some(now_playing) = &self.now_playing else {
+            return Ok(());
+        };
+        if self::track_id(now_playing).as_ref() != track_id || position < 0 {
+            return Ok(());
+        }
+        self.seek_to(Duration::from_micros(position as u64), &emitter)
+            .await
+    }
+
+    async fn open_uri(&self, uri: String) -> fdo::Result<()> {
+        self.client.play_url(&uri).await.map_err(to_fdo)
+    }
+
+    #[zbus(signal)]
+    async fn seeked(emitter: &SignalEmitter<'_>, position: i64) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn playback_status(&self) -> String {
+        playback_status(self.now_playing.as_ref().map(|n| &n.state)).to_string()
+    }
+
+    #[zbus(property)]
+    fn loop_status(&self) -> String {
+        loop_status(
+            self.now_playing
+                .as_ref()
+                .map(|n| n.loop_mode)
+                .unwrap_or_default(),
+        )
+        .to_string()
+    }
+
+    #[zbus(property)]
+    async fn set_loop_status(&mut self, value: String) -> zbus::Result<()> {
+        let repeat = repeat_mode(&value)?;
+        self.client
+            .set_repeat(repeat)
+            .await
+            .map_err(|e| zbus::Error::from(to_fdo(e)))
+    }
+
+    #[zbus(property)]
+    fn shuffle(&self) -> bool {
+        self.now_playing
+            .as_ref()
+            .is_some_and(|n| n.loop_mode.shuffle)
+    }
+
+    #[zbus(property)]
+    async fn set_shuffle(&mut self, value: bool) -> zbus::Result<()> {
+        self.client
+            .set_shuffle(value)
+            .await
+            .map_err(|e| zbus::Error::from(to_fdo(e)))
+    }
+
+    #[zbus(property)]
+    fn metadata(&self) -> HashMap<String, OwnedValue> {
+        self.now_playing.as_ref().map(metadata).unwrap_or_default()
+    }
+
+    #[zbus(property)]
+    fn volume(&self) -> f64 {
+        self.now_playing
+            .as_ref()
+            .map_or(0.0, |n| n.volume.fraction())
+    }
+
+    #[zbus(property)]
+    async fn set_volume(&mut self, value: f64) -> zbus::Result<()> {
+        let volume = (value.clamp(0.0, 1.0) * 100.0).round() as u8;
+        self.client
+            .set_volume(Volume::saturating(volume))
+            .await
+            .map_err(|e| zbus::Error::from(to_fdo(e)))
+    }
+
+    #[zbus(property(emits_changed_signal = "false"))]
+    fn position(&self) -> i64 {
+        micros(self.current_position())
+    }
+
+    #[zbus(property)]
+    fn rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn minimum_rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn maximum_rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn can_go_next(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_go_previous(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_play(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_pause(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_seek(&self) -> bool {
+        self.now_playing
+            .as_ref()
+            .is_some_and(|n| !n.duration.is_zero())
+    }
+
+    #[zbus(property(emits_changed_signal = "const"))]
+    fn can_control(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn now_playing() -> NowPlaying {
+        NowPlaying {
+            title: Some("Song".to_string()),
+            artist: Some("Band".to_string()),
+            album_art_uri: Some("https://example.com/art.jpg".to_string()),
+            state: PlayState::Playing,
+            volume: Volume::saturating(40),
+            position: Duration::from_secs(1),
+            duration: Duration::from_secs(180),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_metadata() {
+        let metadata = metadata(&now_playing());
+        assert_eq!(
+            metadata["mpris:length"],
+            owned(180_000_000i64),
+            "length is in microseconds"
+        );
+        assert_eq!(metadata["xesam:title"], owned("Song".to_string()));
+        assert_eq!(metadata["xesam:artist"], owned(vec!["Band".to_string()]));
+        assert!(!metadata.contains_key("xesam:album"));
+
+        // The track id only changes with the track
+        let mut later = now_playing();
+        later.position = Duration::from_secs(90);
+        assert_eq!(track_id(&now_playing()), track_id(&later));
+        later.title = Some("Other".to_string());
+        assert_ne!(track_id(&now_playing()), track_id(&later));
+    }
+
+    #[test]
+    fn test_status_mapping() {
+        assert_eq!(playback_status(Some(&PlayState::Loading)), "Playing");
+        assert_eq!(playback_status(None), "Stopped");
+        for status in ["None", "Track", "Playlist"] {
+            let mode = LoopMode {
+                repeat: repeat_mode(status).unwrap(),
+                shuffle: false,
+            };
+            assert_eq!(loop_status(mode), status);
+        }
+        assert!(repeat_mode("Forever").is_err());
+    }
+}
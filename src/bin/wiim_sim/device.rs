@@ -0,0 +1,405 @@
+//! The simulated device: playback state and the LinkPlay commands that act on it
+
+use serde_json::{json, Value};
+use std::time::{Duration, Instant};
+use wiim_api::fixtures;
+
+/// Which captured `getStatusEx` the device reports, and so which inputs it has
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Model {
+    Mini,
+    Amp,
+    Arylic,
+}
+
+impl Model {
+    fn status_ex(&self) -> &'static str {
+        match self {
+            Model::Mini => fixtures::status_ex::WIIM_MINI,
+            Model::Amp => fixtures::status_ex::WIIM_AMP,
+            Model::Arylic => fixtures::status_ex::ARYLIC_UP2STREAM,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Track {
+    title: String,
+    artist: String,
+    album: String,
+    /// Zero for streams without an end
+    duration_ms: u64,
+}
+
+impl Track {
+    fn new(title: &str, artist: &str, album: &str, duration_ms: u64) -> Self {
+        Track {
+            title: title.to_string(),
+            artist: artist.to_string(),
+            album: album.to_string(),
+            duration_ms,
+        }
+    }
+}
+
+fn demo_queue() -> Vec<Track> {
+    vec![
+        Track::new("Harbour Lights", "The Night Ferries", "Low Tide", 214_000),
+        Track::new("Paper Satellites", "The Night Ferries", "Low Tide", 187_000),
+        Track::new("Glasshouse", "Mira Okafor", "Weather Systems", 251_000),
+        Track::new("Slow Orbit", "Mira Okafor", "Weather Systems", 302_000),
+    ]
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Play,
+    Pause,
+    Stop,
+}
+
+impl Status {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Status::Play => "play",
+            Status::Pause => "pause",
+            Status::Stop => "stop",
+        }
+    }
+}
+
+/// `switchmode` names and the player status `mode` each one selects
+const INPUT_MODES: [(&str, u32); 7] = [
+    ("wifi", 10),
+    ("line-in", 40),
+    ("bluetooth", 41),
+    ("optical", 43),
+    ("co-axial", 45),
+    ("hdmi", 49),
+    ("pcusb", 51),
+];
+
+/// Network playback; every other mode is a physical input without track metadata
+const MODE_NETWORK: u32 = 10;
+
+pub struct SimDevice {
+    model: Model,
+    name: String,
+    queue: Vec<Track>,
+    index: usize,
+    status: Status,
+    position_ms: u64,
+    /// When `position_ms` was last brought up to date
+    updated: Instant,
+    volume: u8,
+    muted: bool,
+    loop_code: u8,
+    eq: u8,
+    mode: u32,
+    shutdown_at: Option<Instant>,
+}
+
+impl SimDevice {
+    pub fn new(model: Model, name: &str, now: Instant) -> Self {
+        SimDevice {
+            model,
+            name: name.to_string(),
+            queue: demo_queue(),
+            index: 0,
+            status: Status::Play,
+            position_ms: 0,
+            updated: now,
+            volume: 30,
+            muted: false,
+            loop_code: 4,
+            eq: 0,
+            mode: MODE_NETWORK,
+            shutdown_at: None,
+        }
+    }
+
+    /// Move the position on by the time spent playing, going to the next track at the end
+    fn tick(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_millis() as u64;
+        self.updated = now;
+        if self.status != Status::Play || self.mode != MODE_NETWORK {
+            return;
+        }
+        self.position_ms += elapsed;
+        while self.track().duration_ms > 0 && self.position_ms >= self.track().duration_ms {
+            self.position_ms -= self.track().duration_ms;
+            self.index = (self.index + 1) % self.queue.len();
+        }
+    }
+
+    fn track(&self) -> &Track {
+        &self.queue[self.index]
+    }
+
+    fn skip(&mut self, forward: bool) {
+        let len = self.queue.len();
+        self.index = if forward {
+            (self.index + 1) % len
+        } else {
+            (self.index + len - 1) % len
+        };
+        self.position_ms = 0;
+        self.status = Status::Play;
+    }
+
+    /// Answer a command the way a LinkPlay device does
+    pub fn handle(&mut self, command: &str, now: Instant) -> String {
+        self.tick(now);
+        let (name, argument) = match command.strip_prefix("setPlayerCmd:") {
+            Some(player) => match player.split_once(':') {
+                Some((action, argument)) => (format!("setPlayerCmd:{action}"), argument),
+                None => (command.to_string(), ""),
+            },
+            None => match command.split_once(':') {
+                Some((name, argument)) => (name.to_string(), argument),
+                None => (command.to_string(), ""),
+            },
+        };
+        let ok = |done: bool| if done { "OK" } else { "Failed" }.to_string();
+
+        match name.as_str() {
+            "getPlayerStatus" => self.player_status().to_string(),
+            "getMetaInfo" => self.meta_info().to_string(),
+            "getStatusEx" => self.status_ex().to_string(),
+            "multiroom" if argument == "getSlaveList" => {
+                json!({ "slaves": 0, "slave_list": [] }).to_string()
+            }
+            "setPlayerCmd:pause" => {
+                self.status = Status::Pause;
+                ok(true)
+            }
+            "setPlayerCmd:resume" => {
+                self.status = Status::Play;
+                ok(true)
+            }
+            "setPlayerCmd:onepause" => {
+                self.status = match self.status {
+                    Status::Play => Status::Pause,
+                    Status::Pause | Status::Stop => Status::Play,
+                };
+                ok(true)
+            }
+            "setPlayerCmd:stop" => {
+                self.status = Status::Stop;
+                self.position_ms = 0;
+                ok(true)
+            }
+            "setPlayerCmd:next" => {
+                self.skip(true);
+                ok(true)
+            }
+            "setPlayerCmd:prev" => {
+                self.skip(false);
+                ok(true)
+            }
+            "setPlayerCmd:vol" => match argument.parse::<u8>() {
+                Ok(volume) if volume <= 100 => {
+                    self.volume = volume;
+                    ok(true)
+                }
+                _ => ok(false),
+            },
+            "setPlayerCmd:mute" => match argument {
+                "0" | "1" => {
+                    self.muted = argument == "1";
+                    ok(true)
+                }
+                _ => ok(false),
+            },
+            "setPlayerCmd:loopmode" => match argument.parse() {
+                Ok(code) => {
+                    self.loop_code = code;
+                    ok(true)
+                }
+                Err(_) => ok(false),
+            },
+            "setPlayerCmd:equalizer" => match argument.parse() {
+                Ok(eq) => {
+                    self.eq = eq;
+                    ok(true)
+                }
+                Err(_) => ok(false),
+            },
+            "setPlayerCmd:seek" => match argument.parse::<u64>() {
+                Ok(seconds) => {
+                    self.position_ms = (seconds * 1000).min(self.track().duration_ms);
+                    ok(true)
+                }
+                Err(_) => ok(false),
+            },
+            "setPlayerCmd:playindex" => match argument.parse::<usize>() {
+                Ok(index) if (1..=self.queue.len()).contains(&index) => {
+                    self.index = index - 1;
+                    self.position_ms = 0;
+                    self.status = Status::Play;
+                    ok(true)
+                }
+                _ => ok(false),
+            },
+            "setPlayerCmd:play" => {
+                let title = argument.rsplit('/').next().unwrap_or(argument);
+                self.queue = vec![Track::new(title, "", "", 0)];
+                self.index = 0;
+                self.position_ms = 0;
+                self.status = Status::Play;
+                self.mode = MODE_NETWORK;
+                ok(true)
+            }
+            "setPlayerCmd:switchmode" => {
+                let mode = INPUT_MODES
+                    .iter()
+                    .find(|(input, _)| input.eq_ignore_ascii_case(argument))
+                    .map(|(_, mode)| *mode);
+                match mode {
+                    Some(mode) => {
+                        self.mode = mode;
+                        self.status = Status::Play;
+                        ok(true)
+                    }
+                    None => ok(false),
+                }
+            }
+            "setDeviceName" => {
+                if !argument.is_empty() {
+                    self.name = argument.to_string();
+                }
+                ok(!argument.is_empty())
+            }
+            "getShutdown" => self
+                .shutdown_at
+                .map(|at| at.saturating_duration_since(now).as_secs() as i64)
+                .unwrap_or(0)
+                .to_string(),
+            "setShutdown" => match argument.parse::<i64>() {
+                Ok(seconds) if seconds > 0 => {
+                    self.shutdown_at = Some(now + Duration::from_secs(seconds as u64));
+                    ok(true)
+                }
+                Ok(_) => {
+                    self.shutdown_at = None;
+                    ok(true)
+                }
+                Err(_) => ok(false),
+            },
+            "reboot" => {
+                *self = SimDevice::new(self.model, &self.name, now);
+                ok(true)
+            }
+            _ => "unknown command".to_string(),
+        }
+    }
+
+    fn player_status(&self) -> Value {
+        let network = self.mode == MODE_NETWORK;
+        let (position, duration) = if network {
+            (self.position_ms, self.track().duration_ms)
+        } else {
+            (0, 0)
+        };
+        json!({
+            "type": "0",
+            "ch": "0",
+            "mode": self.mode.to_string(),
+            "loop": self.loop_code.to_string(),
+            "eq": self.eq.to_string(),
+            "status": self.status.as_str(),
+            "curpos": position.to_string(),
+            "offset_pts": position.to_string(),
+            "totlen": duration.to_string(),
+            "alarmflag": "0",
+            "plicount": if network { self.queue.len() } else { 0 }.to_string(),
+            "plicurr": if network { self.index + 1 } else { 0 }.to_string(),
+            "vol": self.volume.to_string(),
+            "mute": if self.muted { "1" } else { "0" },
+        })
+    }
+
+    fn meta_info(&self) -> Value {
+        let track = self.track();
+        if self.mode != MODE_NETWORK {
+            return serde_json::from_str(fixtures::meta_info::EMPTY)
+                .expect("bundled fixtures are valid JSON");
+        }
+        json!({
+            "metaData": {
+                "album": track.album,
+                "title": track.title,
+                "subtitle": "",
+                "artist": track.artist,
+                "sampleRate": "44100",
+                "bitDepth": "16",
+                "bitRate": "1411",
+                "trackId": format!("sim-{}", self.index + 1),
+            }
+        })
+    }
+
+    fn status_ex(&self) -> Value {
+        let mut status: Value =
+            serde_json::from_str(self.model.status_ex()).expect("bundled fixtures are valid JSON");
+        status["DeviceName"] = json!(self.name);
+        status["GroupName"] = json!(self.name);
+        status["ssid"] = json!(self.name);
+        status
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(response: &str, key: &str) -> String {
+        let value: Value = serde_json::from_str(response).unwrap();
+        value[key].as_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_transport_and_volume() {
+        let start = Instant::now();
+        let mut device = SimDevice::new(Model::Mini, "Sim", start);
+        assert_eq!(device.handle("setPlayerCmd:vol:55", start), "OK");
+        assert_eq!(device.handle("setPlayerCmd:vol:150", start), "Failed");
+        assert_eq!(device.handle("setPlayerCmd:pause", start), "OK");
+        let status = device.handle("getPlayerStatus", start + Duration::from_secs(5));
+        assert_eq!(field(&status, "vol"), "55");
+        assert_eq!(field(&status, "status"), "pause");
+        assert_eq!(field(&status, "curpos"), "0");
+        assert_eq!(device.handle("getLineInGain", start), "unknown command");
+        assert_eq!(
+            device.handle("multiroom:getSlaveList", start),
+            r#"{"slave_list":[],"slaves":0}"#
+        );
+    }
+
+    #[test]
+    fn test_position_advances_to_next_track() {
+        let start = Instant::now();
+        let mut device = SimDevice::new(Model::Mini, "Sim", start);
+        let status = device.handle("getPlayerStatus", start + Duration::from_secs(220));
+        assert_eq!(field(&status, "plicurr"), "2");
+        assert_eq!(field(&status, "curpos"), "6000");
+        let meta: Value = serde_json::from_str(&device.handle("getMetaInfo", start)).unwrap();
+        assert_eq!(meta["metaData"]["title"], "Paper Satellites");
+    }
+
+    #[test]
+    fn test_switch_input() {
+        let start = Instant::now();
+        let mut device = SimDevice::new(Model::Amp, "Sim", start);
+        assert_eq!(device.handle("setPlayerCmd:switchmode:HDMI", start), "OK");
+        let status = device.handle("getPlayerStatus", start);
+        assert_eq!(field(&status, "mode"), "49");
+        assert_eq!(field(&status, "totlen"), "0");
+        assert_eq!(
+            device.handle("setPlayerCmd:switchmode:vinyl", start),
+            "Failed"
+        );
+        let status_ex = device.handle("getStatusEx", start);
+        assert_eq!(field(&status_ex, "DeviceName"), "Sim");
+    }
+}
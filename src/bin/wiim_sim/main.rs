@@ -0,0 +1,128 @@
+//! `wiim-sim`: a pretend LinkPlay device for developing and demoing without hardware
+//!
+//! Serves `/httpapi.asp?command=...` over plain HTTP with a small queue of demo tracks that
+//! play in real time. Volume, mute, transport, seeking, loop mode, inputs, the device name
+//! and the sleep timer keep state; commands it doesn't simulate answer "unknown command",
+//! as older firmware does.
+
+mod device;
+
+use axum::extract::{RawQuery, State};
+use axum::routing::get;
+use axum::Router;
+use clap::Parser;
+use device::{Model, SimDevice};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+#[derive(Parser)]
+#[command(name = "wiim-sim", version)]
+#[command(about = "Simulate a WiiM device over HTTP for offline development")]
+struct Args {
+    /// Address to listen on
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    listen: SocketAddr,
+
+    /// Device model, which decides the reported inputs and capabilities
+    #[arg(long, value_enum, default_value = "mini")]
+    model: Model,
+
+    /// Device name reported in getStatusEx
+    #[arg(long, default_value = "WiiM Sim")]
+    name: String,
+
+    /// Print every command as it arrives
+    #[arg(short, long)]
+    verbose: bool,
+}
+
+struct Sim {
+    device: Mutex<SimDevice>,
+    verbose: bool,
+}
+
+/// Decode `%XX` escapes; anything malformed is kept as it is
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| text.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// The `command` parameter of a query string
+fn command(query: &str) -> Option<String> {
+    query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("command="))
+        .map(percent_decode)
+}
+
+async fn httpapi(State(sim): State<Arc<Sim>>, RawQuery(query): RawQuery) -> String {
+    let Some(command) = query.as_deref().and_then(command) else {
+        return "unknown command".to_string();
+    };
+    let response = sim
+        .device
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .handle(&command, Instant::now());
+    if sim.verbose {
+        eprintln!("{command} → {response}");
+    }
+    response
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+    let sim = Arc::new(Sim {
+        device: Mutex::new(SimDevice::new(args.model, &args.name, Instant::now())),
+        verbose: args.verbose,
+    });
+    let app = Router::new()
+        .route("/httpapi.asp", get(httpapi))
+        .with_state(sim);
+
+    let listener = tokio::net::TcpListener::bind(args.listen).await?;
+    let address = listener.local_addr()?;
+    eprintln!("🎵 Simulating \"{}\" at http://{address}", args.name);
+    eprintln!("   Try: wiim-control --device http://{address} status");
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_from_query() {
+        assert_eq!(
+            command("command=setDeviceName:Living%20Room").as_deref(),
+            Some("setDeviceName:Living Room")
+        );
+        assert_eq!(
+            command("x=1&command=setPlayerCmd:vol:30").as_deref(),
+            Some("setPlayerCmd:vol:30")
+        );
+        assert_eq!(command("x=1"), None);
+        assert_eq!(percent_decode("100%"), "100%");
+    }
+}
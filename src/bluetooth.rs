@@ -0,0 +1,209 @@
+//! Sending audio to Bluetooth headphones and speakers
+//!
+//! `startbtdiscovery:seconds` starts a scan whose findings `getbtdiscoveryresult` reports
+//! once `scan_status` reaches 4. Devices paired before are listed by `getbthistory`, in the
+//! same format. `connectbta2dpsynk:mac` connects to a device, pairing with it first if
+//! needed, and `getbtpairstatus` tells whether that worked; `disconnectbta2dpsynk:mac`
+//! drops the connection but keeps the pairing.
+
+use crate::compat::string_or_number;
+use crate::rt::Instant;
+use crate::{Result, WiimClient, WiimError};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// How often scan and pairing progress is polled
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// `scan_status` once the scan has finished
+const SCAN_DONE: &str = "4";
+/// Extra time allowed on top of the requested scan duration
+const SCAN_GRACE: Duration = Duration::from_secs(5);
+/// Headphones usually need a few seconds to accept a pairing
+const PAIR_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// A Bluetooth device seen in a scan or paired before
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BluetoothDevice {
+    pub name: String,
+    /// MAC address, e.g. "38:18:4c:aa:bb:cc"
+    pub address: String,
+    pub connected: bool,
+    /// As reported, e.g. "Audio Sink" for headphones and speakers
+    pub role: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawBluetoothDevice {
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    ad: String,
+    #[serde(default, deserialize_with = "string_or_number")]
+    ct: String,
+    #[serde(default)]
+    role: String,
+}
+
+impl From<RawBluetoothDevice> for BluetoothDevice {
+    fn from(raw: RawBluetoothDevice) -> Self {
+        BluetoothDevice {
+            name: raw.name,
+            address: raw.ad.to_ascii_lowercase(),
+            connected: raw.ct == "1",
+            role: Some(raw.role).filter(|role| !role.is_empty()),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct BluetoothList {
+    #[serde(default, deserialize_with = "string_or_number")]
+    scan_status: String,
+    #[serde(default)]
+    list: Vec<RawBluetoothDevice>,
+}
+
+impl BluetoothList {
+    fn devices(self) -> Vec<BluetoothDevice> {
+        self.list.into_iter().map(BluetoothDevice::from).collect()
+    }
+}
+
+/// Check a MAC address and bring it into the lowercase form the device reports
+fn parse_address(address: &str) -> Result<String> {
+    let address = address.trim().to_ascii_lowercase();
+    let parts: Vec<&str> = address.split(':').collect();
+    let valid = parts.len() == 6
+        && parts
+            .iter()
+            .all(|part| part.len() == 2 && part.chars().all(|c| c.is_ascii_hexdigit()));
+    if valid {
+        Ok(address)
+    } else {
+        Err(WiimError::InvalidResponse(format!(
+            "Invalid Bluetooth address: {address}"
+        )))
+    }
+}
+
+impl WiimClient {
+    /// Look for Bluetooth devices for `duration` (at least a second)
+    ///
+    /// Headphones only show up while in pairing mode.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use wiim_api::WiimClient;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> wiim_api::Result<()> {
+    ///     let client = WiimClient::new("192.168.1.100");
+    ///     for device in client.scan_bluetooth(Duration::from_secs(5)).await? {
+    ///         println!("{} {}", device.address, device.name);
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn scan_bluetooth(&self, duration: Duration) -> Result<Vec<BluetoothDevice>> {
+        let seconds = duration.as_secs().max(1);
+        self.send_command(&format!("startbtdiscovery:{seconds}"))
+            .await?;
+        let deadline = Instant::now() + Duration::from_secs(seconds) + SCAN_GRACE;
+        loop {
+            crate::rt::sleep(POLL_INTERVAL).await;
+            let response = self.send_command("getbtdiscoveryresult").await?;
+            let list: BluetoothList = serde_json::from_str(&response)?;
+            // Whatever was found counts once the scan should long be over
+            if list.scan_status == SCAN_DONE || Instant::now() > deadline {
+                return Ok(list.devices());
+            }
+        }
+    }
+
+    /// List the Bluetooth devices paired before, including the connected one
+    pub async fn list_bluetooth_paired(&self) -> Result<Vec<BluetoothDevice>> {
+        let response = self.send_command("getbthistory").await?;
+        let list: BluetoothList = serde_json::from_str(&response)?;
+        Ok(list.devices())
+    }
+
+    /// Pair with a device found by [`scan_bluetooth`](Self::scan_bluetooth) and connect to it
+    ///
+    /// # Errors
+    /// Returns `WiimError::InvalidResponse` if the address is malformed and
+    /// `WiimError::Timeout` if the device hasn't accepted the pairing after 20 seconds
+    pub async fn pair_bluetooth(&self, address: &str) -> Result<()> {
+        let address = parse_address(address)?;
+        self.send_command(&format!("connectbta2dpsynk:{address}"))
+            .await?;
+        let deadline = Instant::now() + PAIR_TIMEOUT;
+        loop {
+            crate::rt::sleep(POLL_INTERVAL).await;
+            let response = self.send_command("getbtpairstatus").await?;
+            let status: serde_json::Value = serde_json::from_str(&response)?;
+            if crate::compat::json_number(&status["result"]) == Some(1) {
+                return Ok(());
+            }
+            if Instant::now() > deadline {
+                return Err(WiimError::Timeout(PAIR_TIMEOUT));
+            }
+        }
+    }
+
+    /// Connect to a paired device
+    ///
+    /// # Errors
+    /// Returns `WiimError::InvalidResponse` if the address is malformed
+    pub async fn connect_bluetooth(&self, address: &str) -> Result<()> {
+        let address = parse_address(address)?;
+        self.send_command(&format!("connectbta2dpsynk:{address}"))
+            .await?;
+        Ok(())
+    }
+
+    /// Disconnect from a device, keeping it paired
+    ///
+    /// # Errors
+    /// Returns `WiimError::InvalidResponse` if the address is malformed
+    pub async fn disconnect_bluetooth(&self, address: &str) -> Result<()> {
+        let address = parse_address(address)?;
+        self.send_command(&format!("disconnectbta2dpsynk:{address}"))
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bluetooth_list_parsing() {
+        let list: BluetoothList = serde_json::from_str(
+            r#"{"num":2,"scan_status":4,"list":[{"name":"WH-1000XM4","ad":"38:18:4C:AA:BB:CC","ct":1,"role":"Audio Sink"},{"name":"Kitchen","ad":"00:11:22:33:44:55","ct":"0","role":""}]}"#,
+        )
+        .unwrap();
+        assert_eq!(list.scan_status, SCAN_DONE);
+        let devices = list.devices();
+        assert_eq!(devices[0].address, "38:18:4c:aa:bb:cc");
+        assert!(devices[0].connected);
+        assert_eq!(devices[0].role.as_deref(), Some("Audio Sink"));
+        assert!(!devices[1].connected);
+        assert_eq!(devices[1].role, None);
+
+        let list: BluetoothList = serde_json::from_str(r#"{"num":0,"scan_status":"3"}"#).unwrap();
+        assert!(list.devices().is_empty());
+    }
+
+    #[test]
+    fn test_parse_address() {
+        assert_eq!(
+            parse_address(" 38:18:4C:AA:BB:CC").unwrap(),
+            "38:18:4c:aa:bb:cc"
+        );
+        assert!(parse_address("38:18:4c:aa:bb").is_err());
+        assert!(parse_address("38-18-4c-aa-bb-cc").is_err());
+        assert!(parse_address("zz:18:4c:aa:bb:cc").is_err());
+    }
+}
@@ -0,0 +1,146 @@
+//! Remembering a place in a track to come back to later
+//!
+//! Audiobooks and podcasts restart from the beginning when the device switches away from
+//! the source and back. A bookmark records the track and position so the position can be
+//! restored once the same track is playing again.
+
+use crate::rt::Instant;
+use crate::watch::{next_delay, FIRST_POLL_DELAY};
+use crate::{MetaData, PlayState, Result, WiimClient, WiimError};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// A track and a position within it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Bookmark {
+    /// The source's id for the track, when it reports one
+    pub track_id: Option<String>,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub position_ms: u64,
+}
+
+impl Bookmark {
+    fn from_meta(meta: MetaData, position_ms: u64) -> Self {
+        Bookmark {
+            track_id: meta.track_id.filter(|id| !id.is_empty()),
+            title: meta.title,
+            artist: meta.artist,
+            album: meta.album,
+            position_ms,
+        }
+    }
+
+    /// Whether `meta` describes the bookmarked track
+    ///
+    /// Track ids are compared when both sides have one, since titles repeat across
+    /// podcast episodes; otherwise title, artist and album must all match.
+    fn is_track(&self, meta: &MetaData) -> bool {
+        match (
+            &self.track_id,
+            meta.track_id.as_ref().filter(|id| !id.is_empty()),
+        ) {
+            (Some(id), Some(current)) => id == current,
+            _ => {
+                self.title.is_some()
+                    && self.title == meta.title
+                    && self.artist == meta.artist
+                    && self.album == meta.album
+            }
+        }
+    }
+}
+
+impl WiimClient {
+    /// Record the current track and position
+    ///
+    /// # Errors
+    /// Returns `WiimError::InvalidResponse` if the device reports no current track
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use wiim_api::WiimClient;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> wiim_api::Result<()> {
+    ///     let client = WiimClient::new("192.168.1.100");
+    ///     let bookmark = client.save_bookmark().await?;
+    ///
+    ///     // ... the source changes, and later the audiobook is started again ...
+    ///     client
+    ///         .restore_bookmark(&bookmark, Duration::from_secs(30))
+    ///         .await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn save_bookmark(&self) -> Result<Bookmark> {
+        let (status, meta) = self.get_status_and_meta().await?;
+        if meta.title.is_none() && meta.track_id.as_deref().is_none_or(str::is_empty) {
+            return Err(WiimError::InvalidResponse(
+                "Nothing to bookmark: the device reports no current track".to_string(),
+            ));
+        }
+        let position_ms = Self::parse_position(&status.curpos)?;
+        Ok(Bookmark::from_meta(meta, position_ms))
+    }
+
+    /// Wait until the bookmarked track is playing, then seek to the bookmarked position
+    ///
+    /// Failed requests while waiting are retried, since the source may still be starting.
+    ///
+    /// # Errors
+    /// Returns `WiimError::Timeout` if the track isn't playing within `timeout`
+    pub async fn restore_bookmark(&self, bookmark: &Bookmark, timeout: Duration) -> Result<()> {
+        let deadline = Instant::now() + timeout;
+        let mut delay = FIRST_POLL_DELAY;
+
+        loop {
+            if let Ok((status, meta)) = self.get_status_and_meta().await {
+                let playing = Self::parse_play_state(&status.status) == PlayState::Playing;
+                if playing && bookmark.is_track(&meta) {
+                    return self.seek(Duration::from_millis(bookmark.position_ms)).await;
+                }
+            }
+            if Instant::now() + delay > deadline {
+                return Err(WiimError::Timeout(timeout));
+            }
+            crate::rt::sleep(delay).await;
+            delay = next_delay(delay);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn meta(track_id: Option<&str>, title: &str) -> MetaData {
+        serde_json::from_value(serde_json::json!({
+            "trackId": track_id,
+            "title": title,
+            "artist": "Author",
+            "album": "Book",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_bookmark_matches_track_id_first() {
+        let bookmark = Bookmark::from_meta(meta(Some("ep-12"), "Chapter 1"), 754_000);
+        assert!(bookmark.is_track(&meta(Some("ep-12"), "Chapter 1")));
+        // Same title, different episode
+        assert!(!bookmark.is_track(&meta(Some("ep-13"), "Chapter 1")));
+        // The id can go missing after a source switch
+        assert!(bookmark.is_track(&meta(None, "Chapter 1")));
+    }
+
+    #[test]
+    fn test_bookmark_matches_title_without_track_id() {
+        let bookmark = Bookmark::from_meta(meta(Some(""), "Chapter 1"), 0);
+        assert_eq!(bookmark.track_id, None);
+        assert!(bookmark.is_track(&meta(None, "Chapter 1")));
+        assert!(!bookmark.is_track(&meta(None, "Chapter 2")));
+    }
+}
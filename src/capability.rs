@@ -0,0 +1,126 @@
+//! What a particular model and firmware can do
+//!
+//! Devices answer commands they don't implement with free text such as "unknown command",
+//! or silently ignore them. Model-specific methods check [`Capabilities`] first and fail
+//! with [`WiimError::Unsupported`] instead.
+
+use crate::{Input, Result, StatusEx, WiimClient, WiimError};
+use std::fmt;
+
+/// A model- or firmware-specific feature
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Feature {
+    Input(Input),
+    SubwooferOutput,
+    /// Delaying TV input audio to match the picture
+    LipSync,
+}
+
+impl fmt::Display for Feature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Feature::Input(input) => write!(f, "the {input} input"),
+            Feature::SubwooferOutput => write!(f, "a subwoofer output"),
+            Feature::LipSync => write!(f, "lip-sync adjustment"),
+        }
+    }
+}
+
+/// Features decoded from `getStatusEx`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Capabilities {
+    /// The name errors refer to the device by
+    pub device: String,
+    pub inputs: Vec<Input>,
+    pub sub_out: bool,
+}
+
+impl Capabilities {
+    pub fn supports(&self, feature: Feature) -> bool {
+        match feature {
+            Feature::Input(input) => self.inputs.contains(&input),
+            Feature::SubwooferOutput => self.sub_out,
+            Feature::LipSync => self
+                .inputs
+                .iter()
+                .any(|input| matches!(input, Input::Hdmi | Input::Optical)),
+        }
+    }
+
+    /// Fail with [`WiimError::Unsupported`] unless the device has the feature
+    pub fn ensure(&self, feature: Feature) -> Result<()> {
+        if self.supports(feature) {
+            Ok(())
+        } else {
+            Err(WiimError::Unsupported {
+                feature: feature.to_string(),
+                device: self.device.clone(),
+            })
+        }
+    }
+}
+
+impl From<&StatusEx> for Capabilities {
+    fn from(status: &StatusEx) -> Self {
+        let device = status
+            .device_name
+            .clone()
+            .or_else(|| status.project.clone())
+            .unwrap_or_else(|| "The device".to_string());
+        Capabilities {
+            device,
+            inputs: status.inputs(),
+            sub_out: status.has_sub_out(),
+        }
+    }
+}
+
+impl WiimClient {
+    /// Find out which model-specific features the device has
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use wiim_api::{Feature, Input, WiimClient};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> wiim_api::Result<()> {
+    ///     let client = WiimClient::new("192.168.1.100");
+    ///     let capabilities = client.get_capabilities().await?;
+    ///     if capabilities.supports(Feature::Input(Input::Hdmi)) {
+    ///         println!("{} can play from the TV", capabilities.device);
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn get_capabilities(&self) -> Result<Capabilities> {
+        Ok(Capabilities::from(&self.get_status_ex().await?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ensure_reports_unsupported_feature() {
+        let status: StatusEx = serde_json::from_value(serde_json::json!({
+            "DeviceName": "WiiM Mini-8FA2",
+            "project": "Muzo_Mini",
+            "plm_support": "0x300006",
+        }))
+        .unwrap();
+        let capabilities = Capabilities::from(&status);
+        assert!(capabilities.ensure(Feature::Input(Input::LineIn)).is_ok());
+
+        let error = capabilities
+            .ensure(Feature::Input(Input::Hdmi))
+            .unwrap_err();
+        assert!(matches!(error, WiimError::Unsupported { .. }));
+        assert_eq!(
+            error.to_string(),
+            "WiiM Mini-8FA2 does not support the hdmi input"
+        );
+        assert!(!capabilities.supports(Feature::SubwooferOutput));
+        assert!(!capabilities.supports(Feature::LipSync));
+    }
+}
@@ -0,0 +1,183 @@
+//! Recording device responses and replaying them in place of the device (`cassette` feature)
+//!
+//! A client built with a recording cassette talks to the device as usual and writes every
+//! command and response to a JSON file. A client built with a replaying cassette never
+//! touches the network: each command gets the next response recorded for it, so a bug seen
+//! on one firmware can be reproduced in CI from the file alone.
+//!
+//! Recordings contain the commands verbatim, including Wi-Fi passphrases sent while
+//! provisioning; review them before sharing.
+
+use crate::{Result, WiimError};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// One command and the response body the device sent back
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Interaction {
+    pub command: String,
+    pub response: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Tape {
+    interactions: Vec<Interaction>,
+}
+
+#[derive(Debug)]
+enum Mode {
+    Record,
+    /// Which recorded interactions have been played back already
+    Replay(Vec<bool>),
+}
+
+/// A recording to write to, or to play back from
+///
+/// # Examples
+/// ```no_run
+/// use wiim_api::{Cassette, WiimClient};
+///
+/// #[tokio::main]
+/// async fn main() -> wiim_api::Result<()> {
+///     // Against the real device
+///     let client = WiimClient::builder("192.168.1.100")
+///         .cassette(Cassette::record("mini-4.6.json"))
+///         .build()?;
+///     client.get_now_playing().await?;
+///
+///     // Later, without it
+///     let client = WiimClient::builder("192.168.1.100")
+///         .cassette(Cassette::replay("mini-4.6.json")?)
+///         .build()?;
+///     let now_playing = client.get_now_playing().await?;
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Cassette {
+    path: PathBuf,
+    mode: Mutex<Mode>,
+    tape: Mutex<Tape>,
+}
+
+impl Cassette {
+    /// Record to `path`, replacing the file; it is rewritten after every response
+    pub fn record(path: impl AsRef<Path>) -> Self {
+        Cassette {
+            path: path.as_ref().to_path_buf(),
+            mode: Mutex::new(Mode::Record),
+            tape: Mutex::new(Tape::default()),
+        }
+    }
+
+    /// Play back the recording at `path`
+    ///
+    /// # Errors
+    /// Returns `WiimError::InvalidResponse` if the file can't be read, or
+    /// `WiimError::Json` if it isn't a recording
+    pub fn replay(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let content = std::fs::read_to_string(&path).map_err(|e| {
+            WiimError::InvalidResponse(format!("Cannot read cassette {}: {e}", path.display()))
+        })?;
+        let tape: Tape = serde_json::from_str(&content)?;
+        Ok(Cassette {
+            path,
+            mode: Mutex::new(Mode::Replay(vec![false; tape.interactions.len()])),
+            tape: Mutex::new(tape),
+        })
+    }
+
+    /// Everything recorded so far, or loaded for replay
+    pub fn interactions(&self) -> Vec<Interaction> {
+        self.lock_tape().interactions.clone()
+    }
+
+    pub(crate) fn is_replaying(&self) -> bool {
+        matches!(*self.lock_mode(), Mode::Replay(_))
+    }
+
+    /// The first response recorded for `command` that hasn't been replayed yet
+    pub(crate) fn play(&self, command: &str) -> Result<String> {
+        let tape = self.lock_tape();
+        let mut mode = self.lock_mode();
+        let Mode::Replay(played) = &mut *mode else {
+            unreachable!("play is only called while replaying")
+        };
+        let index = tape
+            .interactions
+            .iter()
+            .zip(played.iter())
+            .position(|(interaction, played)| !played && interaction.command == command)
+            .ok_or_else(|| {
+                WiimError::InvalidResponse(format!(
+                    "No recorded response left for {command} in {}",
+                    self.path.display()
+                ))
+            })?;
+        played[index] = true;
+        Ok(tape.interactions[index].response.clone())
+    }
+
+    /// Add a response to the recording and write the file
+    pub(crate) fn store(&self, command: &str, response: &str) -> Result<()> {
+        let mut tape = self.lock_tape();
+        tape.interactions.push(Interaction {
+            command: command.to_string(),
+            response: response.to_string(),
+        });
+        let content = serde_json::to_string_pretty(&*tape)?;
+        std::fs::write(&self.path, content).map_err(|e| {
+            WiimError::InvalidResponse(format!(
+                "Cannot write cassette {}: {e}",
+                self.path.display()
+            ))
+        })
+    }
+
+    fn lock_tape(&self) -> std::sync::MutexGuard<'_, Tape> {
+        self.tape.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn lock_mode(&self) -> std::sync::MutexGuard<'_, Mode> {
+        self.mode.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Volume, WiimClient};
+
+    #[tokio::test]
+    async fn test_record_then_replay() {
+        let path = std::env::temp_dir().join(format!("wiim-cassette-{}.json", std::process::id()));
+        let recording = Cassette::record(&path);
+        recording
+            .store(
+                "getPlayerStatus",
+                crate::fixtures::player_status::WIIM_PLAYING,
+            )
+            .unwrap();
+        recording.store("setPlayerCmd:vol:20", "OK").unwrap();
+
+        // Nothing listens on port 1, so any request that escapes the cassette fails
+        let client = WiimClient::builder("http://127.0.0.1:1")
+            .cassette(Cassette::replay(&path).unwrap())
+            .build()
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(client.get_volume().await.unwrap(), 38);
+        client.set_volume(Volume::saturating(20)).await.unwrap();
+        let error = client.get_volume().await.unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "Invalid response: No recorded response left for getPlayerStatus in {}",
+                path.display()
+            )
+        );
+    }
+}
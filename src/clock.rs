@@ -0,0 +1,106 @@
+//! The device's clock as chrono types (`time` feature)
+//!
+//! `getStatusEx` reports the clock as `date` ("2022:08:09") and `time` ("07:13:16") in
+//! UTC, the same way alarms are stored, with the local offset in hours in `tz` ("-5.0").
+//! A device whose clock drifts, or that never got the time from the network, fires alarms
+//! at the wrong moment, which [`WiimClient::clock_drift`] makes easy to spot.
+
+use crate::alarm::parse_tz_offset;
+use crate::{Result, StatusEx, WiimClient, WiimError};
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveTime, TimeDelta, Utc};
+
+impl StatusEx {
+    /// The device's clock in its local offset, `None` if `date` or `time` is missing or
+    /// malformed
+    pub fn device_time(&self) -> Option<DateTime<FixedOffset>> {
+        let date = self.date.as_deref()?.trim();
+        let date = NaiveDate::parse_from_str(date, "%Y:%m:%d")
+            .or_else(|_| NaiveDate::parse_from_str(date, "%Y-%m-%d"))
+            .ok()?;
+        let time = NaiveTime::parse_from_str(self.time.as_deref()?.trim(), "%H:%M:%S").ok()?;
+        let offset = FixedOffset::east_opt(parse_tz_offset(self.tz.as_deref()) * 60)?;
+        Some(date.and_time(time).and_utc().with_timezone(&offset))
+    }
+}
+
+impl WiimClient {
+    /// Read the device's clock
+    ///
+    /// # Errors
+    /// Returns `WiimError::InvalidResponse` if the device doesn't report its clock
+    pub async fn device_time(&self) -> Result<DateTime<FixedOffset>> {
+        self.get_status_ex()
+            .await?
+            .device_time()
+            .ok_or_else(|| WiimError::InvalidResponse("Device did not report its clock".into()))
+    }
+
+    /// How far the device's clock is ahead of this machine's (negative when behind)
+    ///
+    /// The device reports whole seconds, and half the request's round trip is allowed for,
+    /// so the result is accurate to about a second.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use wiim_api::WiimClient;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> wiim_api::Result<()> {
+    ///     let client = WiimClient::new("192.168.1.100");
+    ///     let drift = client.clock_drift().await?;
+    ///     if drift.num_seconds().abs() > 60 {
+    ///         println!("Alarms will be off by {} seconds", drift.num_seconds());
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn clock_drift(&self) -> Result<TimeDelta> {
+        let sent = Utc::now();
+        let device = self.device_time().await?;
+        let received = Utc::now();
+        Ok(drift(device, sent, received))
+    }
+}
+
+/// Device time minus host time at the middle of the request
+fn drift(device: DateTime<FixedOffset>, sent: DateTime<Utc>, received: DateTime<Utc>) -> TimeDelta {
+    let midpoint = sent + (received - sent) / 2;
+    device.with_timezone(&Utc) - midpoint
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(date: &str, time: &str, tz: &str) -> StatusEx {
+        serde_json::from_value(serde_json::json!({ "date": date, "time": time, "tz": tz })).unwrap()
+    }
+
+    #[test]
+    fn test_device_time() {
+        let time = status("2022:08:09", "07:13:16", "-5.0")
+            .device_time()
+            .unwrap();
+        assert_eq!(time.to_rfc3339(), "2022-08-09T02:13:16-05:00");
+
+        let time = status("2024-01-31", "23:40:00", "5.5")
+            .device_time()
+            .unwrap();
+        assert_eq!(time.to_rfc3339(), "2024-02-01T05:10:00+05:30");
+
+        assert!(status("", "07:13:16", "0").device_time().is_none());
+        assert!(status("2022:08:09", "25:00:00", "0")
+            .device_time()
+            .is_none());
+    }
+
+    #[test]
+    fn test_drift() {
+        let device = status("2022:08:09", "07:13:16", "-5.0")
+            .device_time()
+            .unwrap();
+        let sent = "2022-08-09T07:13:00Z".parse::<DateTime<Utc>>().unwrap();
+        let received = sent + TimeDelta::seconds(2);
+        assert_eq!(drift(device, sent, received), TimeDelta::seconds(15));
+    }
+}
@@ -0,0 +1,286 @@
+//! Other LinkPlay-based devices
+//!
+//! Arylic, Audio Pro, Dayton Audio and others run the same HTTP API as WiiM, but serve it
+//! over plain HTTP on port 80 and may lack newer endpoints such as `getMetaInfo`. Older
+//! firmware instead reports hex-encoded `Title`, `Artist` and `Album` in the player status.
+//! WiiM firmware does the same for some sources (line-in, Bluetooth, vTuner) while
+//! `getMetaInfo` comes back empty, so those fields fill in whatever the metadata lacks.
+
+use crate::{wifi, MetaData, PlayerStatus, Result, WiimClient, WiimClientBuilder};
+
+/// Which flavour of the LinkPlay API a device speaks
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DeviceFamily {
+    /// WiiM devices: HTTPS with a self-signed certificate and the full command set
+    #[default]
+    WiiM,
+    /// Other LinkPlay devices: plain HTTP, falling back to the player status for
+    /// track metadata
+    LinkPlay,
+}
+
+impl DeviceFamily {
+    pub(crate) fn default_scheme(&self) -> &'static str {
+        match self {
+            DeviceFamily::WiiM => "https",
+            DeviceFamily::LinkPlay => "http",
+        }
+    }
+}
+
+/// Build the base URL for an address, which may already carry a scheme
+pub(crate) fn base_url(address: &str, family: DeviceFamily) -> String {
+    if address.starts_with("http") {
+        address.to_string()
+    } else {
+        format!("{}://{address}", family.default_scheme())
+    }
+}
+
+/// Decode a hex-encoded player status field, keeping the text as it is when it isn't hex
+fn decode_track_field(field: &Option<String>) -> Option<String> {
+    let raw = field.as_deref().filter(|value| !value.is_empty())?;
+    let text = wifi::decode_hex_string(raw).unwrap_or_else(|| raw.to_string());
+    Some(text).filter(|text| !text.trim().is_empty())
+}
+
+impl PlayerStatus {
+    /// The track title, decoded from hex when the firmware encodes it
+    pub fn decoded_title(&self) -> Option<String> {
+        decode_track_field(&self.title)
+    }
+
+    /// The artist, decoded from hex when the firmware encodes it
+    pub fn decoded_artist(&self) -> Option<String> {
+        decode_track_field(&self.artist)
+    }
+
+    /// The album, decoded from hex when the firmware encodes it
+    pub fn decoded_album(&self) -> Option<String> {
+        decode_track_field(&self.album)
+    }
+}
+
+impl MetaData {
+    /// Metadata from the hex-encoded fields of the player status
+    pub(crate) fn from_player_status(status: &PlayerStatus) -> Self {
+        MetaData {
+            album: status.decoded_album(),
+            title: status.decoded_title(),
+            subtitle: None,
+            artist: status.decoded_artist(),
+            album_art_uri: None,
+            sample_rate: None,
+            bit_depth: None,
+            bit_rate: None,
+            track_id: None,
+        }
+    }
+
+    /// Fill empty title, artist and album from the player status
+    pub(crate) fn fill_from_player_status(&mut self, status: &PlayerStatus) {
+        let missing = |field: &Option<String>| field.as_deref().is_none_or(|v| v.trim().is_empty());
+        if missing(&self.title) {
+            self.title = status.decoded_title();
+        }
+        if missing(&self.artist) {
+            self.artist = status.decoded_artist();
+        }
+        if missing(&self.album) {
+            self.album = status.decoded_album();
+        }
+    }
+}
+
+impl WiimClientBuilder {
+    /// Create the client, working out whether the device is a WiiM or another LinkPlay
+    /// device by which of HTTPS and HTTP it answers on
+    ///
+    /// An address with an explicit scheme is only tried as given.
+    ///
+    /// # Errors
+    /// Returns the HTTPS error when the device answers on neither
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use wiim_api::{DeviceFamily, WiimClient};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> wiim_api::Result<()> {
+    ///     let client = WiimClient::builder("192.168.1.120").detect().await?;
+    ///     if client.family() == DeviceFamily::LinkPlay {
+    ///         println!("Not a WiiM, some features may be missing");
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn detect(self) -> Result<WiimClient> {
+        if self.ip_address.starts_with("http") {
+            let family = if self.ip_address.starts_with("https") {
+                DeviceFamily::WiiM
+            } else {
+                DeviceFamily::LinkPlay
+            };
+            let client = self.family(family).build()?;
+            client.test_connection().await?;
+            return Ok(client);
+        }
+
+        let wiim = self.clone().family(DeviceFamily::WiiM).build()?;
+        let https_error = match wiim.test_connection().await {
+            Ok(()) => return Ok(wiim),
+            Err(e) => e,
+        };
+        let linkplay = self.family(DeviceFamily::LinkPlay).build()?;
+        match linkplay.test_connection().await {
+            Ok(()) => Ok(linkplay),
+            Err(_) => Err(https_error),
+        }
+    }
+}
+
+/// Deserialize a string field that some firmware sends as a number or boolean instead
+///
+/// Booleans become "1" and "0", the way the API writes flags as strings.
+pub(crate) fn string_or_number<'de, D>(deserializer: D) -> std::result::Result<String, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    use serde::de::Error;
+    use serde::Deserialize;
+
+    match serde_json::Value::deserialize(deserializer)? {
+        serde_json::Value::String(text) => Ok(text),
+        serde_json::Value::Number(number) => Ok(number.to_string()),
+        serde_json::Value::Bool(flag) => Ok(if flag { "1" } else { "0" }.to_string()),
+        other => Err(D::Error::custom(format!(
+            "expected a string or number, got {other}"
+        ))),
+    }
+}
+
+/// [`string_or_number`] for optional fields, where `null` means missing
+pub(crate) fn opt_string_or_number<'de, D>(
+    deserializer: D,
+) -> std::result::Result<Option<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    use serde::Deserialize;
+
+    match Option::<serde_json::Value>::deserialize(deserializer)? {
+        None | Some(serde_json::Value::Null) => Ok(None),
+        Some(value) => string_or_number(value)
+            .map(Some)
+            .map_err(serde::de::Error::custom),
+    }
+}
+
+/// (De)serialize a `Duration` as whole milliseconds, the unit the API reports times in
+pub(crate) mod millis {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub(crate) fn serialize<S: Serializer>(
+        duration: &Duration,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_u64(duration.as_millis() as u64)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Duration, D::Error> {
+        u64::deserialize(deserializer).map(Duration::from_millis)
+    }
+}
+
+/// A JSON number, or a string holding one, as firmware versions differ in quoting numbers
+pub(crate) fn json_number(value: &serde_json::Value) -> Option<i64> {
+    match value {
+        serde_json::Value::Number(number) => number.as_i64(),
+        serde_json::Value::String(text) => text.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StatusEx;
+
+    #[test]
+    fn test_base_url() {
+        assert_eq!(
+            base_url("192.168.1.5", DeviceFamily::WiiM),
+            "https://192.168.1.5"
+        );
+        assert_eq!(
+            base_url("192.168.1.5", DeviceFamily::LinkPlay),
+            "http://192.168.1.5"
+        );
+        assert_eq!(
+            base_url("https://192.168.1.5", DeviceFamily::LinkPlay),
+            "https://192.168.1.5"
+        );
+    }
+
+    #[test]
+    fn test_numbers_where_strings_are_expected() {
+        let status: PlayerStatus = serde_json::from_str(
+            r#"{"type":0,"mode":10,"loop":"0","status":"play","curpos":61000,"totlen":180000,
+                "vol":20,"mute":false,"plicount":3,"Title":null}"#,
+        )
+        .unwrap();
+        assert_eq!(status.vol, "20");
+        assert_eq!(status.mute, "0");
+        assert_eq!(status.plicount, "3");
+        assert_eq!(status.title, None);
+
+        let status: StatusEx =
+            serde_json::from_str(r#"{"RSSI":-52,"wlanSnr":35,"internet":1,"project":"WiiM_Pro"}"#)
+                .unwrap();
+        assert_eq!(status.rssi_dbm(), Some(-52));
+        assert_eq!(status.wlan_snr.as_deref(), Some("35"));
+        assert!(status.has_internet());
+
+        let error = serde_json::from_str::<PlayerStatus>(
+            r#"{"type":"0","mode":"10","loop":"0","status":"play","curpos":"0","totlen":"0",
+                "vol":[20],"mute":"0"}"#,
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("expected a string or number"));
+    }
+
+    #[test]
+    fn test_metadata_from_player_status() {
+        let status: PlayerStatus = serde_json::from_str(
+            r#"{"type":"0","mode":"10","loop":"0","status":"play","curpos":"0","totlen":"0",
+                "vol":"20","mute":"0","Title":"536F6E67","Artist":"417274697374","Album":""}"#,
+        )
+        .unwrap();
+        let meta = MetaData::from_player_status(&status);
+        assert_eq!(meta.title.as_deref(), Some("Song"));
+        assert_eq!(meta.artist.as_deref(), Some("Artist"));
+        assert_eq!(meta.album, None);
+    }
+
+    #[test]
+    fn test_player_status_fields_fill_empty_metadata() {
+        let status: PlayerStatus = serde_json::from_str(
+            r#"{"type":"0","mode":"40","loop":"0","status":"play","curpos":"0","totlen":"0",
+                "vol":"20","mute":"0","Title":"4C696E6520496E","Artist":"Turntable","Album":"ABBA"}"#,
+        )
+        .unwrap();
+        // Not hex, and hex that isn't UTF-8, are kept as sent
+        assert_eq!(status.decoded_artist().as_deref(), Some("Turntable"));
+        assert_eq!(status.decoded_album().as_deref(), Some("ABBA"));
+
+        let mut meta: MetaData =
+            serde_json::from_str(r#"{"title":"","artist":"Known Artist"}"#).unwrap();
+        meta.fill_from_player_status(&status);
+        assert_eq!(meta.title.as_deref(), Some("Line In"));
+        assert_eq!(meta.artist.as_deref(), Some("Known Artist"));
+        assert_eq!(meta.album.as_deref(), Some("ABBA"));
+    }
+}
@@ -0,0 +1,165 @@
+//! Coalescing bursts of volume changes
+//!
+//! A volume knob or slider can produce dozens of values a second, and each
+//! `setPlayerCmd:vol` is a round trip the device handles one at a time. With
+//! [`WiimClient::set_volume_debounced`] the first value of a burst goes out at once, and
+//! after that only the latest value is sent, at most once per interval. The call that
+//! started the burst carries it to the end; calls made meanwhile return straight away.
+
+use crate::{rt, Result, Volume, WiimClient};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Default gap between volume commands from [`WiimClient::set_volume_debounced`]
+pub const DEFAULT_VOLUME_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The burst in progress, shared by a client and its clones
+#[derive(Debug, Default)]
+pub(crate) struct VolumeDebounce {
+    state: Mutex<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// The latest value not yet sent
+    pending: Option<Volume>,
+    /// Whether a caller is sending; it picks up `pending`
+    sending: bool,
+}
+
+impl VolumeDebounce {
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Record a value; true if the caller should send it, false if a burst already in
+    /// progress will
+    fn offer(&self, volume: Volume) -> bool {
+        let mut state = self.lock();
+        state.pending = Some(volume);
+        !std::mem::replace(&mut state.sending, true)
+    }
+
+    /// The value to send next, or `None` once the burst is over
+    fn next(&self) -> Option<Volume> {
+        let mut state = self.lock();
+        let next = state.pending.take();
+        state.sending = next.is_some();
+        next
+    }
+
+    /// End the burst early, after a failure or when the sending call is dropped
+    fn abandon(&self) {
+        *self.lock() = State::default();
+    }
+}
+
+/// Ends the burst if the sending call stops before it is over
+struct Burst<'a>(Option<&'a VolumeDebounce>);
+
+impl Drop for Burst<'_> {
+    fn drop(&mut self) {
+        if let Some(debounce) = self.0 {
+            debounce.abandon();
+        }
+    }
+}
+
+impl WiimClient {
+    /// Set the volume, coalescing rapid calls into one command per interval
+    ///
+    /// The first call of a burst sends its value immediately and then keeps sending the
+    /// latest value given since, waiting [`volume_interval`](crate::WiimClientBuilder::volume_interval)
+    /// between commands, until no newer value arrives; it returns when the burst is over.
+    /// Calls made during a burst return at once and their value, if still the latest, is
+    /// sent by that first call. Clones of a client share bursts.
+    ///
+    /// # Errors
+    /// The call sending the burst returns the first failure, which ends the burst
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use wiim_api::{Volume, WiimClient};
+    ///
+    /// # async fn on_knob_turned(client: &WiimClient, level: u8) -> wiim_api::Result<()> {
+    /// client.set_volume_debounced(Volume::saturating(level)).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn set_volume_debounced(&self, volume: Volume) -> Result<()> {
+        if !self.volume_debounce.offer(volume) {
+            return Ok(());
+        }
+        let mut burst = Burst(Some(&self.volume_debounce));
+        while let Some(volume) = self.volume_debounce.next() {
+            self.set_volume(volume).await?;
+            rt::sleep(self.volume_interval).await;
+        }
+        burst.0 = None;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offer_and_next() {
+        let debounce = VolumeDebounce::default();
+        assert!(debounce.offer(Volume::saturating(10)));
+        assert_eq!(debounce.next(), Some(Volume::saturating(10)));
+        for level in 11..=20 {
+            assert!(!debounce.offer(Volume::saturating(level)));
+        }
+        assert_eq!(debounce.next(), Some(Volume::saturating(20)));
+        assert_eq!(debounce.next(), None);
+        // The burst is over, so the next value is sent by its own caller
+        assert!(debounce.offer(Volume::saturating(30)));
+    }
+
+    #[test]
+    fn test_dropped_burst_is_abandoned() {
+        let debounce = VolumeDebounce::default();
+        assert!(debounce.offer(Volume::saturating(10)));
+        drop(Burst(Some(&debounce)));
+        assert!(debounce.offer(Volume::saturating(20)));
+    }
+
+    #[test]
+    fn test_new_address_gets_its_own_burst() {
+        let client = WiimClient::new("192.168.1.100");
+        let mut moved = client.clone();
+        assert!(std::sync::Arc::ptr_eq(
+            &client.volume_debounce,
+            &moved.volume_debounce
+        ));
+
+        assert!(client.volume_debounce.offer(Volume::saturating(10)));
+        moved.set_ip_address("192.168.1.101");
+        assert!(moved.volume_debounce.offer(Volume::saturating(20)));
+    }
+
+    #[cfg(feature = "cassette")]
+    #[tokio::test]
+    async fn test_burst_sends_first_and_latest() {
+        use crate::Cassette;
+
+        let path = std::env::temp_dir().join(format!("wiim-debounce-{}.json", std::process::id()));
+        let recording = Cassette::record(&path);
+        recording.store("setPlayerCmd:vol:10", "OK").unwrap();
+        recording.store("setPlayerCmd:vol:20", "OK").unwrap();
+        // Any other volume command would find no recorded response and fail
+        let client = WiimClient::builder("http://127.0.0.1:1")
+            .cassette(Cassette::replay(&path).unwrap())
+            .volume_interval(Duration::from_millis(20))
+            .build()
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let calls = (10..=20).map(|level| client.set_volume_debounced(Volume::saturating(level)));
+        for result in futures_util::future::join_all(calls).await {
+            result.unwrap();
+        }
+    }
+}
@@ -0,0 +1,147 @@
+//! Kotlin and Swift bindings through uniffi
+//!
+//! Exposes a [`WiimDevice`] object wrapping [`WiimClient`] with the calls a companion app
+//! needs: now playing, transport and volume. Async methods run on tokio, so the app can
+//! call them from coroutines or Swift concurrency.
+
+use crate::{
+    DeviceErrorKind, NowPlaying, PlayState, PlaybackSource, Volume, WiimClient, WiimError,
+};
+use std::sync::Arc;
+
+/// Errors as seen from Kotlin and Swift
+#[derive(Debug, thiserror::Error, uniffi::Error)]
+pub enum DeviceError {
+    /// The device did not answer
+    #[error("{message}")]
+    Unreachable { message: String },
+    /// The device answered with an error or unexpected data
+    #[error("{message}")]
+    Rejected { message: String },
+    /// The device's model or firmware lacks the feature
+    #[error("{message}")]
+    Unsupported { message: String },
+    /// The device is busy, e.g. forming a group; try again shortly
+    #[error("{message}")]
+    Busy { message: String },
+}
+
+impl From<WiimError> for DeviceError {
+    fn from(error: WiimError) -> Self {
+        let message = error.to_string();
+        match error {
+            WiimError::Request(_)
+            | WiimError::Timeout(_)
+            | WiimError::CertificateMismatch { .. } => DeviceError::Unreachable { message },
+            WiimError::Json(_) | WiimError::InvalidResponse(_) => DeviceError::Rejected { message },
+            WiimError::Unsupported { .. } | WiimError::UnsupportedCommand { .. } => {
+                DeviceError::Unsupported { message }
+            }
+            WiimError::Device {
+                kind: DeviceErrorKind::Busy,
+                ..
+            } => DeviceError::Busy { message },
+            WiimError::Device { .. } => DeviceError::Rejected { message },
+        }
+    }
+}
+
+/// [`NowPlaying`] with plain types
+#[derive(Debug, Clone, uniffi::Record)]
+pub struct NowPlayingInfo {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub album_art_uri: Option<String>,
+    pub state: PlayState,
+    pub source: PlaybackSource,
+    pub volume: u8,
+    pub is_muted: bool,
+    pub position_ms: u64,
+    pub duration_ms: u64,
+}
+
+impl From<NowPlaying> for NowPlayingInfo {
+    fn from(now_playing: NowPlaying) -> Self {
+        NowPlayingInfo {
+            title: now_playing.title,
+            artist: now_playing.artist,
+            album: now_playing.album,
+            album_art_uri: now_playing.album_art_uri,
+            state: now_playing.state,
+            source: now_playing.source,
+            volume: now_playing.volume.get(),
+            is_muted: now_playing.is_muted,
+            position_ms: now_playing.position.as_millis() as u64,
+            duration_ms: now_playing.duration.as_millis() as u64,
+        }
+    }
+}
+
+/// A WiiM or other LinkPlay device
+#[derive(uniffi::Object)]
+pub struct WiimDevice {
+    client: WiimClient,
+}
+
+#[uniffi::export(async_runtime = "tokio")]
+impl WiimDevice {
+    /// A device at `address`, without checking that it answers
+    #[uniffi::constructor]
+    pub fn new(address: String) -> Arc<Self> {
+        Arc::new(WiimDevice {
+            client: WiimClient::new(&address),
+        })
+    }
+
+    /// A device at `address`, detecting whether it's a WiiM or another LinkPlay device
+    #[uniffi::constructor]
+    pub async fn connect(address: String) -> Result<Arc<Self>, DeviceError> {
+        let client = WiimClient::builder(&address).detect().await?;
+        Ok(Arc::new(WiimDevice { client }))
+    }
+
+    pub fn address(&self) -> String {
+        self.client.get_ip_address().to_string()
+    }
+
+    pub async fn now_playing(&self) -> Result<NowPlayingInfo, DeviceError> {
+        Ok(self.client.get_now_playing().await?.into())
+    }
+
+    pub async fn resume(&self) -> Result<(), DeviceError> {
+        Ok(self.client.resume().await?)
+    }
+
+    pub async fn pause(&self) -> Result<(), DeviceError> {
+        Ok(self.client.pause().await?)
+    }
+
+    pub async fn toggle_play_pause(&self) -> Result<(), DeviceError> {
+        Ok(self.client.toggle_play_pause().await?)
+    }
+
+    pub async fn stop(&self) -> Result<(), DeviceError> {
+        Ok(self.client.stop().await?)
+    }
+
+    pub async fn next_track(&self) -> Result<(), DeviceError> {
+        Ok(self.client.next_track().await?)
+    }
+
+    pub async fn previous_track(&self) -> Result<(), DeviceError> {
+        Ok(self.client.previous_track().await?)
+    }
+
+    pub async fn set_volume(&self, volume: u8) -> Result<(), DeviceError> {
+        Ok(self.client.set_volume(Volume::try_from(volume)?).await?)
+    }
+
+    pub async fn set_muted(&self, muted: bool) -> Result<(), DeviceError> {
+        if muted {
+            Ok(self.client.mute().await?)
+        } else {
+            Ok(self.client.unmute().await?)
+        }
+    }
+}
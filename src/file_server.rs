@@ -0,0 +1,557 @@
+//! Playing local files by serving them to the device over HTTP
+//!
+//! The device can only play what it can fetch, so [`FileServer`] serves a single file, or
+//! the audio files of a directory with an m3u playlist of them, on the address this
+//! machine uses to reach the device, with range requests for seeking.
+//! [`WiimClient::play_file`] and [`WiimClient::play_directory`] hand the device the URL
+//! and keep serving until playback ends, which covers local FLACs without running a DLNA
+//! server. Not available in the browser, which can't accept connections.
+
+use crate::{NowPlaying, PlayState, Result, WiimClient, WiimError};
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::{JoinHandle, JoinSet};
+
+/// Requests with longer headers are dropped
+const MAX_HEADER_BYTES: usize = 8 * 1024;
+/// How long the device may take to start playing the file
+const START_TIMEOUT: Duration = Duration::from_secs(30);
+/// How often playback is checked while the file plays
+const PLAYBACK_POLL: Duration = Duration::from_secs(2);
+/// Consecutive failed checks after which the device is considered gone
+const MAX_POLL_FAILURES: u32 = 5;
+
+/// Name of the generated playlist when serving a directory
+const PLAYLIST_NAME: &str = "playlist.m3u";
+
+/// A local file or directory served over HTTP until dropped
+///
+/// # Examples
+/// ```no_run
+/// use wiim_api::WiimClient;
+///
+/// #[tokio::main]
+/// async fn main() -> wiim_api::Result<()> {
+///     let client = WiimClient::new("192.168.1.100");
+///     let server = client.serve_file("/music/track.flac").await?;
+///     client.play_url(server.url()).await?;
+///     // ... keep `server` alive for as long as the device is playing
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct FileServer {
+    url: String,
+    tracks: usize,
+    task: JoinHandle<()>,
+}
+
+/// What a server hands out
+#[derive(Debug)]
+enum Content {
+    /// One file, whatever path is asked for
+    File(PathBuf),
+    /// Files addressed by their index, and the playlist listing them
+    Directory {
+        files: Vec<PathBuf>,
+        playlist: String,
+    },
+}
+
+impl Content {
+    /// The file or generated text a request target refers to
+    fn resolve(&self, target: &str) -> Option<Body<'_>> {
+        match self {
+            Content::File(path) => Some(Body::File(path)),
+            Content::Directory { files, playlist } => {
+                let target = target.trim_start_matches('/');
+                if target == PLAYLIST_NAME {
+                    return Some(Body::Playlist(playlist));
+                }
+                let index: usize = target.split('/').next()?.parse().ok()?;
+                files.get(index).map(|path| Body::File(path))
+            }
+        }
+    }
+}
+
+enum Body<'a> {
+    File(&'a Path),
+    Playlist(&'a str),
+}
+
+impl FileServer {
+    /// Serve `path` on the local address the device at `device` reaches this machine by
+    ///
+    /// # Errors
+    /// Returns `WiimError::InvalidResponse` if the file can't be read or no local address
+    /// can reach the device
+    pub async fn start(path: impl AsRef<Path>, device: &str) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let metadata = tokio::fs::metadata(&path).await.map_err(|e| {
+            WiimError::InvalidResponse(format!("Cannot read {}: {e}", path.display()))
+        })?;
+        if !metadata.is_file() {
+            return Err(WiimError::InvalidResponse(format!(
+                "{} is not a file",
+                path.display()
+            )));
+        }
+
+        let (listener, address) = listen_for(device).await?;
+        let url = format!("http://{address}/{}", percent_encode(&file_name(&path)));
+        let task = tokio::spawn(accept_loop(listener, Arc::new(Content::File(path))));
+        Ok(FileServer {
+            url,
+            tracks: 1,
+            task,
+        })
+    }
+
+    /// Serve the audio files in `dir` and its subdirectories, in path order, with an m3u
+    /// playlist of them
+    ///
+    /// # Errors
+    /// Returns `WiimError::InvalidResponse` if the directory can't be read or holds no
+    /// audio files, or no local address can reach the device
+    pub async fn start_directory(dir: impl AsRef<Path>, device: &str) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        let files = {
+            let dir = dir.clone();
+            tokio::task::spawn_blocking(move || audio_files(&dir))
+                .await
+                .map_err(|e| WiimError::InvalidResponse(e.to_string()))?
+        }
+        .map_err(|e| WiimError::InvalidResponse(format!("Cannot read {}: {e}", dir.display())))?;
+        if files.is_empty() {
+            return Err(WiimError::InvalidResponse(format!(
+                "No audio files in {}",
+                dir.display()
+            )));
+        }
+
+        let (listener, address) = listen_for(device).await?;
+        let playlist = playlist(&format!("http://{address}"), &files);
+        let tracks = files.len();
+        let content = Content::Directory { files, playlist };
+        let task = tokio::spawn(accept_loop(listener, Arc::new(content)));
+        Ok(FileServer {
+            url: format!("http://{address}/{PLAYLIST_NAME}"),
+            tracks,
+            task,
+        })
+    }
+
+    /// Where the device can fetch the file, or the playlist when serving a directory
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// How many files are served
+    pub fn tracks(&self) -> usize {
+        self.tracks
+    }
+}
+
+impl Drop for FileServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Listen on the local address that reaches `device`, on a free port
+async fn listen_for(device: &str) -> Result<(TcpListener, SocketAddr)> {
+    let host = device_host(device);
+    let local_ip = local_address_towards(host)
+        .await
+        .map_err(|e| WiimError::InvalidResponse(format!("No local address reaches {host}: {e}")))?;
+    let listener = TcpListener::bind(SocketAddr::new(local_ip, 0))
+        .await
+        .map_err(|e| WiimError::InvalidResponse(format!("Cannot listen on {local_ip}: {e}")))?;
+    let address = listener
+        .local_addr()
+        .map_err(|e| WiimError::InvalidResponse(e.to_string()))?;
+    Ok((listener, address))
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Audio files under `dir`, sorted by path so albums play in track order
+fn audio_files(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for entry in std::fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                pending.push(path);
+            } else if is_audio(&path) {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// An extended m3u listing every file by its URL on the server at `base`
+fn playlist(base: &str, files: &[PathBuf]) -> String {
+    let mut text = String::from("#EXTM3U\n");
+    for (index, path) in files.iter().enumerate() {
+        let title = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        text.push_str(&format!(
+            "#EXTINF:-1,{title}\n{base}/{index}/{}\n",
+            percent_encode(&file_name(path))
+        ));
+    }
+    text
+}
+
+/// The host part of a device address such as "192.168.1.100" or "https://wiim.local:443"
+fn device_host(device: &str) -> &str {
+    let authority = device
+        .split_once("://")
+        .map_or(device, |(_, rest)| rest)
+        .split('/')
+        .next()
+        .unwrap_or_default();
+    authority.split(':').next().unwrap_or(authority)
+}
+
+/// The local address the OS would send packets to `host` from
+async fn local_address_towards(host: &str) -> std::io::Result<IpAddr> {
+    let socket = tokio::net::UdpSocket::bind("0.0.0.0:0").await?;
+    // Connecting a UDP socket only picks a route; nothing is sent
+    socket.connect((host, 80)).await?;
+    Ok(socket.local_addr()?.ip())
+}
+
+fn percent_encode(text: &str) -> String {
+    text.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+fn is_audio(path: &Path) -> bool {
+    content_type(path) != "application/octet-stream"
+}
+
+fn content_type(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "flac" => "audio/flac",
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "m4a" | "aac" | "alac" => "audio/mp4",
+        "ogg" | "oga" => "audio/ogg",
+        "opus" => "audio/opus",
+        "aif" | "aiff" => "audio/aiff",
+        _ => "application/octet-stream",
+    }
+}
+
+/// The inclusive byte range a `Range` header asks for, or `None` if it can't be served
+fn parse_range(value: &str, len: u64) -> Option<(u64, u64)> {
+    let spec = value.trim().strip_prefix("bytes=")?;
+    // Only the first of several ranges is served
+    let (start, end) = spec.split(',').next()?.trim().split_once('-')?;
+    let last = len.checked_sub(1)?;
+    let (start, end) = match (start.trim(), end.trim()) {
+        ("", suffix) => {
+            let suffix: u64 = suffix.parse().ok()?;
+            (len.saturating_sub(suffix), last)
+        }
+        (start, "") => (start.parse().ok()?, last),
+        (start, end) => (start.parse().ok()?, end.parse::<u64>().ok()?.min(last)),
+    };
+    (start <= end && start <= last).then_some((start, end))
+}
+
+async fn accept_loop(listener: TcpListener, content: Arc<Content>) {
+    // Dropping the set when the server stops aborts transfers in progress
+    let mut connections = JoinSet::new();
+    while let Ok((stream, _)) = listener.accept().await {
+        while connections.try_join_next().is_some() {}
+        let content = Arc::clone(&content);
+        connections.spawn(async move {
+            let _ = serve_connection(stream, &content).await;
+        });
+    }
+}
+
+async fn serve_connection(mut stream: TcpStream, content: &Content) -> std::io::Result<()> {
+    let mut request = Vec::new();
+    let mut buffer = [0u8; 1024];
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+        let read = stream.read(&mut buffer).await?;
+        if read == 0 || request.len() > MAX_HEADER_BYTES {
+            return Ok(());
+        }
+        request.extend_from_slice(&buffer[..read]);
+    }
+    let request = String::from_utf8_lossy(&request);
+    let mut lines = request.lines();
+    let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+    let method = request_line.next().unwrap_or_default().to_string();
+    let target = request_line.next().unwrap_or_default().to_string();
+    let range = lines.find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        name.trim()
+            .eq_ignore_ascii_case("range")
+            .then(|| value.trim().to_string())
+    });
+
+    if method != "GET" && method != "HEAD" {
+        let response = "HTTP/1.1 405 Method Not Allowed\r\nAllow: GET, HEAD\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+        return stream.write_all(response.as_bytes()).await;
+    }
+    let path = match content.resolve(&target) {
+        Some(Body::File(path)) => path,
+        Some(Body::Playlist(playlist)) => {
+            let mut response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: audio/x-mpegurl\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                playlist.len()
+            );
+            if method == "GET" {
+                response.push_str(playlist);
+            }
+            return stream.write_all(response.as_bytes()).await;
+        }
+        None => {
+            let response =
+                "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+            return stream.write_all(response.as_bytes()).await;
+        }
+    };
+    let mut file = tokio::fs::File::open(path).await?;
+    let len = file.metadata().await?.len();
+    let (status, start, end) = match range {
+        None if len == 0 => ("200 OK", 0, 0),
+        None => ("200 OK", 0, len - 1),
+        Some(range) => match parse_range(&range, len) {
+            Some((start, end)) => ("206 Partial Content", start, end),
+            None => {
+                let response = format!(
+                    "HTTP/1.1 416 Range Not Satisfiable\r\nContent-Range: bytes */{len}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                );
+                return stream.write_all(response.as_bytes()).await;
+            }
+        },
+    };
+    let body_len = if len == 0 { 0 } else { end - start + 1 };
+
+    let mut head = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: {}\r\nContent-Length: {body_len}\r\nAccept-Ranges: bytes\r\nConnection: close\r\n",
+        content_type(path)
+    );
+    if status.starts_with("206") {
+        head.push_str(&format!("Content-Range: bytes {start}-{end}/{len}\r\n"));
+    }
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes()).await?;
+    if method == "GET" && body_len > 0 {
+        file.seek(std::io::SeekFrom::Start(start)).await?;
+        tokio::io::copy(&mut file.take(body_len), &mut stream).await?;
+    }
+    stream.shutdown().await
+}
+
+impl WiimClient {
+    /// Serve a local file on an address this device can reach
+    ///
+    /// The file is served for as long as the returned [`FileServer`] lives.
+    pub async fn serve_file(&self, path: impl AsRef<Path>) -> Result<FileServer> {
+        FileServer::start(path, &self.base_url).await
+    }
+
+    /// Serve the audio files of a directory and an m3u playlist of them
+    ///
+    /// The files are served for as long as the returned [`FileServer`] lives.
+    pub async fn serve_directory(&self, dir: impl AsRef<Path>) -> Result<FileServer> {
+        FileServer::start_directory(dir, &self.base_url).await
+    }
+
+    /// Play a local file, serving it to the device until playback ends
+    ///
+    /// Returns once the device stops or moves on to something else. Pausing doesn't end
+    /// playback.
+    ///
+    /// # Errors
+    /// Returns `WiimError::InvalidResponse` if the file can't be served or the device
+    /// doesn't start playing it within 30 seconds, or the last request error if the
+    /// device stops answering
+    pub async fn play_file(&self, path: impl AsRef<Path>) -> Result<()> {
+        let server = self.serve_file(path).await?;
+        self.play_url(server.url()).await?;
+        self.serve_while_playing(server, |started, now_playing| {
+            now_playing.is_different_track(started)
+        })
+        .await
+    }
+
+    /// Play the audio files of a directory as the device's queue, serving them until
+    /// playback ends
+    ///
+    /// Next and previous move through the directory's files. Returns once the device
+    /// stops, such as after the last file, or switches to another source.
+    ///
+    /// # Errors
+    /// Returns `WiimError::InvalidResponse` if the directory can't be served or the
+    /// device doesn't start playing within 30 seconds, or the last request error if the
+    /// device stops answering
+    pub async fn play_directory(&self, dir: impl AsRef<Path>) -> Result<()> {
+        let server = self.serve_directory(dir).await?;
+        self.play_playlist(server.url(), 0).await?;
+        self.serve_while_playing(server, |_, _| false).await
+    }
+
+    /// Keep `server` up until playback stops, the source changes or `moved_on` says
+    /// something else is playing
+    async fn serve_while_playing(
+        &self,
+        server: FileServer,
+        moved_on: impl Fn(&NowPlaying, &NowPlaying) -> bool,
+    ) -> Result<()> {
+        let started = self
+            .wait_for_state(PlayState::Playing, START_TIMEOUT)
+            .await?;
+
+        let mut failures = 0;
+        loop {
+            crate::rt::sleep(PLAYBACK_POLL).await;
+            match self.get_now_playing().await {
+                Ok(now_playing)
+                    if playback_ended(&started, &now_playing)
+                        || moved_on(&started, &now_playing) =>
+                {
+                    drop(server);
+                    return Ok(());
+                }
+                Ok(_) => failures = 0,
+                Err(e) => {
+                    failures += 1;
+                    if failures >= MAX_POLL_FAILURES {
+                        return Err(e);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Whether playback has finished, or another source has taken over
+fn playback_ended(started: &NowPlaying, now_playing: &NowPlaying) -> bool {
+    now_playing.state == PlayState::Stopped || now_playing.source != started.source
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range("bytes=0-99", 1000), Some((0, 99)));
+        assert_eq!(parse_range("bytes=500-", 1000), Some((500, 999)));
+        assert_eq!(parse_range("bytes=-100", 1000), Some((900, 999)));
+        assert_eq!(parse_range("bytes=900-5000", 1000), Some((900, 999)));
+        assert_eq!(parse_range("bytes=1000-", 1000), None);
+        assert_eq!(parse_range("bytes=50-10", 1000), None);
+        assert_eq!(parse_range("items=0-1", 1000), None);
+        assert_eq!(parse_range("bytes=0-", 0), None);
+    }
+
+    #[test]
+    fn test_device_host_and_encoding() {
+        assert_eq!(device_host("192.168.1.100"), "192.168.1.100");
+        assert_eq!(device_host("https://192.168.1.100"), "192.168.1.100");
+        assert_eq!(device_host("http://wiim.local:8080/"), "wiim.local");
+        assert_eq!(percent_encode("01 Só.flac"), "01%20S%C3%B3.flac");
+        assert_eq!(content_type(Path::new("a/B.FLAC")), "audio/flac");
+    }
+
+    #[tokio::test]
+    async fn test_serves_ranges() {
+        let path =
+            std::env::temp_dir().join(format!("wiim-file-server-{}.mp3", std::process::id()));
+        std::fs::write(&path, b"0123456789").unwrap();
+        let server = FileServer::start(&path, "127.0.0.1").await.unwrap();
+        assert!(server.url().ends_with(".mp3"));
+
+        let client = reqwest::Client::new();
+        let response = client
+            .get(server.url())
+            .header("Range", "bytes=2-5")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 206);
+        assert_eq!(response.text().await.unwrap(), "2345");
+        let response = client.get(server.url()).send().await.unwrap();
+        assert_eq!(
+            response.headers()["content-type"].to_str().unwrap(),
+            "audio/mpeg"
+        );
+        assert_eq!(response.text().await.unwrap(), "0123456789");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_serves_directory() {
+        let dir = std::env::temp_dir().join(format!("wiim-cast-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("CD 2")).unwrap();
+        std::fs::write(dir.join("02 Two.flac"), b"two").unwrap();
+        std::fs::write(dir.join("01 One.flac"), b"one").unwrap();
+        std::fs::write(dir.join("CD 2/01 Three.mp3"), b"three").unwrap();
+        std::fs::write(dir.join("cover.jpg"), b"jpeg").unwrap();
+
+        let server = FileServer::start_directory(&dir, "127.0.0.1")
+            .await
+            .unwrap();
+        assert_eq!(server.tracks(), 3);
+        let client = reqwest::Client::new();
+        let playlist = client
+            .get(server.url())
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        let urls: Vec<&str> = playlist.lines().filter(|l| !l.starts_with('#')).collect();
+        assert_eq!(urls.len(), 3);
+        assert!(urls[0].ends_with("/0/01%20One.flac"));
+        assert!(playlist.contains("#EXTINF:-1,02 Two\n"));
+        let body = client
+            .get(urls[2])
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert_eq!(body, "three");
+        let base = server.url().trim_end_matches(PLAYLIST_NAME);
+        let missing = client.get(format!("{base}7/x.flac")).send().await.unwrap();
+        assert_eq!(missing.status(), 404);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
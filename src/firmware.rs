@@ -0,0 +1,179 @@
+//! Firmware updates over the air
+//!
+//! `getStatusEx` reports whether the vendor has a newer firmware (`VersionUpdate`) and its
+//! version (`NewVer`). `getMvRemoteUpdateStart` starts the download, after which
+//! `getMvRemoteUpdateStatus` reports a stage code and `getMvRomBurnPrecent` (sic) how much
+//! has been written to flash. The device reboots on its own once the new firmware is in
+//! place, so the update ends with it dropping off the network for a while.
+
+use crate::{Result, StatusEx, WiimClient, WiimError};
+use std::fmt;
+
+/// The installed firmware and whether a newer one is waiting
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FirmwareStatus {
+    pub current: Option<String>,
+    /// Version of the update on offer, if any
+    pub available: Option<String>,
+}
+
+impl FirmwareStatus {
+    pub fn update_available(&self) -> bool {
+        self.available.is_some()
+    }
+}
+
+impl StatusEx {
+    /// Installed firmware and any update the device has been offered
+    pub fn firmware_status(&self) -> FirmwareStatus {
+        let offered = self.version_update.as_deref() == Some("1");
+        FirmwareStatus {
+            current: self.firmware.clone(),
+            available: self
+                .new_ver
+                .clone()
+                .filter(|version| offered && !version.is_empty() && version != "0"),
+        }
+    }
+}
+
+/// Where a running update is, from `getMvRemoteUpdateStatus`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpdateProgress {
+    /// Looking for the update on the vendor's server
+    Checking,
+    Downloading,
+    /// Writing to flash; the percentage comes from `getMvRomBurnPrecent`
+    Installing {
+        percent: Option<u8>,
+    },
+    /// Written; the device is about to reboot into it
+    Complete,
+    /// Download or verification failed, with the stage code the device reported
+    Failed {
+        code: u32,
+    },
+    /// A stage code without a known meaning
+    Other {
+        code: u32,
+    },
+}
+
+impl UpdateProgress {
+    fn from_code(code: u32) -> Self {
+        match code {
+            10..=19 => UpdateProgress::Checking,
+            20 | 21 => UpdateProgress::Downloading,
+            22 | 25 | 26 => UpdateProgress::Installing { percent: None },
+            27 | 30 => UpdateProgress::Complete,
+            23 | 24 | 31 | 40 => UpdateProgress::Failed { code },
+            _ => UpdateProgress::Other { code },
+        }
+    }
+}
+
+impl fmt::Display for UpdateProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UpdateProgress::Checking => write!(f, "checking"),
+            UpdateProgress::Downloading => write!(f, "downloading"),
+            UpdateProgress::Installing {
+                percent: Some(percent),
+            } => {
+                write!(f, "installing {percent}%")
+            }
+            UpdateProgress::Installing { percent: None } => write!(f, "installing"),
+            UpdateProgress::Complete => write!(f, "complete"),
+            UpdateProgress::Failed { code } => write!(f, "failed (code {code})"),
+            UpdateProgress::Other { code } => write!(f, "stage {code}"),
+        }
+    }
+}
+
+fn parse_code(response: &str) -> Result<u32> {
+    response
+        .trim()
+        .parse()
+        .map_err(|_| WiimError::InvalidResponse(format!("Invalid update status: {response}")))
+}
+
+impl WiimClient {
+    /// Get the installed firmware and any update on offer
+    pub async fn get_firmware_status(&self) -> Result<FirmwareStatus> {
+        Ok(self.get_status_ex().await?.firmware_status())
+    }
+
+    /// Start downloading and installing the firmware update on offer
+    ///
+    /// The device reboots by itself when done; follow along with
+    /// [`get_update_progress`](Self::get_update_progress).
+    ///
+    /// # Errors
+    /// Returns `WiimError::InvalidResponse` if no update is on offer
+    pub async fn start_firmware_update(&self) -> Result<()> {
+        if !self.get_firmware_status().await?.update_available() {
+            return Err(WiimError::InvalidResponse(
+                "No firmware update available".to_string(),
+            ));
+        }
+        self.send_command("getMvRemoteUpdateStart").await?;
+        Ok(())
+    }
+
+    /// Where a running firmware update is
+    ///
+    /// Expect request errors once the device reboots into the new firmware.
+    pub async fn get_update_progress(&self) -> Result<UpdateProgress> {
+        let code = parse_code(&self.send_command("getMvRemoteUpdateStatus").await?)?;
+        match UpdateProgress::from_code(code) {
+            UpdateProgress::Installing { .. } => {
+                let percent = self
+                    .send_command("getMvRomBurnPrecent")
+                    .await
+                    .ok()
+                    .and_then(|response| response.trim().parse::<u8>().ok())
+                    .map(|percent| percent.min(100));
+                Ok(UpdateProgress::Installing { percent })
+            }
+            progress => Ok(progress),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_firmware_status() {
+        let status: StatusEx = serde_json::from_value(serde_json::json!({
+            "firmware": "Linkplay.4.6.719753",
+            "VersionUpdate": "1",
+            "NewVer": "Linkplay.5.0.731281",
+        }))
+        .unwrap();
+        let firmware = status.firmware_status();
+        assert!(firmware.update_available());
+        assert_eq!(firmware.available.as_deref(), Some("Linkplay.5.0.731281"));
+
+        let current: StatusEx =
+            serde_json::from_str(crate::fixtures::status_ex::WIIM_MINI).unwrap();
+        assert_eq!(current.firmware_status().available, None);
+    }
+
+    #[test]
+    fn test_update_progress_codes() {
+        assert_eq!(UpdateProgress::from_code(10), UpdateProgress::Checking);
+        assert_eq!(UpdateProgress::from_code(21), UpdateProgress::Downloading);
+        assert_eq!(
+            UpdateProgress::from_code(25),
+            UpdateProgress::Installing { percent: None }
+        );
+        assert_eq!(UpdateProgress::from_code(30), UpdateProgress::Complete);
+        assert_eq!(
+            UpdateProgress::from_code(23).to_string(),
+            "failed (code 23)"
+        );
+        assert!(parse_code("abc").is_err());
+    }
+}
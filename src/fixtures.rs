@@ -0,0 +1,86 @@
+//! Captured device responses for testing without hardware (`fixtures` feature)
+//!
+//! Each constant is a JSON body as a device returned it, for deserializing into the
+//! matching type: [`PlayerStatus`](crate::PlayerStatus), [`MetaInfo`](crate::MetaInfo) or
+//! [`StatusEx`](crate::StatusEx). They cover the quirks the crate handles: quoted and bare
+//! numbers, hex-encoded titles, `unknow` placeholders and empty metadata.
+//!
+//! ```
+//! use wiim_api::{fixtures, StatusEx};
+//!
+//! for json in fixtures::status_ex::ALL {
+//!     let status: StatusEx = serde_json::from_str(json).unwrap();
+//!     assert!(status.device_name.is_some());
+//! }
+//! ```
+
+/// `getPlayerStatus` responses
+pub mod player_status {
+    /// WiiM firmware streaming track 3 of 12, everything quoted
+    pub const WIIM_PLAYING: &str = include_str!("fixtures/player_status_wiim_playing.json");
+    /// WiiM firmware on line-in, muted, with a hex-encoded title and no duration
+    pub const WIIM_LINE_IN: &str = include_str!("fixtures/player_status_wiim_line_in.json");
+    /// Older LinkPlay firmware: bare numbers and hex-encoded title, artist and album
+    pub const LINKPLAY_HEX: &str = include_str!("fixtures/player_status_linkplay_hex.json");
+
+    pub const ALL: &[&str] = &[WIIM_PLAYING, WIIM_LINE_IN, LINKPLAY_HEX];
+}
+
+/// `getMetaInfo` responses
+pub mod meta_info {
+    /// Hi-res Qobuz track with every field filled in
+    pub const QOBUZ: &str = include_str!("fixtures/meta_info_qobuz.json");
+    /// Internet radio: `unknow` placeholders and bare numbers for the audio format
+    pub const RADIO: &str = include_str!("fixtures/meta_info_radio.json");
+    /// Nothing playing, or a source without metadata: every field empty
+    pub const EMPTY: &str = include_str!("fixtures/meta_info_empty.json");
+
+    pub const ALL: &[&str] = &[QOBUZ, RADIO, EMPTY];
+}
+
+/// `getStatusEx` responses
+pub mod status_ex {
+    /// WiiM Mini on Wi-Fi, firmware 4.6
+    pub const WIIM_MINI: &str = include_str!("fixtures/status_ex_wiim_mini.json");
+    /// WiiM Amp on Ethernet, firmware 5.2, with HDMI and a subwoofer output
+    pub const WIIM_AMP: &str = include_str!("fixtures/status_ex_wiim_amp.json");
+    /// Arylic Up2Stream Amp on older LinkPlay firmware, with bare numbers
+    pub const ARYLIC_UP2STREAM: &str = include_str!("fixtures/status_ex_arylic.json");
+
+    pub const ALL: &[&str] = &[WIIM_MINI, WIIM_AMP, ARYLIC_UP2STREAM];
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Input, MetaInfo, PlayerStatus, StatusEx};
+
+    #[test]
+    fn test_fixtures_deserialize() {
+        for json in player_status::ALL {
+            serde_json::from_str::<PlayerStatus>(json).unwrap();
+        }
+        for json in meta_info::ALL {
+            serde_json::from_str::<MetaInfo>(json).unwrap();
+        }
+        for json in status_ex::ALL {
+            serde_json::from_str::<StatusEx>(json).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_fixture_quirks() {
+        let status: PlayerStatus = serde_json::from_str(player_status::LINKPLAY_HEX).unwrap();
+        assert_eq!(status.vol, "60");
+        assert_eq!(
+            status.decoded_artist().as_deref(),
+            Some("Nova Cascade & La Banda")
+        );
+
+        let amp: StatusEx = serde_json::from_str(status_ex::WIIM_AMP).unwrap();
+        assert!(amp.has_sub_out());
+        assert!(amp.inputs().contains(&Input::Hdmi));
+        let arylic: StatusEx = serde_json::from_str(status_ex::ARYLIC_UP2STREAM).unwrap();
+        assert_eq!(arylic.rssi_dbm(), Some(-61));
+    }
+}
@@ -0,0 +1,170 @@
+//! Polling many devices at once
+//!
+//! A [`Fleet`] holds named clients and fetches a snapshot of each with a limit on how many
+//! requests run at the same time, so a large installation doesn't open dozens of
+//! connections at once. A device that fails or takes too long only loses its own entry.
+
+use crate::{rt, GroupRole, NowPlaying, Result, WiimClient, DEFAULT_TIMEOUT};
+use futures_util::stream::{self, StreamExt};
+use std::time::Duration;
+
+/// What one device was doing when polled
+#[derive(Debug, Clone)]
+pub struct DeviceSnapshot {
+    pub now_playing: NowPlaying,
+    pub group_role: GroupRole,
+}
+
+/// One device's part of [`Fleet::poll_all`]
+#[derive(Debug)]
+pub struct FleetEntry {
+    pub name: String,
+    pub snapshot: Result<DeviceSnapshot>,
+}
+
+/// Named clients polled together
+///
+/// # Examples
+/// ```no_run
+/// use std::time::Duration;
+/// use wiim_api::{Fleet, WiimClient};
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let fleet = Fleet::new()
+///         .add("kitchen", WiimClient::new("192.168.1.100"))
+///         .add("office", WiimClient::new("192.168.1.101"))
+///         .timeout(Duration::from_secs(3));
+///     for entry in fleet.poll_all(4).await {
+///         match entry.snapshot {
+///             Ok(snapshot) => println!("{}: {}", entry.name, snapshot.now_playing.state),
+///             Err(e) => println!("{}: {e}", entry.name),
+///         }
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Fleet {
+    devices: Vec<(String, WiimClient)>,
+    timeout: Duration,
+}
+
+impl Default for Fleet {
+    fn default() -> Self {
+        Fleet {
+            devices: Vec::new(),
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+}
+
+impl Fleet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a device under the name its results are reported by
+    pub fn add(mut self, name: impl Into<String>, client: WiimClient) -> Self {
+        self.devices.push((name.into(), client));
+        self
+    }
+
+    /// Longest a single device's snapshot may take, retries included (default 10s)
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.devices.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.devices.is_empty()
+    }
+
+    /// Fetch a snapshot of every device, at most `concurrency` at a time (at least one)
+    ///
+    /// Entries come back in the order the devices were added. A device that doesn't
+    /// answer within the [`timeout`](Self::timeout) gets `WiimError::Timeout`.
+    pub async fn poll_all(&self, concurrency: usize) -> Vec<FleetEntry> {
+        stream::iter(&self.devices)
+            .map(|(name, client)| async move {
+                let snapshot = rt::timeout(self.timeout, snapshot(client)).await;
+                FleetEntry {
+                    name: name.clone(),
+                    snapshot,
+                }
+            })
+            .buffered(concurrency.max(1))
+            .collect()
+            .await
+    }
+}
+
+impl<S: Into<String>> FromIterator<(S, WiimClient)> for Fleet {
+    fn from_iter<I: IntoIterator<Item = (S, WiimClient)>>(devices: I) -> Self {
+        devices
+            .into_iter()
+            .fold(Fleet::new(), |fleet, (name, client)| {
+                fleet.add(name, client)
+            })
+    }
+}
+
+async fn snapshot(client: &WiimClient) -> Result<DeviceSnapshot> {
+    let (now_playing, group_role) =
+        futures_util::try_join!(client.get_now_playing(), client.get_group_role())?;
+    Ok(DeviceSnapshot {
+        now_playing,
+        group_role,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::WiimError;
+
+    fn unreachable() -> WiimClient {
+        // Nothing listens on port 1, so every request is refused immediately
+        WiimClient::builder("http://127.0.0.1:1").build().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_poll_all_keeps_order_and_failures() {
+        let fleet: Fleet = ["kitchen", "office", "den"]
+            .into_iter()
+            .map(|name| (name, unreachable()))
+            .collect();
+        let entries = fleet.poll_all(2).await;
+        let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["kitchen", "office", "den"]);
+        assert!(entries
+            .iter()
+            .all(|e| matches!(e.snapshot, Err(WiimError::Request(_)))));
+    }
+
+    #[tokio::test]
+    async fn test_poll_all_times_out_per_device() {
+        // Accepts connections but never answers
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut open = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                open.push(socket);
+            }
+        });
+        let silent = WiimClient::builder(&format!("http://{address}"))
+            .build()
+            .unwrap();
+        let fleet = Fleet::new()
+            .add("silent", silent)
+            .add("gone", unreachable())
+            .timeout(Duration::from_millis(200));
+        let entries = fleet.poll_all(0).await;
+        assert!(matches!(entries[0].snapshot, Err(WiimError::Timeout(_))));
+        assert!(matches!(entries[1].snapshot, Err(WiimError::Request(_))));
+    }
+}
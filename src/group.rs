@@ -0,0 +1,102 @@
+//! Multiroom group membership
+//!
+//! A device in a group is either the leader, which plays the source and streams it on,
+//! or a follower, whose player status reports `mode` 99. Leaders list their followers
+//! through `multiroom:getSlaveList`.
+
+use crate::{PlaybackSource, Result, WiimClient};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// A device following this one in a multiroom group
+#[derive(Debug, Clone, Deserialize)]
+pub struct GroupFollower {
+    pub name: Option<String>,
+    pub ip: Option<String>,
+    pub uuid: Option<String>,
+    /// 0 stereo, 1 left, 2 right; see [`crate::Channel`]
+    pub channel: Option<u8>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SlaveList {
+    #[serde(default)]
+    slave_list: Vec<GroupFollower>,
+}
+
+/// A device's place in a multiroom group
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GroupRole {
+    /// Not grouped
+    Standalone,
+    /// Playing for one or more followers
+    Leader,
+    /// Playing what the group leader streams
+    Follower,
+}
+
+impl fmt::Display for GroupRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GroupRole::Standalone => write!(f, "standalone"),
+            GroupRole::Leader => write!(f, "leader"),
+            GroupRole::Follower => write!(f, "follower"),
+        }
+    }
+}
+
+impl WiimClient {
+    /// Get the devices following this one in a multiroom group
+    pub async fn get_group_followers(&self) -> Result<Vec<GroupFollower>> {
+        let response = self.send_command("multiroom:getSlaveList").await?;
+        let list: SlaveList = serde_json::from_str(&response)?;
+        Ok(list.slave_list)
+    }
+
+    /// Find out whether the device leads, follows, or isn't part of a multiroom group
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use wiim_api::{GroupRole, WiimClient};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> wiim_api::Result<()> {
+    ///     let client = WiimClient::new("192.168.1.100");
+    ///     if client.get_group_role().await? == GroupRole::Follower {
+    ///         println!("Controlled by the group leader");
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn get_group_role(&self) -> Result<GroupRole> {
+        let status = self.get_player_status().await?;
+        if PlaybackSource::from_mode(&status.mode) == PlaybackSource::Multiroom {
+            return Ok(GroupRole::Follower);
+        }
+        if self.get_group_followers().await?.is_empty() {
+            Ok(GroupRole::Standalone)
+        } else {
+            Ok(GroupRole::Leader)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slave_list_parsing() {
+        let list: SlaveList = serde_json::from_str(r#"{"slaves":0}"#).unwrap();
+        assert!(list.slave_list.is_empty());
+
+        let list: SlaveList = serde_json::from_str(
+            r#"{"slaves":1,"wmrm_version":"4.2","slave_list":[{"name":"Kitchen","uuid":"FF31F09E","ip":"192.168.1.101","version":"4.2","type":"WiiM_Pro","channel":0,"volume":40,"mute":0,"battery_percent":0,"battery_charging":0}]}"#,
+        )
+        .unwrap();
+        assert_eq!(list.slave_list.len(), 1);
+        assert_eq!(list.slave_list[0].name.as_deref(), Some("Kitchen"));
+        assert_eq!(list.slave_list[0].ip.as_deref(), Some("192.168.1.101"));
+    }
+}
@@ -0,0 +1,174 @@
+//! One call for monitoring agents to judge a device
+//!
+//! [`WiimClient::health_check`] times a single `getStatusEx`, which also carries the
+//! internet check, the Wi-Fi signal and any firmware update on offer.
+
+use crate::rt::Instant;
+use crate::{StatusEx, WiimClient};
+use serde::Serialize;
+use std::fmt;
+use std::time::Duration;
+
+/// Wi-Fi signal strength, graded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WifiQuality {
+    /// -50 dBm or better
+    Excellent,
+    /// -60 dBm or better
+    Good,
+    /// -70 dBm or better
+    Fair,
+    Poor,
+}
+
+impl WifiQuality {
+    pub fn from_rssi(rssi: i32) -> Self {
+        match rssi {
+            rssi if rssi >= -50 => WifiQuality::Excellent,
+            rssi if rssi >= -60 => WifiQuality::Good,
+            rssi if rssi >= -70 => WifiQuality::Fair,
+            _ => WifiQuality::Poor,
+        }
+    }
+}
+
+impl fmt::Display for WifiQuality {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WifiQuality::Excellent => write!(f, "excellent"),
+            WifiQuality::Good => write!(f, "good"),
+            WifiQuality::Fair => write!(f, "fair"),
+            WifiQuality::Poor => write!(f, "poor"),
+        }
+    }
+}
+
+impl StatusEx {
+    /// Grade of the Wi-Fi signal; `None` on Ethernet or when RSSI isn't reported
+    pub fn wifi_quality(&self) -> Option<WifiQuality> {
+        self.rssi_dbm().map(WifiQuality::from_rssi)
+    }
+}
+
+/// How a device is doing, from [`WiimClient::health_check`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct HealthReport {
+    pub reachable: bool,
+    /// Why the device couldn't be reached
+    pub error: Option<String>,
+    /// Round trip of the request
+    pub latency: Option<Duration>,
+    /// Whether the device can reach the internet
+    pub internet: Option<bool>,
+    pub wifi_quality: Option<WifiQuality>,
+    /// Version of a firmware update waiting to be installed
+    pub firmware_update: Option<String>,
+}
+
+impl HealthReport {
+    fn from_status(status: &StatusEx, latency: Duration) -> Self {
+        HealthReport {
+            reachable: true,
+            error: None,
+            latency: Some(latency),
+            internet: Some(status.has_internet()),
+            wifi_quality: status.wifi_quality(),
+            firmware_update: status.firmware_status().available,
+        }
+    }
+
+    fn unreachable(error: String) -> Self {
+        HealthReport {
+            reachable: false,
+            error: Some(error),
+            latency: None,
+            internet: None,
+            wifi_quality: None,
+            firmware_update: None,
+        }
+    }
+
+    /// Reachable, online and not on a poor Wi-Fi signal; a pending update doesn't count
+    pub fn is_healthy(&self) -> bool {
+        self.reachable
+            && self.internet != Some(false)
+            && self.wifi_quality != Some(WifiQuality::Poor)
+    }
+}
+
+impl WiimClient {
+    /// Check reachability, latency, internet, Wi-Fi quality and pending updates in one call
+    ///
+    /// A device that can't be reached gives a report with `reachable` false rather than an
+    /// error, so the result can be logged or exported as is.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use wiim_api::WiimClient;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let client = WiimClient::new("192.168.1.100");
+    ///     let report = client.health_check().await;
+    ///     if !report.is_healthy() {
+    ///         eprintln!("{report:?}");
+    ///     }
+    /// }
+    /// ```
+    pub async fn health_check(&self) -> HealthReport {
+        let started = Instant::now();
+        match self.get_status_ex().await {
+            Ok(status) => HealthReport::from_status(&status, started.elapsed()),
+            Err(e) => HealthReport::unreachable(e.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_from_status() {
+        let status: StatusEx = serde_json::from_value(serde_json::json!({
+            "internet": "1",
+            "RSSI": "-72",
+            "VersionUpdate": "1",
+            "NewVer": "Linkplay.4.8.618888",
+        }))
+        .unwrap();
+        let report = HealthReport::from_status(&status, Duration::from_millis(40));
+        assert!(report.reachable);
+        assert_eq!(report.internet, Some(true));
+        assert_eq!(report.wifi_quality, Some(WifiQuality::Poor));
+        assert_eq!(
+            report.firmware_update.as_deref(),
+            Some("Linkplay.4.8.618888")
+        );
+        assert!(!report.is_healthy());
+
+        let status: StatusEx = serde_json::from_value(serde_json::json!({
+            "internet": "1",
+            "RSSI": "-55",
+            "VersionUpdate": "0",
+        }))
+        .unwrap();
+        let report = HealthReport::from_status(&status, Duration::from_millis(40));
+        assert_eq!(report.wifi_quality, Some(WifiQuality::Good));
+        assert_eq!(report.firmware_update, None);
+        assert!(report.is_healthy());
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_device() {
+        let client = WiimClient::builder("http://127.0.0.1:1")
+            .retries(0)
+            .build()
+            .unwrap();
+        let report = client.health_check().await;
+        assert!(!report.reachable);
+        assert!(report.error.is_some());
+        assert!(!report.is_healthy());
+    }
+}
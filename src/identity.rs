@@ -0,0 +1,96 @@
+//! Telling devices apart without keeping all of `getStatusEx` around
+
+use crate::{Result, StatusEx, WiimClient};
+use serde::{Deserialize, Serialize};
+
+/// Who a device is: the parts of [`StatusEx`] registries and logs need
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceIdentity {
+    /// Name set in the WiiM app, e.g. "Kitchen"
+    pub name: String,
+    /// Hardware project, e.g. "WiiM_Pro_with_gc4a"
+    pub model: Option<String>,
+    /// Stays the same across renames and IP changes
+    pub uuid: Option<String>,
+    /// Wi-Fi MAC address, e.g. "08:e9:f6:8f:8f:a2"
+    pub mac: Option<String>,
+    pub firmware: Option<String>,
+    /// LAN address, Wi-Fi first
+    pub ip: Option<String>,
+}
+
+fn non_empty(value: &Option<String>) -> Option<String> {
+    value
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+}
+
+impl From<&StatusEx> for DeviceIdentity {
+    fn from(status: &StatusEx) -> Self {
+        DeviceIdentity {
+            name: non_empty(&status.device_name)
+                .or_else(|| non_empty(&status.ssid))
+                .unwrap_or_default(),
+            model: non_empty(&status.project),
+            uuid: non_empty(&status.uuid),
+            mac: non_empty(&status.mac).map(|mac| mac.to_ascii_lowercase()),
+            firmware: non_empty(&status.firmware),
+            ip: status.ip_address().map(str::to_string),
+        }
+    }
+}
+
+impl WiimClient {
+    /// Get the device's name, model, UUID, MAC, firmware and IP in one request
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use wiim_api::WiimClient;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> wiim_api::Result<()> {
+    ///     let client = WiimClient::new("192.168.1.100");
+    ///     let identity = client.get_identity().await?;
+    ///     println!("{} ({:?})", identity.name, identity.uuid);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn get_identity(&self) -> Result<DeviceIdentity> {
+        Ok(DeviceIdentity::from(&self.get_status_ex().await?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identity_from_status() {
+        let status: StatusEx = serde_json::from_value(serde_json::json!({
+            "DeviceName": "Kitchen",
+            "ssid": "WiiM Mini-8FA2",
+            "project": "Muzo_Mini",
+            "uuid": "FF970016A6FE22C1660AB4D8",
+            "MAC": "08:E9:F6:8F:8F:A2",
+            "firmware": "Linkplay.4.6.425351",
+            "apcli0": "192.168.1.62",
+            "eth0": "0.0.0.0",
+        }))
+        .unwrap();
+        let identity = DeviceIdentity::from(&status);
+        assert_eq!(identity.name, "Kitchen");
+        assert_eq!(identity.model.as_deref(), Some("Muzo_Mini"));
+        assert_eq!(identity.mac.as_deref(), Some("08:e9:f6:8f:8f:a2"));
+        assert_eq!(identity.ip.as_deref(), Some("192.168.1.62"));
+
+        let status: StatusEx =
+            serde_json::from_value(serde_json::json!({ "DeviceName": "", "ssid": "WiiM Pro" }))
+                .unwrap();
+        let identity = DeviceIdentity::from(&status);
+        assert_eq!(identity.name, "WiiM Pro");
+        assert_eq!(identity.uuid, None);
+        assert_eq!(identity.ip, None);
+    }
+}
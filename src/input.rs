@@ -0,0 +1,376 @@
+//! Physical inputs and switching between them
+//!
+//! `setPlayerCmd:switchmode` selects an input. Which inputs a device has is reported by
+//! the `plm_support` bitmask in `getStatusEx`, except HDMI, which only the WiiM Amp
+//! and Ultra models have and is recognised from the `project` name.
+//!
+//! Audio from a TV input (HDMI, optical) can be delayed to line up with the picture with
+//! `setAudioSyncDelay`. That is separate from the SPDIF *output* delay, which holds back
+//! what the device sends on to an external DAC.
+//!
+//! The analog line input has an adjustable gain (`setLineInGain`), for sources such as
+//! turntable preamps that are quieter or louder than the usual 2V line level.
+
+use crate::compat::json_number;
+use crate::{Capabilities, Feature, PlaybackSource, Result, StatusEx, WiimClient, WiimError};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::time::Duration;
+
+/// Longest lip-sync delay the firmware accepts
+pub const MAX_LIP_SYNC_DELAY: Duration = Duration::from_millis(1000);
+/// Line-in gain range in dB
+pub const LINE_IN_GAIN_DB: RangeInclusive<i8> = -12..=12;
+
+/// An input the device can play from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Input {
+    /// Network streaming, the default on every device
+    Wifi,
+    LineIn,
+    Bluetooth,
+    Optical,
+    Coaxial,
+    /// HDMI ARC from a TV (WiiM Amp and Ultra)
+    Hdmi,
+    UsbDac,
+}
+
+impl Input {
+    pub const ALL: [Input; 7] = [
+        Input::Wifi,
+        Input::LineIn,
+        Input::Bluetooth,
+        Input::Optical,
+        Input::Coaxial,
+        Input::Hdmi,
+        Input::UsbDac,
+    ];
+
+    /// Name used by `setPlayerCmd:switchmode`
+    fn mode_name(&self) -> &'static str {
+        match self {
+            Input::Wifi => "wifi",
+            Input::LineIn => "line-in",
+            Input::Bluetooth => "bluetooth",
+            Input::Optical => "optical",
+            Input::Coaxial => "co-axial",
+            Input::Hdmi => "HDMI",
+            Input::UsbDac => "PCUSB",
+        }
+    }
+
+    /// `plm_support` bit announcing the input, if it has one
+    fn plm_bit(&self) -> Option<u64> {
+        match self {
+            Input::LineIn => Some(0x2),
+            Input::Bluetooth => Some(0x4),
+            Input::Optical => Some(0x10),
+            Input::Coaxial => Some(0x40),
+            Input::UsbDac => Some(0x8000),
+            Input::Wifi | Input::Hdmi => None,
+        }
+    }
+
+    /// The source the player status reports while this input is selected
+    pub fn source(&self) -> PlaybackSource {
+        match self {
+            Input::Wifi => PlaybackSource::Network,
+            Input::LineIn => PlaybackSource::LineIn,
+            Input::Bluetooth => PlaybackSource::Bluetooth,
+            Input::Optical => PlaybackSource::Optical,
+            Input::Coaxial => PlaybackSource::Coaxial,
+            Input::Hdmi => PlaybackSource::Hdmi,
+            Input::UsbDac => PlaybackSource::UsbDac,
+        }
+    }
+
+    /// The input behind a physical source, or `None` for network and idle sources
+    pub fn from_source(source: PlaybackSource) -> Option<Input> {
+        Input::ALL
+            .into_iter()
+            .filter(|input| *input != Input::Wifi)
+            .find(|input| input.source() == source)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Input::Wifi => "wifi",
+            Input::LineIn => "line-in",
+            Input::Bluetooth => "bluetooth",
+            Input::Optical => "optical",
+            Input::Coaxial => "coaxial",
+            Input::Hdmi => "hdmi",
+            Input::UsbDac => "usb-dac",
+        }
+    }
+}
+
+impl fmt::Display for Input {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl StatusEx {
+    /// Whether this model has an HDMI input
+    pub fn has_hdmi(&self) -> bool {
+        self.project.as_deref().is_some_and(|project| {
+            let project = project.to_ascii_lowercase();
+            project.contains("amp") || project.contains("ultra")
+        })
+    }
+
+    /// The inputs this device has
+    pub fn inputs(&self) -> Vec<Input> {
+        let plm = self
+            .plm_support
+            .as_deref()
+            .and_then(|mask| u64::from_str_radix(mask.trim_start_matches("0x"), 16).ok())
+            .unwrap_or(0);
+        Input::ALL
+            .into_iter()
+            .filter(|input| match (input, input.plm_bit()) {
+                (Input::Wifi, _) => true,
+                (Input::Hdmi, _) => self.has_hdmi(),
+                (_, Some(bit)) => plm & bit != 0,
+                (_, None) => false,
+            })
+            .collect()
+    }
+}
+
+impl WiimClient {
+    /// Get the inputs this device has
+    pub async fn get_inputs(&self) -> Result<Vec<Input>> {
+        Ok(self.get_status_ex().await?.inputs())
+    }
+
+    /// Switch to an input
+    ///
+    /// # Errors
+    /// Returns `WiimError::Unsupported` if the device doesn't have the input
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use wiim_api::{Input, WiimClient};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> wiim_api::Result<()> {
+    ///     let client = WiimClient::new("192.168.1.100");
+    ///     client.select_input(Input::Hdmi).await?;
+    ///     if client.is_hdmi_audio_present().await? {
+    ///         println!("The TV is sending audio");
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn select_input(&self, input: Input) -> Result<()> {
+        Capabilities::from(&self.get_status_ex().await?).ensure(Feature::Input(input))?;
+        let command = format!("setPlayerCmd:switchmode:{}", input.mode_name());
+        // Network playback and idle both count as the Wi-Fi input
+        self.send_setting(&command, |status| {
+            Input::from_source(PlaybackSource::from_mode(&status.mode)).unwrap_or(Input::Wifi)
+                == input
+        })
+        .await
+    }
+
+    /// Get how long TV input audio is delayed to match the picture
+    ///
+    /// # Errors
+    /// Returns `WiimError::Unsupported` if the device has no HDMI or optical input
+    pub async fn get_lip_sync_delay(&self) -> Result<Duration> {
+        Capabilities::from(&self.get_status_ex().await?).ensure(Feature::LipSync)?;
+        let response = self.send_command("getAudioSyncDelay").await?;
+        parse_lip_sync_delay(&response)
+    }
+
+    /// Delay TV input audio by `delay` (up to one second, in whole milliseconds)
+    ///
+    /// # Errors
+    /// Returns `WiimError::InvalidResponse` if the delay is too long, or
+    /// `WiimError::Unsupported` if the device has no HDMI or optical input
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use wiim_api::WiimClient;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> wiim_api::Result<()> {
+    ///     let client = WiimClient::new("192.168.1.100");
+    ///     client.set_lip_sync_delay(Duration::from_millis(120)).await?;
+    ///     // The voices are still early: nudge by another 20ms
+    ///     client.adjust_lip_sync_delay(20).await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn set_lip_sync_delay(&self, delay: Duration) -> Result<()> {
+        if delay > MAX_LIP_SYNC_DELAY {
+            return Err(WiimError::InvalidResponse(format!(
+                "Lip-sync delay must be at most {}ms, got {}ms",
+                MAX_LIP_SYNC_DELAY.as_millis(),
+                delay.as_millis()
+            )));
+        }
+        Capabilities::from(&self.get_status_ex().await?).ensure(Feature::LipSync)?;
+        let command = format!("setAudioSyncDelay:{}", delay.as_millis());
+        self.send_command(&command).await?;
+        Ok(())
+    }
+
+    /// Lengthen (positive) or shorten (negative) the lip-sync delay by `millis`, clamped
+    /// to the supported range, and return the new delay
+    pub async fn adjust_lip_sync_delay(&self, millis: i64) -> Result<Duration> {
+        let current = self.get_lip_sync_delay().await?.as_millis() as i64;
+        let max = MAX_LIP_SYNC_DELAY.as_millis() as i64;
+        let delay = Duration::from_millis((current + millis).clamp(0, max) as u64);
+        self.set_lip_sync_delay(delay).await?;
+        Ok(delay)
+    }
+
+    /// Get the line-in gain in dB
+    ///
+    /// # Errors
+    /// Returns `WiimError::Unsupported` if the device has no line input
+    pub async fn get_line_in_gain(&self) -> Result<i8> {
+        Capabilities::from(&self.get_status_ex().await?).ensure(Feature::Input(Input::LineIn))?;
+        let response = self.send_command("getLineInGain").await?;
+        parse_line_in_gain(&response)
+    }
+
+    /// Set the line-in gain (-12 to +12 dB)
+    ///
+    /// # Errors
+    /// Returns `WiimError::InvalidResponse` if the gain is out of range, or
+    /// `WiimError::Unsupported` if the device has no line input
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use wiim_api::WiimClient;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> wiim_api::Result<()> {
+    ///     let client = WiimClient::new("192.168.1.100");
+    ///     // A quiet phono preamp
+    ///     client.set_line_in_gain(6).await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn set_line_in_gain(&self, db: i8) -> Result<()> {
+        if !LINE_IN_GAIN_DB.contains(&db) {
+            return Err(WiimError::InvalidResponse(format!(
+                "Line-in gain must be between {} and {} dB, got {db}",
+                LINE_IN_GAIN_DB.start(),
+                LINE_IN_GAIN_DB.end()
+            )));
+        }
+        Capabilities::from(&self.get_status_ex().await?).ensure(Feature::Input(Input::LineIn))?;
+        self.send_command(&format!("setLineInGain:{db}")).await?;
+        Ok(())
+    }
+
+    /// Whether HDMI is selected and the TV is sending audio over ARC
+    ///
+    /// The device reports the HDMI input as playing only while audio arrives.
+    pub async fn is_hdmi_audio_present(&self) -> Result<bool> {
+        let status = self.get_player_status().await?;
+        Ok(
+            PlaybackSource::from_mode(&status.mode) == PlaybackSource::Hdmi
+                && status.status == "play",
+        )
+    }
+}
+
+/// `getAudioSyncDelay` answers with a bare number of milliseconds or `{"delay": ms}`
+fn parse_lip_sync_delay(response: &str) -> Result<Duration> {
+    let value: serde_json::Value = serde_json::from_str(response.trim())
+        .unwrap_or_else(|_| serde_json::Value::String(response.to_string()));
+    let millis = value.get("delay").unwrap_or(&value);
+    json_number(millis)
+        .and_then(|millis| u64::try_from(millis).ok())
+        .map(Duration::from_millis)
+        .ok_or_else(|| WiimError::InvalidResponse(format!("Invalid lip-sync delay: {response}")))
+}
+
+/// `getLineInGain` answers with a bare number of dB or `{"gain": db}`
+fn parse_line_in_gain(response: &str) -> Result<i8> {
+    let value: serde_json::Value = serde_json::from_str(response.trim())
+        .unwrap_or_else(|_| serde_json::Value::String(response.to_string()));
+    let gain = value.get("gain").unwrap_or(&value);
+    json_number(gain)
+        .and_then(|gain| i8::try_from(gain).ok())
+        .ok_or_else(|| WiimError::InvalidResponse(format!("Invalid line-in gain: {response}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_line_in_gain() {
+        assert_eq!(parse_line_in_gain("-6").unwrap(), -6);
+        assert_eq!(parse_line_in_gain(r#"{"gain":3}"#).unwrap(), 3);
+        assert!(parse_line_in_gain("unknown command").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_set_line_in_gain_out_of_range() {
+        let client = WiimClient::new("192.168.1.100");
+        assert!(matches!(
+            client.set_line_in_gain(20).await,
+            Err(WiimError::InvalidResponse(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_lip_sync_delay() {
+        assert_eq!(
+            parse_lip_sync_delay("120").unwrap(),
+            Duration::from_millis(120)
+        );
+        assert_eq!(
+            parse_lip_sync_delay(r#"{"delay":"80"}"#).unwrap(),
+            Duration::from_millis(80)
+        );
+        assert!(parse_lip_sync_delay("unknown command").is_err());
+        assert!(parse_lip_sync_delay("-5").is_err());
+    }
+
+    fn status_ex(project: &str, plm_support: &str) -> StatusEx {
+        serde_json::from_value(serde_json::json!({
+            "project": project,
+            "plm_support": plm_support,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_inputs_from_capabilities() {
+        let mini = status_ex("Muzo_Mini", "0x300006");
+        assert_eq!(
+            mini.inputs(),
+            vec![Input::Wifi, Input::LineIn, Input::Bluetooth]
+        );
+        assert!(!mini.has_hdmi());
+
+        let amp = status_ex("WiiM_Amp_4layer", "0x8016");
+        assert_eq!(
+            amp.inputs(),
+            vec![
+                Input::Wifi,
+                Input::LineIn,
+                Input::Bluetooth,
+                Input::Optical,
+                Input::Hdmi,
+                Input::UsbDac
+            ]
+        );
+
+        let unknown: StatusEx = serde_json::from_str("{}").unwrap();
+        assert_eq!(unknown.inputs(), vec![Input::Wifi]);
+    }
+}
@@ -0,0 +1,422 @@
+//! The device's address on the network, and how well its connection performs
+//!
+//! Devices take an address from DHCP unless given a static one. `getStaticIpInfo`
+//! reports the address in use and whether it is static; `setWlanStaticIp` pins one and
+//! `setWlanDhcp` goes back to DHCP. Either change takes effect once the device
+//! reconnects, which drops requests for a few seconds.
+//!
+//! [`WiimClient::network_report`] samples the Wi-Fi figures from `getStatusEx` and times
+//! each request, so dropouts can be pinned on the network or ruled out in one call.
+
+use crate::rt::Instant;
+use crate::{Result, StatusEx, WiimClient};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+/// Delay between samples in a [`NetworkReport`]
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How the device is addressed right now
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct IpConfig {
+    /// `false` when the address comes from DHCP
+    pub is_static: bool,
+    pub ip: Option<Ipv4Addr>,
+    pub netmask: Option<Ipv4Addr>,
+    pub gateway: Option<Ipv4Addr>,
+    pub dns: Option<Ipv4Addr>,
+}
+
+/// A fixed address to give the device
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StaticIp {
+    pub ip: Ipv4Addr,
+    pub netmask: Ipv4Addr,
+    pub gateway: Ipv4Addr,
+    pub dns: Ipv4Addr,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawIpInfo {
+    #[serde(rename = "wlanStaticIpEnable", default)]
+    static_enabled: String,
+    #[serde(rename = "wlanStaticIp", default)]
+    ip: String,
+    #[serde(rename = "wlanMask", default)]
+    netmask: String,
+    #[serde(rename = "wlanGateWay", default)]
+    gateway: String,
+    #[serde(rename = "wlanDnsServer", default)]
+    dns: String,
+}
+
+/// Addresses are reported as strings, with "0.0.0.0" or "" when unset
+fn parse_address(value: &str) -> Option<Ipv4Addr> {
+    value
+        .trim()
+        .parse()
+        .ok()
+        .filter(|ip: &Ipv4Addr| !ip.is_unspecified())
+}
+
+impl From<RawIpInfo> for IpConfig {
+    fn from(raw: RawIpInfo) -> Self {
+        IpConfig {
+            is_static: raw.static_enabled == "1",
+            ip: parse_address(&raw.ip),
+            netmask: parse_address(&raw.netmask),
+            gateway: parse_address(&raw.gateway),
+            dns: parse_address(&raw.dns),
+        }
+    }
+}
+
+impl WiimClient {
+    /// Get the device's current address and whether it is static or from DHCP
+    pub async fn get_ip_config(&self) -> Result<IpConfig> {
+        let response = self.send_command("getStaticIpInfo").await?;
+        let info: RawIpInfo = serde_json::from_str(&response)?;
+        Ok(info.into())
+    }
+
+    /// Pin the device to a fixed address instead of asking DHCP
+    ///
+    /// The device reconnects with the new address, so point the client at
+    /// `config.ip` afterwards.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use std::net::Ipv4Addr;
+    /// use wiim_api::{StaticIp, WiimClient};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> wiim_api::Result<()> {
+    ///     let mut client = WiimClient::new("192.168.1.100");
+    ///     let config = StaticIp {
+    ///         ip: Ipv4Addr::new(192, 168, 1, 50),
+    ///         netmask: Ipv4Addr::new(255, 255, 255, 0),
+    ///         gateway: Ipv4Addr::new(192, 168, 1, 1),
+    ///         dns: Ipv4Addr::new(192, 168, 1, 1),
+    ///     };
+    ///     client.set_static_ip(&config).await?;
+    ///     client.set_ip_address(&config.ip.to_string());
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn set_static_ip(&self, config: &StaticIp) -> Result<()> {
+        self.send_command(&static_ip_command(config)).await?;
+        Ok(())
+    }
+
+    /// Go back to taking an address from DHCP
+    pub async fn use_dhcp(&self) -> Result<()> {
+        self.send_command("setWlanDhcp").await?;
+        Ok(())
+    }
+}
+
+/// Lowest, average and highest of a sampled figure
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct SampleStats {
+    pub min: i32,
+    pub mean: f64,
+    pub max: i32,
+}
+
+impl SampleStats {
+    fn from_samples(values: impl IntoIterator<Item = i32>) -> Option<Self> {
+        let values: Vec<i32> = values.into_iter().collect();
+        let min = *values.iter().min()?;
+        let max = *values.iter().max()?;
+        let mean = values.iter().map(|v| f64::from(*v)).sum::<f64>() / values.len() as f64;
+        Some(SampleStats { min, mean, max })
+    }
+
+    /// How far the figure swung during the window
+    pub fn spread(&self) -> i32 {
+        self.max - self.min
+    }
+}
+
+/// Overall judgement of a [`NetworkReport`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NetworkVerdict {
+    Good,
+    /// Works, but dropouts are likely under load or when the signal dips
+    Marginal,
+    /// Dropouts are to be expected
+    Poor,
+}
+
+impl fmt::Display for NetworkVerdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetworkVerdict::Good => write!(f, "good"),
+            NetworkVerdict::Marginal => write!(f, "marginal"),
+            NetworkVerdict::Poor => write!(f, "poor"),
+        }
+    }
+}
+
+/// Wi-Fi and API figures sampled over a short window
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NetworkReport {
+    /// Requests made, including failed ones
+    pub samples: usize,
+    pub failures: usize,
+    /// Signal strength in dBm
+    pub rssi: Option<SampleStats>,
+    /// Signal-to-noise ratio in dB
+    pub snr: Option<SampleStats>,
+    /// Noise floor in dBm
+    pub noise: Option<SampleStats>,
+    /// Link rate in Mbps
+    pub data_rate: Option<SampleStats>,
+    /// API round trips of the successful requests
+    pub latency_min: Duration,
+    pub latency_mean: Duration,
+    pub latency_max: Duration,
+    pub verdict: NetworkVerdict,
+    /// Why the verdict isn't [`NetworkVerdict::Good`]
+    pub problems: Vec<String>,
+}
+
+struct Sample {
+    status: StatusEx,
+    latency: Duration,
+}
+
+fn parse_figure(value: &Option<String>) -> Option<i32> {
+    value.as_deref()?.trim().parse().ok()
+}
+
+fn summarize(samples: &[Sample], failures: usize) -> NetworkReport {
+    let stats = |field: fn(&StatusEx) -> &Option<String>| {
+        SampleStats::from_samples(
+            samples
+                .iter()
+                .filter_map(|s| parse_figure(field(&s.status))),
+        )
+    };
+    let rssi = stats(|s| &s.rssi);
+    let snr = stats(|s| &s.wlan_snr);
+    let noise = stats(|s| &s.wlan_noise);
+    let data_rate = stats(|s| &s.wlan_data_rate);
+
+    let latencies = samples.iter().map(|s| s.latency);
+    let latency_min = latencies.clone().min().unwrap_or_default();
+    let latency_max = latencies.clone().max().unwrap_or_default();
+    let latency_mean = match samples.len() {
+        0 => Duration::ZERO,
+        n => latencies.sum::<Duration>() / n as u32,
+    };
+
+    let mut verdict = NetworkVerdict::Good;
+    let mut problems = Vec::new();
+    let mut flag = |level: NetworkVerdict, problem: String| {
+        verdict = verdict.max(level);
+        problems.push(problem);
+    };
+    if failures > 0 {
+        flag(
+            NetworkVerdict::Poor,
+            format!("{failures} of {} requests failed", samples.len() + failures),
+        );
+    }
+    if let Some(rssi) = rssi {
+        if rssi.mean < -75.0 {
+            flag(
+                NetworkVerdict::Poor,
+                format!("weak signal ({:.0} dBm)", rssi.mean),
+            );
+        } else if rssi.mean < -67.0 {
+            flag(
+                NetworkVerdict::Marginal,
+                format!("modest signal ({:.0} dBm)", rssi.mean),
+            );
+        }
+        if rssi.spread() > 15 {
+            flag(
+                NetworkVerdict::Marginal,
+                format!("unstable signal (swung {} dB)", rssi.spread()),
+            );
+        }
+    }
+    if let Some(snr) = snr {
+        if snr.mean < 15.0 {
+            flag(
+                NetworkVerdict::Poor,
+                format!("low SNR ({:.0} dB)", snr.mean),
+            );
+        } else if snr.mean < 25.0 {
+            flag(
+                NetworkVerdict::Marginal,
+                format!("modest SNR ({:.0} dB)", snr.mean),
+            );
+        }
+    }
+    if latency_max > Duration::from_secs(1) {
+        flag(
+            NetworkVerdict::Poor,
+            format!("slow API responses (up to {} ms)", latency_max.as_millis()),
+        );
+    } else if latency_mean > Duration::from_millis(250) {
+        flag(
+            NetworkVerdict::Marginal,
+            format!(
+                "sluggish API responses ({} ms average)",
+                latency_mean.as_millis()
+            ),
+        );
+    }
+
+    NetworkReport {
+        samples: samples.len() + failures,
+        failures,
+        rssi,
+        snr,
+        noise,
+        data_rate,
+        latency_min,
+        latency_mean,
+        latency_max,
+        verdict,
+        problems,
+    }
+}
+
+impl WiimClient {
+    /// Sample the Wi-Fi signal and API latency over `window` and judge the connection
+    ///
+    /// Samples are taken every half second, with at least one sample.
+    ///
+    /// # Errors
+    /// Returns the last request error if every sample failed
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use wiim_api::WiimClient;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> wiim_api::Result<()> {
+    ///     let client = WiimClient::new("192.168.1.100");
+    ///     let report = client.network_report(Duration::from_secs(5)).await?;
+    ///     println!("Network is {}", report.verdict);
+    ///     for problem in &report.problems {
+    ///         println!("  - {problem}");
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn network_report(&self, window: Duration) -> Result<NetworkReport> {
+        let count = (window.as_millis() / SAMPLE_INTERVAL.as_millis()).max(1);
+        let mut samples = Vec::new();
+        let mut failures = 0;
+        let mut last_error = None;
+        for index in 0..count {
+            if index > 0 {
+                crate::rt::sleep(SAMPLE_INTERVAL).await;
+            }
+            let started = Instant::now();
+            match self.get_status_ex().await {
+                Ok(status) => samples.push(Sample {
+                    status,
+                    latency: started.elapsed(),
+                }),
+                Err(e) => {
+                    failures += 1;
+                    last_error = Some(e);
+                }
+            }
+        }
+        match last_error {
+            Some(e) if samples.is_empty() => Err(e),
+            _ => Ok(summarize(&samples, failures)),
+        }
+    }
+}
+
+fn static_ip_command(config: &StaticIp) -> String {
+    format!(
+        "setWlanStaticIp:{}:{}:{}:{}",
+        config.ip, config.netmask, config.gateway, config.dns
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ip_info_parsing() {
+        let info: RawIpInfo = serde_json::from_str(
+            r#"{"wlanStaticIpEnable":"1","wlanStaticIp":"192.168.1.50","wlanMask":"255.255.255.0","wlanGateWay":"192.168.1.1","wlanDnsServer":"0.0.0.0"}"#,
+        )
+        .unwrap();
+        let config = IpConfig::from(info);
+        assert!(config.is_static);
+        assert_eq!(config.ip, Some(Ipv4Addr::new(192, 168, 1, 50)));
+        assert_eq!(config.gateway, Some(Ipv4Addr::new(192, 168, 1, 1)));
+        assert_eq!(config.dns, None);
+
+        let info: RawIpInfo = serde_json::from_str(r#"{"wlanStaticIpEnable":"0"}"#).unwrap();
+        let config = IpConfig::from(info);
+        assert!(!config.is_static);
+        assert_eq!(config.ip, None);
+    }
+
+    #[test]
+    fn test_static_ip_command() {
+        let config = StaticIp {
+            ip: Ipv4Addr::new(192, 168, 1, 50),
+            netmask: Ipv4Addr::new(255, 255, 255, 0),
+            gateway: Ipv4Addr::new(192, 168, 1, 1),
+            dns: Ipv4Addr::new(1, 1, 1, 1),
+        };
+        assert_eq!(
+            static_ip_command(&config),
+            "setWlanStaticIp:192.168.1.50:255.255.255.0:192.168.1.1:1.1.1.1"
+        );
+    }
+
+    fn sample(rssi: &str, snr: &str, latency_ms: u64) -> Sample {
+        let status: StatusEx = serde_json::from_value(serde_json::json!({
+            "RSSI": rssi,
+            "wlanSnr": snr,
+            "wlanNoise": "-92",
+            "wlanDataRate": "390",
+        }))
+        .unwrap();
+        Sample {
+            status,
+            latency: Duration::from_millis(latency_ms),
+        }
+    }
+
+    #[test]
+    fn test_summarize_good_network() {
+        let report = summarize(&[sample("-48", "40", 20), sample("-52", "38", 40)], 0);
+        assert_eq!(report.verdict, NetworkVerdict::Good);
+        assert!(report.problems.is_empty());
+        let rssi = report.rssi.unwrap();
+        assert_eq!((rssi.min, rssi.max), (-52, -48));
+        assert_eq!(rssi.mean, -50.0);
+        assert_eq!(report.latency_mean, Duration::from_millis(30));
+    }
+
+    #[test]
+    fn test_summarize_flags_problems() {
+        let report = summarize(&[sample("-70", "20", 300), sample("-72", "22", 300)], 0);
+        assert_eq!(report.verdict, NetworkVerdict::Marginal);
+        assert_eq!(report.problems.len(), 3);
+
+        let report = summarize(&[sample("-80", "10", 20)], 1);
+        assert_eq!(report.verdict, NetworkVerdict::Poor);
+        assert_eq!(report.samples, 2);
+        assert_eq!(report.problems[0], "1 of 2 requests failed");
+    }
+}
@@ -0,0 +1,209 @@
+//! First-time Wi-Fi setup without the mobile app
+//!
+//! An unconfigured device opens its own setup hotspot and answers on
+//! [`SETUP_HOTSPOT_IP`]. With the computer joined to that hotspot, the device is told
+//! which network to join with `wlanConnectApEx`, then `wlanGetConnectState` reports
+//! progress. Once connected, `getStatusEx` on the hotspot shows the address the device
+//! got on the LAN.
+
+use crate::rt::Instant;
+use crate::{wifi, AccessPoint, Result, WiimClient, WiimError};
+use std::time::Duration;
+
+/// Address of a device on its own setup hotspot
+pub const SETUP_HOTSPOT_IP: &str = "10.10.10.254";
+
+/// Delay between progress checks while the device joins a network
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Progress of joining a Wi-Fi network, from `wlanGetConnectState`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WifiConnectState {
+    Connecting,
+    Connected,
+    WrongPassword,
+    Failed,
+    /// No network has been requested
+    Idle,
+}
+
+impl WifiConnectState {
+    fn parse(response: &str) -> Result<Self> {
+        match response.trim() {
+            "PROCESS" => Ok(WifiConnectState::Connecting),
+            "OK" => Ok(WifiConnectState::Connected),
+            "PAIRFAIL" => Ok(WifiConnectState::WrongPassword),
+            "FAIL" => Ok(WifiConnectState::Failed),
+            "NO" | "" => Ok(WifiConnectState::Idle),
+            other => Err(WiimError::InvalidResponse(format!(
+                "Unknown Wi-Fi connect state: {other}"
+            ))),
+        }
+    }
+}
+
+fn join_command(ap: &AccessPoint, passphrase: &str) -> String {
+    format!(
+        "wlanConnectApEx:ssid={}:ch={}:auth={}:encry={}:pwd={}:chext=0",
+        wifi::encode_hex_string(&ap.ssid),
+        ap.channel.unwrap_or_default(),
+        ap.auth,
+        ap.encryption,
+        wifi::encode_hex_string(passphrase),
+    )
+}
+
+impl WiimClient {
+    /// Ask the device to join a Wi-Fi network it can see
+    ///
+    /// The channel and security settings are taken from a [`wifi_scan`](Self::wifi_scan),
+    /// picking the strongest access point with that SSID.
+    ///
+    /// # Errors
+    /// Returns `WiimError::InvalidResponse` if the device can't see the network
+    pub async fn join_wifi(&self, ssid: &str, passphrase: &str) -> Result<()> {
+        let access_points = self.wifi_scan().await?;
+        let ap = access_points
+            .iter()
+            .find(|ap| ap.ssid == ssid)
+            .ok_or_else(|| {
+                WiimError::InvalidResponse(format!("The device can't see network '{ssid}'"))
+            })?;
+        self.send_command(&join_command(ap, passphrase)).await?;
+        Ok(())
+    }
+
+    /// Get the progress of the last [`join_wifi`](Self::join_wifi)
+    pub async fn get_wifi_connect_state(&self) -> Result<WifiConnectState> {
+        let response = self.send_command("wlanGetConnectState").await?;
+        WifiConnectState::parse(&response)
+    }
+
+    /// Put a device on a Wi-Fi network from its setup hotspot, returning a client for
+    /// its new address on that network
+    ///
+    /// Call this on a client for [`SETUP_HOTSPOT_IP`] while the computer is joined to
+    /// the device's hotspot. The returned client keeps this client's timeouts and
+    /// retries; once the computer is back on the LAN, use
+    /// [`wait_until_reachable`](Self::wait_until_reachable) to know the device is ready.
+    ///
+    /// # Errors
+    /// Returns `WiimError::InvalidResponse` if the passphrase is rejected or the device
+    /// fails to join, and `WiimError::Timeout` if `timeout` passes first
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use wiim_api::{WiimClient, SETUP_HOTSPOT_IP};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> wiim_api::Result<()> {
+    ///     let setup = WiimClient::new(SETUP_HOTSPOT_IP);
+    ///     let device = setup
+    ///         .provision_wifi("Home", "hunter22", Duration::from_secs(90))
+    ///         .await?;
+    ///     println!("Joined as {}", device.get_ip_address());
+    ///
+    ///     // Switch the computer back to the home network, then:
+    ///     device.wait_until_reachable(Duration::from_secs(60)).await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn provision_wifi(
+        &self,
+        ssid: &str,
+        passphrase: &str,
+        timeout: Duration,
+    ) -> Result<WiimClient> {
+        let deadline = Instant::now() + timeout;
+        self.join_wifi(ssid, passphrase).await?;
+
+        loop {
+            // The hotspot can drop briefly while the radio switches networks
+            match self.get_wifi_connect_state().await {
+                Ok(WifiConnectState::Connected) => break,
+                Ok(WifiConnectState::WrongPassword) => {
+                    return Err(WiimError::InvalidResponse(format!(
+                        "The passphrase for '{ssid}' was rejected"
+                    )))
+                }
+                Ok(WifiConnectState::Failed) => {
+                    return Err(WiimError::InvalidResponse(format!(
+                        "The device failed to join '{ssid}'"
+                    )))
+                }
+                Ok(_) | Err(_) => {}
+            }
+            wait_or_give_up(deadline, timeout).await?;
+        }
+
+        loop {
+            if let Ok(status) = self.get_status_ex().await {
+                if let Some(ip) = status.apcli0.filter(|ip| !ip.is_empty() && ip != "0.0.0.0") {
+                    let mut device = self.clone();
+                    device.set_ip_address(&ip);
+                    return Ok(device);
+                }
+            }
+            wait_or_give_up(deadline, timeout).await?;
+        }
+    }
+
+    /// Wait until the device answers, such as after it joins a network or reboots
+    ///
+    /// # Errors
+    /// Returns `WiimError::Timeout` if it doesn't answer within `timeout`
+    pub async fn wait_until_reachable(&self, timeout: Duration) -> Result<()> {
+        let deadline = Instant::now() + timeout;
+        while self.test_connection().await.is_err() {
+            wait_or_give_up(deadline, timeout).await?;
+        }
+        Ok(())
+    }
+}
+
+async fn wait_or_give_up(deadline: Instant, timeout: Duration) -> Result<()> {
+    if Instant::now() + POLL_INTERVAL > deadline {
+        return Err(WiimError::Timeout(timeout));
+    }
+    crate::rt::sleep(POLL_INTERVAL).await;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_join_command() {
+        let ap = AccessPoint {
+            ssid: "Home".to_string(),
+            bssid: "8c:25:05:1c:41:40".to_string(),
+            channel: Some(36),
+            rssi: Some(-60),
+            auth: "WPA2PSK".to_string(),
+            encryption: "AES".to_string(),
+        };
+        assert_eq!(
+            join_command(&ap, "pass:word"),
+            "wlanConnectApEx:ssid=486F6D65:ch=36:auth=WPA2PSK:encry=AES:pwd=706173733A776F7264:chext=0"
+        );
+    }
+
+    #[test]
+    fn test_connect_state_parsing() {
+        assert_eq!(
+            WifiConnectState::parse("PROCESS").unwrap(),
+            WifiConnectState::Connecting
+        );
+        assert_eq!(
+            WifiConnectState::parse("OK\n").unwrap(),
+            WifiConnectState::Connected
+        );
+        assert_eq!(
+            WifiConnectState::parse("PAIRFAIL").unwrap(),
+            WifiConnectState::WrongPassword
+        );
+        assert!(WifiConnectState::parse("maybe").is_err());
+    }
+}
@@ -0,0 +1,34 @@
+//! Timers for both native builds (tokio) and the browser (the JS event loop)
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) use tokio::time::{sleep, Instant};
+#[cfg(target_arch = "wasm32")]
+pub(crate) use wasmtimer::{std::Instant, tokio::sleep};
+
+use crate::{Result, WiimError};
+use futures_util::future::{select, Either};
+use std::future::Future;
+use std::time::Duration;
+
+/// Run `future`, failing with `WiimError::Timeout` if it takes longer than `limit`
+pub(crate) async fn timeout<T>(
+    limit: Duration,
+    future: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    let future = std::pin::pin!(future);
+    let expired = std::pin::pin!(sleep(limit));
+    match select(future, expired).await {
+        Either::Left((result, _)) => result,
+        Either::Right(_) => Err(WiimError::Timeout(limit)),
+    }
+}
+
+/// Whether a failed request is worth retrying
+pub(crate) fn is_transient(error: &reqwest::Error) -> bool {
+    #[cfg(not(target_arch = "wasm32"))]
+    let connect = error.is_connect();
+    // Fetch doesn't tell connection failures apart from other request errors
+    #[cfg(target_arch = "wasm32")]
+    let connect = error.is_request();
+    connect || error.is_timeout()
+}
@@ -0,0 +1,212 @@
+//! Cleaning up track metadata before it reaches consumers
+//!
+//! Streaming services and radio stations send titles with HTML entities (`Rock &amp; Roll`),
+//! embedded newlines, control characters and UTF-8 that was decoded as Latin-1 somewhere
+//! along the way (`BeyoncÃ©`). Left alone these end up in status bars and break line-based
+//! or JSON output, so [`get_now_playing`](WiimClient::get_now_playing) cleans the title,
+//! artist and album according to the client's [`MetadataCleanup`].
+
+use crate::NowPlaying;
+#[cfg(doc)]
+use crate::WiimClient;
+
+/// Which cleanup steps are applied to track metadata
+///
+/// All steps are on by default; use [`MetadataCleanup::NONE`] to get the fields exactly
+/// as the device reports them.
+///
+/// # Examples
+/// ```
+/// use wiim_api::{MetadataCleanup, WiimClient};
+///
+/// // Keep line breaks and entities, but still repair mis-decoded UTF-8
+/// let client = WiimClient::builder("192.168.1.100")
+///     .metadata_cleanup(MetadataCleanup {
+///         html_entities: false,
+///         whitespace: false,
+///         ..MetadataCleanup::default()
+///     })
+///     .build()
+///     .expect("valid client configuration");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MetadataCleanup {
+    /// Repair UTF-8 that was decoded as Latin-1 and drop replacement characters
+    pub encoding: bool,
+    /// Decode HTML entities such as `&amp;`, `&#39;` and `&#x2013;`
+    pub html_entities: bool,
+    /// Turn newlines and tabs into spaces, collapse runs of spaces and trim the ends
+    pub whitespace: bool,
+    /// Remove control characters other than whitespace
+    pub control_chars: bool,
+    /// Treat empty values and the firmware's `unknow` placeholder as missing
+    pub placeholders: bool,
+}
+
+impl MetadataCleanup {
+    /// Leave metadata untouched
+    pub const NONE: MetadataCleanup = MetadataCleanup {
+        encoding: false,
+        html_entities: false,
+        whitespace: false,
+        control_chars: false,
+        placeholders: false,
+    };
+
+    /// Apply every enabled step to a single value
+    ///
+    /// Returns `None` when placeholder removal is on and nothing meaningful is left.
+    pub fn clean(&self, text: &str) -> Option<String> {
+        let mut text = text.to_string();
+        if self.encoding {
+            text = fix_encoding(&text);
+        }
+        if self.html_entities {
+            text = decode_entities(&text);
+        }
+        if self.control_chars {
+            text.retain(|c| !c.is_control() || c.is_whitespace());
+        }
+        if self.whitespace {
+            text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        }
+        if self.placeholders && is_placeholder(&text) {
+            return None;
+        }
+        Some(text)
+    }
+
+    fn clean_field(&self, field: Option<String>) -> Option<String> {
+        field.and_then(|text| self.clean(&text))
+    }
+}
+
+impl Default for MetadataCleanup {
+    fn default() -> Self {
+        MetadataCleanup {
+            encoding: true,
+            html_entities: true,
+            whitespace: true,
+            control_chars: true,
+            placeholders: true,
+        }
+    }
+}
+
+impl NowPlaying {
+    /// The same state with title, artist and album cleaned up
+    pub fn cleaned(mut self, cleanup: &MetadataCleanup) -> NowPlaying {
+        self.title = cleanup.clean_field(self.title);
+        self.artist = cleanup.clean_field(self.artist);
+        self.album = cleanup.clean_field(self.album);
+        self
+    }
+}
+
+fn is_placeholder(text: &str) -> bool {
+    let text = text.trim();
+    text.is_empty() || text.eq_ignore_ascii_case("unknow")
+}
+
+/// Undo UTF-8 that was read as Latin-1 (`CafÃ©` → `Café`) and drop U+FFFD
+///
+/// Only text made up entirely of Latin-1 characters whose bytes form valid UTF-8 is
+/// re-decoded, so genuine Latin-1 text such as `Café` is left as it is.
+fn fix_encoding(text: &str) -> String {
+    let repaired = if text.chars().any(|c| ('\u{80}'..='\u{ff}').contains(&c)) {
+        text.chars()
+            .map(|c| u8::try_from(u32::from(c)).ok())
+            .collect::<Option<Vec<u8>>>()
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+    } else {
+        None
+    };
+    let mut text = repaired.unwrap_or_else(|| text.to_string());
+    text.retain(|c| c != char::REPLACEMENT_CHARACTER);
+    text
+}
+
+/// Decode named and numeric HTML entities; unknown or malformed ones stay as they are
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest[1..]
+            .find(';')
+            .filter(|&end| end <= 10)
+            .and_then(|end| Some((entity_char(&rest[1..=end])?, end + 2)));
+        match entity {
+            Some((c, len)) => {
+                decoded.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+fn entity_char(name: &str) -> Option<char> {
+    if let Some(number) = name.strip_prefix('#') {
+        let code = match number.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => number.parse().ok()?,
+        };
+        return char::from_u32(code);
+    }
+    Some(match name {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => ' ',
+        "ndash" => '–',
+        "mdash" => '—',
+        "hellip" => '…',
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean_defaults() {
+        let cleanup = MetadataCleanup::default();
+        assert_eq!(
+            cleanup.clean("Rock &amp; Roll &#39;74&#x21;").as_deref(),
+            Some("Rock & Roll '74!")
+        );
+        assert_eq!(
+            cleanup.clean("  Live at\r\nWembley\t\u{7}").as_deref(),
+            Some("Live at Wembley")
+        );
+        assert_eq!(cleanup.clean("BeyoncÃ©").as_deref(), Some("Beyoncé"));
+        assert_eq!(cleanup.clean("Café").as_deref(), Some("Café"));
+        assert_eq!(
+            cleanup.clean("Sigur R\u{fffd}s").as_deref(),
+            Some("Sigur Rs")
+        );
+        assert_eq!(cleanup.clean("AC&DC & co;").as_deref(), Some("AC&DC & co;"));
+        assert_eq!(cleanup.clean("unknow"), None);
+        assert_eq!(cleanup.clean(" \n "), None);
+    }
+
+    #[test]
+    fn test_clean_none_keeps_text() {
+        let text = "Rock &amp; Roll\n";
+        assert_eq!(MetadataCleanup::NONE.clean(text).as_deref(), Some(text));
+        assert_eq!(
+            MetadataCleanup::NONE.clean("unknow").as_deref(),
+            Some("unknow")
+        );
+    }
+}
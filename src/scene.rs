@@ -0,0 +1,128 @@
+//! Capturing the device's settings so they can be put back later
+//!
+//! Automations that briefly take over the device, such as a doorbell chime played with
+//! `play_url`, capture a [`Scene`] first and apply it once they are done. Everything in a
+//! scene comes from a single `getPlayerStatus` request.
+
+use crate::{Input, LoopMode, PlaybackSource, PlayerStatus, Result, Volume, WiimClient};
+use serde::{Deserialize, Serialize};
+
+/// Volume, mute, source, EQ preset and loop mode at one moment
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Scene {
+    pub volume: Volume,
+    pub muted: bool,
+    pub source: PlaybackSource,
+    /// Index of the EQ preset, when the device reports one
+    pub eq: Option<u8>,
+    pub loop_mode: LoopMode,
+}
+
+impl Scene {
+    fn from_status(status: &PlayerStatus) -> Result<Self> {
+        Ok(Scene {
+            volume: WiimClient::parse_volume(&status.vol)?,
+            muted: WiimClient::parse_mute(&status.mute),
+            source: PlaybackSource::from_mode(&status.mode),
+            eq: status.eq.parse().ok(),
+            loop_mode: WiimClient::parse_loop_mode(&status.loop_mode).unwrap_or_default(),
+        })
+    }
+}
+
+impl WiimClient {
+    /// Capture the current volume, mute, source, EQ preset and loop mode
+    ///
+    /// # Errors
+    /// Returns `WiimError::InvalidResponse` if the device reports an invalid volume
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use wiim_api::{Volume, WiimClient};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> wiim_api::Result<()> {
+    ///     let client = WiimClient::new("192.168.1.100");
+    ///     let scene = client.capture_scene().await?;
+    ///
+    ///     client.set_volume(Volume::try_from(60)?).await?;
+    ///     client.play_url("http://192.168.1.10/doorbell.mp3").await?;
+    ///     // ... wait for the chime to finish ...
+    ///
+    ///     client.apply_scene(&scene).await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn capture_scene(&self) -> Result<Scene> {
+        Scene::from_status(&self.get_player_status().await?)
+    }
+
+    /// Put the device back into a captured scene
+    ///
+    /// A physical input (line-in, optical, HDMI, ...) is selected again. Network sources
+    /// can't be reselected, since the scene doesn't record what was streaming. The input
+    /// is switched first, because switching can change the volume.
+    ///
+    /// # Errors
+    /// Returns `WiimError::Unsupported` if the scene's input isn't on this device
+    pub async fn apply_scene(&self, scene: &Scene) -> Result<()> {
+        let current = self.capture_scene().await?;
+
+        if current.source != scene.source {
+            if let Some(input) = Input::from_source(scene.source) {
+                self.select_input(input).await?;
+            }
+        }
+        if let Some(eq) = scene.eq.filter(|eq| current.eq != Some(*eq)) {
+            self.send_command(&format!("setPlayerCmd:equalizer:{eq}"))
+                .await?;
+        }
+        if current.loop_mode != scene.loop_mode {
+            self.set_loop_mode(scene.loop_mode).await?;
+        }
+        self.set_volume(scene.volume).await?;
+        if scene.muted {
+            self.mute().await
+        } else {
+            self.unmute().await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RepeatMode;
+
+    #[test]
+    fn test_scene_from_status() {
+        let status: PlayerStatus = serde_json::from_value(serde_json::json!({
+            "type": "0",
+            "mode": "43",
+            "loop": "2",
+            "eq": "3",
+            "status": "play",
+            "curpos": "0",
+            "totlen": "0",
+            "vol": "35",
+            "mute": "1",
+        }))
+        .unwrap();
+        let scene = Scene::from_status(&status).unwrap();
+        assert_eq!(
+            scene,
+            Scene {
+                volume: Volume::saturating(35),
+                muted: true,
+                source: PlaybackSource::Optical,
+                eq: Some(3),
+                loop_mode: LoopMode {
+                    repeat: RepeatMode::All,
+                    shuffle: true
+                },
+            }
+        );
+        assert_eq!(Input::from_source(scene.source), Some(Input::Optical));
+        assert_eq!(Input::from_source(PlaybackSource::Network), None);
+    }
+}
@@ -0,0 +1,238 @@
+//! Stereo pairs: two devices in one multiroom group, one playing left and one right
+//!
+//! The partner joins the leader's group with `ConnectMasterAp:JoinGroupMaster`. The
+//! leader's own channel is set with `setPlayerCmd:ch` and the partner's through the
+//! leader with `multiroom:SlaveChannel`. Both report their channel back, in the player
+//! status `ch` field and the follower list, which is how the result is verified.
+
+use crate::rt::Instant;
+use crate::watch::{next_delay, FIRST_POLL_DELAY};
+use crate::{GroupFollower, Result, WiimClient, WiimError};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::Duration;
+
+/// How long to wait for the partner to show up in the leader's group
+const JOIN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The audio channel a device plays
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Channel {
+    /// Both channels, the normal setting
+    #[default]
+    Stereo,
+    Left,
+    Right,
+}
+
+impl Channel {
+    /// Decode the player status `ch` / follower `channel` value
+    pub fn from_code(code: u8) -> Option<Self> {
+        match code {
+            0 => Some(Channel::Stereo),
+            1 => Some(Channel::Left),
+            2 => Some(Channel::Right),
+            _ => None,
+        }
+    }
+
+    pub fn code(&self) -> u8 {
+        match self {
+            Channel::Stereo => 0,
+            Channel::Left => 1,
+            Channel::Right => 2,
+        }
+    }
+
+    /// The other side of a pair; stereo stays stereo
+    pub fn opposite(&self) -> Self {
+        match self {
+            Channel::Stereo => Channel::Stereo,
+            Channel::Left => Channel::Right,
+            Channel::Right => Channel::Left,
+        }
+    }
+}
+
+impl fmt::Display for Channel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Channel::Stereo => write!(f, "stereo"),
+            Channel::Left => write!(f, "left"),
+            Channel::Right => write!(f, "right"),
+        }
+    }
+}
+
+/// The bare address of a client, without scheme, port or path
+fn host(client: &WiimClient) -> &str {
+    let address = client.get_ip_address();
+    let address = address.split_once("://").map_or(address, |(_, rest)| rest);
+    address.split(['/', ':']).next().unwrap_or(address)
+}
+
+/// Check that `followers` contains `partner_host` playing `channel`
+fn verify_partner(followers: &[GroupFollower], partner_host: &str, channel: Channel) -> Result<()> {
+    let partner = followers
+        .iter()
+        .find(|follower| follower.ip.as_deref() == Some(partner_host))
+        .ok_or_else(|| WiimError::InvalidResponse(format!("{partner_host} is not in the group")))?;
+    match partner.channel.and_then(Channel::from_code) {
+        Some(reported) if reported == channel => Ok(()),
+        reported => Err(WiimError::InvalidResponse(format!(
+            "{partner_host} plays {} instead of {channel}",
+            reported.map_or("an unknown channel".to_string(), |c| c.to_string())
+        ))),
+    }
+}
+
+impl WiimClient {
+    /// Get the channel this device plays
+    ///
+    /// # Errors
+    /// Returns `WiimError::InvalidResponse` if the device reports an unknown channel
+    pub async fn get_channel(&self) -> Result<Channel> {
+        let status = self.get_player_status().await?;
+        status
+            .ch
+            .parse()
+            .ok()
+            .and_then(Channel::from_code)
+            .ok_or_else(|| {
+                WiimError::InvalidResponse(format!("Invalid channel value: {}", status.ch))
+            })
+    }
+
+    /// Pair this device with `partner` as a stereo pair, this one leading and playing
+    /// `channel` and the partner playing the other
+    ///
+    /// The partner leaves any group it is in. Once both channels are set, the pairing is
+    /// read back from both devices.
+    ///
+    /// # Errors
+    /// Returns `WiimError::InvalidResponse` if `channel` is stereo or the devices report a
+    /// different pairing afterwards, and `WiimError::Timeout` if the partner doesn't join
+    /// within 30 seconds
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use wiim_api::{Channel, WiimClient};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> wiim_api::Result<()> {
+    ///     let left = WiimClient::new("192.168.1.100");
+    ///     let right = WiimClient::new("192.168.1.101");
+    ///     left.create_stereo_pair(&right, Channel::Left).await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn create_stereo_pair(&self, partner: &WiimClient, channel: Channel) -> Result<()> {
+        if channel == Channel::Stereo {
+            return Err(WiimError::InvalidResponse(
+                "A stereo pair needs the leader on the left or right channel".to_string(),
+            ));
+        }
+        let partner_host = host(partner);
+
+        partner
+            .send_command(&format!(
+                "ConnectMasterAp:JoinGroupMaster:eth{}:wifi0.0.0.0",
+                host(self)
+            ))
+            .await?;
+        self.wait_for_follower(partner_host).await?;
+
+        self.send_command(&format!("setPlayerCmd:ch:{}", channel.code()))
+            .await?;
+        self.send_command(&format!(
+            "multiroom:SlaveChannel:{partner_host}:{}",
+            channel.opposite().code()
+        ))
+        .await?;
+
+        let reported = self.get_channel().await?;
+        if reported != channel {
+            return Err(WiimError::InvalidResponse(format!(
+                "The leader plays {reported} instead of {channel}"
+            )));
+        }
+        verify_partner(
+            &self.get_group_followers().await?,
+            partner_host,
+            channel.opposite(),
+        )
+    }
+
+    /// Split a stereo pair led by this device: both play stereo again and the group is
+    /// dissolved
+    pub async fn break_stereo_pair(&self) -> Result<()> {
+        for follower in self.get_group_followers().await? {
+            if let Some(ip) = follower.ip {
+                let command = format!("multiroom:SlaveChannel:{ip}:{}", Channel::Stereo.code());
+                self.send_command(&command).await?;
+            }
+        }
+        self.send_command(&format!("setPlayerCmd:ch:{}", Channel::Stereo.code()))
+            .await?;
+        self.send_command("multiroom:Ungroup").await?;
+        Ok(())
+    }
+
+    async fn wait_for_follower(&self, follower_host: &str) -> Result<()> {
+        let deadline = Instant::now() + JOIN_TIMEOUT;
+        let mut delay = FIRST_POLL_DELAY;
+        loop {
+            if let Ok(followers) = self.get_group_followers().await {
+                if followers
+                    .iter()
+                    .any(|follower| follower.ip.as_deref() == Some(follower_host))
+                {
+                    return Ok(());
+                }
+            }
+            if Instant::now() + delay > deadline {
+                return Err(WiimError::Timeout(JOIN_TIMEOUT));
+            }
+            crate::rt::sleep(delay).await;
+            delay = next_delay(delay);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_channel_codes() {
+        for channel in [Channel::Stereo, Channel::Left, Channel::Right] {
+            assert_eq!(Channel::from_code(channel.code()), Some(channel));
+        }
+        assert_eq!(Channel::from_code(3), None);
+        assert_eq!(Channel::Left.opposite(), Channel::Right);
+    }
+
+    #[test]
+    fn test_host() {
+        assert_eq!(host(&WiimClient::new("192.168.1.100")), "192.168.1.100");
+        assert_eq!(
+            host(&WiimClient::new("http://192.168.1.100:8080/")),
+            "192.168.1.100"
+        );
+    }
+
+    #[test]
+    fn test_verify_partner() {
+        let followers: Vec<GroupFollower> =
+            serde_json::from_str(r#"[{"name":"Right","ip":"192.168.1.101","channel":2}]"#).unwrap();
+        assert!(verify_partner(&followers, "192.168.1.101", Channel::Right).is_ok());
+
+        let error = verify_partner(&followers, "192.168.1.101", Channel::Left).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Invalid response: 192.168.1.101 plays right instead of left"
+        );
+        assert!(verify_partner(&followers, "192.168.1.102", Channel::Right).is_err());
+    }
+}
@@ -0,0 +1,120 @@
+//! Responses handed out in chunks as they arrive
+//!
+//! Most answers are a few hundred bytes, but `getLocalPlayList` on a full USB stick and
+//! the system log run to megabytes. Streaming them keeps memory bounded on small hosts.
+//! Not available in the browser, like [`SyslogDownload`](crate::SyslogDownload).
+
+use crate::{rt, Result, WiimClient, WiimError, RETRY_BACKOFF};
+use bytes::Bytes;
+use futures_util::future::{self, Either};
+use futures_util::stream::{self, Stream};
+
+/// A response from a cassette, which is already in memory
+type Recorded = stream::Once<future::Ready<Result<Bytes>>>;
+
+/// The body of a response, chunk by chunk; ends after the first error
+pub(crate) fn body_stream(response: reqwest::Response) -> impl Stream<Item = Result<Bytes>> {
+    stream::try_unfold(response, |mut response| async move {
+        let chunk = response.chunk().await?;
+        Ok::<_, WiimError>(chunk.map(|chunk| (chunk, response)))
+    })
+}
+
+impl WiimClient {
+    /// Send an arbitrary LinkPlay API command and stream the raw response body
+    ///
+    /// Like [`send_raw_command`](Self::send_raw_command), but the body isn't held in
+    /// memory; only connecting is retried. With a cassette the recorded response comes
+    /// as a single chunk.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use futures_util::StreamExt;
+    /// use wiim_api::WiimClient;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> wiim_api::Result<()> {
+    ///     let client = WiimClient::new("192.168.1.100");
+    ///     let mut body = Box::pin(client.stream_command("getLocalPlayList").await?);
+    ///     let mut size = 0;
+    ///     while let Some(chunk) = body.next().await {
+    ///         size += chunk?.len();
+    ///     }
+    ///     println!("{size} bytes");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn stream_command(
+        &self,
+        command: &str,
+    ) -> Result<impl Stream<Item = Result<Bytes>> + 'static> {
+        #[cfg(feature = "cassette")]
+        if self.cassette.is_some() {
+            let body = self.fetch(command).await?;
+            let recorded: Recorded = stream::once(future::ready(Ok(Bytes::from(body))));
+            return Ok(Either::Left(recorded));
+        }
+        let url = format!("{}/httpapi.asp?command={command}", self.base_url);
+        let mut attempt = 0;
+        let response = loop {
+            match self.get(&url).send().await {
+                Ok(response) => break response,
+                Err(e) => {
+                    self.check_handshake()?;
+                    if attempt < self.retries && rt::is_transient(&e) {
+                        attempt += 1;
+                        rt::sleep(RETRY_BACKOFF * attempt).await;
+                    } else {
+                        return Err(e.into());
+                    }
+                }
+            }
+        };
+        self.check_response(&response)?;
+        Ok(Either::<Recorded, _>::Right(body_stream(response)))
+    }
+
+    /// Stream the `getLocalPlayList` JSON of a USB or local source without buffering it
+    ///
+    /// Use [`get_track_list`](Self::get_track_list) for the parsed list when it fits in
+    /// memory.
+    pub async fn stream_track_list(&self) -> Result<impl Stream<Item = Result<Bytes>> + 'static> {
+        self.stream_command("getLocalPlayList").await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::TryStreamExt;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_stream_command() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let body = "x".repeat(200_000);
+        let served = body.clone();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = [0; 1024];
+            let _ = socket.read(&mut request).await.unwrap();
+            let head = format!(
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n",
+                served.len()
+            );
+            socket.write_all(head.as_bytes()).await.unwrap();
+            socket.write_all(served.as_bytes()).await.unwrap();
+        });
+
+        let client = WiimClient::builder(&format!("http://{address}"))
+            .build()
+            .unwrap();
+        let stream = client.stream_command("getLocalPlayList").await.unwrap();
+        // The stream owns the response, so it outlives the client
+        drop(client);
+        let chunks: Vec<Bytes> = stream.try_collect().await.unwrap();
+        assert_eq!(chunks.concat(), body.as_bytes());
+    }
+}
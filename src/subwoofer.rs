@@ -0,0 +1,213 @@
+//! Subwoofer output settings on models with a sub out (WiiM Amp, Ultra and Pro Plus)
+//!
+//! `getSubLPF` reports the low-pass filter and level applied to the sub output; each
+//! setting is changed on its own with `setSubLPF:<key>:<value>`. Other models answer
+//! these commands with "unknown command", so they are checked against [`Capabilities`]
+//! first. The model is recognised from the `project` name, like the HDMI input.
+
+use crate::compat::json_number;
+use crate::{Capabilities, Feature, Result, StatusEx, WiimClient, WiimError};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::ops::RangeInclusive;
+
+/// Crossover frequencies the firmware accepts, in Hz
+pub const SUB_CROSSOVER_HZ: RangeInclusive<u16> = 30..=250;
+/// Sub level relative to the main speakers, in dB
+pub const SUB_LEVEL_DB: RangeInclusive<i8> = -15..=15;
+
+/// What the sub output is doing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubwooferSettings {
+    /// Whether the sub output is on
+    pub enabled: bool,
+    /// Low-pass crossover frequency in Hz
+    pub crossover_hz: u16,
+    /// Level in dB relative to the main speakers
+    pub level_db: i8,
+    /// Whether the phase is inverted (180°)
+    pub phase_inverted: bool,
+}
+
+/// `getSubLPF` response; the firmware sends numbers, older versions strings
+#[derive(Debug, Deserialize)]
+struct SubLpf {
+    status: Value,
+    cross: Value,
+    level: Value,
+    phase: Value,
+}
+
+impl TryFrom<SubLpf> for SubwooferSettings {
+    type Error = WiimError;
+
+    fn try_from(raw: SubLpf) -> Result<Self> {
+        let field = |value: &Value, name: &str| {
+            json_number(value).ok_or_else(|| {
+                WiimError::InvalidResponse(format!("Invalid subwoofer {name} value: {value}"))
+            })
+        };
+        let crossover = field(&raw.cross, "crossover")?;
+        let level = field(&raw.level, "level")?;
+        Ok(SubwooferSettings {
+            enabled: field(&raw.status, "status")? != 0,
+            crossover_hz: u16::try_from(crossover).map_err(|_| {
+                WiimError::InvalidResponse(format!(
+                    "Invalid subwoofer crossover value: {crossover}"
+                ))
+            })?,
+            level_db: i8::try_from(level).map_err(|_| {
+                WiimError::InvalidResponse(format!("Invalid subwoofer level value: {level}"))
+            })?,
+            phase_inverted: field(&raw.phase, "phase")? == 180,
+        })
+    }
+}
+
+impl StatusEx {
+    /// Whether this model has a subwoofer output
+    pub fn has_sub_out(&self) -> bool {
+        self.project.as_deref().is_some_and(|project| {
+            let project = project.to_ascii_lowercase().replace(['_', '-', ' '], "");
+            project.contains("amp") || project.contains("ultra") || project.contains("proplus")
+        })
+    }
+}
+
+impl WiimClient {
+    async fn ensure_sub_out(&self) -> Result<()> {
+        Capabilities::from(&self.get_status_ex().await?).ensure(Feature::SubwooferOutput)
+    }
+
+    /// Get the subwoofer output settings
+    ///
+    /// # Errors
+    /// Returns `WiimError::Unsupported` if the model has no sub out
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use wiim_api::WiimClient;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> wiim_api::Result<()> {
+    ///     let client = WiimClient::new("192.168.1.100");
+    ///     let sub = client.get_subwoofer().await?;
+    ///     println!("Crossover {} Hz, level {} dB", sub.crossover_hz, sub.level_db);
+    ///     client.set_sub_crossover(80).await?;
+    ///     client.set_sub_level(-3).await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn get_subwoofer(&self) -> Result<SubwooferSettings> {
+        self.ensure_sub_out().await?;
+        let response = self.send_command("getSubLPF").await?;
+        let raw: SubLpf = serde_json::from_str(&response)?;
+        SubwooferSettings::try_from(raw)
+    }
+
+    /// Turn the sub output on or off
+    ///
+    /// # Errors
+    /// Returns `WiimError::Unsupported` if the model has no sub out
+    pub async fn set_subwoofer_enabled(&self, enabled: bool) -> Result<()> {
+        self.ensure_sub_out().await?;
+        self.send_command(&format!("setSubLPF:status:{}", u8::from(enabled)))
+            .await?;
+        Ok(())
+    }
+
+    /// Set the low-pass crossover frequency (30-250 Hz)
+    ///
+    /// # Errors
+    /// Returns `WiimError::InvalidResponse` if the frequency is out of range, or
+    /// `WiimError::Unsupported` if the model has no sub out
+    pub async fn set_sub_crossover(&self, hz: u16) -> Result<()> {
+        if !SUB_CROSSOVER_HZ.contains(&hz) {
+            return Err(WiimError::InvalidResponse(format!(
+                "Crossover must be between {} and {} Hz, got {hz}",
+                SUB_CROSSOVER_HZ.start(),
+                SUB_CROSSOVER_HZ.end()
+            )));
+        }
+        self.ensure_sub_out().await?;
+        self.send_command(&format!("setSubLPF:cross:{hz}")).await?;
+        Ok(())
+    }
+
+    /// Set the sub level relative to the main speakers (-15 to +15 dB)
+    ///
+    /// # Errors
+    /// Returns `WiimError::InvalidResponse` if the level is out of range, or
+    /// `WiimError::Unsupported` if the model has no sub out
+    pub async fn set_sub_level(&self, db: i8) -> Result<()> {
+        if !SUB_LEVEL_DB.contains(&db) {
+            return Err(WiimError::InvalidResponse(format!(
+                "Sub level must be between {} and {} dB, got {db}",
+                SUB_LEVEL_DB.start(),
+                SUB_LEVEL_DB.end()
+            )));
+        }
+        self.ensure_sub_out().await?;
+        self.send_command(&format!("setSubLPF:level:{db}")).await?;
+        Ok(())
+    }
+
+    /// Set the sub phase to 0° or, inverted, 180°
+    ///
+    /// # Errors
+    /// Returns `WiimError::Unsupported` if the model has no sub out
+    pub async fn set_sub_phase(&self, inverted: bool) -> Result<()> {
+        self.ensure_sub_out().await?;
+        let degrees = if inverted { 180 } else { 0 };
+        self.send_command(&format!("setSubLPF:phase:{degrees}"))
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sub_lpf_parsing() {
+        let raw: SubLpf =
+            serde_json::from_str(r#"{"status":1,"cross":80,"phase":180,"level":-3,"sub_delay":0}"#)
+                .unwrap();
+        assert_eq!(
+            SubwooferSettings::try_from(raw).unwrap(),
+            SubwooferSettings {
+                enabled: true,
+                crossover_hz: 80,
+                level_db: -3,
+                phase_inverted: true,
+            }
+        );
+
+        let raw: SubLpf =
+            serde_json::from_str(r#"{"status":"0","cross":"120","phase":"0","level":"2"}"#)
+                .unwrap();
+        let settings = SubwooferSettings::try_from(raw).unwrap();
+        assert!(!settings.enabled);
+        assert_eq!(settings.crossover_hz, 120);
+    }
+
+    #[test]
+    fn test_has_sub_out() {
+        let status = |project: &str| -> StatusEx {
+            serde_json::from_value(serde_json::json!({ "project": project })).unwrap()
+        };
+        assert!(status("WiiM_Amp_4layer").has_sub_out());
+        assert!(status("WiiM_Ultra").has_sub_out());
+        assert!(status("WiiM_Pro_Plus").has_sub_out());
+        assert!(!status("WiiM_Pro").has_sub_out());
+        assert!(!status("Muzo_Mini").has_sub_out());
+    }
+
+    #[tokio::test]
+    async fn test_set_sub_crossover_out_of_range() {
+        let client = WiimClient::new("192.168.1.100");
+        let error = client.set_sub_crossover(20).await.unwrap_err();
+        assert!(matches!(error, WiimError::InvalidResponse(_)));
+    }
+}
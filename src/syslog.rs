@@ -0,0 +1,82 @@
+//! Downloading the device's system log
+//!
+//! `getsyslog` makes the device gather its logs into `/data/sys.log` on its web server,
+//! which is then fetched like any other file. Logs run to a few megabytes, so the body is
+//! handed out in chunks as it arrives. Not available in the browser, where the device's
+//! web server can't be read across origins anyway.
+
+use crate::streaming::body_stream;
+use crate::{Result, WiimClient};
+use bytes::Bytes;
+use futures_util::stream::Stream;
+
+/// A system log being downloaded
+///
+/// # Examples
+/// ```no_run
+/// use wiim_api::WiimClient;
+///
+/// #[tokio::main]
+/// async fn main() -> wiim_api::Result<()> {
+///     let client = WiimClient::new("192.168.1.100");
+///     let mut download = client.download_syslog().await?;
+///     let mut log = Vec::new();
+///     while let Some(chunk) = download.next_chunk().await? {
+///         log.extend_from_slice(&chunk);
+///         if let Some(total) = download.total_bytes() {
+///             eprint!("\r{} of {total} bytes", download.received_bytes());
+///         }
+///     }
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct SyslogDownload {
+    response: reqwest::Response,
+    received: u64,
+}
+
+impl SyslogDownload {
+    /// Size of the whole log, when the device sends one
+    pub fn total_bytes(&self) -> Option<u64> {
+        self.response.content_length()
+    }
+
+    /// How much has arrived so far
+    pub fn received_bytes(&self) -> u64 {
+        self.received
+    }
+
+    /// The next piece of the log, or `None` once it is complete
+    pub async fn next_chunk(&mut self) -> Result<Option<Vec<u8>>> {
+        let chunk = self.response.chunk().await?;
+        if let Some(chunk) = &chunk {
+            self.received += chunk.len() as u64;
+        }
+        Ok(chunk.map(|chunk| chunk.to_vec()))
+    }
+
+    /// The rest of the log as a stream, for writing straight to a file or socket
+    pub fn into_stream(self) -> impl Stream<Item = Result<Bytes>> {
+        body_stream(self.response)
+    }
+}
+
+impl WiimClient {
+    /// Have the device write out its system log and start downloading it
+    pub async fn download_syslog(&self) -> Result<SyslogDownload> {
+        self.send_command("getsyslog").await?;
+        let url = format!("{}/data/sys.log", self.base_url);
+        let response = match self.get(&url).send().await {
+            Ok(response) => response.error_for_status()?,
+            Err(e) => {
+                self.check_handshake()?;
+                return Err(e.into());
+            }
+        };
+        Ok(SyslogDownload {
+            response,
+            received: 0,
+        })
+    }
+}
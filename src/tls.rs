@@ -0,0 +1,379 @@
+//! Checking who answers over HTTPS
+//!
+//! WiiM devices present self-signed certificates that don't name their address, so by
+//! default any certificate is accepted. A deployment that wants more can trust a CA (or
+//! the device's own certificate) with [`WiimClientBuilder::ca_certificate`], pin the
+//! certificate's SHA-256 fingerprint with [`WiimClientBuilder::pin_certificate`], or let
+//! [`WiimClientBuilder::trust_on_first_use`] record the fingerprint the first time and
+//! hold the device to it afterwards.
+//!
+//! CAs and pins are both checked during the handshake, so an impostor is turned away
+//! before it sees a command.
+
+use crate::{Result, WiimClient, WiimError};
+use ring::digest::{digest, SHA256};
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::WebPkiSupportedAlgorithms;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{CertificateError, DigitallySignedStruct, RootCertStore, SignatureScheme};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Where the expected certificate comes from, as given to the builder
+#[derive(Debug, Clone)]
+pub(crate) enum PinSource {
+    Fingerprint(String),
+    File(PathBuf),
+}
+
+/// The fingerprint a device must present, once known
+#[derive(Debug)]
+pub(crate) struct CertificatePin {
+    expected: Mutex<Option<String>>,
+    /// Where a fingerprint seen for the first time is saved
+    store: Option<PathBuf>,
+    /// Why the last handshake was refused, until the failed request picks it up
+    rejection: Mutex<Option<WiimError>>,
+}
+
+impl CertificatePin {
+    pub(crate) fn new(source: &PinSource) -> Result<Self> {
+        match source {
+            PinSource::Fingerprint(fingerprint) => Ok(CertificatePin {
+                expected: Mutex::new(Some(normalize(fingerprint)?)),
+                store: None,
+                rejection: Mutex::new(None),
+            }),
+            PinSource::File(path) => {
+                let expected = match std::fs::read_to_string(path) {
+                    Ok(content) => Some(normalize(&content)?),
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+                    Err(e) => {
+                        return Err(WiimError::InvalidResponse(format!(
+                            "Cannot read certificate pin {}: {e}",
+                            path.display()
+                        )))
+                    }
+                };
+                Ok(CertificatePin {
+                    expected: Mutex::new(expected),
+                    store: Some(path.clone()),
+                    rejection: Mutex::new(None),
+                })
+            }
+        }
+    }
+
+    /// Compare the certificate the device presents against the pin, recording it if
+    /// this is the first one seen
+    pub(crate) fn check(&self, certificate: Option<&[u8]>) -> Result<()> {
+        let presented = certificate.map(fingerprint);
+        let mut expected = self.expected.lock().unwrap_or_else(|e| e.into_inner());
+        match (expected.as_deref(), presented) {
+            (Some(expected), Some(presented)) if expected == presented => Ok(()),
+            (None, Some(presented)) => {
+                if let Some(path) = &self.store {
+                    save(path, &presented)?;
+                }
+                *expected = Some(presented);
+                Ok(())
+            }
+            (expected, presented) => Err(WiimError::CertificateMismatch {
+                expected: expected.unwrap_or("a certificate").to_string(),
+                presented: presented.unwrap_or_else(|| "none (plain HTTP)".to_string()),
+            }),
+        }
+    }
+
+    /// Why the last handshake was refused, if it was
+    pub(crate) fn take_rejection(&self) -> Option<WiimError> {
+        self.rejection
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take()
+    }
+}
+
+/// Holds the device to its pin during the handshake, and to a CA if one is given as well
+#[derive(Debug)]
+struct PinVerifier {
+    pin: Arc<CertificatePin>,
+    roots: Option<RootCertStore>,
+    algorithms: WebPkiSupportedAlgorithms,
+}
+
+impl ServerCertVerifier for PinVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> std::result::Result<ServerCertVerified, rustls::Error> {
+        // Device certificates don't name their address, so only the chain is checked
+        if let Some(roots) = &self.roots {
+            let certificate = rustls::server::ParsedCertificate::try_from(end_entity)?;
+            rustls::client::verify_server_cert_signed_by_trust_anchor(
+                &certificate,
+                roots,
+                intermediates,
+                now,
+                self.algorithms.all,
+            )?;
+        }
+        match self.pin.check(Some(end_entity.as_ref())) {
+            Ok(()) => Ok(ServerCertVerified::assertion()),
+            Err(e) => {
+                *self.pin.rejection.lock().unwrap_or_else(|e| e.into_inner()) = Some(e);
+                Err(rustls::Error::InvalidCertificate(
+                    CertificateError::ApplicationVerificationFailure,
+                ))
+            }
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        certificate: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(message, certificate, dss, &self.algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        certificate: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(message, certificate, dss, &self.algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.algorithms.supported_schemes()
+    }
+}
+
+/// TLS settings for a pinned client, optionally also trusting only `ca` (PEM)
+pub(crate) fn pinned_config(
+    pin: Arc<CertificatePin>,
+    ca: Option<&[u8]>,
+) -> Result<rustls::ClientConfig> {
+    let invalid_ca = |e: &dyn std::fmt::Display| {
+        WiimError::InvalidResponse(format!("Invalid CA certificate: {e}"))
+    };
+    let roots = match ca {
+        Some(pem) => {
+            let mut roots = RootCertStore::empty();
+            for certificate in CertificateDer::pem_slice_iter(pem) {
+                roots
+                    .add(certificate.map_err(|e| invalid_ca(&e))?)
+                    .map_err(|e| invalid_ca(&e))?;
+            }
+            if roots.is_empty() {
+                return Err(invalid_ca(&"no certificate in PEM"));
+            }
+            Some(roots)
+        }
+        None => None,
+    };
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let verifier = PinVerifier {
+        pin,
+        roots,
+        algorithms: provider.signature_verification_algorithms,
+    };
+    Ok(rustls::ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .map_err(|e| WiimError::InvalidResponse(format!("TLS setup failed: {e}")))?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(verifier))
+        .with_no_client_auth())
+}
+
+/// SHA-256 of a DER certificate as colon-separated uppercase hex, as `openssl x509
+/// -fingerprint -sha256` prints it
+pub(crate) fn fingerprint(certificate: &[u8]) -> String {
+    digest(&SHA256, certificate)
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{byte:02X}"))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+/// Accept a fingerprint with or without colons, in either case
+fn normalize(fingerprint: &str) -> Result<String> {
+    let hex: String = fingerprint
+        .chars()
+        .filter(|c| *c != ':' && !c.is_whitespace())
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    if hex.len() != 64 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(WiimError::InvalidResponse(format!(
+            "Invalid SHA-256 certificate fingerprint: {}",
+            fingerprint.trim()
+        )));
+    }
+    Ok(hex
+        .as_bytes()
+        .chunks(2)
+        .map(|pair| String::from_utf8_lossy(pair).into_owned())
+        .collect::<Vec<_>>()
+        .join(":"))
+}
+
+fn save(path: &Path, fingerprint: &str) -> Result<()> {
+    let write = || -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, format!("{fingerprint}\n"))
+    };
+    write().map_err(|e| {
+        WiimError::InvalidResponse(format!(
+            "Cannot save certificate pin {}: {e}",
+            path.display()
+        ))
+    })
+}
+
+impl WiimClient {
+    /// SHA-256 fingerprint of the certificate the device presents, for pinning it
+    ///
+    /// The certificate is read whether or not it matches a pin already set.
+    ///
+    /// # Errors
+    /// Returns `WiimError::InvalidResponse` if the device answers over plain HTTP
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use wiim_api::WiimClient;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> wiim_api::Result<()> {
+    ///     let fingerprint = WiimClient::new("192.168.1.100").certificate_fingerprint().await?;
+    ///     let client = WiimClient::builder("192.168.1.100")
+    ///         .pin_certificate(fingerprint)
+    ///         .build()?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn certificate_fingerprint(&self) -> Result<String> {
+        let url = format!("{}/httpapi.asp?command=getStatusEx", self.base_url);
+        let response = match self.get(&url).send().await {
+            Ok(response) => response,
+            Err(e) => {
+                return match self.pin.as_ref().and_then(|pin| pin.take_rejection()) {
+                    Some(WiimError::CertificateMismatch { presented, .. }) => Ok(presented),
+                    Some(rejection) => Err(rejection),
+                    None => Err(e.into()),
+                }
+            }
+        };
+        response
+            .extensions()
+            .get::<reqwest::tls::TlsInfo>()
+            .and_then(|info| info.peer_certificate())
+            .map(fingerprint)
+            .ok_or_else(|| {
+                WiimError::InvalidResponse("The device did not present a certificate".into())
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CERTIFICATE: &[u8] = b"not really DER, but any bytes hash";
+
+    #[test]
+    fn test_fingerprint_format() {
+        let printed = fingerprint(CERTIFICATE);
+        assert_eq!(printed.len(), 32 * 3 - 1);
+        assert_eq!(
+            normalize(&printed.replace(':', "").to_lowercase()).unwrap(),
+            printed
+        );
+        assert!(normalize("AB:CD").is_err());
+        assert!(normalize(&"ZZ".repeat(32)).is_err());
+    }
+
+    #[test]
+    fn test_pinned_fingerprint() {
+        let pin = CertificatePin::new(&PinSource::Fingerprint(fingerprint(CERTIFICATE))).unwrap();
+        assert!(pin.check(Some(CERTIFICATE)).is_ok());
+        assert!(matches!(
+            pin.check(Some(b"someone else")),
+            Err(WiimError::CertificateMismatch { .. })
+        ));
+        assert!(matches!(
+            pin.check(None),
+            Err(WiimError::CertificateMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_verifier_refuses_handshake() {
+        let pin = Arc::new(
+            CertificatePin::new(&PinSource::Fingerprint(fingerprint(CERTIFICATE))).unwrap(),
+        );
+        let verifier = PinVerifier {
+            pin: pin.clone(),
+            roots: None,
+            algorithms: rustls::crypto::ring::default_provider().signature_verification_algorithms,
+        };
+        let verify = |certificate: &'static [u8]| {
+            verifier.verify_server_cert(
+                &CertificateDer::from(certificate),
+                &[],
+                &ServerName::try_from("192.168.1.100").unwrap(),
+                &[],
+                UnixTime::now(),
+            )
+        };
+
+        assert!(verify(CERTIFICATE).is_ok());
+        assert!(pin.take_rejection().is_none());
+        assert!(verify(b"someone else").is_err());
+        assert!(matches!(
+            pin.take_rejection(),
+            Some(WiimError::CertificateMismatch { presented, .. })
+                if presented == fingerprint(b"someone else")
+        ));
+        assert!(pin.take_rejection().is_none());
+    }
+
+    #[test]
+    fn test_pinned_config_rejects_bad_ca() {
+        let pin = Arc::new(
+            CertificatePin::new(&PinSource::Fingerprint(fingerprint(CERTIFICATE))).unwrap(),
+        );
+        assert!(pinned_config(pin.clone(), None).is_ok());
+        assert!(pinned_config(pin, Some(b"not a certificate")).is_err());
+    }
+
+    #[test]
+    fn test_trust_on_first_use() {
+        let dir = std::env::temp_dir().join(format!("wiim-pin-{}", std::process::id()));
+        let path = dir.join("kitchen.pin");
+        let _ = std::fs::remove_file(&path);
+
+        let pin = CertificatePin::new(&PinSource::File(path.clone())).unwrap();
+        assert!(pin.check(Some(CERTIFICATE)).is_ok());
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap().trim(),
+            fingerprint(CERTIFICATE)
+        );
+
+        // A new client holds the device to the recorded certificate
+        let pin = CertificatePin::new(&PinSource::File(path.clone())).unwrap();
+        assert!(pin.check(Some(CERTIFICATE)).is_ok());
+        assert!(pin.check(Some(b"someone else")).is_err());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
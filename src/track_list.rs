@@ -0,0 +1,234 @@
+//! The local track list entry by entry, as it downloads
+//!
+//! `getLocalPlayList` takes no range, so a big USB library comes back as one JSON document
+//! of several megabytes. [`WiimClient::track_entries`] reads it as it arrives and hands out
+//! each entry as soon as it is complete, holding at most one entry in memory. Not available
+//! in the browser, like the other streaming commands.
+
+use crate::{Result, TrackEntry, WiimClient, WiimError};
+use bytes::Bytes;
+use futures_util::stream::{self, Stream, StreamExt};
+use std::collections::VecDeque;
+use std::pin::Pin;
+
+/// Finds the objects in the top-level `list` array of a JSON document fed in pieces
+#[derive(Debug, Default)]
+struct EntryScanner {
+    /// Bytes not yet discarded: the current entry or top-level string, and anything after
+    buffer: Vec<u8>,
+    /// How far into `buffer` has been looked at
+    scanned: usize,
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+    string_start: usize,
+    /// The last string seen directly in the top-level object, i.e. the key before a value
+    last_key: Vec<u8>,
+    in_list: bool,
+    saw_list: bool,
+    entry_start: Option<usize>,
+}
+
+impl EntryScanner {
+    /// Take the next piece of the document and return the entries it completes
+    fn push(&mut self, chunk: &[u8]) -> Result<Vec<TrackEntry>> {
+        self.buffer.extend_from_slice(chunk);
+        let mut entries = Vec::new();
+        while self.scanned < self.buffer.len() {
+            let index = self.scanned;
+            let byte = self.buffer[index];
+            self.scanned += 1;
+            if self.in_string {
+                if self.escaped {
+                    self.escaped = false;
+                } else if byte == b'\\' {
+                    self.escaped = true;
+                } else if byte == b'"' {
+                    self.in_string = false;
+                    if self.depth == 1 {
+                        self.last_key = self.buffer[self.string_start..index].to_vec();
+                    }
+                }
+                continue;
+            }
+            match byte {
+                b'"' => {
+                    self.in_string = true;
+                    self.string_start = index + 1;
+                }
+                b'{' | b'[' => {
+                    if byte == b'{' && self.in_list && self.depth == 2 {
+                        self.entry_start = Some(index);
+                    }
+                    if byte == b'[' && self.depth == 1 && self.last_key == b"list" {
+                        self.in_list = true;
+                        self.saw_list = true;
+                    }
+                    self.depth += 1;
+                }
+                b'}' | b']' => {
+                    self.depth = self.depth.checked_sub(1).ok_or_else(malformed)?;
+                    if byte == b'}' && self.in_list && self.depth == 2 {
+                        if let Some(start) = self.entry_start.take() {
+                            entries.push(serde_json::from_slice(&self.buffer[start..=index])?);
+                        }
+                    }
+                    if self.in_list && self.depth == 1 {
+                        self.in_list = false;
+                    }
+                }
+                _ => {}
+            }
+        }
+        self.discard();
+        Ok(entries)
+    }
+
+    /// Drop what has been scanned and is no longer needed
+    fn discard(&mut self) {
+        let keep = match self.entry_start {
+            Some(start) => start,
+            None if self.in_string && self.depth == 1 => self.string_start,
+            None => self.scanned,
+        };
+        self.buffer.drain(..keep);
+        self.scanned -= keep;
+        self.string_start = self.string_start.saturating_sub(keep);
+        self.entry_start = self.entry_start.map(|start| start - keep);
+    }
+
+    /// Check the document ended where it should
+    fn finish(&self) -> Result<()> {
+        if !self.saw_list {
+            return Err(WiimError::InvalidResponse(
+                "The device has no local track list".into(),
+            ));
+        }
+        if self.depth != 0 || self.in_string {
+            return Err(malformed());
+        }
+        Ok(())
+    }
+}
+
+fn malformed() -> WiimError {
+    WiimError::InvalidResponse("Malformed or truncated track list".into())
+}
+
+struct Entries {
+    body: Pin<Box<dyn Stream<Item = Result<Bytes>> + Send>>,
+    scanner: EntryScanner,
+    pending: VecDeque<TrackEntry>,
+    done: bool,
+}
+
+impl WiimClient {
+    /// Stream the local track list (USB/local sources) one entry at a time
+    ///
+    /// Entries come as soon as they have downloaded, so a UI can show the start of a large
+    /// library while the rest loads, without the whole list ever being in memory. Use
+    /// [`get_track_list`](Self::get_track_list) when it is small.
+    ///
+    /// # Errors
+    /// The stream ends with `WiimError::InvalidResponse` if the device has no local track
+    /// list or the list is cut off
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use futures_util::StreamExt;
+    /// use wiim_api::WiimClient;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> wiim_api::Result<()> {
+    ///     let client = WiimClient::new("192.168.1.100");
+    ///     let mut entries = Box::pin(client.track_entries().await?);
+    ///     while let Some(entry) = entries.next().await {
+    ///         println!("{}", entry?.display_name().unwrap_or_default());
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn track_entries(&self) -> Result<impl Stream<Item = Result<TrackEntry>> + 'static> {
+        let state = Entries {
+            body: Box::pin(self.stream_track_list().await?),
+            scanner: EntryScanner::default(),
+            pending: VecDeque::new(),
+            done: false,
+        };
+        Ok(stream::try_unfold(state, |mut state| async move {
+            loop {
+                if let Some(entry) = state.pending.pop_front() {
+                    return Ok(Some((entry, state)));
+                }
+                if state.done {
+                    return Ok(None);
+                }
+                match state.body.next().await {
+                    Some(chunk) => state.pending.extend(state.scanner.push(&chunk?)?),
+                    None => {
+                        state.scanner.finish()?;
+                        state.done = true;
+                    }
+                }
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIST: &str = r#"{"num": "3", "note": "list", "list": [
+        {"name": "01 {intro}.mp3", "Title": "Say \"hi\" [live]", "Artist": "Band"},
+        {"name": "02.flac", "nested": {"list": [1, 2]}},
+        {"name": "03.mp3"}
+    ], "index": "0"}"#;
+
+    fn names(entries: &[TrackEntry]) -> Vec<String> {
+        entries.iter().filter_map(|e| e.name.clone()).collect()
+    }
+
+    #[test]
+    fn test_scanner_at_every_split() {
+        let bytes = LIST.as_bytes();
+        for split in 0..bytes.len() {
+            let mut scanner = EntryScanner::default();
+            let mut entries = scanner.push(&bytes[..split]).unwrap();
+            entries.extend(scanner.push(&bytes[split..]).unwrap());
+            scanner.finish().unwrap();
+            assert_eq!(names(&entries), ["01 {intro}.mp3", "02.flac", "03.mp3"]);
+            assert_eq!(entries[0].title.as_deref(), Some("Say \"hi\" [live]"));
+        }
+    }
+
+    #[test]
+    fn test_scanner_keeps_only_the_current_entry() {
+        let mut scanner = EntryScanner::default();
+        for byte in LIST.as_bytes() {
+            scanner.push(&[*byte]).unwrap();
+            assert!(scanner.buffer.len() <= 90);
+        }
+        scanner.finish().unwrap();
+    }
+
+    #[test]
+    fn test_scanner_errors() {
+        let mut scanner = EntryScanner::default();
+        assert!(scanner
+            .push(br#"{"num": "0", "list": []}"#)
+            .unwrap()
+            .is_empty());
+        assert!(scanner.finish().is_ok());
+
+        let mut scanner = EntryScanner::default();
+        scanner.push(b"unknown command").unwrap();
+        assert!(scanner.finish().is_err());
+
+        let mut scanner = EntryScanner::default();
+        scanner
+            .push(br#"{"list": [{"name": "01.mp3"}, {"na"#)
+            .unwrap();
+        assert!(scanner.finish().is_err());
+    }
+}
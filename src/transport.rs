@@ -0,0 +1,72 @@
+//! Connection pools shared between clients
+//!
+//! Each reqwest client keeps its own pool, TLS setup and DNS resolver. A program talking to
+//! many devices saves memory and handshakes by building its clients on one [`Transport`];
+//! clients with default settings do this on their own, sharing a pool created on first use.
+//! A client on a shared transport sends its timeout and headers with each request instead
+//! of baking them into the pool.
+
+use crate::Result;
+use reqwest::Client;
+use std::sync::OnceLock;
+#[cfg(not(target_arch = "wasm32"))]
+use {crate::DEFAULT_CONNECT_TIMEOUT, std::time::Duration};
+
+/// The pool clients with default settings share
+static SHARED: OnceLock<Transport> = OnceLock::new();
+
+/// A connection pool for any number of [`WiimClient`](crate::WiimClient)s
+///
+/// Cloning is cheap and shares the pool.
+///
+/// # Examples
+/// ```
+/// use wiim_api::{Transport, WiimClient};
+///
+/// let transport = Transport::new()?;
+/// let clients: Vec<WiimClient> = ["192.168.1.100", "192.168.1.101"]
+///     .into_iter()
+///     .map(|ip| WiimClient::builder(ip).transport(&transport).build())
+///     .collect::<Result<_, _>>()?;
+/// # Ok::<(), wiim_api::WiimError>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct Transport {
+    pub(crate) client: Client,
+}
+
+impl Transport {
+    /// A pool accepting the devices' self-signed certificates, with the default connect
+    /// timeout
+    pub fn new() -> Result<Self> {
+        #[cfg(not(target_arch = "wasm32"))]
+        return Self::with_connect_timeout(DEFAULT_CONNECT_TIMEOUT);
+        #[cfg(target_arch = "wasm32")]
+        Ok(Transport {
+            client: Client::builder().build()?,
+        })
+    }
+
+    /// A pool giving up on connections that take longer than `connect_timeout`
+    ///
+    /// Clients on this transport use it in place of their own connect timeout.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_connect_timeout(connect_timeout: Duration) -> Result<Self> {
+        let client = Client::builder()
+            .danger_accept_invalid_certs(true)
+            // Keeps the certificate for WiimClient::certificate_fingerprint
+            .tls_info(true)
+            .connect_timeout(connect_timeout)
+            .build()?;
+        Ok(Transport { client })
+    }
+
+    /// The pool shared by clients built with default settings
+    pub(crate) fn shared() -> Result<Self> {
+        if let Some(transport) = SHARED.get() {
+            return Ok(transport.clone());
+        }
+        let transport = Transport::new()?;
+        Ok(SHARED.get_or_init(|| transport).clone())
+    }
+}
@@ -0,0 +1,148 @@
+//! Volume levels, which devices only accept from 0 to 100
+
+use crate::WiimError;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// A volume level from 0 to 100
+///
+/// Build one with [`Volume::new`] or `Volume::try_from`, or clamp any `u8` with
+/// [`Volume::saturating`]. Serialized as a plain number.
+///
+/// # Examples
+/// ```
+/// use wiim_api::Volume;
+///
+/// let volume = Volume::try_from(75).unwrap();
+/// assert_eq!(volume.to_string(), "75%");
+/// assert!(Volume::try_from(150).is_err());
+/// assert_eq!(volume.saturating_add(40), Volume::MAX);
+/// ```
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(try_from = "u8", into = "u8")]
+pub struct Volume(u8);
+
+impl Volume {
+    pub const MIN: Volume = Volume(0);
+    pub const MAX: Volume = Volume(100);
+
+    /// `None` above 100
+    pub const fn new(level: u8) -> Option<Self> {
+        if level <= 100 {
+            Some(Volume(level))
+        } else {
+            None
+        }
+    }
+
+    /// Levels above 100 become 100
+    pub const fn saturating(level: u8) -> Self {
+        if level <= 100 {
+            Volume(level)
+        } else {
+            Volume::MAX
+        }
+    }
+
+    pub const fn get(self) -> u8 {
+        self.0
+    }
+
+    /// Louder by `step`, stopping at 100
+    pub const fn saturating_add(self, step: u8) -> Self {
+        Volume::saturating(self.0.saturating_add(step))
+    }
+
+    /// Quieter by `step`, stopping at 0
+    pub const fn saturating_sub(self, step: u8) -> Self {
+        Volume(self.0.saturating_sub(step))
+    }
+
+    /// The level as a fraction from 0.0 to 1.0
+    pub fn fraction(self) -> f64 {
+        f64::from(self.0) / 100.0
+    }
+}
+
+impl TryFrom<u8> for Volume {
+    type Error = WiimError;
+
+    fn try_from(level: u8) -> Result<Self, Self::Error> {
+        Volume::new(level).ok_or_else(|| WiimError::InvalidResponse("Volume must be 0-100".into()))
+    }
+}
+
+/// Parses "75" or "75%"
+impl FromStr for Volume {
+    type Err = WiimError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let level = text.trim();
+        let level = level.strip_suffix('%').unwrap_or(level);
+        level
+            .parse::<u8>()
+            .ok()
+            .and_then(Volume::new)
+            .ok_or_else(|| {
+                WiimError::InvalidResponse(format!("Volume must be 0-100, got '{text}'"))
+            })
+    }
+}
+
+impl From<Volume> for u8 {
+    fn from(volume: Volume) -> Self {
+        volume.0
+    }
+}
+
+impl PartialEq<u8> for Volume {
+    fn eq(&self, other: &u8) -> bool {
+        self.0 == *other
+    }
+}
+
+impl fmt::Display for Volume {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}%", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_volume_bounds() {
+        assert_eq!(Volume::new(0), Some(Volume::MIN));
+        assert_eq!(Volume::new(100), Some(Volume::MAX));
+        assert_eq!(Volume::new(101), None);
+        for level in [101, 150, 255] {
+            let error = Volume::try_from(level).unwrap_err();
+            assert!(
+                matches!(error, WiimError::InvalidResponse(msg) if msg == "Volume must be 0-100")
+            );
+        }
+        assert_eq!(Volume::saturating(180), Volume::MAX);
+        assert_eq!(Volume::saturating(40).saturating_sub(50), Volume::MIN);
+        assert_eq!(Volume::saturating(98).saturating_add(5), 100);
+    }
+
+    #[test]
+    fn test_volume_serde() {
+        let volume: Volume = serde_json::from_str("42").unwrap();
+        assert_eq!(volume, 42);
+        assert_eq!(serde_json::to_string(&volume).unwrap(), "42");
+        assert!(serde_json::from_str::<Volume>("101").is_err());
+    }
+
+    #[test]
+    fn test_volume_from_str() {
+        assert_eq!("75".parse::<Volume>().unwrap(), 75);
+        assert_eq!(" 30% ".parse::<Volume>().unwrap(), 30);
+        assert!("101".parse::<Volume>().is_err());
+        assert!("-5".parse::<Volume>().is_err());
+    }
+}
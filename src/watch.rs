@@ -0,0 +1,225 @@
+//! Change notifications built on periodic polling
+//!
+//! The LinkPlay HTTP API has no push mechanism, so watching a device means polling
+//! `get_now_playing` and comparing snapshots. Position updates alone are not
+//! considered a change; track, playback state, volume, mute and loop mode are.
+
+use crate::rt::Instant;
+use crate::{NowPlaying, PlayState, Result, WiimClient, WiimError};
+use futures_util::stream::{self, Stream};
+use std::time::Duration;
+
+/// First delay between polls in the `wait_for_*` helpers
+pub(crate) const FIRST_POLL_DELAY: Duration = Duration::from_millis(250);
+/// The delay doubles after each poll up to this
+const MAX_POLL_DELAY: Duration = Duration::from_secs(2);
+
+impl NowPlaying {
+    /// Whether the title, artist or album differs between two snapshots
+    pub fn is_different_track(&self, other: &NowPlaying) -> bool {
+        self.title != other.title || self.artist != other.artist || self.album != other.album
+    }
+
+    /// Whether anything other than the playback position differs between two snapshots
+    pub fn differs_from(&self, other: &NowPlaying) -> bool {
+        self.is_different_track(other)
+            || self.state != other.state
+            || self.volume != other.volume
+            || self.is_muted != other.is_muted
+            || self.loop_mode != other.loop_mode
+    }
+}
+
+struct WatchState {
+    client: WiimClient,
+    interval: Duration,
+    last: Option<NowPlaying>,
+    first: bool,
+}
+
+impl WiimClient {
+    /// Poll the device every `interval` and yield now playing information whenever the
+    /// track, playback state, volume, mute or loop mode changes
+    ///
+    /// The first item is yielded immediately. Request failures are yielded as errors and
+    /// polling continues; the next successful poll after an error is always yielded.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use futures_util::StreamExt;
+    /// use std::time::Duration;
+    /// use wiim_api::WiimClient;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> wiim_api::Result<()> {
+    ///     let client = WiimClient::new("192.168.1.100");
+    ///     let mut updates = Box::pin(client.watch(Duration::from_secs(2)));
+    ///
+    ///     while let Some(update) = updates.next().await {
+    ///         let now_playing = update?;
+    ///         println!("{} - {:?}", now_playing.state, now_playing.title);
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn watch(&self, interval: Duration) -> impl Stream<Item = Result<NowPlaying>> {
+        let state = WatchState {
+            client: self.clone(),
+            interval,
+            last: None,
+            first: true,
+        };
+
+        stream::unfold(state, |mut state| async move {
+            loop {
+                if !state.first {
+                    crate::rt::sleep(state.interval).await;
+                }
+                state.first = false;
+
+                match state.client.get_now_playing().await {
+                    Ok(now_playing) => {
+                        let changed = state
+                            .last
+                            .as_ref()
+                            .is_none_or(|last| now_playing.differs_from(last));
+                        if changed {
+                            state.last = Some(now_playing.clone());
+                            return Some((Ok(now_playing), state));
+                        }
+                    }
+                    Err(e) => {
+                        state.last = None;
+                        return Some((Err(e), state));
+                    }
+                }
+            }
+        })
+    }
+
+    /// Poll until the device reports `state`, returning the matching now playing information
+    ///
+    /// Polling starts fast and backs off to every two seconds. Failed requests are retried,
+    /// since the device may be busy switching sources.
+    ///
+    /// # Errors
+    /// Returns `WiimError::Timeout` if the state isn't reached within `timeout`
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use wiim_api::{PlayState, WiimClient};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> wiim_api::Result<()> {
+    ///     let client = WiimClient::new("192.168.1.100");
+    ///     client.play_url("http://ice1.somafm.com/groovesalad-128-mp3").await?;
+    ///     client
+    ///         .wait_for_state(PlayState::Playing, Duration::from_secs(10))
+    ///         .await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn wait_for_state(&self, state: PlayState, timeout: Duration) -> Result<NowPlaying> {
+        self.poll_until(timeout, |_, now_playing| now_playing.state == state)
+            .await
+    }
+
+    /// Poll until the title, artist or album differs from the first reading, returning the
+    /// new track's now playing information
+    ///
+    /// # Errors
+    /// Returns `WiimError::Timeout` if the track doesn't change within `timeout`
+    pub async fn wait_for_track_change(&self, timeout: Duration) -> Result<NowPlaying> {
+        self.poll_until(timeout, |first, now_playing| {
+            now_playing.is_different_track(first)
+        })
+        .await
+    }
+
+    /// Poll with backoff until `done(first, current)` holds for a reading
+    async fn poll_until(
+        &self,
+        timeout: Duration,
+        done: impl Fn(&NowPlaying, &NowPlaying) -> bool,
+    ) -> Result<NowPlaying> {
+        let deadline = Instant::now() + timeout;
+        let mut first: Option<NowPlaying> = None;
+        let mut delay = FIRST_POLL_DELAY;
+
+        loop {
+            if let Ok(now_playing) = self.get_now_playing().await {
+                let first = first.get_or_insert_with(|| now_playing.clone());
+                if done(first, &now_playing) {
+                    return Ok(now_playing);
+                }
+            }
+            if Instant::now() + delay > deadline {
+                return Err(WiimError::Timeout(timeout));
+            }
+            crate::rt::sleep(delay).await;
+            delay = next_delay(delay);
+        }
+    }
+}
+
+pub(crate) fn next_delay(delay: Duration) -> Duration {
+    (delay * 2).min(MAX_POLL_DELAY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Volume;
+
+    fn now_playing() -> NowPlaying {
+        NowPlaying {
+            title: Some("Title".to_string()),
+            artist: Some("Artist".to_string()),
+            state: PlayState::Playing,
+            volume: Volume::saturating(40),
+            position: Duration::from_secs(1),
+            duration: Duration::from_secs(180),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_differs_from_ignores_position() {
+        let a = now_playing();
+        let mut b = now_playing();
+        b.position = Duration::from_secs(5);
+        assert!(!a.differs_from(&b));
+
+        b.volume = Volume::saturating(41);
+        assert!(a.differs_from(&b));
+
+        let mut c = now_playing();
+        c.state = PlayState::Paused;
+        assert!(a.differs_from(&c));
+
+        let mut d = now_playing();
+        d.title = Some("Next Song".to_string());
+        assert!(a.differs_from(&d));
+        assert!(a.is_different_track(&d));
+        assert!(!a.is_different_track(&c));
+    }
+
+    #[test]
+    fn test_poll_delay_backs_off() {
+        let delays: Vec<_> =
+            std::iter::successors(Some(FIRST_POLL_DELAY), |d| Some(next_delay(*d)))
+                .take(6)
+                .map(|d| d.as_millis())
+                .collect();
+        assert_eq!(delays, [250, 500, 1000, 2000, 2000, 2000]);
+    }
+
+    #[tokio::test]
+    async fn test_wait_times_out() {
+        // Nothing listens on port 1, so every poll fails straight away
+        let client = WiimClient::new("http://127.0.0.1:1");
+        let result = client.wait_for_track_change(Duration::ZERO).await;
+        assert!(matches!(result, Err(WiimError::Timeout(timeout)) if timeout.is_zero()));
+    }
+}
@@ -0,0 +1,139 @@
+//! Wi-Fi networks visible to the device
+//!
+//! `wlanGetApListEx` returns every access point the device can hear, with the SSID
+//! usually hex-encoded and all numbers as strings.
+
+use crate::{Result, WiimClient};
+use serde::{Deserialize, Serialize};
+
+/// An access point seen by the device's Wi-Fi radio
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AccessPoint {
+    pub ssid: String,
+    pub bssid: String,
+    pub channel: Option<u16>,
+    /// Signal strength in dBm
+    pub rssi: Option<i32>,
+    /// Authentication mode as reported, e.g. "OPEN", "WPA2PSK" or "WPAPSKWPA2PSK"
+    pub auth: String,
+    /// Cipher as reported, e.g. "NONE", "AES" or "TKIPAES"
+    pub encryption: String,
+}
+
+impl AccessPoint {
+    /// Whether joining needs no password
+    pub fn is_open(&self) -> bool {
+        self.auth.eq_ignore_ascii_case("OPEN")
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct RawAccessPoint {
+    #[serde(default)]
+    ssid: String,
+    #[serde(default)]
+    bssid: String,
+    #[serde(default)]
+    channel: String,
+    #[serde(default)]
+    rssi: String,
+    #[serde(default)]
+    auth: String,
+    #[serde(default)]
+    encry: String,
+}
+
+impl From<RawAccessPoint> for AccessPoint {
+    fn from(raw: RawAccessPoint) -> Self {
+        AccessPoint {
+            // Some firmware sends the name as-is rather than hex-encoded
+            ssid: decode_hex_string(&raw.ssid).unwrap_or(raw.ssid),
+            bssid: raw.bssid,
+            channel: raw.channel.trim().parse().ok(),
+            rssi: raw.rssi.trim().parse().ok(),
+            auth: raw.auth,
+            encryption: raw.encry,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ApList {
+    #[serde(default)]
+    aplist: Vec<RawAccessPoint>,
+}
+
+/// Decode a hex-encoded UTF-8 string, as the device uses for SSIDs
+pub(crate) fn decode_hex_string(hex: &str) -> Option<String> {
+    if hex.is_empty() || !hex.is_ascii() || !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    let bytes = (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+        .collect::<std::result::Result<Vec<u8>, _>>()
+        .ok()?;
+    String::from_utf8(bytes).ok()
+}
+
+/// Hex-encode a string the way the device expects SSIDs and passphrases
+pub(crate) fn encode_hex_string(value: &str) -> String {
+    value.bytes().map(|byte| format!("{byte:02X}")).collect()
+}
+
+impl WiimClient {
+    /// List the Wi-Fi networks the device can see, strongest first
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use wiim_api::WiimClient;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> wiim_api::Result<()> {
+    ///     let client = WiimClient::new("192.168.1.100");
+    ///     for ap in client.wifi_scan().await? {
+    ///         println!("{} ch {:?}: {:?} dBm", ap.ssid, ap.channel, ap.rssi);
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn wifi_scan(&self) -> Result<Vec<AccessPoint>> {
+        let response = self.send_command("wlanGetApListEx").await?;
+        let list: ApList = serde_json::from_str(&response)?;
+        let mut access_points: Vec<AccessPoint> =
+            list.aplist.into_iter().map(AccessPoint::from).collect();
+        access_points.sort_by_key(|ap| std::cmp::Reverse(ap.rssi.unwrap_or(i32::MIN)));
+        Ok(access_points)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ap_list_parsing() {
+        let list: ApList = serde_json::from_str(
+            r#"{"res":"2","aplist":[{"ssid":"486F6D65","bssid":"8c:25:05:1c:41:40","rssi":"-71","channel":"36","auth":"WPA2PSK","encry":"AES","extch":"0"},{"ssid":"Guest Net","bssid":"8c:25:05:1c:41:41","rssi":"-48","channel":"6","auth":"OPEN","encry":"NONE","extch":"1"}]}"#,
+        )
+        .unwrap();
+        let aps: Vec<AccessPoint> = list.aplist.into_iter().map(AccessPoint::from).collect();
+        assert_eq!(aps[0].ssid, "Home");
+        assert_eq!(aps[0].channel, Some(36));
+        assert_eq!(aps[0].rssi, Some(-71));
+        assert!(!aps[0].is_open());
+        assert_eq!(aps[1].ssid, "Guest Net");
+        assert!(aps[1].is_open());
+
+        let list: ApList = serde_json::from_str(r#"{"res":"0"}"#).unwrap();
+        assert!(list.aplist.is_empty());
+    }
+
+    #[test]
+    fn test_hex_strings() {
+        assert_eq!(decode_hex_string("4D794E6574").as_deref(), Some("MyNet"));
+        assert_eq!(decode_hex_string("MyNet"), None);
+        assert_eq!(decode_hex_string(""), None);
+        assert_eq!(encode_hex_string("MyNet"), "4D794E6574");
+    }
+}
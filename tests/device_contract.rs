@@ -0,0 +1,230 @@
+//! Contract tests against a real device, run with
+//!
+//! ```bash
+//! WIIM_DEVICE=192.168.1.100 cargo test --features device-tests --test device_contract
+//! ```
+//!
+//! Every wrapped endpoint is called once. Setters are only given the value the device
+//! already has, or a neighbouring one that is put back straight away, and the volume,
+//! mute, input, EQ and loop mode are restored from a scene captured at the start. Endpoints
+//! the model or firmware lacks are reported as unsupported rather than failing the run.
+//!
+//! A Markdown report named after the model and firmware is written to
+//! `target/device-reports` (or `WIIM_REPORT_DIR`), so runs on different firmware versions
+//! can be compared side by side.
+#![cfg(feature = "device-tests")]
+
+use std::fmt::Debug;
+use std::path::PathBuf;
+use std::time::Duration;
+use wiim_api::{Result, Volume, WiimClient, WiimError};
+
+enum Outcome {
+    Pass(String),
+    Unsupported(String),
+    Fail(String),
+}
+
+#[derive(Default)]
+struct Report {
+    rows: Vec<(&'static str, Outcome)>,
+}
+
+impl Report {
+    /// Note how a call went, handing back its value when it succeeded
+    fn record<T: Debug>(&mut self, endpoint: &'static str, result: Result<T>) -> Option<T> {
+        let (outcome, value) = match result {
+            Ok(value) => {
+                let mut summary = format!("{value:?}");
+                if summary.chars().count() > 80 {
+                    summary = summary.chars().take(77).collect::<String>() + "...";
+                }
+                (Outcome::Pass(summary), Some(value))
+            }
+            Err(e @ (WiimError::Unsupported { .. } | WiimError::UnsupportedCommand { .. })) => {
+                (Outcome::Unsupported(e.to_string()), None)
+            }
+            Err(e) => (Outcome::Fail(e.to_string()), None),
+        };
+        self.rows.push((endpoint, outcome));
+        value
+    }
+
+    fn failures(&self) -> Vec<String> {
+        self.rows
+            .iter()
+            .filter_map(|(endpoint, outcome)| match outcome {
+                Outcome::Fail(message) => Some(format!("{endpoint}: {message}")),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn markdown(&self, device: &str, model: &str, firmware: &str) -> String {
+        let mut text = format!(
+            "# {model} on firmware {firmware}\n\n\
+             Device: {device}, tested {}\n\n\
+             | Endpoint | Result | Details |\n\
+             |----------|--------|---------|\n",
+            chrono::Local::now().format("%Y-%m-%d %H:%M")
+        );
+        for (endpoint, outcome) in &self.rows {
+            let (result, details) = match outcome {
+                Outcome::Pass(details) => ("✅ pass", details),
+                Outcome::Unsupported(details) => ("➖ unsupported", details),
+                Outcome::Fail(details) => ("❌ fail", details),
+            };
+            let details = details.replace('|', "\\|");
+            text.push_str(&format!("| `{endpoint}` | {result} | {details} |\n"));
+        }
+        text
+    }
+}
+
+fn report_path(model: &str, firmware: &str) -> PathBuf {
+    let dir = std::env::var_os("WIIM_REPORT_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("target/device-reports"));
+    let name: String = format!("{model}-{firmware}")
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    dir.join(format!("{name}.md"))
+}
+
+/// The calls that only read state
+async fn check_getters(client: &WiimClient, report: &mut Report) {
+    report.record("getPlayerStatus", client.get_player_status().await);
+    report.record("getMetaInfo", client.get_meta_info().await);
+    report.record("get_now_playing", client.get_now_playing().await);
+    report.record("get_volume", client.get_volume().await);
+    report.record("get_muted", client.get_muted().await);
+    report.record("get_loop_mode", client.get_loop_mode().await);
+    report.record("get_queue_position", client.get_queue_position().await);
+    report.record("getLocalPlayList", client.get_track_list().await);
+    report.record("get_album_art", client.get_album_art().await);
+    report.record("getShutdown", client.get_sleep_timer().await);
+    report.record("get_device_name", client.get_device_name().await);
+    report.record("get_capabilities", client.get_capabilities().await);
+    report.record("get_inputs", client.get_inputs().await);
+    report.record(
+        "is_hdmi_audio_present",
+        client.is_hdmi_audio_present().await,
+    );
+    report.record("getAudioSyncDelay", client.get_lip_sync_delay().await);
+    report.record("getLineInGain", client.get_line_in_gain().await);
+    report.record("getSubLPF", client.get_subwoofer().await);
+    report.record("get_channel", client.get_channel().await);
+    report.record("multiroom:getSlaveList", client.get_group_followers().await);
+    report.record("get_group_role", client.get_group_role().await);
+    report.record("getAlarmClock", client.list_alarms().await);
+    report.record("getStaticIpInfo", client.get_ip_config().await);
+    report.record("wlanGetApListEx", client.wifi_scan().await);
+    report.record("wlanGetConnectState", client.get_wifi_connect_state().await);
+}
+
+/// The setters, each given a value that leaves the device as it was
+async fn check_setters(client: &WiimClient, report: &mut Report) {
+    let client = client.verified();
+
+    if let Ok(volume) = client.get_volume().await {
+        let nudged = if volume < Volume::MAX {
+            volume.saturating_add(1)
+        } else {
+            volume.saturating_sub(1)
+        };
+        report.record("setPlayerCmd:vol", client.set_volume(nudged).await);
+    }
+    if let Ok(muted) = client.get_muted().await {
+        let toggled = if muted {
+            client.unmute().await
+        } else {
+            client.mute().await
+        };
+        report.record("setPlayerCmd:mute", toggled);
+    }
+    if let Ok(mode) = client.get_loop_mode().await {
+        report.record("setPlayerCmd:loopmode", client.set_loop_mode(mode).await);
+    }
+    if let Some(name) = client.get_device_name().await.ok().flatten() {
+        report.record("setDeviceName", client.set_device_name(&name).await);
+    }
+    // Only when no timer is running, so an existing one isn't cut short
+    if let Ok(None) = client.get_sleep_timer().await {
+        report.record(
+            "setShutdown",
+            client.set_sleep_timer(Duration::from_secs(3600)).await,
+        );
+        report.record("setShutdown:-1", client.cancel_sleep_timer().await);
+    }
+    if let Ok(delay) = client.get_lip_sync_delay().await {
+        report.record("setAudioSyncDelay", client.set_lip_sync_delay(delay).await);
+    }
+    if let Ok(gain) = client.get_line_in_gain().await {
+        report.record("setLineInGain", client.set_line_in_gain(gain).await);
+    }
+    if let Ok(sub) = client.get_subwoofer().await {
+        report.record(
+            "setSubLPF:status",
+            client.set_subwoofer_enabled(sub.enabled).await,
+        );
+        report.record(
+            "setSubLPF:cross",
+            client.set_sub_crossover(sub.crossover_hz).await,
+        );
+        report.record("setSubLPF:level", client.set_sub_level(sub.level_db).await);
+        report.record(
+            "setSubLPF:phase",
+            client.set_sub_phase(sub.phase_inverted).await,
+        );
+    }
+}
+
+#[tokio::test]
+async fn device_contract() -> Result<()> {
+    // `--all-features` builds in CI have no device to talk to
+    let Ok(device) = std::env::var("WIIM_DEVICE") else {
+        eprintln!("Skipping the device contract: set WIIM_DEVICE to a device address");
+        return Ok(());
+    };
+    let client = WiimClient::builder(&device).detect().await?;
+
+    let status_ex = client.get_status_ex().await?;
+    let model = status_ex
+        .project
+        .clone()
+        .unwrap_or_else(|| "unknown".to_string());
+    let firmware = status_ex
+        .firmware
+        .clone()
+        .unwrap_or_else(|| "unknown".to_string());
+    let scene = client.capture_scene().await?;
+
+    let mut report = Report::default();
+    report.record("getStatusEx", Ok(status_ex));
+    report.record("capture_scene", Ok(scene.clone()));
+    check_getters(&client, &mut report).await;
+    check_setters(&client, &mut report).await;
+    report.record("apply_scene", client.apply_scene(&scene).await);
+
+    let path = report_path(&model, &firmware);
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).expect("create the report directory");
+    }
+    std::fs::write(&path, report.markdown(&device, &model, &firmware)).expect("write the report");
+    println!("Report written to {}", path.display());
+
+    let failures = report.failures();
+    assert!(
+        failures.is_empty(),
+        "Endpoints failed:\n{}",
+        failures.join("\n")
+    );
+    Ok(())
+}
@@ -1,5 +1,5 @@
 use std::time::Instant;
-use wiim_api::{Result, WiimClient};
+use wiim_api::{Result, Volume, WiimClient};
 
 #[tokio::test]
 #[ignore] // Requires actual device
@@ -13,7 +13,7 @@ async fn test_volume_performance() -> Result<()> {
     let start = Instant::now();
     let new_vol = client.volume_up(Some(1)).await?;
     let library_time = start.elapsed();
-    println!("Library volume_up: {library_time:?} ({original_volume}% -> {new_vol}%)");
+    println!("Library volume_up: {library_time:?} ({original_volume} -> {new_vol})");
 
     // Restore volume
     client.set_volume(original_volume).await?;
@@ -24,7 +24,7 @@ async fn test_volume_performance() -> Result<()> {
     let current: u8 = status.vol.parse().map_err(|_| {
         wiim_api::WiimError::InvalidResponse(format!("Invalid volume: {vol}", vol = status.vol))
     })?;
-    client.set_volume(current + 1).await?;
+    client.set_volume(Volume::saturating(current + 1)).await?;
     let manual_same_client_time = start.elapsed();
     println!("Manual same client: {manual_same_client_time:?}");
 
@@ -39,7 +39,7 @@ async fn test_volume_performance() -> Result<()> {
         wiim_api::WiimError::InvalidResponse(format!("Invalid volume: {vol}", vol = status.vol))
     })?;
     let client2 = WiimClient::new("192.168.86.52");
-    client2.set_volume(current + 1).await?;
+    client2.set_volume(Volume::saturating(current + 1)).await?;
     let manual_new_clients_time = start.elapsed();
     println!("Manual new clients: {manual_new_clients_time:?}");
 